@@ -6,17 +6,17 @@
 //! - Using different message types
 //! - Ending the dialog
 
+use chrono::Utc;
 use cim_domain_dialog::{
     aggregate::DialogType,
-    events::{DialogDomainEvent, DialogStarted, TurnAdded, DialogEnded},
+    events::{DialogDomainEvent, DialogEnded, DialogStarted, TurnAdded},
     projections::SimpleProjectionUpdater,
     queries::{DialogQuery, DialogQueryHandler, DialogQueryResult},
     value_objects::{
-        Message, MessageContent, MessageIntent, Participant, ParticipantRole, 
-        ParticipantType, Turn, TurnMetadata, TurnType, ConversationMetrics,
+        ConversationMetrics, Message, MessageContent, MessageIntent, Participant, ParticipantRole,
+        ParticipantType, Turn, TurnMetadata, TurnType,
     },
 };
-use chrono::Utc;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -35,6 +35,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Step 1: Start a dialog
     println!("1. Starting dialog...");
     let start_event = DialogDomainEvent::DialogStarted(DialogStarted {
+        event_id: Uuid::new_v4(),
         dialog_id,
         dialog_type: DialogType::Support,
         primary_participant: Participant {
@@ -53,6 +54,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Step 2: User sends first message
     println!("\n2. User sending first message...");
     let turn1 = DialogDomainEvent::TurnAdded(TurnAdded {
+        event_id: Uuid::new_v4(),
         dialog_id,
         turn: Turn {
             turn_id: Uuid::new_v4(),
@@ -83,6 +85,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Step 3: Agent responds
     println!("\n3. Agent responding...");
     let turn2 = DialogDomainEvent::TurnAdded(TurnAdded {
+        event_id: Uuid::new_v4(),
         dialog_id,
         turn: Turn {
             turn_id: Uuid::new_v4(),
@@ -115,15 +118,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Step 4: User provides more details
     println!("\n4. User providing more details...");
     let turn3 = DialogDomainEvent::TurnAdded(TurnAdded {
+        event_id: Uuid::new_v4(),
         dialog_id,
         turn: Turn {
             turn_id: Uuid::new_v4(),
             turn_number: 3,
             participant_id: user_id,
             message: Message {
-                content: MessageContent::Text(
-                    "I forgot my password and can't log in.".to_string()
-                ),
+                content: MessageContent::Text("I forgot my password and can't log in.".to_string()),
                 intent: Some(MessageIntent::Statement),
                 language: "en".to_string(),
                 sentiment: Some(-0.3),
@@ -168,6 +170,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     });
 
     let turn4 = DialogDomainEvent::TurnAdded(TurnAdded {
+        event_id: Uuid::new_v4(),
         dialog_id,
         turn: Turn {
             turn_id: Uuid::new_v4(),
@@ -200,7 +203,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let updater_arc = Arc::new(RwLock::new(updater));
     let query_handler = DialogQueryHandler::new(updater_arc.clone());
 
-    let result = query_handler.execute(DialogQuery::GetDialogById { dialog_id }).await;
+    let result = query_handler
+        .execute(DialogQuery::GetDialogById { dialog_id })
+        .await;
     if let DialogQueryResult::Dialog(Some(dialog)) = result {
         println!("   Dialog type: {:?}", dialog.dialog_type);
         println!("   Status: {:?}", dialog.status);
@@ -211,9 +216,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Step 7: End the dialog
     println!("\n7. Ending dialog...");
     let end_event = DialogDomainEvent::DialogEnded(DialogEnded {
+        event_id: Uuid::new_v4(),
         dialog_id,
         ended_at: Utc::now(),
-        reason: Some("Issue resolved - password reset instructions provided".to_string()),
+        reason: Some("Issue resolved - password reset instructions provided".into()),
         final_metrics: ConversationMetrics {
             turn_count: 4,
             avg_response_time_ms: 215.0,
@@ -230,11 +236,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Final query
     println!("\n8. Final dialog state:");
-    let result = query_handler.execute(DialogQuery::GetDialogById { dialog_id }).await;
+    let result = query_handler
+        .execute(DialogQuery::GetDialogById { dialog_id })
+        .await;
     if let DialogQueryResult::Dialog(Some(dialog)) = result {
         println!("   Status: {:?}", dialog.status);
         if let Some(metrics) = &dialog.metrics {
-            println!("   Average response time: {:.0}ms", metrics.avg_response_time_ms);
+            println!(
+                "   Average response time: {:.0}ms",
+                metrics.avg_response_time_ms
+            );
             println!("   Sentiment trend: {:.2}", metrics.sentiment_trend);
             println!("   Coherence score: {:.2}", metrics.coherence_score);
         }
@@ -242,4 +253,4 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("\n=== Example completed successfully! ===");
     Ok(())
-}
\ No newline at end of file
+}
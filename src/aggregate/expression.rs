@@ -0,0 +1,316 @@
+//! Expressions for computed context variables
+//!
+//! The language is deliberately small: numbers, double-quoted strings,
+//! variable names, `+ - * /`, unary minus and parentheses. `+` adds numbers
+//! and concatenates when either side is a string. Expressions cannot call
+//! functions or touch anything but the variables they name, nesting is capped
+//! at [`MAX_EXPRESSION_DEPTH`] and results must be finite, so they are safe to
+//! accept from agent frameworks.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::value_objects::ContextScope;
+
+/// Deepest an expression may nest, counting parentheses, unary minus and each
+/// operator in a chain like `a + b + c`
+pub const MAX_EXPRESSION_DEPTH: usize = 128;
+
+/// Errors from parsing or evaluating an expression
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ExpressionError {
+    /// The expression text is malformed
+    #[error("invalid expression: {0}")]
+    Parse(String),
+    /// The expression names a variable that is not set
+    #[error("unknown variable '{0}'")]
+    UnknownVariable(String),
+    /// An operator was applied to values it does not support
+    #[error("cannot apply '{0}' to these values")]
+    TypeMismatch(char),
+    /// Division by zero
+    #[error("division by zero")]
+    DivisionByZero,
+    /// The result overflowed to infinity or is not a number
+    #[error("result is not a finite number")]
+    NonFinite,
+    /// Computed variables depend on each other in a cycle
+    #[error("computed variable '{0}' depends on itself")]
+    Cycle(String),
+}
+
+/// A parsed expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Expression {
+    Number(f64),
+    Text(String),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, char, Box<Expression>),
+}
+
+/// A context variable whose value is recomputed from an expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComputedVariable {
+    pub name: String,
+    /// Expression as written, kept for display
+    pub source: String,
+    pub expression: Expression,
+    pub scope: ContextScope,
+}
+
+impl ComputedVariable {
+    /// Parse `source` into a computed variable
+    pub fn new(
+        name: impl Into<String>,
+        source: impl Into<String>,
+        scope: ContextScope,
+    ) -> Result<Self, ExpressionError> {
+        let source = source.into();
+        let expression = Expression::parse(&source)?;
+        Ok(Self {
+            name: name.into(),
+            source,
+            expression,
+            scope,
+        })
+    }
+}
+
+impl Expression {
+    /// Parse an expression
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+        let expression = parser.sum()?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(expression),
+            Some(token) => Err(ExpressionError::Parse(format!("unexpected {token:?}"))),
+        }
+    }
+
+    /// Names of the variables the expression reads
+    pub fn variables(&self) -> Vec<&str> {
+        match self {
+            Expression::Number(_) | Expression::Text(_) => Vec::new(),
+            Expression::Variable(name) => vec![name.as_str()],
+            Expression::Negate(inner) => inner.variables(),
+            Expression::Binary(left, _, right) => {
+                let mut names = left.variables();
+                names.extend(right.variables());
+                names
+            }
+        }
+    }
+
+    /// Evaluate the expression, looking variables up with `lookup`
+    pub fn evaluate(
+        &self,
+        lookup: &mut dyn FnMut(&str) -> Result<Value, ExpressionError>,
+    ) -> Result<Value, ExpressionError> {
+        match self {
+            Expression::Number(n) => number(*n),
+            Expression::Text(text) => Ok(Value::String(text.clone())),
+            Expression::Variable(name) => lookup(name),
+            Expression::Negate(inner) => match inner.evaluate(lookup)?.as_f64() {
+                Some(n) => number(-n),
+                None => Err(ExpressionError::TypeMismatch('-')),
+            },
+            Expression::Binary(left, op, right) => {
+                let (left, right) = (left.evaluate(lookup)?, right.evaluate(lookup)?);
+                if *op == '+' && (left.is_string() || right.is_string()) {
+                    return Ok(Value::String(format!("{}{}", text(&left)?, text(&right)?)));
+                }
+
+                let (Some(a), Some(b)) = (left.as_f64(), right.as_f64()) else {
+                    return Err(ExpressionError::TypeMismatch(*op));
+                };
+                match op {
+                    '+' => number(a + b),
+                    '-' => number(a - b),
+                    '*' => number(a * b),
+                    '/' if b == 0.0 => Err(ExpressionError::DivisionByZero),
+                    '/' => number(a / b),
+                    _ => Err(ExpressionError::TypeMismatch(*op)),
+                }
+            }
+        }
+    }
+}
+
+/// Whole results are stored as JSON integers so `2 + 3` reads back as `5`
+///
+/// JSON has no infinity or NaN, so those are errors rather than `null`.
+fn number(n: f64) -> Result<Value, ExpressionError> {
+    if !n.is_finite() {
+        Err(ExpressionError::NonFinite)
+    } else if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Ok(Value::from(n as i64))
+    } else {
+        Ok(Value::from(n))
+    }
+}
+
+fn text(value: &Value) -> Result<String, ExpressionError> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err(ExpressionError::TypeMismatch('+')),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Name(String),
+    Op(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' | '(' | ')' => {
+                tokens.push(Token::Op(c));
+                chars.next();
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => text.push(c),
+                        None => {
+                            return Err(ExpressionError::Parse("unterminated string".to_string()));
+                        }
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    digits.push(c);
+                    chars.next();
+                }
+                let n = digits
+                    .parse()
+                    .map_err(|_| ExpressionError::Parse(format!("invalid number '{digits}'")))?;
+                tokens.push(Token::Number(n));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|c| c.is_alphanumeric() || **c == '_' || **c == '.')
+                {
+                    name.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Name(name));
+            }
+            _ => {
+                return Err(ExpressionError::Parse(format!(
+                    "unexpected character '{c}'"
+                )));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Nesting of the expression being parsed, bounded by [`MAX_EXPRESSION_DEPTH`]
+    depth: usize,
+}
+
+impl Parser {
+    fn descend(&mut self) -> Result<(), ExpressionError> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(ExpressionError::Parse(format!(
+                "expression nests deeper than {MAX_EXPRESSION_DEPTH}"
+            )));
+        }
+        Ok(())
+    }
+
+    fn next_op(&mut self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                self.position += 1;
+                Some(*op)
+            }
+            _ => None,
+        }
+    }
+
+    // Each operator in a chain deepens the tree, so it counts as a level
+    fn sum(&mut self) -> Result<Expression, ExpressionError> {
+        let depth = self.depth;
+        let mut expression = self.product()?;
+        while let Some(op) = self.next_op(&['+', '-']) {
+            self.descend()?;
+            expression = Expression::Binary(Box::new(expression), op, Box::new(self.product()?));
+        }
+        self.depth = depth;
+        Ok(expression)
+    }
+
+    fn product(&mut self) -> Result<Expression, ExpressionError> {
+        let depth = self.depth;
+        let mut expression = self.unary()?;
+        while let Some(op) = self.next_op(&['*', '/']) {
+            self.descend()?;
+            expression = Expression::Binary(Box::new(expression), op, Box::new(self.unary()?));
+        }
+        self.depth = depth;
+        Ok(expression)
+    }
+
+    fn unary(&mut self) -> Result<Expression, ExpressionError> {
+        if self.next_op(&['-']).is_some() {
+            self.descend()?;
+            let inner = self.unary()?;
+            self.depth -= 1;
+            return Ok(Expression::Negate(Box::new(inner)));
+        }
+
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        match token {
+            Some(Token::Number(n)) => Ok(Expression::Number(n)),
+            Some(Token::Text(text)) => Ok(Expression::Text(text)),
+            Some(Token::Name(name)) => Ok(Expression::Variable(name)),
+            Some(Token::Op('(')) => {
+                self.descend()?;
+                let expression = self.sum()?;
+                self.depth -= 1;
+                match self.next_op(&[')']) {
+                    Some(_) => Ok(expression),
+                    None => Err(ExpressionError::Parse("missing ')'".to_string())),
+                }
+            }
+            Some(token) => Err(ExpressionError::Parse(format!("unexpected {token:?}"))),
+            None => Err(ExpressionError::Parse(
+                "unexpected end of expression".to_string(),
+            )),
+        }
+    }
+}
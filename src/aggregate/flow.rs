@@ -0,0 +1,169 @@
+//! Conversation flow specifications
+//!
+//! A dialog's phase is read from the `phase` property of its turns (see
+//! [`PHASE_PROPERTY`]). A [`FlowSpec`] lists the phases a scripted dialog may
+//! start in and the phases allowed to follow each one, so a dialog's phase
+//! transitions can be audited against the script.
+//!
+//! The typed phase set with `Dialog::set_phase` is tracked separately and is
+//! not checked against a flow.
+//!
+//! [`PHASE_PROPERTY`]: crate::value_objects::PHASE_PROPERTY
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+/// A change of phase between consecutive phased turns
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTransition {
+    /// Phase before the transition (None for the first phase entered)
+    pub from: Option<String>,
+    pub to: String,
+    /// Turn that entered the new phase
+    pub turn_id: Uuid,
+}
+
+/// How a dialog's phase transitions break a flow specification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlowViolation {
+    /// The dialog started in a phase the flow does not start with
+    InvalidStart { phase: String, turn_id: Uuid },
+    /// The phase is not part of the flow
+    UnknownPhase { phase: String, turn_id: Uuid },
+    /// The transition jumps ahead, skipping the listed phases
+    SkippedPhases {
+        from: String,
+        to: String,
+        skipped: Vec<String>,
+        turn_id: Uuid,
+    },
+    /// The transition is not allowed and does not lead forward in the flow
+    OutOfOrder {
+        from: String,
+        to: String,
+        turn_id: Uuid,
+    },
+}
+
+/// Allowed phases and phase transitions of a scripted dialog
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlowSpec {
+    /// Phases a dialog may start in
+    pub start: Vec<String>,
+    /// Phases allowed to directly follow each phase
+    pub transitions: HashMap<String, Vec<String>>,
+}
+
+impl FlowSpec {
+    /// A flow that must pass through `phases` in order
+    pub fn linear(phases: &[&str]) -> Self {
+        let mut spec = Self {
+            start: phases.first().map(|p| p.to_string()).into_iter().collect(),
+            transitions: HashMap::new(),
+        };
+        for pair in phases.windows(2) {
+            spec = spec.allow(pair[0], pair[1]);
+        }
+        for phase in phases {
+            spec.transitions.entry(phase.to_string()).or_default();
+        }
+        spec
+    }
+
+    /// Also allow `to` to directly follow `from`
+    pub fn allow(mut self, from: &str, to: &str) -> Self {
+        self.transitions.entry(to.to_string()).or_default();
+        let next = self.transitions.entry(from.to_string()).or_default();
+        if !next.iter().any(|p| p == to) {
+            next.push(to.to_string());
+        }
+        self
+    }
+
+    /// Whether `phase` is part of the flow
+    pub fn contains(&self, phase: &str) -> bool {
+        self.transitions.contains_key(phase) || self.start.iter().any(|p| p == phase)
+    }
+
+    /// Check phase transitions against the flow
+    pub fn check(&self, transitions: &[PhaseTransition]) -> Vec<FlowViolation> {
+        let mut violations = Vec::new();
+
+        for transition in transitions {
+            let to = &transition.to;
+            let turn_id = transition.turn_id;
+            if !self.contains(to) {
+                violations.push(FlowViolation::UnknownPhase {
+                    phase: to.clone(),
+                    turn_id,
+                });
+                continue;
+            }
+
+            let Some(from) = &transition.from else {
+                if !self.start.contains(to) {
+                    violations.push(FlowViolation::InvalidStart {
+                        phase: to.clone(),
+                        turn_id,
+                    });
+                }
+                continue;
+            };
+            if !self.contains(from)
+                || self
+                    .transitions
+                    .get(from)
+                    .is_some_and(|next| next.contains(to))
+            {
+                // Leaving an unknown phase was already reported on entry
+                continue;
+            }
+
+            violations.push(match self.shortest_path(from, to) {
+                Some(skipped) => FlowViolation::SkippedPhases {
+                    from: from.clone(),
+                    to: to.clone(),
+                    skipped,
+                    turn_id,
+                },
+                None => FlowViolation::OutOfOrder {
+                    from: from.clone(),
+                    to: to.clone(),
+                    turn_id,
+                },
+            });
+        }
+
+        violations
+    }
+
+    /// Phases strictly between `from` and `to` on the shortest allowed path
+    fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(phase) = queue.pop_front() {
+            for next in self.transitions.get(phase).into_iter().flatten() {
+                if !seen.insert(next.as_str()) {
+                    continue;
+                }
+                previous.insert(next, phase);
+                if next == to {
+                    let mut skipped = Vec::new();
+                    let mut current = phase;
+                    while current != from {
+                        skipped.push(current.to_string());
+                        current = previous[current];
+                    }
+                    skipped.reverse();
+                    return Some(skipped);
+                }
+                queue.push_back(next);
+            }
+        }
+
+        None
+    }
+}
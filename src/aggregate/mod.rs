@@ -6,16 +6,242 @@
 //! - Context and state management
 //! - Topic tracking and relevance
 
-use chrono::{DateTime, Utc};
-use cim_domain::{AggregateRoot, DomainError, DomainEvent, DomainResult, Entity, EntityId};
+use chrono::{DateTime, Datelike, Timelike, Utc, Weekday};
+use cim_domain::{AggregateRoot, DomainError, DomainResult, Entity, EntityId};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use thiserror::Error;
+use tracing::warn;
 use uuid::Uuid;
 
+use crate::events::{
+    ContextStateChanged, ContextUpdated, ContextVariablesExpired, DialogAbandoned,
+    DialogDomainEvent, DialogLocked, DialogMetadataSet, DialogUnlocked, EmbeddingAttached,
+    MetricsUpdated, ParticipantRemoved, PhaseChanged, ResolutionSet, ResolutionTurnMarked,
+    ScheduledTurnRejected, TopicCompleted, TopicsRelated, TopicsUnrelated, TurnEdited, TurnFlagged,
+    TurnPinned, TurnRetracted, TurnScheduled, TurnUnpinned, TurnsArchived,
+};
 use crate::value_objects::{
-    ContextVariable, ContextScope, ConversationMetrics, Participant, Topic, TopicStatus, Turn,
+    CLOCK_SKEW_PROPERTY, ContextScope, ContextVariable, ConversationMetrics, DefaultSanitizer,
+    EMBEDDING_NORM_TOLERANCE, EndReason, EngagementMetrics, Message, MessageContent, MessageIntent,
+    MetricsDelta, PHASE_PROPERTY, Participant, ParticipantType, ResolutionOutcome, Sanitizer,
+    Topic, TopicStatus, Turn, TurnType, cosine_similarity, embedding_norm, is_normalized,
+    normalize_embedding,
 };
-use crate::events::{DialogMetadataSet, ContextUpdated, ParticipantRemoved, TopicCompleted};
+
+pub mod expression;
+pub mod flow;
+mod replay;
+
+pub use expression::{ComputedVariable, Expression, ExpressionError, MAX_EXPRESSION_DEPTH};
+pub use flow::{FlowSpec, FlowViolation, PhaseTransition};
+
+/// Default maximum number of pinned turns per dialog
+pub const DEFAULT_MAX_PINNED_TURNS: usize = 5;
+
+/// Errors from validating the references of a turn
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TurnReferenceError {
+    /// A reference points at a turn that is not in the dialog
+    #[error("turn {turn_id} references unknown turn {reference}")]
+    DanglingReference { turn_id: Uuid, reference: Uuid },
+}
+
+/// A topic cannot be completed before its required subtopics
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("topic {topic_id} has incomplete required subtopics: {incomplete:?}")]
+pub struct IncompleteSubtopicsError {
+    pub topic_id: Uuid,
+    /// Required subtopics that are not completed (or not in the dialog)
+    pub incomplete: Vec<Uuid>,
+}
+
+/// Errors from dialog operations that report more than a [`DomainError`] can
+///
+/// Returned by the mutators that validate turn references or subtopics, so
+/// callers can match on the IDs involved instead of parsing a message.
+#[derive(Debug, Error)]
+pub enum DialogError {
+    #[error(transparent)]
+    Domain(#[from] DomainError),
+    #[error(transparent)]
+    TurnReference(#[from] TurnReferenceError),
+    #[error(transparent)]
+    IncompleteSubtopics(#[from] IncompleteSubtopicsError),
+}
+
+/// Result of a dialog operation that can fail with a [`DialogError`]
+pub type DialogResult<T> = Result<T, DialogError>;
+
+/// Number of most recent turns included in a handoff briefing
+pub const HANDOFF_RECENT_TURNS: usize = 5;
+
+/// How many turns back a reference may point and still count towards coherence
+pub const COHERENCE_REFERENCE_WINDOW: usize = 3;
+
+/// An open topic as seen by an agent taking over a dialog
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopicBriefing {
+    pub topic_id: Uuid,
+    pub name: String,
+    pub status: TopicStatus,
+    /// Relevance after decay at the time of the briefing
+    pub relevance: f32,
+}
+
+/// Everything an agent needs to pick up a dialog from another agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandoffBriefing {
+    pub dialog_id: Uuid,
+    pub status: DialogStatus,
+    /// Whether the context is normal, awaiting clarification, and so on
+    pub context_state: ContextState,
+    /// Conversation phase set with [`Dialog::set_phase`], independent of the context state
+    #[serde(default)]
+    pub phase: Option<ConversationPhase>,
+    pub current_topic: Option<Uuid>,
+    /// Active and paused topics, most relevant first
+    pub open_topics: Vec<TopicBriefing>,
+    /// The last few turns, oldest first
+    pub recent_turns: Vec<Turn>,
+    /// Questions no other participant has answered yet, oldest first
+    pub unanswered_questions: Vec<Turn>,
+    /// Dialog- and global-scoped context variables, by name
+    pub variables: Vec<ContextVariable>,
+}
+
+/// Everything one participant contributed to a dialog, for data access requests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParticipantExport {
+    pub dialog_id: Uuid,
+    pub participant_id: Uuid,
+    /// Participant record (None if they have left the dialog)
+    pub participant: Option<Participant>,
+    /// Their archived and live turns, oldest first
+    pub turns: Vec<Turn>,
+    /// Context variables they set, by name
+    pub variables: Vec<ContextVariable>,
+}
+
+/// How `add_turn` treats a turn timestamped before the previous turn
+///
+/// Such turns are usually caused by clock skew between services. Whatever the
+/// policy, accepted skewed turns record the skew under
+/// [`CLOCK_SKEW_PROPERTY`](crate::value_objects::CLOCK_SKEW_PROPERTY).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClockSkewPolicy {
+    /// Refuse the turn
+    Reject,
+    /// Move the timestamp forward to the previous turn's and log a warning (the default)
+    #[default]
+    Clamp,
+    /// Keep the timestamp as given
+    Allow,
+}
+
+/// How embeddings attached to turns and topics are brought to unit length
+///
+/// Cosine similarity between embeddings from mixed sources is only
+/// meaningful when they are all normalized. Norms within
+/// [`EMBEDDING_NORM_TOLERANCE`](crate::value_objects::EMBEDDING_NORM_TOLERANCE)
+/// of 1.0 are accepted as they are; embeddings with zero magnitude are always
+/// refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmbeddingNormalization {
+    /// Scale other embeddings to unit length
+    Normalize,
+    /// Refuse other embeddings
+    Reject,
+}
+
+/// At most `max_turns` turns within any `window`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Turns allowed per window
+    pub max_turns: usize,
+    /// Length of the sliding window, measured on turn timestamps
+    pub window: std::time::Duration,
+}
+
+/// Per-dialog settings, supplied at creation and adjustable through the setters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DialogConfig {
+    /// Maximum number of pinned turns
+    pub max_pinned_turns: usize,
+    /// Whether turn references must point at existing turns
+    pub validate_references: bool,
+    /// Handling of turns timestamped before the previous turn
+    pub clock_skew_policy: ClockSkewPolicy,
+    /// Fraction of turns allowed outside the dominant language (None disables the check)
+    pub max_foreign_language_fraction: Option<f32>,
+    /// Maximum estimated tokens across all turns
+    pub token_budget: Option<usize>,
+    /// Maximum turn rate
+    pub rate_limit: Option<RateLimit>,
+    /// Time without turns after which the dialog counts as idle and
+    /// `check_inactivity` abandons it by default
+    pub idle_timeout: Option<std::time::Duration>,
+    /// Maximum number of current participants, including the primary
+    pub max_participants: Option<usize>,
+    /// Language every turn must be in
+    pub enforced_language: Option<String>,
+    /// Whether `add_turn` passes message content through the dialog's sanitizer
+    pub sanitize_content: bool,
+    /// Agent turns with a confidence below this put the context into
+    /// `AwaitingClarification`
+    pub clarification_confidence_threshold: Option<f32>,
+    /// Lowest turn number accepted by `add_turns` (e.g. 0 for 0-based transcripts)
+    pub turn_number_base: u32,
+    /// Normalization required of turn and topic embeddings (None accepts any)
+    pub require_normalized_embeddings: Option<EmbeddingNormalization>,
+}
+
+impl Default for DialogConfig {
+    fn default() -> Self {
+        Self {
+            max_pinned_turns: DEFAULT_MAX_PINNED_TURNS,
+            validate_references: true,
+            clock_skew_policy: ClockSkewPolicy::default(),
+            max_foreign_language_fraction: None,
+            token_budget: None,
+            rate_limit: None,
+            idle_timeout: None,
+            max_participants: None,
+            enforced_language: None,
+            sanitize_content: false,
+            clarification_confidence_threshold: None,
+            turn_number_base: 1,
+            require_normalized_embeddings: None,
+        }
+    }
+}
+
+/// Non-fatal issue found while validating a dialog
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ValidationWarning {
+    /// Too many turns differ from the dialog's dominant language
+    MixedLanguage {
+        dominant: String,
+        /// Turns in another language, in conversation order
+        differing_turns: Vec<Uuid>,
+        /// Fraction of turns in another language
+        fraction: f32,
+    },
+}
+
+/// Result of [`Dialog::validate`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub warnings: Vec<ValidationWarning>,
+}
+
+impl ValidationReport {
+    /// Whether validation found nothing to warn about
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
 
 /// Marker type for Dialog entities
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -45,6 +271,12 @@ pub struct Dialog {
     /// Turns in the conversation
     turns: Vec<Turn>,
 
+    /// Turns moved out of the live conversation
+    archived_turns: Vec<Turn>,
+
+    /// Turns queued for future delivery, ordered by delivery time
+    scheduled: Vec<(DateTime<Utc>, Turn)>,
+
     /// Active topics
     topics: HashMap<Uuid, Topic>,
 
@@ -57,8 +289,38 @@ pub struct Dialog {
     /// Dialog metadata
     metadata: HashMap<String, serde_json::Value>,
 
+    /// Whether new turns are blocked
+    locked: bool,
+
+    /// Whether the user's issue was resolved
+    resolution: Option<ResolutionOutcome>,
+
+    /// Per-dialog settings
+    config: DialogConfig,
+
+    /// Sanitizer applied to new turns when `config.sanitize_content` is set
+    sanitizer: Arc<dyn Sanitizer>,
+
+    /// Pinned turns in pin order
+    pinned_turns: Vec<Uuid>,
+
+    /// Current conversation phase
+    phase: Option<ConversationPhase>,
+
+    /// Turn that resolved the user's issue
+    resolution_turn: Option<Uuid>,
+
+    /// Agent responses averaged into `metrics.avg_response_time_ms`
+    response_time_samples: u32,
+
+    /// When the dialog started
+    started_at: DateTime<Utc>,
+
     /// Version for optimistic concurrency
     version: u64,
+
+    /// State-changing events applied, checked against `version` in debug builds
+    applied_changes: u64,
 }
 
 /// Types of dialogs
@@ -92,14 +354,20 @@ pub enum DialogStatus {
 }
 
 /// Conversation context management
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConversationContext {
     /// Current context state
     pub state: ContextState,
 
-    /// Context variables
+    /// Context variables (most recently set value per name)
     pub variables: HashMap<String, ContextVariable>,
 
+    /// Context variables keyed by (source, name)
+    pub namespaced: HashMap<(Uuid, String), ContextVariable>,
+
+    /// Variables recomputed from expressions by `recompute_derived`, keyed by name
+    pub computed: HashMap<String, ComputedVariable>,
+
     /// Context history (for backtracking)
     pub history: Vec<ContextSnapshot>,
 
@@ -120,8 +388,24 @@ pub enum ContextState {
     Error,
 }
 
+/// Stage of a scripted conversation, e.g. a support flow
+///
+/// Independent of [`ContextState`]: a dialog in any phase may be awaiting
+/// clarification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConversationPhase {
+    /// Opening exchange
+    Greeting,
+    /// Working out what the user needs
+    Triage,
+    /// Addressing the need
+    Resolution,
+    /// Wrapping up
+    Closing,
+}
+
 /// Snapshot of context at a point in time
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContextSnapshot {
     /// When snapshot was taken
     pub timestamp: DateTime<Utc>,
@@ -134,8 +418,23 @@ pub struct ContextSnapshot {
 }
 
 impl Dialog {
-    /// Create a new dialog
+    /// Create a new dialog with the default configuration
     pub fn new(id: Uuid, dialog_type: DialogType, primary_participant: Participant) -> Self {
+        Self::with_config(
+            id,
+            dialog_type,
+            primary_participant,
+            DialogConfig::default(),
+        )
+    }
+
+    /// Create a new dialog with the given configuration
+    pub fn with_config(
+        id: Uuid,
+        dialog_type: DialogType,
+        primary_participant: Participant,
+        config: DialogConfig,
+    ) -> Self {
         let mut participants = HashMap::new();
         participants.insert(primary_participant.id, primary_participant.clone());
 
@@ -148,6 +447,8 @@ impl Dialog {
             context: ConversationContext {
                 state: ContextState::Normal,
                 variables: HashMap::new(),
+                namespaced: HashMap::new(),
+                computed: HashMap::new(),
                 history: Vec::new(),
                 max_history: 10,
             },
@@ -163,7 +464,19 @@ impl Dialog {
                 coherence_score: 1.0,
             },
             metadata: HashMap::new(),
+            archived_turns: Vec::new(),
+            scheduled: Vec::new(),
+            locked: false,
+            resolution: None,
+            config,
+            sanitizer: Arc::new(DefaultSanitizer),
+            pinned_turns: Vec::new(),
+            phase: None,
+            resolution_turn: None,
+            response_time_samples: 0,
+            started_at: Utc::now(),
             version: 0,
+            applied_changes: 0,
         }
     }
 
@@ -172,6 +485,11 @@ impl Dialog {
         *self.entity.id.as_uuid()
     }
 
+    /// Get the dialog's configuration
+    pub fn config(&self) -> &DialogConfig {
+        &self.config
+    }
+
     /// Get the dialog type
     pub fn dialog_type(&self) -> DialogType {
         self.dialog_type
@@ -182,6 +500,22 @@ impl Dialog {
         self.status
     }
 
+    /// When the dialog started
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    /// Timestamp of the latest live or archived turn, or the start time if
+    /// there are no turns yet
+    pub fn last_activity(&self) -> DateTime<Utc> {
+        self.turns
+            .iter()
+            .chain(&self.archived_turns)
+            .map(|t| t.timestamp)
+            .max()
+            .unwrap_or(self.started_at)
+    }
+
     /// Get participants
     pub fn participants(&self) -> &HashMap<Uuid, Participant> {
         &self.participants
@@ -202,6 +536,26 @@ impl Dialog {
         self.current_topic.and_then(|id| self.topics.get(&id))
     }
 
+    /// Get a topic by ID
+    pub fn topic(&self, topic_id: Uuid) -> Option<&Topic> {
+        self.topics.get(&topic_id)
+    }
+
+    /// Get every topic ever discussed, whatever its status, oldest first
+    pub fn all_topics(&self) -> Vec<&Topic> {
+        let mut topics: Vec<&Topic> = self.topics.values().collect();
+        topics.sort_by_key(|t| (t.introduced_at, t.id));
+        topics
+    }
+
+    /// Get the topics with the given status, oldest first
+    pub fn topics_by_status(&self, status: TopicStatus) -> Vec<&Topic> {
+        self.all_topics()
+            .into_iter()
+            .filter(|t| t.status == status)
+            .collect()
+    }
+
     /// Get primary participant ID
     pub fn primary_participant(&self) -> Uuid {
         self.primary_participant
@@ -212,11 +566,108 @@ impl Dialog {
         &self.metadata
     }
 
+    /// Get the context variable a specific source set under `name`
+    pub fn get_variable_from(&self, source: Uuid, name: &str) -> Option<&ContextVariable> {
+        self.context.get_from(source, name)
+    }
+
+    /// Resolve a context variable, preferring `source`'s own value over the most recent one
+    pub fn resolve_variable(&self, source: Uuid, name: &str) -> Option<&ContextVariable> {
+        self.context.resolve(source, name)
+    }
+
+    /// Get conversation metrics
+    pub fn metrics(&self) -> &ConversationMetrics {
+        &self.metrics
+    }
+
+    /// Derive the conversation metrics from the live and archived turns
+    ///
+    /// The sentiment trend is the least-squares slope of sentiment per turn
+    /// across the turns that carry one (0.0 with fewer than two). Response
+    /// time, topic switches and clarifications are kept from the running
+    /// metrics, which `add_turn` and `switch_topic` maintain; clarifications
+    /// count both `Clarification` turns and low-confidence replies that moved
+    /// the context to `AwaitingClarification`. Coherence comes from
+    /// [`Dialog::coherence_score`].
+    pub fn compute_metrics(&self) -> ConversationMetrics {
+        let turns: Vec<&Turn> = self.archived_turns.iter().chain(&self.turns).collect();
+
+        let sentiments: Vec<(f32, f32)> = turns
+            .iter()
+            .enumerate()
+            .filter_map(|(i, t)| t.message.sentiment.map(|s| (i as f32, s)))
+            .collect();
+        let sentiment_trend = if sentiments.len() < 2 {
+            0.0
+        } else {
+            let n = sentiments.len() as f32;
+            let mean_x = sentiments.iter().map(|(x, _)| x).sum::<f32>() / n;
+            let mean_y = sentiments.iter().map(|(_, y)| y).sum::<f32>() / n;
+            let covariance: f32 = sentiments
+                .iter()
+                .map(|(x, y)| (x - mean_x) * (y - mean_y))
+                .sum();
+            let variance: f32 = sentiments.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+            covariance / variance
+        };
+
+        ConversationMetrics {
+            turn_count: turns.len() as u32,
+            avg_response_time_ms: self.metrics.avg_response_time_ms,
+            topic_switches: self.metrics.topic_switches,
+            clarification_count: self.metrics.clarification_count,
+            sentiment_trend,
+            coherence_score: self.coherence_score(),
+        }
+    }
+
+    /// Score how well the conversation holds together, from 0.0 to 1.0
+    ///
+    /// With `n` live and archived turns, `s` topic switches after the opening
+    /// topic and `r` the fraction of turns referencing one of the
+    /// [`COHERENCE_REFERENCE_WINDOW`] turns before them:
+    ///
+    /// ```text
+    /// switch_rate = min(s / max(n - 1, 1), 1)
+    /// coherence   = 1 - switch_rate * (1 - r / 2)
+    /// ```
+    ///
+    /// A dialog that stays on one topic scores 1.0; one that switches topic
+    /// on every turn scores 0.0, or up to 0.5 if its turns keep referring back
+    /// to what was just said.
+    pub fn coherence_score(&self) -> f32 {
+        let turns: Vec<&Turn> = self.archived_turns.iter().chain(&self.turns).collect();
+        let n = turns.len();
+        if n == 0 {
+            return 1.0;
+        }
+
+        // The first switch only sets the opening topic
+        let switches = self.metrics.topic_switches.saturating_sub(1) as f32;
+        let switch_rate = (switches / n.saturating_sub(1).max(1) as f32).min(1.0);
+
+        let referencing = turns
+            .iter()
+            .enumerate()
+            .filter(|(i, turn)| {
+                let recent = &turns[i.saturating_sub(COHERENCE_REFERENCE_WINDOW)..*i];
+                turn.metadata
+                    .references
+                    .iter()
+                    .any(|id| recent.iter().any(|t| t.turn_id == *id))
+            })
+            .count();
+        let reference_rate = referencing as f32 / n as f32;
+
+        (1.0 - switch_rate * (1.0 - reference_rate / 2.0)).clamp(0.0, 1.0)
+    }
+
     /// Add a participant to the dialog
     pub fn add_participant(
         &mut self,
         participant: Participant,
-    ) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Active {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -230,54 +681,161 @@ impl Dialog {
             ));
         }
 
-        self.participants
-            .insert(participant.id, participant.clone());
-        self.entity.touch();
-        self.version += 1;
+        if let Some(max) = self.config.max_participants
+            && self.participants.len() >= max
+        {
+            return Err(DomainError::ValidationError(format!(
+                "Dialog already has the maximum of {max} participants"
+            )));
+        }
 
         let event = crate::events::ParticipantAdded {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             participant,
             added_at: Utc::now(),
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![
+            self.record(DialogDomainEvent::ParticipantAdded(event)),
+        ])
     }
 
     /// Add a turn to the conversation
-    pub fn add_turn(&mut self, turn: Turn) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    ///
+    /// An agent response directly after a user query counts towards the
+    /// running average response time in the metrics.
+    pub fn add_turn(&mut self, mut turn: Turn) -> DialogResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Active {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
                 to: "Active (required for adding turns)".to_string(),
-            });
+            }
+            .into());
+        }
+
+        if self.locked {
+            return Err(DomainError::ValidationError("Dialog is locked".to_string()).into());
         }
 
         if !self.participants.contains_key(&turn.participant_id) {
-            return Err(DomainError::ValidationError(
-                "Participant not in dialog".to_string(),
-            ));
+            return Err(
+                DomainError::ValidationError("Participant not in dialog".to_string()).into(),
+            );
         }
 
-        // Update metrics
-        self.metrics.turn_count += 1;
+        if self.config.sanitize_content {
+            turn.message.content = self.sanitizer.sanitize(&turn.message.content);
+        }
 
-        // Add turn
-        self.turns.push(turn.clone());
-        self.entity.touch();
-        self.version += 1;
+        if self.config.validate_references {
+            self.check_references(&turn)?;
+        }
+
+        if let Some(embedding) = turn.message.embeddings.as_mut() {
+            self.check_embedding(embedding)?;
+        }
+        self.check_limits(&turn)?;
+        self.normalize_timestamp(&mut turn)?;
 
+        let metrics_before = self.metrics.clone();
+        let needs_clarification = self.needs_clarification(&turn);
         let event = crate::events::TurnAdded {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             turn,
-            turn_number: self.metrics.turn_count,
+            turn_number: self.metrics.turn_count + 1,
+        };
+
+        let mut events = vec![self.record(DialogDomainEvent::TurnAdded(event))];
+        if needs_clarification {
+            let event = ContextStateChanged {
+                event_id: Uuid::new_v4(),
+                dialog_id: self.id(),
+                previous_state: self.context.state,
+                new_state: ContextState::AwaitingClarification,
+                changed_at: Utc::now(),
+            };
+            events.push(self.record(DialogDomainEvent::ContextStateChanged(event)));
+        }
+        if let Some(metrics_updated) = self.metrics_updated(&metrics_before) {
+            events.push(self.record(DialogDomainEvent::MetricsUpdated(metrics_updated)));
+        }
+        Ok(events)
+    }
+
+    /// Whether `turn` is an agent turn below the clarification confidence
+    /// threshold while the context is not already awaiting clarification
+    fn needs_clarification(&self, turn: &Turn) -> bool {
+        let Some(threshold) = self.config.clarification_confidence_threshold else {
+            return false;
         };
+        let from_agent = self
+            .participants
+            .get(&turn.participant_id)
+            .is_some_and(|p| p.participant_type == ParticipantType::AIAgent);
+
+        from_agent
+            && self.context.state != ContextState::AwaitingClarification
+            && turn.metadata.confidence.is_some_and(|c| c < threshold)
+    }
+
+    /// Add a batch of turns, such as an imported transcript, keeping their
+    /// supplied turn numbers
+    ///
+    /// Numbers start at or above `turn_number_base` and may skip values, but
+    /// must increase from turn to turn and past the dialog's last turn. The
+    /// batch is added all or nothing. Metrics and `TurnAdded::turn_number`
+    /// count turns from one whatever the supplied numbering.
+    pub fn add_turns(&mut self, turns: Vec<Turn>) -> DialogResult<Vec<DialogDomainEvent>> {
+        let mut previous = self.turns.last().map(|t| t.turn_number);
+        for turn in &turns {
+            if turn.turn_number < self.config.turn_number_base {
+                return Err(DomainError::ValidationError(format!(
+                    "Turn number {} is below the base of {}",
+                    turn.turn_number, self.config.turn_number_base
+                ))
+                .into());
+            }
+            if let Some(previous) = previous
+                && turn.turn_number <= previous
+            {
+                return Err(DomainError::ValidationError(format!(
+                    "Turn number {} does not increase from {}",
+                    turn.turn_number, previous
+                ))
+                .into());
+            }
+            previous = Some(turn.turn_number);
+        }
+
+        let mut staged = self.clone();
+        let mut events = Vec::new();
+        for turn in turns {
+            events.extend(staged.add_turn(turn)?);
+        }
+        *self = staged;
+
+        Ok(events)
+    }
 
-        Ok(vec![Box::new(event)])
+    /// Build a `MetricsUpdated` event if metrics changed since `before`
+    ///
+    /// The event accompanies the event that changed the metrics and does not
+    /// advance the version on its own.
+    pub(crate) fn metrics_updated(&self, before: &ConversationMetrics) -> Option<MetricsUpdated> {
+        let delta = MetricsDelta::between(before, &self.metrics);
+        (!delta.is_zero()).then(|| MetricsUpdated {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            metrics: self.metrics.clone(),
+            delta,
+            updated_at: Utc::now(),
+        })
     }
 
     /// Switch to a new topic
-    pub fn switch_topic(&mut self, topic: Topic) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    pub fn switch_topic(&mut self, mut topic: Topic) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Active {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -285,39 +843,26 @@ impl Dialog {
             });
         }
 
-        // Mark current topic as paused if exists
-        if let Some(current_id) = self.current_topic {
-            if let Some(current) = self.topics.get_mut(&current_id) {
-                current.status = TopicStatus::Paused;
-            }
+        if let Some(embedding) = topic.embedding.as_mut() {
+            self.check_embedding(embedding)?;
         }
 
-        // Add new topic
-        let topic_id = topic.id;
-        self.topics.insert(topic_id, topic.clone());
-        self.current_topic = Some(topic_id);
-
-        // Update metrics
-        self.metrics.topic_switches += 1;
-
-        self.entity.touch();
-        self.version += 1;
-
         let event = crate::events::ContextSwitched {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             previous_topic: self.current_topic,
             new_topic: topic,
             switched_at: Utc::now(),
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![self.record(DialogDomainEvent::ContextSwitched(event))])
     }
 
     /// Add a context variable
     pub fn add_context_variable(
         &mut self,
         variable: ContextVariable,
-    ) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status == DialogStatus::Ended || self.status == DialogStatus::Abandoned {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -325,23 +870,63 @@ impl Dialog {
             });
         }
 
-        self.context
-            .variables
-            .insert(variable.name.clone(), variable.clone());
-        self.entity.touch();
-        self.version += 1;
+        // A value that has already expired would only be pruned again
+        if variable.is_expired(Utc::now()) {
+            return Ok(vec![]);
+        }
 
         let event = crate::events::ContextVariableAdded {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             variable,
             added_at: Utc::now(),
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![
+            self.record(DialogDomainEvent::ContextVariableAdded(event)),
+        ])
+    }
+
+    /// Remove the context variables that have expired by `now`
+    ///
+    /// Returns the removed names, sorted, and records a
+    /// `ContextVariablesExpired` event if there were any. Expired values other
+    /// sources set under those names are removed as well. The event is not
+    /// returned, so callers that persist or publish events should use
+    /// [`expire_context`](Self::expire_context) instead.
+    pub fn prune_expired_context(&mut self, now: DateTime<Utc>) -> Vec<String> {
+        match self.expire_context(now) {
+            Some(DialogDomainEvent::ContextVariablesExpired(e)) => e.names,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Remove the context variables that have expired by `now`, returning the
+    /// recorded `ContextVariablesExpired` event if there were any
+    pub fn expire_context(&mut self, now: DateTime<Utc>) -> Option<DialogDomainEvent> {
+        let mut names: Vec<String> = self
+            .context
+            .variables
+            .values()
+            .filter(|v| v.is_expired(now))
+            .map(|v| v.name.clone())
+            .collect();
+        if names.is_empty() {
+            return None;
+        }
+        names.sort();
+
+        let event = ContextVariablesExpired {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            names,
+            expired_at: now,
+        };
+        Some(self.record(DialogDomainEvent::ContextVariablesExpired(event)))
     }
 
     /// Pause the dialog
-    pub fn pause(&mut self) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    pub fn pause(&mut self) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Active {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -349,34 +934,19 @@ impl Dialog {
             });
         }
 
-        // Take context snapshot
-        let snapshot = ContextSnapshot {
-            timestamp: Utc::now(),
-            turn_number: self.metrics.turn_count,
-            active_topic: self.current_topic,
-            variables: self.context.variables.clone(),
-        };
-
-        self.context.history.push(snapshot);
-        if self.context.history.len() > self.context.max_history {
-            self.context.history.remove(0);
-        }
-
-        self.status = DialogStatus::Paused;
-        self.entity.touch();
-        self.version += 1;
-
+        // Applying the event takes the context snapshot
         let event = crate::events::DialogPaused {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             paused_at: Utc::now(),
             context_snapshot: self.context.variables.clone(),
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![self.record(DialogDomainEvent::DialogPaused(event))])
     }
 
     /// Resume the dialog
-    pub fn resume(&mut self) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    pub fn resume(&mut self) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Paused {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -384,20 +954,45 @@ impl Dialog {
             });
         }
 
-        self.status = DialogStatus::Active;
-        self.entity.touch();
-        self.version += 1;
+        let event = crate::events::DialogResumed {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            resumed_at: Utc::now(),
+            restored_variables: None,
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::DialogResumed(event))])
+    }
+
+    /// Resume the dialog, restoring the context taken when it was paused
+    ///
+    /// The most recent snapshot is removed from the context history and its
+    /// variables and active topic replace the current ones, discarding any
+    /// changes made while paused. With no snapshot this is a plain `resume`.
+    pub fn resume_restoring(&mut self) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.status != DialogStatus::Paused {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active".to_string(),
+            });
+        }
+
+        let Some(restored_variables) = self.context.snapshot_variable_names() else {
+            return self.resume();
+        };
 
         let event = crate::events::DialogResumed {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             resumed_at: Utc::now(),
+            restored_variables: Some(restored_variables),
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![self.record(DialogDomainEvent::DialogResumed(event))])
     }
 
     /// End the dialog
-    pub fn end(&mut self, reason: Option<String>) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    pub fn end(&mut self, reason: Option<EndReason>) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status == DialogStatus::Ended || self.status == DialogStatus::Abandoned {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -405,18 +1000,35 @@ impl Dialog {
             });
         }
 
-        self.status = DialogStatus::Ended;
-        self.entity.touch();
-        self.version += 1;
-
         let event = crate::events::DialogEnded {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             ended_at: Utc::now(),
             reason,
-            final_metrics: self.metrics.clone(),
+            final_metrics: self.compute_metrics(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::DialogEnded(event))])
+    }
+
+    /// Abandon the dialog, e.g. after the user walked away, rather than
+    /// ending it gracefully
+    pub fn abandon(&mut self, reason: Option<String>) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.status != DialogStatus::Active && self.status != DialogStatus::Paused {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Abandoned".to_string(),
+            });
+        }
+
+        let event = DialogAbandoned {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            abandoned_at: Utc::now(),
+            reason,
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![self.record(DialogDomainEvent::DialogAbandoned(event))])
     }
 }
 
@@ -432,8 +1044,9 @@ impl AggregateRoot for Dialog {
     }
 
     fn increment_version(&mut self) {
-        self.version += 1;
-        self.entity.touch();
+        // Counted as a change so the version check in `apply` still holds
+        self.applied_changes += 1;
+        self.bump();
     }
 }
 
@@ -442,12 +1055,121 @@ impl Default for ConversationContext {
         Self {
             state: ContextState::Normal,
             variables: HashMap::new(),
+            namespaced: HashMap::new(),
+            computed: HashMap::new(),
             history: Vec::new(),
             max_history: 10,
         }
     }
 }
 
+impl ConversationContext {
+    /// Set a variable under its source's namespace
+    ///
+    /// The unqualified `variables` entry for the name is replaced only if this
+    /// value is at least as recent as the one already there.
+    pub fn set_variable(&mut self, variable: ContextVariable) {
+        let is_latest = self
+            .variables
+            .get(&variable.name)
+            .is_none_or(|existing| variable.set_at >= existing.set_at);
+        if is_latest {
+            self.variables
+                .insert(variable.name.clone(), variable.clone());
+        }
+        self.namespaced
+            .insert((variable.source, variable.name.clone()), variable);
+    }
+
+    /// Names of the variables in the most recent snapshot, sorted, or `None`
+    /// if there is no snapshot
+    pub fn snapshot_variable_names(&self) -> Option<Vec<String>> {
+        let snapshot = self.history.last()?;
+        let mut names: Vec<String> = snapshot.variables.keys().cloned().collect();
+        names.sort();
+        Some(names)
+    }
+
+    /// Get the variable a specific source set under `name`
+    pub fn get_from(&self, source: Uuid, name: &str) -> Option<&ContextVariable> {
+        self.namespaced.get(&(source, name.to_string()))
+    }
+
+    /// Resolve `name` for `source`: its own value first, then the most recent from any source
+    pub fn resolve(&self, source: Uuid, name: &str) -> Option<&ContextVariable> {
+        self.get_from(source, name)
+            .or_else(|| self.variables.get(name))
+    }
+
+    /// Define (or replace) a computed variable
+    ///
+    /// Its value is set the next time `recompute_derived` runs.
+    pub fn define_computed(&mut self, variable: ComputedVariable) {
+        self.computed.insert(variable.name.clone(), variable);
+    }
+
+    /// Re-evaluate every computed variable and store the results
+    ///
+    /// Computed variables may reference each other. Results are stored as
+    /// ordinary variables with a nil source; a variable whose expression fails
+    /// (unknown name, wrong type, cycle) keeps its previous value.
+    pub fn recompute_derived(&mut self) {
+        let mut results = HashMap::new();
+        // Every outcome, including failures, is collected in `results`
+        for name in self.computed.keys() {
+            let _ = self.evaluate_computed(name, &mut results, &mut Vec::new());
+        }
+
+        let now = Utc::now();
+        for (name, result) in results {
+            match result {
+                Ok(value) => {
+                    let scope = self.computed[&name].scope;
+                    self.set_variable(ContextVariable {
+                        name,
+                        value,
+                        scope,
+                        set_at: now,
+                        expires_at: None,
+                        source: Uuid::nil(),
+                    });
+                }
+                Err(err) => warn!("Could not recompute context variable '{}': {}", name, err),
+            }
+        }
+    }
+
+    fn evaluate_computed(
+        &self,
+        name: &str,
+        results: &mut HashMap<String, Result<serde_json::Value, ExpressionError>>,
+        evaluating: &mut Vec<String>,
+    ) -> Result<serde_json::Value, ExpressionError> {
+        if let Some(result) = results.get(name) {
+            return result.clone();
+        }
+        if evaluating.iter().any(|n| n == name) {
+            return Err(ExpressionError::Cycle(name.to_string()));
+        }
+
+        evaluating.push(name.to_string());
+        let result = self.computed[name].expression.evaluate(&mut |var| {
+            if self.computed.contains_key(var) {
+                self.evaluate_computed(var, results, evaluating)
+            } else {
+                self.variables
+                    .get(var)
+                    .map(|v| v.value.clone())
+                    .ok_or_else(|| ExpressionError::UnknownVariable(var.to_string()))
+            }
+        });
+        evaluating.pop();
+
+        results.insert(name.to_string(), result.clone());
+        result
+    }
+}
+
 impl Clone for Dialog {
     fn clone(&self) -> Self {
         Self {
@@ -462,12 +1184,81 @@ impl Clone for Dialog {
             current_topic: self.current_topic,
             metrics: self.metrics.clone(),
             metadata: self.metadata.clone(),
+            archived_turns: self.archived_turns.clone(),
+            scheduled: self.scheduled.clone(),
+            locked: self.locked,
+            resolution: self.resolution,
+            config: self.config.clone(),
+            sanitizer: self.sanitizer.clone(),
+            pinned_turns: self.pinned_turns.clone(),
+            phase: self.phase,
+            resolution_turn: self.resolution_turn,
+            response_time_samples: self.response_time_samples,
+            started_at: self.started_at,
             version: self.version,
+            applied_changes: self.applied_changes,
         }
     }
 }
 
+/// Dialogs are equal when their domain state and version match
+///
+/// Entity timestamps, the start time, configuration and the sanitizer are not
+/// compared, so a dialog rebuilt from its events equals the one that emitted
+/// them.
+impl PartialEq for Dialog {
+    fn eq(&self, other: &Self) -> bool {
+        self.entity.id == other.entity.id
+            && self.dialog_type == other.dialog_type
+            && self.status == other.status
+            && self.participants == other.participants
+            && self.primary_participant == other.primary_participant
+            && self.context == other.context
+            && self.turns == other.turns
+            && self.archived_turns == other.archived_turns
+            && self.scheduled == other.scheduled
+            && self.topics == other.topics
+            && self.current_topic == other.current_topic
+            && self.metrics == other.metrics
+            && self.metadata == other.metadata
+            && self.locked == other.locked
+            && self.resolution == other.resolution
+            && self.pinned_turns == other.pinned_turns
+            && self.phase == other.phase
+            && self.resolution_turn == other.resolution_turn
+            && self.response_time_samples == other.response_time_samples
+            && self.version == other.version
+    }
+}
+
 impl Dialog {
+    /// Record a state change: touch the entity and advance the version
+    ///
+    /// Outside `increment_version`, only `apply` calls this, once per event it
+    /// folds in, so the version always equals
+    /// [`Dialog::expected_version_from_events`] for the dialog's events.
+    fn bump(&mut self) {
+        self.entity.touch();
+        self.version += 1;
+    }
+
+    /// Version a dialog should have after the given events
+    ///
+    /// `DialogStarted` creates the dialog at version 0 and `MetricsUpdated`
+    /// only reports the effect of the event it accompanies; every other event
+    /// is a state change that advances the version by one.
+    pub fn expected_version_from_events(events: &[DialogDomainEvent]) -> u64 {
+        events.iter().filter(|e| Self::changes_state(e)).count() as u64
+    }
+
+    /// Whether applying `event` advances the version
+    fn changes_state(event: &DialogDomainEvent) -> bool {
+        !matches!(
+            event,
+            DialogDomainEvent::DialogStarted(_) | DialogDomainEvent::MetricsUpdated(_)
+        )
+    }
+
     /// Check if the dialog has ended
     pub fn is_ended(&self) -> bool {
         matches!(self.status, DialogStatus::Ended | DialogStatus::Abandoned)
@@ -483,7 +1274,7 @@ impl Dialog {
         &mut self,
         key: String,
         value: serde_json::Value,
-    ) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status == DialogStatus::Ended || self.status == DialogStatus::Abandoned {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -491,25 +1282,24 @@ impl Dialog {
             });
         }
 
-        self.metadata.insert(key.clone(), value.clone());
-        self.entity.touch();
-        self.version += 1;
-
         let event = DialogMetadataSet {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             key,
             value,
             set_at: Utc::now(),
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![
+            self.record(DialogDomainEvent::DialogMetadataSet(event)),
+        ])
     }
 
     /// Update context variables in bulk
     pub fn update_context(
         &mut self,
         variables: HashMap<String, serde_json::Value>,
-    ) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Active {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -517,29 +1307,15 @@ impl Dialog {
             });
         }
 
-        // Update context variables
-        for (key, value) in &variables {
-            let var = ContextVariable {
-                name: key.clone(),
-                value: value.clone(),
-                scope: ContextScope::Dialog,
-                set_at: Utc::now(),
-                expires_at: None,
-                source: self.id(), // Use dialog ID as source
-            };
-            self.context.variables.insert(key.clone(), var);
-        }
-
-        self.entity.touch();
-        self.version += 1;
-
+        // Applying the event sets each variable in dialog scope
         let event = ContextUpdated {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             updated_variables: variables,
             updated_at: Utc::now(),
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![self.record(DialogDomainEvent::ContextUpdated(event))])
     }
 
     /// Remove a participant from the dialog
@@ -547,7 +1323,7 @@ impl Dialog {
         &mut self,
         participant_id: Uuid,
         reason: Option<String>,
-    ) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Active {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
@@ -570,31 +1346,36 @@ impl Dialog {
             });
         }
 
-        self.participants.remove(&participant_id);
-        self.entity.touch();
-        self.version += 1;
-
         let event = ParticipantRemoved {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             participant_id,
             removed_at: Utc::now(),
             reason,
         };
 
-        Ok(vec![Box::new(event)])
+        Ok(vec![
+            self.record(DialogDomainEvent::ParticipantRemoved(event)),
+        ])
     }
 
     /// Mark a topic as complete
+    ///
+    /// Completing a topic with incomplete required subtopics is rejected
+    /// unless `cascade` is set, in which case those subtopics (and theirs) are
+    /// completed first, each with its own `TopicCompleted` event.
     pub fn mark_topic_complete(
         &mut self,
         topic_id: Uuid,
         resolution: Option<String>,
-    ) -> DomainResult<Vec<Box<dyn DomainEvent>>> {
+        cascade: bool,
+    ) -> DialogResult<Vec<DialogDomainEvent>> {
         if self.status != DialogStatus::Active {
             return Err(DomainError::InvalidStateTransition {
                 from: format!("{:?}", self.status),
                 to: "Active (required for completing topics)".to_string(),
-            });
+            }
+            .into());
         }
 
         // Check topic exists
@@ -602,24 +1383,1383 @@ impl Dialog {
             return Err(DomainError::EntityNotFound {
                 entity_type: "Topic".to_string(),
                 id: topic_id.to_string(),
-            });
+            }
+            .into());
         }
 
-        // Mark topic as complete by updating its status
-        if let Some(topic) = self.topics.get_mut(&topic_id) {
-            topic.status = TopicStatus::Completed;
+        let subtopics = if cascade {
+            let pending = self.pending_required_subtopics(topic_id);
+            if let Some(missing) = pending.iter().find(|id| !self.topics.contains_key(id)) {
+                return Err(DomainError::EntityNotFound {
+                    entity_type: "Topic".to_string(),
+                    id: missing.to_string(),
+                }
+                .into());
+            }
+            pending
+        } else {
+            self.check_subtopics(topic_id)?;
+            Vec::new()
+        };
+
+        let mut events: Vec<DialogDomainEvent> = Vec::new();
+        for subtopic_id in subtopics {
+            events.push(self.complete_topic(subtopic_id, None));
         }
+        events.push(self.complete_topic(topic_id, resolution));
 
-        self.entity.touch();
-        self.version += 1;
+        Ok(events)
+    }
 
+    fn complete_topic(&mut self, topic_id: Uuid, resolution: Option<String>) -> DialogDomainEvent {
         let event = TopicCompleted {
+            event_id: Uuid::new_v4(),
             dialog_id: self.id(),
             topic_id,
             completed_at: Utc::now(),
             resolution,
         };
 
-        Ok(vec![Box::new(event)])
+        self.record(DialogDomainEvent::TopicCompleted(event))
+    }
+
+    /// Check that every required subtopic of a topic is completed
+    pub fn check_subtopics(&self, topic_id: Uuid) -> Result<(), IncompleteSubtopicsError> {
+        let incomplete: Vec<Uuid> = self
+            .topics
+            .get(&topic_id)
+            .into_iter()
+            .flat_map(|t| &t.subtopics)
+            .filter(|link| link.required && !self.is_topic_completed(link.topic_id))
+            .map(|link| link.topic_id)
+            .collect();
+
+        if incomplete.is_empty() {
+            Ok(())
+        } else {
+            Err(IncompleteSubtopicsError {
+                topic_id,
+                incomplete,
+            })
+        }
+    }
+
+    /// Incomplete required subtopics of a topic, including theirs, in the
+    /// order a cascading completion completes them (deepest first)
+    pub fn pending_required_subtopics(&self, topic_id: Uuid) -> Vec<Uuid> {
+        let mut visited = HashSet::from([topic_id]);
+        let mut pending = Vec::new();
+        self.collect_pending_subtopics(topic_id, &mut visited, &mut pending);
+        pending
+    }
+
+    fn collect_pending_subtopics(
+        &self,
+        topic_id: Uuid,
+        visited: &mut HashSet<Uuid>,
+        pending: &mut Vec<Uuid>,
+    ) {
+        let Some(topic) = self.topics.get(&topic_id) else {
+            return;
+        };
+        for link in topic.subtopics.iter().filter(|link| link.required) {
+            if self.is_topic_completed(link.topic_id) || !visited.insert(link.topic_id) {
+                continue;
+            }
+            self.collect_pending_subtopics(link.topic_id, visited, pending);
+            pending.push(link.topic_id);
+        }
+    }
+
+    fn is_topic_completed(&self, topic_id: Uuid) -> bool {
+        self.topics
+            .get(&topic_id)
+            .is_some_and(|t| t.status == TopicStatus::Completed)
+    }
+
+    /// Average sentiment with outliers trimmed
+    ///
+    /// Collects the sentiment of every turn that carries one, sorts the values
+    /// and discards `trim_fraction` of the samples from each end (rounded down)
+    /// before averaging, so a single extreme turn cannot dominate the result.
+    /// `trim_fraction` is clamped to `[0.0, 0.49]`. Returns `None` when no samples
+    /// remain after trimming.
+    pub fn robust_sentiment(&self, trim_fraction: f32) -> Option<f32> {
+        let mut samples: Vec<f32> = self
+            .turns
+            .iter()
+            .filter_map(|turn| turn.message.sentiment)
+            .collect();
+        samples.sort_by(|a, b| a.total_cmp(b));
+
+        let trim_fraction = trim_fraction.clamp(0.0, 0.49);
+        let trimmed = (samples.len() as f32 * trim_fraction).floor() as usize;
+        let kept = &samples[trimmed..samples.len() - trimmed];
+
+        if kept.is_empty() {
+            return None;
+        }
+
+        Some(kept.iter().sum::<f32>() / kept.len() as f32)
+    }
+
+    /// Get pinned turns in pin order
+    pub fn pinned_turns(&self) -> Vec<&Turn> {
+        self.pinned_turns
+            .iter()
+            .filter_map(|id| self.turns.iter().find(|t| t.turn_id == *id))
+            .collect()
+    }
+
+    /// Set the maximum number of pinned turns
+    pub fn set_max_pinned_turns(&mut self, max_pinned_turns: usize) {
+        self.config.max_pinned_turns = max_pinned_turns;
+    }
+
+    /// Flag a turn for review, recording the reason in its `flagged` property
+    pub fn flag_turn(
+        &mut self,
+        turn_id: Uuid,
+        reason: String,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        if !self.turns.iter().any(|t| t.turn_id == turn_id) {
+            return Err(DomainError::EntityNotFound {
+                entity_type: "Turn".to_string(),
+                id: turn_id.to_string(),
+            });
+        }
+
+        let event = TurnFlagged {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_id,
+            reason,
+            flagged_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TurnFlagged(event))])
+    }
+
+    /// Pin a turn
+    pub fn pin_turn(&mut self, turn_id: Uuid) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.status == DialogStatus::Ended || self.status == DialogStatus::Abandoned {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for pinning turns)".to_string(),
+            });
+        }
+
+        if !self.turns.iter().any(|t| t.turn_id == turn_id) {
+            return Err(DomainError::EntityNotFound {
+                entity_type: "Turn".to_string(),
+                id: turn_id.to_string(),
+            });
+        }
+
+        if self.pinned_turns.contains(&turn_id) {
+            return Err(DomainError::ValidationError(
+                "Turn already pinned".to_string(),
+            ));
+        }
+
+        if self.pinned_turns.len() >= self.config.max_pinned_turns {
+            return Err(DomainError::ValidationError(format!(
+                "Cannot pin more than {} turns",
+                self.config.max_pinned_turns
+            )));
+        }
+
+        let event = TurnPinned {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_id,
+            pinned_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TurnPinned(event))])
+    }
+
+    /// Unpin a turn
+    pub fn unpin_turn(&mut self, turn_id: Uuid) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.status == DialogStatus::Ended || self.status == DialogStatus::Abandoned {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for unpinning turns)".to_string(),
+            });
+        }
+
+        if !self.pinned_turns.contains(&turn_id) {
+            return Err(DomainError::ValidationError(
+                "Turn is not pinned".to_string(),
+            ));
+        }
+
+        let event = TurnUnpinned {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_id,
+            unpinned_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TurnUnpinned(event))])
+    }
+
+    /// Get archived turns
+    pub fn archived_turns(&self) -> &[Turn] {
+        &self.archived_turns
+    }
+
+    /// Retract a turn, removing it from the conversation
+    pub fn retract_turn(
+        &mut self,
+        turn_id: Uuid,
+        reason: Option<String>,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.is_ended() {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for retracting turns)".to_string(),
+            });
+        }
+
+        let known = self
+            .turns
+            .iter()
+            .chain(&self.archived_turns)
+            .any(|t| t.turn_id == turn_id);
+        if !known {
+            return Err(DomainError::EntityNotFound {
+                entity_type: "Turn".to_string(),
+                id: turn_id.to_string(),
+            });
+        }
+
+        let event = TurnRetracted {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_id,
+            retracted_at: Utc::now(),
+            reason,
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TurnRetracted(event))])
+    }
+
+    /// Replace the message of a live or archived turn, e.g. once a streamed
+    /// response settles on its final text
+    pub fn edit_turn(
+        &mut self,
+        turn_id: Uuid,
+        mut new_message: Message,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.is_ended() {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for editing turns)".to_string(),
+            });
+        }
+
+        let known = self
+            .turns
+            .iter()
+            .chain(&self.archived_turns)
+            .any(|t| t.turn_id == turn_id);
+        if !known {
+            return Err(DomainError::EntityNotFound {
+                entity_type: "Turn".to_string(),
+                id: turn_id.to_string(),
+            });
+        }
+
+        if self.config.sanitize_content {
+            new_message.content = self.sanitizer.sanitize(&new_message.content);
+        }
+
+        let event = TurnEdited {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_id,
+            new_message,
+            edited_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TurnEdited(event))])
+    }
+
+    /// Archive all live turns numbered below `before_turn_number`
+    ///
+    /// Archived turns still count towards `metrics.turn_count` but are no
+    /// longer part of the live conversation, so they are also unpinned.
+    pub fn archive_turns(
+        &mut self,
+        before_turn_number: u32,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.is_ended() {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for archiving turns)".to_string(),
+            });
+        }
+
+        let turn_ids: Vec<Uuid> = self
+            .turns
+            .iter()
+            .filter(|t| t.turn_number < before_turn_number)
+            .map(|t| t.turn_id)
+            .collect();
+
+        if turn_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let event = TurnsArchived {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_ids,
+            archived_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TurnsArchived(event))])
+    }
+
+    /// Check internal consistency of the aggregate
+    pub fn validate_invariants(&self) -> DomainResult<()> {
+        let stored_turns = self.turns.len() + self.archived_turns.len();
+        if self.metrics.turn_count as usize != stored_turns {
+            return Err(DomainError::ValidationError(format!(
+                "metrics.turn_count is {} but dialog holds {} turns ({} archived)",
+                self.metrics.turn_count,
+                stored_turns,
+                self.archived_turns.len()
+            )));
+        }
+
+        if let Some(id) = self
+            .pinned_turns
+            .iter()
+            .find(|id| !self.turns.iter().any(|t| t.turn_id == **id))
+        {
+            return Err(DomainError::ValidationError(format!(
+                "Pinned turn {id} is not a live turn"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Recompute `metrics.turn_count` from the live and archived turns
+    pub fn reconcile_turn_count(&mut self) {
+        self.metrics.turn_count = (self.turns.len() + self.archived_turns.len()) as u32;
+    }
+
+    /// Check whether the dialog is locked against new turns
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Lock the dialog so no new turns can be added, without changing its status
+    pub fn lock(&mut self, reason: Option<String>) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.is_ended() {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for locking)".to_string(),
+            });
+        }
+
+        if self.locked {
+            return Err(DomainError::ValidationError(
+                "Dialog already locked".to_string(),
+            ));
+        }
+
+        let event = DialogLocked {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            locked_at: Utc::now(),
+            reason,
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::DialogLocked(event))])
+    }
+
+    /// Unlock the dialog so turns can be added again
+    pub fn unlock(&mut self) -> DomainResult<Vec<DialogDomainEvent>> {
+        if !self.locked {
+            return Err(DomainError::ValidationError(
+                "Dialog is not locked".to_string(),
+            ));
+        }
+
+        let event = DialogUnlocked {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            unlocked_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::DialogUnlocked(event))])
+    }
+
+    /// Relate two topics in both directions
+    ///
+    /// Relating topics that are already related is a no-op and emits no events.
+    pub fn relate_topics(&mut self, a: Uuid, b: Uuid) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.check_topic_pair(a, b)?;
+
+        let changed = [(a, b), (b, a)].iter().any(|(from, to)| {
+            self.topics
+                .get(from)
+                .is_some_and(|topic| !topic.related_topics.contains(to))
+        });
+
+        if !changed {
+            return Ok(vec![]);
+        }
+
+        let event = TopicsRelated {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            topic_a: a,
+            topic_b: b,
+            related_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TopicsRelated(event))])
+    }
+
+    /// Remove the relation between two topics in both directions
+    ///
+    /// Unrelating topics that are not related is a no-op and emits no events.
+    pub fn unrelate_topics(&mut self, a: Uuid, b: Uuid) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.check_topic_pair(a, b)?;
+
+        let changed = [(a, b), (b, a)].iter().any(|(from, to)| {
+            self.topics
+                .get(from)
+                .is_some_and(|topic| topic.related_topics.contains(to))
+        });
+
+        if !changed {
+            return Ok(vec![]);
+        }
+
+        let event = TopicsUnrelated {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            topic_a: a,
+            topic_b: b,
+            unrelated_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TopicsUnrelated(event))])
+    }
+
+    fn check_topic_pair(&self, a: Uuid, b: Uuid) -> DomainResult<()> {
+        if self.is_ended() {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for relating topics)".to_string(),
+            });
+        }
+
+        if a == b {
+            return Err(DomainError::ValidationError(
+                "A topic cannot be related to itself".to_string(),
+            ));
+        }
+
+        for id in [a, b] {
+            if !self.topics.contains_key(&id) {
+                return Err(DomainError::EntityNotFound {
+                    entity_type: "Topic".to_string(),
+                    id: id.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enable or disable reference validation in `add_turn`
+    ///
+    /// Bulk imports that replay turns out of order can disable validation and
+    /// re-enable it once the import is complete.
+    pub fn set_reference_validation(&mut self, enabled: bool) {
+        self.config.validate_references = enabled;
+    }
+
+    /// Check that every reference of a turn points at a live or archived turn
+    pub fn check_references(&self, turn: &Turn) -> Result<(), TurnReferenceError> {
+        let known = |id: &Uuid| {
+            self.turns
+                .iter()
+                .chain(&self.archived_turns)
+                .any(|t| t.turn_id == *id)
+        };
+
+        match turn.metadata.references.iter().find(|id| !known(id)) {
+            Some(reference) => Err(TurnReferenceError::DanglingReference {
+                turn_id: turn.turn_id,
+                reference: *reference,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Participant a turn replies to: the author of the most recent turn it
+    /// references
+    ///
+    /// Returns `None` if the turn is unknown, has no references, or the
+    /// author is no longer a participant.
+    pub fn reply_target_participant(&self, turn_id: Uuid) -> Option<&Participant> {
+        let all_turns = || self.archived_turns.iter().chain(&self.turns);
+        let turn = all_turns().find(|t| t.turn_id == turn_id)?;
+        let target = all_turns()
+            .filter(|t| turn.metadata.references.contains(&t.turn_id))
+            .max_by_key(|t| t.timestamp)?;
+
+        self.participants.get(&target.participant_id)
+    }
+
+    /// Drift of each turn away from the current topic
+    ///
+    /// Returns `(turn_id, 1.0 - cosine_similarity)` for every turn whose
+    /// embedding is comparable with the current topic's embedding. Empty when
+    /// there is no current topic or it has no embedding.
+    pub fn topic_drift_series(&self) -> Vec<(Uuid, f32)> {
+        let Some(topic_embedding) = self.current_topic().and_then(|t| t.embedding.as_deref())
+        else {
+            return Vec::new();
+        };
+
+        self.turns
+            .iter()
+            .filter_map(|turn| {
+                let embedding = turn.message.embeddings.as_deref()?;
+                let similarity = cosine_similarity(embedding, topic_embedding)?;
+                Some((turn.turn_id, 1.0 - similarity))
+            })
+            .collect()
+    }
+
+    /// Largest drift away from the current topic across all turns
+    pub fn max_drift(&self) -> Option<f32> {
+        self.topic_drift_series()
+            .into_iter()
+            .map(|(_, drift)| drift)
+            .max_by(f32::total_cmp)
+    }
+
+    /// How much semantic space the conversation covered
+    ///
+    /// Average pairwise cosine distance (`1.0 - cosine_similarity`) between
+    /// the embeddings of the turns: low for a focused dialog, high for a
+    /// wide-ranging one. Pairs whose embeddings are not comparable are
+    /// skipped. Returns `None` when no pair of embedded turns is comparable.
+    pub fn semantic_spread(&self) -> Option<f32> {
+        let embeddings: Vec<&[f32]> = self
+            .turns
+            .iter()
+            .filter_map(|t| t.message.embeddings.as_deref())
+            .collect();
+
+        let distances: Vec<f32> = embeddings
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| embeddings[i + 1..].iter().map(move |b| (*a, *b)))
+            .filter_map(|(a, b)| cosine_similarity(a, b).map(|s| 1.0 - s))
+            .collect();
+        if distances.is_empty() {
+            return None;
+        }
+
+        Some(distances.iter().sum::<f32>() / distances.len() as f32)
+    }
+
+    /// How bursty a participant's contributions are
+    ///
+    /// Computes the coefficient of variation (standard deviation over mean) of
+    /// the intervals between the participant's consecutive turns: near 0.0 for
+    /// a steady cadence, higher when turns arrive in bursts separated by long
+    /// pauses. Returns `None` with fewer than three turns or when all turns
+    /// share a timestamp.
+    pub fn burstiness(&self, participant_id: Uuid) -> Option<f32> {
+        let mut timestamps: Vec<DateTime<Utc>> = self
+            .turns
+            .iter()
+            .filter(|t| t.participant_id == participant_id)
+            .map(|t| t.timestamp)
+            .collect();
+        if timestamps.len() < 3 {
+            return None;
+        }
+        timestamps.sort();
+
+        let intervals: Vec<f64> = timestamps
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).num_milliseconds() as f64)
+            .collect();
+        let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+        if mean <= 0.0 {
+            return None;
+        }
+
+        let variance =
+            intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+        Some((variance.sqrt() / mean) as f32)
+    }
+
+    /// Summarize a participant's engagement across live and archived turns
+    ///
+    /// Response latency is measured from the previous turn when it came from
+    /// someone else (0.0 if the participant never answered anyone), and the
+    /// engagement score is the participant's share of all turns. A topic
+    /// counts as initiated by whoever took the first turn after it was
+    /// introduced. Burstiness comes from [`Dialog::burstiness`].
+    pub fn engagement_metrics(&self, participant_id: Uuid) -> EngagementMetrics {
+        let turns: Vec<&Turn> = self.archived_turns.iter().chain(&self.turns).collect();
+        let own: Vec<&Turn> = turns
+            .iter()
+            .copied()
+            .filter(|t| t.participant_id == participant_id)
+            .collect();
+
+        let avg_message_length = if own.is_empty() {
+            0.0
+        } else {
+            own.iter()
+                .map(|t| t.message.content.to_searchable_string().chars().count() as f64)
+                .sum::<f64>()
+                / own.len() as f64
+        };
+
+        let latencies: Vec<f64> = turns
+            .windows(2)
+            .filter(|pair| {
+                pair[1].participant_id == participant_id && pair[0].participant_id != participant_id
+            })
+            .map(|pair| {
+                (pair[1].timestamp - pair[0].timestamp)
+                    .num_milliseconds()
+                    .max(0) as f64
+            })
+            .collect();
+        let avg_response_latency_ms = if latencies.is_empty() {
+            0.0
+        } else {
+            latencies.iter().sum::<f64>() / latencies.len() as f64
+        };
+
+        let topics_initiated = self
+            .topics
+            .values()
+            .filter(|topic| {
+                turns
+                    .iter()
+                    .filter(|t| t.timestamp >= topic.introduced_at)
+                    .min_by_key(|t| t.timestamp)
+                    .is_some_and(|t| t.participant_id == participant_id)
+            })
+            .count() as u32;
+
+        EngagementMetrics {
+            participant_id,
+            turn_contributions: own.len() as u32,
+            avg_message_length,
+            avg_response_latency_ms,
+            engagement_score: if turns.is_empty() {
+                0.0
+            } else {
+                own.len() as f32 / turns.len() as f32
+            },
+            topics_initiated,
+            burstiness: self.burstiness(participant_id),
+        }
+    }
+
+    /// Count a participant's turns by weekday and hour of day (0-23, UTC)
+    ///
+    /// Covers live and archived turns; buckets without turns are absent.
+    pub fn activity_heatmap(&self, participant_id: Uuid) -> HashMap<(Weekday, u32), usize> {
+        let mut heatmap = HashMap::new();
+        for turn in self.archived_turns.iter().chain(&self.turns) {
+            if turn.participant_id == participant_id {
+                *heatmap
+                    .entry((turn.timestamp.weekday(), turn.timestamp.hour()))
+                    .or_insert(0) += 1;
+            }
+        }
+        heatmap
+    }
+
+    /// Detect an agent repeating itself verbatim
+    ///
+    /// Groups agent turns (by an AI agent participant or of type
+    /// `AgentResponse`) by speaker and identical message content, and returns
+    /// the ids of every turn in a group of at least `min_repeats` turns, in
+    /// conversation order. A `min_repeats` below two is treated as two.
+    pub fn has_repeated_responses(&self, min_repeats: usize) -> Option<Vec<Uuid>> {
+        let min_repeats = min_repeats.max(2);
+        let mut groups: Vec<(Uuid, &MessageContent, Vec<Uuid>)> = Vec::new();
+
+        for turn in &self.turns {
+            let by_agent = turn.metadata.turn_type == TurnType::AgentResponse
+                || self
+                    .participants
+                    .get(&turn.participant_id)
+                    .is_some_and(|p| p.participant_type == ParticipantType::AIAgent);
+            if !by_agent {
+                continue;
+            }
+
+            let content = &turn.message.content;
+            match groups
+                .iter_mut()
+                .find(|(speaker, c, _)| *speaker == turn.participant_id && *c == content)
+            {
+                Some((_, _, turn_ids)) => turn_ids.push(turn.turn_id),
+                None => groups.push((turn.participant_id, content, vec![turn.turn_id])),
+            }
+        }
+
+        let mut repeated: Vec<Uuid> = groups
+            .into_iter()
+            .filter(|(_, _, turn_ids)| turn_ids.len() >= min_repeats)
+            .flat_map(|(_, _, turn_ids)| turn_ids)
+            .collect();
+        if repeated.is_empty() {
+            return None;
+        }
+
+        repeated.sort_by_key(|id| self.turns.iter().position(|t| t.turn_id == *id));
+        Some(repeated)
+    }
+
+    /// Whether the user's issue was resolved, if recorded
+    pub fn resolution(&self) -> Option<ResolutionOutcome> {
+        self.resolution
+    }
+
+    /// Record whether the user's issue was resolved
+    ///
+    /// Allowed in any status, including after the dialog has ended, and may be
+    /// changed later as the outcome becomes clearer.
+    pub fn set_resolution(
+        &mut self,
+        resolution: ResolutionOutcome,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        let event = ResolutionSet {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            resolution,
+            set_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::ResolutionSet(event))])
+    }
+
+    /// Turn marked as the one that resolved the user's issue
+    ///
+    /// Archived turns are included; a retracted turn is no longer marked.
+    pub fn resolution_turn(&self) -> Option<&Turn> {
+        let turn_id = self.resolution_turn?;
+        self.turns
+            .iter()
+            .chain(&self.archived_turns)
+            .find(|t| t.turn_id == turn_id)
+    }
+
+    /// Mark the turn that resolved the user's issue, replacing any earlier mark
+    ///
+    /// Like [`set_resolution`](Self::set_resolution), allowed in any status.
+    pub fn mark_resolution_turn(&mut self, turn_id: Uuid) -> DomainResult<Vec<DialogDomainEvent>> {
+        let known = self
+            .turns
+            .iter()
+            .chain(&self.archived_turns)
+            .any(|t| t.turn_id == turn_id);
+        if !known {
+            return Err(DomainError::EntityNotFound {
+                entity_type: "Turn".to_string(),
+                id: turn_id.to_string(),
+            });
+        }
+
+        let event = ResolutionTurnMarked {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_id,
+            marked_at: Utc::now(),
+        };
+
+        Ok(vec![
+            self.record(DialogDomainEvent::ResolutionTurnMarked(event)),
+        ])
+    }
+
+    /// Current conversation phase, if one has been set
+    pub fn phase(&self) -> Option<ConversationPhase> {
+        self.phase
+    }
+
+    /// Move the dialog into a conversation phase
+    ///
+    /// Setting the current phase again is a no-op and emits no events. Flow
+    /// validation does not see these changes; it only checks phases tagged on
+    /// turns (see [`phase_transitions`](Self::phase_transitions)).
+    pub fn set_phase(&mut self, phase: ConversationPhase) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.is_ended() {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for changing phase)".to_string(),
+            });
+        }
+
+        if self.phase == Some(phase) {
+            return Ok(vec![]);
+        }
+
+        let event = PhaseChanged {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            previous_phase: self.phase,
+            new_phase: phase,
+            changed_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::PhaseChanged(event))])
+    }
+
+    /// Most common language across the live turns
+    ///
+    /// Ties go to the language that appeared first. Returns `None` for a
+    /// dialog without turns.
+    pub fn dominant_language(&self) -> Option<String> {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for turn in &self.turns {
+            let language = turn.message.language.as_str();
+            match counts.iter_mut().find(|(l, _)| *l == language) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((language, 1)),
+            }
+        }
+
+        // max_by_key keeps the last maximum, so scan in reverse to favour the first
+        counts
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(language, _)| language.to_string())
+    }
+
+    /// Warn when more than `max_fraction` of turns differ from the dominant language
+    ///
+    /// Pass `None` to disable the check (the default).
+    pub fn set_language_consistency_check(&mut self, max_fraction: Option<f32>) {
+        self.config.max_foreign_language_fraction = max_fraction;
+    }
+
+    /// Phase changes across live and archived turns
+    ///
+    /// A turn's phase is its `PHASE_PROPERTY` metadata property; turns without
+    /// one stay in the current phase. Phases entered with
+    /// [`set_phase`](Self::set_phase) are not tagged on any turn and are not
+    /// included.
+    pub fn phase_transitions(&self) -> Vec<PhaseTransition> {
+        let mut transitions = Vec::new();
+        let mut current: Option<&str> = None;
+
+        for turn in self.archived_turns.iter().chain(&self.turns) {
+            let Some(phase) = turn
+                .metadata
+                .properties
+                .get(PHASE_PROPERTY)
+                .and_then(|p| p.as_str())
+            else {
+                continue;
+            };
+            if current != Some(phase) {
+                transitions.push(PhaseTransition {
+                    from: current.map(str::to_string),
+                    to: phase.to_string(),
+                    turn_id: turn.turn_id,
+                });
+                current = Some(phase);
+            }
+        }
+
+        transitions
+    }
+
+    /// Check the dialog's phase transitions against a flow specification
+    pub fn validate_flow(&self, spec: &FlowSpec) -> Result<(), Vec<FlowViolation>> {
+        let violations = spec.check(&self.phase_transitions());
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Run the enabled non-fatal checks
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        if let Some(max_fraction) = self.config.max_foreign_language_fraction
+            && let Some(dominant) = self.dominant_language()
+        {
+            let differing_turns: Vec<Uuid> = self
+                .turns
+                .iter()
+                .filter(|t| t.message.language != dominant)
+                .map(|t| t.turn_id)
+                .collect();
+            let fraction = differing_turns.len() as f32 / self.turns.len() as f32;
+            if fraction > max_fraction {
+                report.warnings.push(ValidationWarning::MixedLanguage {
+                    dominant,
+                    differing_turns,
+                    fraction,
+                });
+            }
+        }
+
+        report
+    }
+
+    /// Attach an embedding to a turn that lacks one, e.g. when backfilling
+    ///
+    /// Allowed in any status and on archived turns, since backfills target
+    /// historical conversations. Replaces any existing embedding.
+    pub fn attach_embedding(
+        &mut self,
+        turn_id: Uuid,
+        mut embeddings: Vec<f32>,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        if embeddings.is_empty() {
+            return Err(DomainError::ValidationError(
+                "Embedding must not be empty".to_string(),
+            ));
+        }
+        self.check_embedding(&mut embeddings)?;
+
+        let known = self
+            .turns
+            .iter()
+            .chain(&self.archived_turns)
+            .any(|t| t.turn_id == turn_id);
+        if !known {
+            return Err(DomainError::EntityNotFound {
+                entity_type: "Turn".to_string(),
+                id: turn_id.to_string(),
+            });
+        }
+
+        let event = EmbeddingAttached {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn_id,
+            embeddings,
+            attached_at: Utc::now(),
+        };
+
+        Ok(vec![
+            self.record(DialogDomainEvent::EmbeddingAttached(event)),
+        ])
+    }
+
+    /// Set how turns timestamped before the previous turn are handled
+    pub fn set_clock_skew_policy(&mut self, policy: ClockSkewPolicy) {
+        self.config.clock_skew_policy = policy;
+    }
+
+    /// Apply the embedding normalization requirement to an incoming embedding
+    fn check_embedding(&self, embedding: &mut [f32]) -> DomainResult<()> {
+        let Some(requirement) = self.config.require_normalized_embeddings else {
+            return Ok(());
+        };
+        if is_normalized(embedding) {
+            return Ok(());
+        }
+
+        let norm = embedding_norm(embedding);
+        if norm == 0.0 || !norm.is_finite() {
+            return Err(DomainError::ValidationError(format!(
+                "Embedding with norm {norm} cannot be normalized"
+            )));
+        }
+        match requirement {
+            EmbeddingNormalization::Normalize => normalize_embedding(embedding),
+            EmbeddingNormalization::Reject => {
+                return Err(DomainError::ValidationError(format!(
+                    "Embedding norm {norm} is not within {EMBEDDING_NORM_TOLERANCE} of 1.0"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the clock skew policy to a turn about to be added
+    fn normalize_timestamp(&self, turn: &mut Turn) -> DomainResult<()> {
+        let Some(previous) = self
+            .turns
+            .last()
+            .or_else(|| self.archived_turns.last())
+            .map(|t| t.timestamp)
+        else {
+            return Ok(());
+        };
+        if turn.timestamp >= previous {
+            return Ok(());
+        }
+
+        let skew_ms = (previous - turn.timestamp).num_milliseconds();
+        match self.config.clock_skew_policy {
+            ClockSkewPolicy::Reject => {
+                return Err(DomainError::ValidationError(format!(
+                    "Turn timestamp is {skew_ms}ms before the previous turn"
+                )));
+            }
+            ClockSkewPolicy::Clamp => {
+                warn!(
+                    "Clamping turn {} on dialog {}: timestamp {}ms before the previous turn",
+                    turn.turn_id,
+                    self.id(),
+                    skew_ms
+                );
+                turn.timestamp = previous;
+            }
+            ClockSkewPolicy::Allow => {}
+        }
+
+        turn.metadata
+            .properties
+            .insert(CLOCK_SKEW_PROPERTY.to_string(), serde_json::json!(skew_ms));
+        Ok(())
+    }
+
+    /// Summarize the dialog for an agent taking it over
+    ///
+    /// A question counts as answered once a later turn from another
+    /// participant either references it or carries the `Answer` intent.
+    pub fn handoff_briefing(&self) -> HandoffBriefing {
+        let mut open_topics: Vec<TopicBriefing> = self
+            .topics
+            .values()
+            .filter(|t| matches!(t.status, TopicStatus::Active | TopicStatus::Paused))
+            .map(|t| TopicBriefing {
+                topic_id: t.id,
+                name: t.name.clone(),
+                status: t.status,
+                relevance: t.current_relevance(),
+            })
+            .collect();
+        open_topics.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+
+        let recent_start = self.turns.len().saturating_sub(HANDOFF_RECENT_TURNS);
+
+        let unanswered_questions = self
+            .turns
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.message.intent == Some(MessageIntent::Question))
+            .filter(|(i, question)| {
+                !self.turns[i + 1..].iter().any(|later| {
+                    later.participant_id != question.participant_id
+                        && (later.metadata.references.contains(&question.turn_id)
+                            || later.message.intent == Some(MessageIntent::Answer))
+                })
+            })
+            .map(|(_, t)| t.clone())
+            .collect();
+
+        let mut variables: Vec<ContextVariable> = self
+            .context
+            .variables
+            .values()
+            .filter(|v| matches!(v.scope, ContextScope::Dialog | ContextScope::Global))
+            .cloned()
+            .collect();
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        HandoffBriefing {
+            dialog_id: self.id(),
+            status: self.status,
+            context_state: self.context.state,
+            phase: self.phase,
+            current_topic: self.current_topic,
+            open_topics,
+            recent_turns: self.turns[recent_start..].to_vec(),
+            unanswered_questions,
+            variables,
+        }
+    }
+
+    /// Turns queued for future delivery, earliest first
+    pub fn scheduled_turns(&self) -> &[(DateTime<Utc>, Turn)] {
+        &self.scheduled
+    }
+
+    /// Queue a turn to be added once `deliver_at` has passed
+    ///
+    /// The turn is not part of the conversation until released by
+    /// [`release_due_turns`](Self::release_due_turns).
+    pub fn schedule_turn(
+        &mut self,
+        turn: Turn,
+        deliver_at: DateTime<Utc>,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.is_ended() {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active/Paused (required for scheduling turns)".to_string(),
+            });
+        }
+
+        if !self.participants.contains_key(&turn.participant_id) {
+            return Err(DomainError::ValidationError(
+                "Participant not in dialog".to_string(),
+            ));
+        }
+
+        // Applying the event queues the turn after any due at the same time
+        let event = TurnScheduled {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            turn,
+            deliver_at,
+            scheduled_at: Utc::now(),
+        };
+
+        Ok(vec![self.record(DialogDomainEvent::TurnScheduled(event))])
+    }
+
+    /// Add every scheduled turn due at or before `now`, earliest first
+    ///
+    /// Released turns are timestamped with their delivery time and go through
+    /// [`add_turn`](Self::add_turn). A due turn that `add_turn` refuses is
+    /// taken off the queue with a `ScheduledTurnRejected` event so it cannot
+    /// hold up later turns. Nothing is released, and an error is returned,
+    /// while the dialog is not active or is locked.
+    pub fn release_due_turns(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        if self.status != DialogStatus::Active {
+            return Err(DomainError::InvalidStateTransition {
+                from: format!("{:?}", self.status),
+                to: "Active (required for releasing turns)".to_string(),
+            });
+        }
+
+        if self.locked {
+            return Err(DomainError::ValidationError("Dialog is locked".to_string()));
+        }
+
+        let mut events = Vec::new();
+
+        while let Some((deliver_at, turn)) = self.scheduled.first().cloned()
+            && deliver_at <= now
+        {
+            let turn_id = turn.turn_id;
+            let mut turn = turn;
+            turn.timestamp = deliver_at;
+            // Applying the turn's TurnAdded takes it off the queue
+            match self.add_turn(turn) {
+                Ok(added) => events.extend(added),
+                Err(e) => {
+                    let event = ScheduledTurnRejected {
+                        event_id: Uuid::new_v4(),
+                        dialog_id: self.id(),
+                        turn_id,
+                        reason: e.to_string(),
+                        rejected_at: now,
+                    };
+                    events.push(self.record(DialogDomainEvent::ScheduledTurnRejected(event)));
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Turn content sanitization in `add_turn` on or off
+    pub fn set_content_sanitization(&mut self, enabled: bool) {
+        self.config.sanitize_content = enabled;
+    }
+
+    /// Replace the sanitizer used when content sanitization is on
+    pub fn set_sanitizer(&mut self, sanitizer: impl Sanitizer + 'static) {
+        self.sanitizer = Arc::new(sanitizer);
+    }
+
+    /// Set the normalization required of turn and topic embeddings (None accepts any)
+    pub fn set_require_normalized_embeddings(
+        &mut self,
+        requirement: Option<EmbeddingNormalization>,
+    ) {
+        self.config.require_normalized_embeddings = requirement;
+    }
+
+    /// Set the lowest turn number accepted by `add_turns`
+    pub fn set_turn_number_base(&mut self, base: u32) {
+        self.config.turn_number_base = base;
+    }
+
+    /// Set the confidence below which agent turns trigger clarification
+    /// (None disables the check)
+    pub fn set_clarification_confidence_threshold(&mut self, threshold: Option<f32>) {
+        self.config.clarification_confidence_threshold = threshold;
+    }
+
+    /// Set the maximum estimated tokens across all turns (None removes the budget)
+    pub fn set_token_budget(&mut self, budget: Option<usize>) {
+        self.config.token_budget = budget;
+    }
+
+    /// Set the maximum turn rate (None removes the limit)
+    pub fn set_rate_limit(&mut self, limit: Option<RateLimit>) {
+        self.config.rate_limit = limit;
+    }
+
+    /// Set how long the dialog may go without turns before it counts as idle
+    pub fn set_idle_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.config.idle_timeout = timeout;
+    }
+
+    /// Set the maximum number of current participants (None removes the limit)
+    pub fn set_max_participants(&mut self, max: Option<usize>) {
+        self.config.max_participants = max;
+    }
+
+    /// Require every new turn to be in `language` (None allows any language)
+    pub fn set_enforced_language(&mut self, language: Option<String>) {
+        self.config.enforced_language = language;
+    }
+
+    /// Estimated tokens across live and archived turns
+    pub fn tokens_used(&self) -> usize {
+        self.turns
+            .iter()
+            .chain(&self.archived_turns)
+            .map(|t| t.message.content.estimated_tokens())
+            .sum()
+    }
+
+    /// Whether more than the idle timeout has passed since the
+    /// [last activity](Self::last_activity)
+    ///
+    /// Dialogs without an idle timeout are never idle.
+    pub fn is_idle(&self, now: DateTime<Utc>) -> bool {
+        self.config
+            .idle_timeout
+            .is_some_and(|timeout| self.inactive_for(now, timeout))
+    }
+
+    /// Whether the last activity is more than `timeout` before `now`
+    fn inactive_for(&self, now: DateTime<Utc>, timeout: std::time::Duration) -> bool {
+        (now - self.last_activity())
+            .to_std()
+            .is_ok_and(|inactive| inactive > timeout)
+    }
+
+    /// Abandon an active dialog whose last activity is more than `timeout`
+    /// before `now`
+    ///
+    /// Pass `None` to use the configured idle timeout. Returns the
+    /// `DialogAbandoned` event if the dialog was abandoned, and `None` if it is
+    /// not active, has been active recently or has no timeout to apply.
+    pub fn check_inactivity(
+        &mut self,
+        now: DateTime<Utc>,
+        timeout: Option<std::time::Duration>,
+    ) -> DomainResult<Option<DialogDomainEvent>> {
+        let Some(timeout) = timeout.or(self.config.idle_timeout) else {
+            return Ok(None);
+        };
+        if self.status != DialogStatus::Active {
+            return Ok(None);
+        }
+
+        if !self.inactive_for(now, timeout) {
+            return Ok(None);
+        }
+
+        let event = DialogAbandoned {
+            event_id: Uuid::new_v4(),
+            dialog_id: self.id(),
+            abandoned_at: now,
+            reason: Some(format!("No activity for more than {}s", timeout.as_secs())),
+        };
+
+        Ok(Some(self.record(DialogDomainEvent::DialogAbandoned(event))))
+    }
+
+    /// Check a turn about to be added against the configured limits
+    fn check_limits(&self, turn: &Turn) -> DomainResult<()> {
+        if let Some(language) = &self.config.enforced_language
+            && turn.message.language != *language
+        {
+            return Err(DomainError::ValidationError(format!(
+                "Turn language '{}' does not match the enforced language '{language}'",
+                turn.message.language
+            )));
+        }
+
+        if let Some(budget) = self.config.token_budget {
+            let needed = self.tokens_used() + turn.message.content.estimated_tokens();
+            if needed > budget {
+                return Err(DomainError::ValidationError(format!(
+                    "Turn would use {needed} tokens, exceeding the budget of {budget}"
+                )));
+            }
+        }
+
+        if let Some(limit) = self.config.rate_limit {
+            let recent = self
+                .turns
+                .iter()
+                .filter(|t| {
+                    (turn.timestamp - t.timestamp)
+                        .to_std()
+                        .map_or(true, |age| age < limit.window)
+                })
+                .count();
+            if recent >= limit.max_turns {
+                return Err(DomainError::ValidationError(format!(
+                    "Rate limit of {} turns per {:?} exceeded",
+                    limit.max_turns, limit.window
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Export everything a participant contributed, excluding other participants' content
+    pub fn export_participant_data(&self, participant_id: Uuid) -> ParticipantExport {
+        let turns = self
+            .archived_turns
+            .iter()
+            .chain(&self.turns)
+            .filter(|t| t.participant_id == participant_id)
+            .cloned()
+            .collect();
+
+        let mut variables: Vec<ContextVariable> = self
+            .context
+            .namespaced
+            .values()
+            .filter(|v| v.source == participant_id)
+            .cloned()
+            .collect();
+        variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+        ParticipantExport {
+            dialog_id: self.id(),
+            participant_id,
+            participant: self.participants.get(&participant_id).cloned(),
+            turns,
+            variables,
+        }
     }
 }
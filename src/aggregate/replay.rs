@@ -0,0 +1,456 @@
+//! Event-sourced reconstruction of dialogs
+//!
+//! [`Dialog::from_events`] folds a dialog's event stream into its state, for
+//! deployments backed by an event store rather than a snapshot repository.
+//! Commands change the aggregate only by applying the events they emit, so
+//! the rebuilt dialog matches the one that produced the events, version
+//! included.
+
+use cim_domain::{DomainError, DomainEvent, DomainResult};
+
+use super::{ContextSnapshot, ContextState, Dialog, DialogStatus};
+use crate::events::DialogDomainEvent;
+use crate::value_objects::{
+    ContextScope, ContextVariable, FLAGGED_PROPERTY, TopicStatus, Turn, TurnType,
+};
+
+impl Dialog {
+    /// Rebuild a dialog from its events, oldest first
+    ///
+    /// The first event must be `DialogStarted`, and every event must belong
+    /// to the dialog it started. The dialog uses the default configuration.
+    pub fn from_events(
+        events: impl IntoIterator<Item = DialogDomainEvent>,
+    ) -> DomainResult<Dialog> {
+        let mut events = events.into_iter();
+        let mut dialog = match events.next() {
+            Some(DialogDomainEvent::DialogStarted(e)) => {
+                let mut dialog = Dialog::new(e.dialog_id, e.dialog_type, e.primary_participant);
+                dialog.started_at = e.started_at;
+                dialog
+            }
+            Some(other) => {
+                return Err(DomainError::ValidationError(format!(
+                    "Event stream must start with DialogStarted, not {}",
+                    other.subject()
+                )));
+            }
+            None => {
+                return Err(DomainError::ValidationError(
+                    "Event stream is empty".to_string(),
+                ));
+            }
+        };
+
+        for event in events {
+            let dialog_id = event.aggregate_id();
+            if dialog_id != dialog.id() {
+                return Err(DomainError::ValidationError(format!(
+                    "Event for dialog {dialog_id} cannot be applied to dialog {}",
+                    dialog.id()
+                )));
+            }
+            if matches!(event, DialogDomainEvent::DialogStarted(_)) {
+                return Err(DomainError::ValidationError(
+                    "Dialog already started".to_string(),
+                ));
+            }
+            dialog.apply(&event);
+        }
+
+        Ok(dialog)
+    }
+
+    /// Fold one event into the dialog's state and version
+    ///
+    /// Commands validate before calling this, and `from_events` checks the
+    /// stream, so no invariants are enforced here.
+    pub(super) fn apply(&mut self, event: &DialogDomainEvent) {
+        self.fold(event);
+
+        if Self::changes_state(event) {
+            self.applied_changes += 1;
+        }
+        debug_assert_eq!(
+            self.version,
+            self.applied_changes,
+            "version out of step with state changes after applying {}",
+            event.event_type()
+        );
+    }
+
+    fn fold(&mut self, event: &DialogDomainEvent) {
+        match event {
+            // The dialog is constructed from this event, not updated by it
+            DialogDomainEvent::DialogStarted(_) => return,
+            DialogDomainEvent::DialogEnded(e) => {
+                self.status = DialogStatus::Ended;
+                self.metrics = e.final_metrics.clone();
+            }
+            DialogDomainEvent::DialogAbandoned(_) => self.status = DialogStatus::Abandoned,
+            DialogDomainEvent::DialogPaused(e) => {
+                self.context.history.push(ContextSnapshot {
+                    timestamp: e.paused_at,
+                    turn_number: self.metrics.turn_count,
+                    active_topic: self.current_topic,
+                    variables: e.context_snapshot.clone(),
+                });
+                if self.context.history.len() > self.context.max_history {
+                    self.context.history.remove(0);
+                }
+                self.status = DialogStatus::Paused;
+            }
+            DialogDomainEvent::DialogResumed(e) => {
+                if e.restored_variables.is_some()
+                    && let Some(snapshot) = self.context.history.pop()
+                {
+                    self.context.variables = snapshot.variables;
+                    self.current_topic = snapshot.active_topic;
+                }
+                self.status = DialogStatus::Active;
+            }
+            DialogDomainEvent::TurnAdded(e) => {
+                // Released scheduled turns leave the queue as they are added
+                self.scheduled.retain(|(_, t)| t.turn_id != e.turn.turn_id);
+                // Agent latency: a response measured from the user query just before it
+                if e.turn.metadata.turn_type == TurnType::AgentResponse
+                    && let Some(previous) = self.turns.last().or(self.archived_turns.last())
+                    && previous.metadata.turn_type == TurnType::UserQuery
+                {
+                    let latency = (e.turn.timestamp - previous.timestamp)
+                        .num_milliseconds()
+                        .max(0);
+                    self.response_time_samples += 1;
+                    self.metrics.avg_response_time_ms += (latency as f64
+                        - self.metrics.avg_response_time_ms)
+                        / f64::from(self.response_time_samples);
+                }
+                if e.turn.metadata.turn_type == TurnType::Clarification {
+                    self.metrics.clarification_count += 1;
+                }
+                self.turns.push(e.turn.clone());
+                self.metrics.turn_count += 1;
+            }
+            DialogDomainEvent::ParticipantAdded(e) => {
+                self.participants
+                    .insert(e.participant.id, e.participant.clone());
+            }
+            DialogDomainEvent::ParticipantRemoved(e) => {
+                self.participants.remove(&e.participant_id);
+            }
+            DialogDomainEvent::ContextSwitched(e) => {
+                if let Some(current) = self.current_topic.and_then(|id| self.topics.get_mut(&id))
+                    && current.status == TopicStatus::Active
+                {
+                    current.status = TopicStatus::Paused;
+                }
+                self.topics.insert(e.new_topic.id, e.new_topic.clone());
+                self.current_topic = Some(e.new_topic.id);
+                self.metrics.topic_switches += 1;
+            }
+            DialogDomainEvent::ContextUpdated(e) => {
+                for (name, value) in &e.updated_variables {
+                    self.context.set_variable(ContextVariable {
+                        name: name.clone(),
+                        value: value.clone(),
+                        scope: ContextScope::Dialog,
+                        set_at: e.updated_at,
+                        expires_at: None,
+                        source: self.id(),
+                    });
+                }
+            }
+            DialogDomainEvent::ContextVariableAdded(e) => {
+                self.context.set_variable(e.variable.clone());
+            }
+            DialogDomainEvent::ContextVariablesExpired(e) => {
+                for name in &e.names {
+                    self.context.variables.remove(name);
+                }
+                self.context
+                    .namespaced
+                    .retain(|(_, name), v| !(e.names.contains(name) && v.is_expired(e.expired_at)));
+            }
+            DialogDomainEvent::DialogMetadataSet(e) => {
+                self.metadata.insert(e.key.clone(), e.value.clone());
+            }
+            DialogDomainEvent::TopicCompleted(e) => {
+                if let Some(topic) = self.topics.get_mut(&e.topic_id) {
+                    topic.status = TopicStatus::Completed;
+                }
+            }
+            DialogDomainEvent::TurnPinned(e) => {
+                if !self.pinned_turns.contains(&e.turn_id) {
+                    self.pinned_turns.push(e.turn_id);
+                }
+            }
+            DialogDomainEvent::TurnUnpinned(e) => {
+                self.pinned_turns.retain(|id| *id != e.turn_id);
+            }
+            DialogDomainEvent::TurnRetracted(e) => {
+                self.turns.retain(|t| t.turn_id != e.turn_id);
+                self.archived_turns.retain(|t| t.turn_id != e.turn_id);
+                self.pinned_turns.retain(|id| *id != e.turn_id);
+                if self.resolution_turn == Some(e.turn_id) {
+                    self.resolution_turn = None;
+                }
+                self.metrics.turn_count = self.metrics.turn_count.saturating_sub(1);
+            }
+            DialogDomainEvent::TurnEdited(e) => {
+                if let Some(turn) = self
+                    .turns
+                    .iter_mut()
+                    .chain(&mut self.archived_turns)
+                    .find(|t| t.turn_id == e.turn_id)
+                {
+                    turn.message = e.new_message.clone();
+                }
+            }
+            DialogDomainEvent::TurnsArchived(e) => {
+                let (archived, live): (Vec<Turn>, Vec<Turn>) = self
+                    .turns
+                    .drain(..)
+                    .partition(|t| e.turn_ids.contains(&t.turn_id));
+                self.turns = live;
+                self.archived_turns.extend(archived);
+                self.pinned_turns.retain(|id| !e.turn_ids.contains(id));
+            }
+            DialogDomainEvent::DialogLocked(_) => self.locked = true,
+            DialogDomainEvent::DialogUnlocked(_) => self.locked = false,
+            DialogDomainEvent::TopicsRelated(e) => {
+                for (from, to) in [(e.topic_a, e.topic_b), (e.topic_b, e.topic_a)] {
+                    if let Some(topic) = self.topics.get_mut(&from)
+                        && !topic.related_topics.contains(&to)
+                    {
+                        topic.related_topics.push(to);
+                    }
+                }
+            }
+            DialogDomainEvent::TopicsUnrelated(e) => {
+                for (from, to) in [(e.topic_a, e.topic_b), (e.topic_b, e.topic_a)] {
+                    if let Some(topic) = self.topics.get_mut(&from) {
+                        topic.related_topics.retain(|id| *id != to);
+                    }
+                }
+            }
+            DialogDomainEvent::TurnFlagged(e) => {
+                if let Some(turn) = self.turns.iter_mut().find(|t| t.turn_id == e.turn_id) {
+                    turn.metadata.properties.insert(
+                        FLAGGED_PROPERTY.to_string(),
+                        serde_json::Value::String(e.reason.clone()),
+                    );
+                }
+            }
+            DialogDomainEvent::ResolutionSet(e) => self.resolution = Some(e.resolution),
+            DialogDomainEvent::ResolutionTurnMarked(e) => self.resolution_turn = Some(e.turn_id),
+            DialogDomainEvent::EmbeddingAttached(e) => {
+                if let Some(turn) = self
+                    .turns
+                    .iter_mut()
+                    .chain(self.archived_turns.iter_mut())
+                    .find(|t| t.turn_id == e.turn_id)
+                {
+                    turn.message.embeddings = Some(e.embeddings.clone());
+                }
+            }
+            DialogDomainEvent::TurnScheduled(e) => {
+                let position = self
+                    .scheduled
+                    .partition_point(|(at, _)| *at <= e.deliver_at);
+                self.scheduled
+                    .insert(position, (e.deliver_at, e.turn.clone()));
+            }
+            DialogDomainEvent::ScheduledTurnRejected(e) => {
+                self.scheduled.retain(|(_, t)| t.turn_id != e.turn_id);
+            }
+            DialogDomainEvent::MetricsUpdated(e) => {
+                // Reports the effect of the preceding event; not a state change
+                self.metrics = e.metrics.clone();
+                return;
+            }
+            DialogDomainEvent::PhaseChanged(e) => self.phase = Some(e.new_phase),
+            DialogDomainEvent::ContextStateChanged(e) => {
+                if e.new_state == ContextState::AwaitingClarification {
+                    self.metrics.clarification_count += 1;
+                }
+                self.context.state = e.new_state;
+            }
+        }
+
+        self.bump();
+    }
+
+    /// Apply an event produced by a command and hand it back to the caller
+    pub(super) fn record(&mut self, event: DialogDomainEvent) -> DialogDomainEvent {
+        self.apply(&event);
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DialogStarted;
+    use crate::value_objects::{
+        Message, Participant, ParticipantRole, ParticipantType, ResolutionOutcome, Topic, TurnType,
+    };
+    use crate::{ConversationPhase, DialogType};
+    use chrono::{Duration, Utc};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn participant(
+        name: &str,
+        participant_type: ParticipantType,
+        role: ParticipantRole,
+    ) -> Participant {
+        Participant {
+            id: Uuid::new_v4(),
+            participant_type,
+            role,
+            name: name.to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_commands_round_trip_through_apply() {
+        let user = participant("User", ParticipantType::Human, ParticipantRole::Primary);
+        let agent = participant(
+            "Agent",
+            ParticipantType::AIAgent,
+            ParticipantRole::Assistant,
+        );
+        let observer = participant(
+            "Observer",
+            ParticipantType::Human,
+            ParticipantRole::Observer,
+        );
+        let started = DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id: Uuid::new_v4(),
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            started_at: Utc::now(),
+        };
+        let mut dialog = Dialog::new(started.dialog_id, started.dialog_type, user.clone());
+        dialog.set_clarification_confidence_threshold(Some(0.5));
+        let mut events = Vec::new();
+
+        let billing = Topic::new("Billing", vec!["invoice".to_string()]);
+        let refunds = Topic::new("Refunds", vec!["refund".to_string()]);
+        let (billing_id, refunds_id) = (billing.id, refunds.id);
+
+        events.extend(dialog.add_participant(agent.clone()).unwrap());
+        events.extend(dialog.add_participant(observer.clone()).unwrap());
+        events.extend(dialog.remove_participant(observer.id, None).unwrap());
+        events.extend(
+            dialog
+                .set_metadata("channel".to_string(), serde_json::json!("web"))
+                .unwrap(),
+        );
+        events.extend(dialog.switch_topic(billing).unwrap());
+        let question = Turn::new(
+            1,
+            user.id,
+            Message::text("My invoice is wrong"),
+            TurnType::UserQuery,
+        );
+        let question_id = question.turn_id;
+        events.extend(dialog.add_turn(question).unwrap());
+        let mut answer = Turn::new(
+            2,
+            agent.id,
+            Message::text("Maybe a refund?"),
+            TurnType::AgentResponse,
+        );
+        answer.metadata.confidence = Some(0.2);
+        let answer_id = answer.turn_id;
+        events.extend(dialog.add_turn(answer).unwrap());
+        events.extend(
+            dialog
+                .update_context(HashMap::from([(
+                    "order".to_string(),
+                    serde_json::json!(42),
+                )]))
+                .unwrap(),
+        );
+        events.extend(dialog.pin_turn(question_id).unwrap());
+        events.extend(dialog.flag_turn(answer_id, "unsure".to_string()).unwrap());
+        events.extend(
+            dialog
+                .edit_turn(answer_id, Message::text("Take the refunds route"))
+                .unwrap(),
+        );
+        events.extend(dialog.attach_embedding(answer_id, vec![0.6, 0.8]).unwrap());
+        events.extend(dialog.switch_topic(refunds).unwrap());
+        events.extend(dialog.relate_topics(billing_id, refunds_id).unwrap());
+        events.extend(
+            dialog
+                .mark_topic_complete(refunds_id, Some("Refunded".to_string()), false)
+                .unwrap(),
+        );
+        events.extend(dialog.set_phase(ConversationPhase::Resolution).unwrap());
+        let reminder = Turn::new(
+            3,
+            agent.id,
+            Message::text("Anything else?"),
+            TurnType::AgentResponse,
+        );
+        let deliver_at = Utc::now() + Duration::seconds(1);
+        events.extend(dialog.schedule_turn(reminder, deliver_at).unwrap());
+        events.extend(dialog.release_due_turns(deliver_at).unwrap());
+        events.extend(dialog.pause().unwrap());
+        events.extend(dialog.resume().unwrap());
+        events.extend(dialog.lock(None).unwrap());
+        events.extend(dialog.unlock().unwrap());
+        events.extend(dialog.archive_turns(2).unwrap());
+        events.extend(dialog.set_resolution(ResolutionOutcome::Resolved).unwrap());
+        events.extend(dialog.mark_resolution_turn(answer_id).unwrap());
+        events.extend(dialog.end(None).unwrap());
+
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, DialogDomainEvent::ContextStateChanged(_)))
+        );
+
+        let rebuilt = Dialog::from_events(
+            std::iter::once(DialogDomainEvent::DialogStarted(started)).chain(events),
+        )
+        .unwrap();
+        assert_eq!(rebuilt, dialog);
+    }
+
+    #[test]
+    fn test_switch_topic_reports_previous_topic() {
+        let user = participant("User", ParticipantType::Human, ParticipantRole::Primary);
+        let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+        let a = Topic::new("Billing", vec![]);
+        let b = Topic::new("Shipping", vec![]);
+        let (a_id, b_id) = (a.id, b.id);
+
+        dialog.switch_topic(a).unwrap();
+        let events = dialog.switch_topic(b).unwrap();
+
+        match events.as_slice() {
+            [DialogDomainEvent::ContextSwitched(e)] => {
+                assert_eq!(e.previous_topic, Some(a_id));
+                assert_eq!(e.new_topic.id, b_id);
+            }
+            other => panic!("Expected one ContextSwitched event, got {other:?}"),
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "version out of step")]
+    fn test_apply_catches_version_drift() {
+        let user = participant("User", ParticipantType::Human, ParticipantRole::Primary);
+        let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+
+        // A version bump with no event behind it
+        dialog.bump();
+        dialog.pause().unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! Dialog analytics exports
+//!
+//! Tabular exports of dialog projections for spreadsheets and BI tools, and
+//! similarity scores for clustering dialogs.
+
+mod similarity;
+
+pub use similarity::{COMPOSITION_WEIGHT, EMBEDDING_WEIGHT, KEYWORD_WEIGHT, similarity};
+
+use crate::projections::SimpleDialogView;
+use crate::value_objects::{DisplayNameResolver, StoredNameResolver};
+
+/// CSV header produced by [`to_csv_rows`]
+///
+/// Columns added later go at the end so existing readers keep their indices.
+pub const CSV_HEADER: &str = "dialog_id,type,status,started_at,ended_at,turn_count,participant_count,avg_sentiment,duration_secs,primary_participant";
+
+/// Export dialog views as CSV with a header row and one row per dialog
+///
+/// Timestamps are RFC 3339. `ended_at`, `avg_sentiment` and `duration_secs`
+/// are left empty when the dialog has not ended or no turn carries a
+/// sentiment score.
+pub fn to_csv_rows(views: &[SimpleDialogView]) -> String {
+    to_csv_rows_with(views, &StoredNameResolver)
+}
+
+/// Export dialog views as CSV, naming the primary participant with `resolver`
+pub fn to_csv_rows_with(views: &[SimpleDialogView], resolver: &dyn DisplayNameResolver) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+
+    for view in views {
+        let sentiments: Vec<f32> = view
+            .turns
+            .iter()
+            .filter_map(|t| t.message.sentiment)
+            .collect();
+        let avg_sentiment = if sentiments.is_empty() {
+            String::new()
+        } else {
+            (sentiments.iter().sum::<f32>() / sentiments.len() as f32).to_string()
+        };
+
+        let fields = [
+            view.dialog_id.to_string(),
+            format!("{:?}", view.dialog_type),
+            format!("{:?}", view.status),
+            view.started_at.to_rfc3339(),
+            view.ended_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            view.turns.len().to_string(),
+            view.participants.len().to_string(),
+            avg_sentiment,
+            view.ended_at
+                .map(|t| (t - view.started_at).num_seconds().to_string())
+                .unwrap_or_default(),
+            resolver.display_name(&view.primary_participant),
+        ];
+
+        let row: Vec<String> = fields.iter().map(|f| escape_field(f)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::DialogType;
+    use crate::events::{DialogDomainEvent, DialogEnded, DialogStarted, TurnAdded};
+    use crate::projections::SimpleProjectionUpdater;
+    use crate::value_objects::{
+        ConversationMetrics, Message, Participant, ParticipantRole, ParticipantType, Turn, TurnType,
+    };
+    use chrono::{Duration, Utc};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_to_csv_rows() {
+        let mut updater = SimpleProjectionUpdater::new();
+        let started_at = Utc::now() - Duration::minutes(5);
+        let ended_id = Uuid::new_v4();
+        let open_id = Uuid::new_v4();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "Doe, \"JD\" Jane".to_string(),
+            metadata: HashMap::new(),
+        };
+
+        let events = vec![
+            DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id: ended_id,
+                dialog_type: DialogType::Support,
+                primary_participant: user.clone(),
+                started_at,
+            }),
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id: ended_id,
+                turn: Turn::new(
+                    1,
+                    user.id,
+                    Message::text("Thanks").with_sentiment(0.5),
+                    TurnType::UserQuery,
+                ),
+                turn_number: 1,
+            }),
+            DialogDomainEvent::DialogEnded(DialogEnded {
+                event_id: Uuid::new_v4(),
+                dialog_id: ended_id,
+                ended_at: started_at + Duration::seconds(90),
+                reason: None,
+                final_metrics: ConversationMetrics {
+                    turn_count: 1,
+                    avg_response_time_ms: 0.0,
+                    topic_switches: 0,
+                    clarification_count: 0,
+                    sentiment_trend: 0.5,
+                    coherence_score: 1.0,
+                },
+            }),
+            DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id: open_id,
+                dialog_type: DialogType::Direct,
+                primary_participant: user.clone(),
+                started_at,
+            }),
+        ];
+        for event in events {
+            updater.handle_event(event).await.unwrap();
+        }
+
+        let views = vec![
+            updater.get_view(&ended_id).unwrap().clone(),
+            updater.get_view(&open_id).unwrap().clone(),
+        ];
+        let csv = to_csv_rows(&views);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},Support,Ended,{},{},1,1,0.5,90,\"Doe, \"\"JD\"\" Jane\"",
+                ended_id,
+                started_at.to_rfc3339(),
+                (started_at + Duration::seconds(90)).to_rfc3339()
+            )
+        );
+        assert!(lines[2].starts_with(&format!("{open_id},Direct,Active,")));
+        assert!(lines[2].contains(",,0,1,,,"));
+    }
+
+    #[tokio::test]
+    async fn test_to_csv_rows_with_resolver() {
+        struct Directory;
+        impl DisplayNameResolver for Directory {
+            fn display_name(&self, participant: &Participant) -> String {
+                format!("{} (directory)", participant.name.to_uppercase())
+            }
+        }
+
+        let mut updater = SimpleProjectionUpdater::new();
+        let dialog_id = Uuid::new_v4();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "jdoe".to_string(),
+            metadata: HashMap::new(),
+        };
+        updater
+            .handle_event(DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                dialog_type: DialogType::Direct,
+                primary_participant: user,
+                started_at: Utc::now(),
+            }))
+            .await
+            .unwrap();
+
+        let views = vec![updater.get_view(&dialog_id).unwrap().clone()];
+        let row = to_csv_rows_with(&views, &Directory)
+            .lines()
+            .nth(1)
+            .unwrap()
+            .to_string();
+        assert!(row.ends_with(",JDOE (directory)"));
+        assert!(!to_csv_rows(&views).contains("directory"));
+    }
+}
@@ -0,0 +1,177 @@
+//! Similarity between dialogs
+//!
+//! Used to cluster similar conversations, e.g. support cases about the same
+//! problem. The score blends three signals, each in 0..1:
+//!
+//! - topic keywords: Jaccard overlap of the (case-insensitive) keywords of
+//!   every topic in each dialog
+//! - composition: how closely the mix of participant types matches, as the
+//!   overlap of the per-type counts (sum of minimums over sum of maximums)
+//! - embeddings: cosine similarity of the averaged message embeddings, mapped
+//!   from -1..1 to 0..1
+//!
+//! Signals are weighted by [`KEYWORD_WEIGHT`], [`COMPOSITION_WEIGHT`] and
+//! [`EMBEDDING_WEIGHT`]. A signal that is not available for the pair (neither
+//! dialog has topic keywords, or either has no usable message embeddings) is
+//! left out and the remaining weights are rescaled, so dialogs without
+//! embeddings are compared on keywords and composition alone.
+
+use crate::projections::SimpleDialogView;
+use crate::value_objects::{ParticipantType, cosine_similarity};
+use std::collections::{HashMap, HashSet};
+
+/// Weight of topic keyword overlap in [`similarity`]
+pub const KEYWORD_WEIGHT: f32 = 0.4;
+
+/// Weight of participant type composition in [`similarity`]
+pub const COMPOSITION_WEIGHT: f32 = 0.2;
+
+/// Weight of averaged message embeddings in [`similarity`]
+pub const EMBEDDING_WEIGHT: f32 = 0.4;
+
+/// Similarity of two dialogs, from 0 (unrelated) to 1 (alike)
+///
+/// See the module documentation for how the score is built.
+pub fn similarity(a: &SimpleDialogView, b: &SimpleDialogView) -> f32 {
+    let signals = [
+        (KEYWORD_WEIGHT, keyword_similarity(a, b)),
+        (COMPOSITION_WEIGHT, Some(composition_similarity(a, b))),
+        (EMBEDDING_WEIGHT, embedding_similarity(a, b)),
+    ];
+
+    let (weighted, total_weight) = signals
+        .iter()
+        .filter_map(|(weight, score)| score.map(|s| (weight * s, *weight)))
+        .fold((0.0, 0.0), |(sum, total), (s, w)| (sum + s, total + w));
+
+    (weighted / total_weight).clamp(0.0, 1.0)
+}
+
+/// Jaccard overlap of topic keywords, `None` if neither dialog has any
+fn keyword_similarity(a: &SimpleDialogView, b: &SimpleDialogView) -> Option<f32> {
+    let keywords = |view: &SimpleDialogView| -> HashSet<String> {
+        view.topics
+            .iter()
+            .flat_map(|t| &t.keywords)
+            .map(|k| k.to_lowercase())
+            .collect()
+    };
+    let (a, b) = (keywords(a), keywords(b));
+
+    let union = a.union(&b).count();
+    (union > 0).then(|| a.intersection(&b).count() as f32 / union as f32)
+}
+
+/// Overlap of the participant type counts
+fn composition_similarity(a: &SimpleDialogView, b: &SimpleDialogView) -> f32 {
+    let counts = |view: &SimpleDialogView| {
+        let mut counts: HashMap<ParticipantType, usize> = HashMap::new();
+        for participant in view.participants.values() {
+            *counts.entry(participant.participant_type).or_default() += 1;
+        }
+        counts
+    };
+    let (a, b) = (counts(a), counts(b));
+
+    let (mut shared, mut total) = (0, 0);
+    for participant_type in a.keys().chain(b.keys()).collect::<HashSet<_>>() {
+        let (x, y) = (
+            a.get(participant_type).copied().unwrap_or(0),
+            b.get(participant_type).copied().unwrap_or(0),
+        );
+        shared += x.min(y);
+        total += x.max(y);
+    }
+
+    if total == 0 {
+        1.0
+    } else {
+        shared as f32 / total as f32
+    }
+}
+
+/// Cosine similarity of averaged message embeddings, rescaled to 0..1
+fn embedding_similarity(a: &SimpleDialogView, b: &SimpleDialogView) -> Option<f32> {
+    let cosine = cosine_similarity(&average_embedding(a)?, &average_embedding(b)?)?;
+    Some((cosine + 1.0) / 2.0)
+}
+
+/// Mean of the message embeddings that share the first embedding's length
+fn average_embedding(view: &SimpleDialogView) -> Option<Vec<f32>> {
+    let mut embeddings = view
+        .turns
+        .iter()
+        .filter_map(|t| t.message.embeddings.as_deref())
+        .filter(|e| !e.is_empty());
+    let first = embeddings.next()?;
+
+    let mut sum = first.to_vec();
+    let mut count = 1;
+    for embedding in embeddings.filter(|e| e.len() == first.len()) {
+        sum.iter_mut().zip(embedding).for_each(|(s, x)| *s += x);
+        count += 1;
+    }
+
+    Some(sum.into_iter().map(|s| s / count as f32).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::DialogType;
+    use crate::events::DialogStarted;
+    use crate::value_objects::{Message, Participant, ParticipantRole, Topic, Turn, TurnType};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn view(keywords: &[&str], embedding: Option<Vec<f32>>) -> SimpleDialogView {
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let mut view = SimpleDialogView::from_started(&DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id: Uuid::new_v4(),
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            started_at: Utc::now(),
+        });
+
+        view.topics.push(Topic::new(
+            "Topic",
+            keywords.iter().map(|k| k.to_string()).collect(),
+        ));
+        let mut message = Message::text("Hello");
+        message.embeddings = embedding;
+        view.turns
+            .push(Turn::new(1, user.id, message, TurnType::UserQuery));
+        view
+    }
+
+    #[test]
+    fn test_similar_dialogs_score_higher() {
+        let refund = view(&["refund", "invoice", "billing"], Some(vec![1.0, 0.1, 0.0]));
+        let refund_again = view(&["Refund", "billing"], Some(vec![0.9, 0.2, 0.0]));
+        let outage = view(&["outage", "network"], Some(vec![0.0, 0.1, 1.0]));
+
+        let related = similarity(&refund, &refund_again);
+        let unrelated = similarity(&refund, &outage);
+        assert!(related > unrelated, "{related} <= {unrelated}");
+        assert!((0.0..=1.0).contains(&related) && (0.0..=1.0).contains(&unrelated));
+        assert!((similarity(&refund, &refund) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_similarity_without_embeddings() {
+        let refund = view(&["refund", "billing"], None);
+        let refund_again = view(&["refund", "billing"], Some(vec![1.0, 0.0]));
+        let outage = view(&["outage"], None);
+
+        // Keywords and composition only: identical keywords and participants
+        assert!((similarity(&refund, &refund_again) - 1.0).abs() < 1e-6);
+        assert!(similarity(&refund, &outage) < similarity(&refund, &refund_again));
+    }
+}
@@ -4,7 +4,9 @@ use cim_domain::Command;
 use serde_json::Value;
 use uuid::Uuid;
 
-use crate::value_objects::{ContextVariable, Participant, Topic, Turn};
+use crate::value_objects::{
+    ContextVariable, EndReason, Participant, ResolutionOutcome, Topic, Turn,
+};
 
 /// Start a new dialog
 #[derive(Debug, Clone)]
@@ -17,6 +19,8 @@ pub struct StartDialog {
     pub primary_participant: Participant,
     /// Initial metadata
     pub metadata: Option<std::collections::HashMap<String, Value>>,
+    /// Settings for the dialog (defaults when None)
+    pub config: Option<crate::aggregate::DialogConfig>,
 }
 
 impl Command for StartDialog {
@@ -33,7 +37,7 @@ pub struct EndDialog {
     /// Dialog ID
     pub id: Uuid,
     /// Reason for ending
-    pub reason: Option<String>,
+    pub reason: Option<EndReason>,
 }
 
 impl Command for EndDialog {
@@ -44,6 +48,23 @@ impl Command for EndDialog {
     }
 }
 
+/// Abandon a dialog, e.g. after the user walked away
+#[derive(Debug, Clone)]
+pub struct AbandonDialog {
+    /// Dialog ID
+    pub id: Uuid,
+    /// Reason for abandoning
+    pub reason: Option<String>,
+}
+
+impl Command for AbandonDialog {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the id field to find the aggregate
+    }
+}
+
 /// Add a turn to the dialog
 #[derive(Debug, Clone)]
 pub struct AddTurn {
@@ -125,6 +146,22 @@ impl Command for ResumeDialog {
     }
 }
 
+/// Resume a paused dialog, restoring the context variables and topic
+/// snapshotted at pause
+#[derive(Debug, Clone)]
+pub struct ResumeDialogRestoring {
+    /// Dialog ID
+    pub id: Uuid,
+}
+
+impl Command for ResumeDialogRestoring {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the id field to find the aggregate
+    }
+}
+
 /// Set dialog metadata
 #[derive(Debug, Clone)]
 pub struct SetDialogMetadata {
@@ -189,6 +226,8 @@ pub struct MarkTopicComplete {
     pub topic_id: Uuid,
     /// Resolution/outcome
     pub resolution: Option<String>,
+    /// Also complete incomplete required subtopics instead of rejecting
+    pub cascade: bool,
 }
 
 impl Command for MarkTopicComplete {
@@ -215,3 +254,222 @@ impl Command for AddContextVariable {
         None // We'll use the dialog_id field to find the aggregate
     }
 }
+
+/// Pin a turn
+#[derive(Debug, Clone)]
+pub struct PinTurn {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+    /// Turn to pin
+    pub turn_id: Uuid,
+}
+
+impl Command for PinTurn {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Unpin a turn
+#[derive(Debug, Clone)]
+pub struct UnpinTurn {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+    /// Turn to unpin
+    pub turn_id: Uuid,
+}
+
+impl Command for UnpinTurn {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Lock a dialog against new turns
+#[derive(Debug, Clone)]
+pub struct LockDialog {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+    /// Reason for locking
+    pub reason: Option<String>,
+}
+
+impl Command for LockDialog {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Unlock a dialog
+#[derive(Debug, Clone)]
+pub struct UnlockDialog {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+}
+
+impl Command for UnlockDialog {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Record whether a dialog's issue was resolved
+#[derive(Debug, Clone)]
+pub struct SetResolution {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+    /// Resolution outcome
+    pub resolution: ResolutionOutcome,
+}
+
+impl Command for SetResolution {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Mark the turn that resolved the user's issue
+#[derive(Debug, Clone)]
+pub struct MarkResolutionTurn {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+    /// Turn that resolved the issue
+    pub turn_id: Uuid,
+}
+
+impl Command for MarkResolutionTurn {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Remove the dialog's expired context variables
+#[derive(Debug, Clone)]
+pub struct PruneExpiredContext {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+}
+
+impl Command for PruneExpiredContext {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Attach an embedding to an existing turn
+#[derive(Debug, Clone)]
+pub struct AttachEmbedding {
+    /// Dialog ID
+    pub dialog_id: Uuid,
+    /// Turn to attach the embedding to
+    pub turn_id: Uuid,
+    /// Embedding vector
+    pub embeddings: Vec<f32>,
+}
+
+impl Command for AttachEmbedding {
+    type Aggregate = crate::Dialog;
+
+    fn aggregate_id(&self) -> Option<cim_domain::EntityId<Self::Aggregate>> {
+        None // We'll use the dialog_id field to find the aggregate
+    }
+}
+
+/// Any dialog command, for code that handles commands generically
+#[derive(Debug, Clone)]
+pub enum DialogCommand {
+    StartDialog(StartDialog),
+    EndDialog(EndDialog),
+    AbandonDialog(AbandonDialog),
+    AddTurn(AddTurn),
+    SwitchContext(SwitchContext),
+    UpdateContext(UpdateContext),
+    PauseDialog(PauseDialog),
+    ResumeDialog(ResumeDialog),
+    ResumeDialogRestoring(ResumeDialogRestoring),
+    SetDialogMetadata(SetDialogMetadata),
+    AddParticipant(AddParticipant),
+    RemoveParticipant(RemoveParticipant),
+    MarkTopicComplete(MarkTopicComplete),
+    AddContextVariable(AddContextVariable),
+    PinTurn(PinTurn),
+    UnpinTurn(UnpinTurn),
+    LockDialog(LockDialog),
+    UnlockDialog(UnlockDialog),
+    SetResolution(SetResolution),
+    MarkResolutionTurn(MarkResolutionTurn),
+    PruneExpiredContext(PruneExpiredContext),
+    AttachEmbedding(AttachEmbedding),
+}
+
+impl DialogCommand {
+    /// ID of the dialog the command targets
+    pub fn dialog_id(&self) -> Uuid {
+        match self {
+            Self::StartDialog(cmd) => cmd.id,
+            Self::EndDialog(cmd) => cmd.id,
+            Self::AbandonDialog(cmd) => cmd.id,
+            Self::AddTurn(cmd) => cmd.dialog_id,
+            Self::SwitchContext(cmd) => cmd.dialog_id,
+            Self::UpdateContext(cmd) => cmd.dialog_id,
+            Self::PauseDialog(cmd) => cmd.id,
+            Self::ResumeDialog(cmd) => cmd.id,
+            Self::ResumeDialogRestoring(cmd) => cmd.id,
+            Self::SetDialogMetadata(cmd) => cmd.dialog_id,
+            Self::AddParticipant(cmd) => cmd.dialog_id,
+            Self::RemoveParticipant(cmd) => cmd.dialog_id,
+            Self::MarkTopicComplete(cmd) => cmd.dialog_id,
+            Self::AddContextVariable(cmd) => cmd.dialog_id,
+            Self::PinTurn(cmd) => cmd.dialog_id,
+            Self::UnpinTurn(cmd) => cmd.dialog_id,
+            Self::LockDialog(cmd) => cmd.dialog_id,
+            Self::UnlockDialog(cmd) => cmd.dialog_id,
+            Self::SetResolution(cmd) => cmd.dialog_id,
+            Self::MarkResolutionTurn(cmd) => cmd.dialog_id,
+            Self::PruneExpiredContext(cmd) => cmd.dialog_id,
+            Self::AttachEmbedding(cmd) => cmd.dialog_id,
+        }
+    }
+
+    /// Name of the command
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StartDialog(_) => "StartDialog",
+            Self::EndDialog(_) => "EndDialog",
+            Self::AbandonDialog(_) => "AbandonDialog",
+            Self::AddTurn(_) => "AddTurn",
+            Self::SwitchContext(_) => "SwitchContext",
+            Self::UpdateContext(_) => "UpdateContext",
+            Self::PauseDialog(_) => "PauseDialog",
+            Self::ResumeDialog(_) => "ResumeDialog",
+            Self::ResumeDialogRestoring(_) => "ResumeDialogRestoring",
+            Self::SetDialogMetadata(_) => "SetDialogMetadata",
+            Self::AddParticipant(_) => "AddParticipant",
+            Self::RemoveParticipant(_) => "RemoveParticipant",
+            Self::MarkTopicComplete(_) => "MarkTopicComplete",
+            Self::AddContextVariable(_) => "AddContextVariable",
+            Self::PinTurn(_) => "PinTurn",
+            Self::UnpinTurn(_) => "UnpinTurn",
+            Self::LockDialog(_) => "LockDialog",
+            Self::UnlockDialog(_) => "UnlockDialog",
+            Self::SetResolution(_) => "SetResolution",
+            Self::MarkResolutionTurn(_) => "MarkResolutionTurn",
+            Self::PruneExpiredContext(_) => "PruneExpiredContext",
+            Self::AttachEmbedding(_) => "AttachEmbedding",
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Compact binary encoding for dialog events
+//!
+//! Events are encoded as MessagePack with structs written as arrays rather
+//! than maps, so field names are not repeated in every event. This typically
+//! makes events around half the size of their JSON form and cheaper to encode
+//! and decode, at the cost of not being human readable and of tying the
+//! encoding to field order: adding, removing or reordering event fields breaks
+//! decoding of previously written bytes, so treat the binary form as a
+//! transport format and keep JSON for long-term storage.
+//!
+//! MessagePack is self-describing, which is what allows the
+//! `serde_json::Value` fields carried by some events to round-trip exactly.
+
+use super::DialogDomainEvent;
+use thiserror::Error;
+
+/// Errors produced by the binary event codec
+#[derive(Debug, Error)]
+pub enum EventCodecError {
+    #[error("failed to encode event: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("failed to decode event: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+}
+
+impl DialogDomainEvent {
+    /// Encode the event in the compact binary format
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EventCodecError> {
+        Ok(rmp_serde::to_vec(self)?)
+    }
+
+    /// Decode an event produced by [`DialogDomainEvent::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EventCodecError> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::fixtures::all_variants;
+    use cim_domain::DomainEvent;
+
+    #[test]
+    fn test_binary_round_trip() {
+        for event in all_variants() {
+            let bytes = event.to_bytes().unwrap();
+            let decoded = DialogDomainEvent::from_bytes(&bytes).unwrap();
+
+            // Events don't implement PartialEq; compare their JSON forms instead
+            assert_eq!(
+                serde_json::to_value(&decoded).unwrap(),
+                serde_json::to_value(&event).unwrap(),
+                "{} did not round-trip",
+                event.event_type()
+            );
+        }
+
+        let turn_added = all_variants()
+            .into_iter()
+            .find(|e| matches!(e, DialogDomainEvent::TurnAdded(_)))
+            .unwrap();
+        let binary = turn_added.to_bytes().unwrap();
+        let json = serde_json::to_vec(&turn_added).unwrap();
+        assert!(binary.len() < json.len());
+
+        assert!(DialogDomainEvent::from_bytes(&[0xc1]).is_err());
+    }
+}
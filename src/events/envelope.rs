@@ -0,0 +1,112 @@
+//! Reconstructing events from message envelopes
+//!
+//! Events arrive from NATS or a persistent store as an event type name, the
+//! ID of the dialog they belong to, and the serialized event struct (the
+//! payload described by [`crate::schema`]). Malformed input is reported as a
+//! [`DialogEventError`] rather than a panic, so one bad message cannot take
+//! down a consumer.
+
+use cim_domain::DomainEvent;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::DialogDomainEvent;
+use crate::schema::EVENT_TYPES;
+
+/// Errors from reconstructing an event
+#[derive(Debug, Error)]
+pub enum DialogEventError {
+    #[error("unknown event type `{0}`")]
+    UnknownEventType(String),
+    #[error("{event_type} payload could not be decoded: {source}")]
+    DeserializationFailed {
+        event_type: String,
+        source: serde_json::Error,
+    },
+    #[error("{event_type} belongs to dialog {found}, not {expected}")]
+    AggregateIdMismatch {
+        event_type: String,
+        expected: Uuid,
+        found: Uuid,
+    },
+}
+
+impl DialogDomainEvent {
+    /// Rebuild an event from its type name, the dialog it was published for
+    /// and its JSON payload
+    pub fn from_envelope(
+        event_type: &str,
+        aggregate_id: Uuid,
+        payload: &[u8],
+    ) -> Result<Self, DialogEventError> {
+        if !EVENT_TYPES.contains(&event_type) {
+            return Err(DialogEventError::UnknownEventType(event_type.to_string()));
+        }
+
+        let deserialization_failed = |source| DialogEventError::DeserializationFailed {
+            event_type: event_type.to_string(),
+            source,
+        };
+        let payload: serde_json::Value =
+            serde_json::from_slice(payload).map_err(deserialization_failed)?;
+        let event: Self = serde_json::from_value(serde_json::json!({ event_type: payload }))
+            .map_err(deserialization_failed)?;
+
+        if event.aggregate_id() != aggregate_id {
+            return Err(DialogEventError::AggregateIdMismatch {
+                event_type: event_type.to_string(),
+                expected: aggregate_id,
+                found: event.aggregate_id(),
+            });
+        }
+
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DialogResumed;
+    use chrono::Utc;
+
+    #[test]
+    fn test_from_envelope() {
+        let dialog_id = Uuid::new_v4();
+        let payload = serde_json::to_vec(&DialogResumed {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            resumed_at: Utc::now(),
+            restored_variables: None,
+        })
+        .unwrap();
+
+        let event = DialogDomainEvent::from_envelope("DialogResumed", dialog_id, &payload).unwrap();
+        assert_eq!(event.event_type(), "DialogResumed");
+
+        assert!(matches!(
+            DialogDomainEvent::from_envelope(
+                "DialogResumed",
+                dialog_id,
+                &payload[..payload.len() / 2]
+            ),
+            Err(DialogEventError::DeserializationFailed { .. })
+        ));
+        assert!(matches!(
+            DialogDomainEvent::from_envelope("DialogExploded", dialog_id, &payload),
+            Err(DialogEventError::UnknownEventType(t)) if t == "DialogExploded"
+        ));
+        // A well-formed payload of the wrong type does not decode either
+        assert!(matches!(
+            DialogDomainEvent::from_envelope("DialogLocked", dialog_id, &payload),
+            Err(DialogEventError::DeserializationFailed { .. })
+        ));
+
+        let other = Uuid::new_v4();
+        assert!(matches!(
+            DialogDomainEvent::from_envelope("DialogResumed", other, &payload),
+            Err(DialogEventError::AggregateIdMismatch { expected, found, .. })
+                if expected == other && found == dialog_id
+        ));
+    }
+}
@@ -0,0 +1,311 @@
+//! Sample events shared by the event tests
+
+use super::*;
+use crate::aggregate::DialogType;
+use crate::value_objects::{
+    ContextScope, ContextVariable, ConversationMetrics, EndReason, EndReasonCode, Message,
+    MetricsDelta, Participant, ParticipantRole, ParticipantType, ResolutionOutcome, Topic, Turn,
+    TurnType,
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+/// One event of every variant
+pub(crate) fn all_variants() -> Vec<DialogDomainEvent> {
+    let dialog_id = Uuid::new_v4();
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "tier".to_string(),
+        serde_json::json!({"level": 3, "ratio": 0.5}),
+    );
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata,
+    };
+    let metrics = ConversationMetrics {
+        turn_count: 2,
+        avg_response_time_ms: 120.5,
+        topic_switches: 1,
+        clarification_count: 0,
+        sentiment_trend: 0.25,
+        coherence_score: 0.9,
+    };
+    let variable = ContextVariable {
+        name: "locale".to_string(),
+        value: serde_json::json!(["en", null, true]),
+        scope: ContextScope::Dialog,
+        set_at: Utc::now(),
+        expires_at: None,
+        source: dialog_id,
+    };
+    let mut turn = Turn::new(
+        1,
+        participant.id,
+        Message::text("Hello")
+            .with_sentiment(0.5)
+            .with_embeddings(vec![0.1, 0.2]),
+        TurnType::UserQuery,
+    );
+    turn.metadata
+        .properties
+        .insert("phase".to_string(), serde_json::json!("opening"));
+    let mut updated_variables = HashMap::new();
+    updated_variables.insert("count".to_string(), serde_json::json!(u64::MAX));
+
+    vec![
+        DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: participant.clone(),
+            started_at: Utc::now(),
+        }),
+        DialogDomainEvent::DialogEnded(DialogEnded {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            ended_at: Utc::now(),
+            reason: Some(EndReason::new(EndReasonCode::Resolved).with_detail("resolved")),
+            final_metrics: metrics.clone(),
+        }),
+        DialogDomainEvent::DialogPaused(DialogPaused {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            paused_at: Utc::now(),
+            context_snapshot: HashMap::from([("locale".to_string(), variable.clone())]),
+        }),
+        DialogDomainEvent::DialogResumed(DialogResumed {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            resumed_at: Utc::now(),
+            restored_variables: Some(vec!["locale".to_string()]),
+        }),
+        DialogDomainEvent::TurnAdded(TurnAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn: turn.clone(),
+            turn_number: 1,
+        }),
+        DialogDomainEvent::ParticipantAdded(ParticipantAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            participant: participant.clone(),
+            added_at: Utc::now(),
+        }),
+        DialogDomainEvent::ParticipantRemoved(ParticipantRemoved {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            participant_id: participant.id,
+            removed_at: Utc::now(),
+            reason: None,
+        }),
+        DialogDomainEvent::ContextSwitched(ContextSwitched {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            previous_topic: None,
+            new_topic: Topic::new("Billing", vec!["invoice".to_string()]),
+            switched_at: Utc::now(),
+        }),
+        DialogDomainEvent::ContextUpdated(ContextUpdated {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            updated_variables,
+            updated_at: Utc::now(),
+        }),
+        DialogDomainEvent::ContextVariableAdded(ContextVariableAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            variable,
+            added_at: Utc::now(),
+        }),
+        DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            key: "priority".to_string(),
+            value: serde_json::json!(-1.5),
+            set_at: Utc::now(),
+        }),
+        DialogDomainEvent::TopicCompleted(TopicCompleted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            topic_id: Uuid::new_v4(),
+            completed_at: Utc::now(),
+            resolution: Some("refunded".to_string()),
+        }),
+        DialogDomainEvent::TurnPinned(TurnPinned {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            pinned_at: Utc::now(),
+        }),
+        DialogDomainEvent::TurnUnpinned(TurnUnpinned {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            unpinned_at: Utc::now(),
+        }),
+        DialogDomainEvent::TurnRetracted(TurnRetracted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            retracted_at: Utc::now(),
+            reason: None,
+        }),
+        DialogDomainEvent::TurnsArchived(TurnsArchived {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_ids: vec![turn.turn_id],
+            archived_at: Utc::now(),
+        }),
+        DialogDomainEvent::DialogLocked(DialogLocked {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            locked_at: Utc::now(),
+            reason: Some("moderation".to_string()),
+        }),
+        DialogDomainEvent::DialogUnlocked(DialogUnlocked {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            unlocked_at: Utc::now(),
+        }),
+        DialogDomainEvent::TopicsRelated(TopicsRelated {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            topic_a: Uuid::new_v4(),
+            topic_b: Uuid::new_v4(),
+            related_at: Utc::now(),
+        }),
+        DialogDomainEvent::TopicsUnrelated(TopicsUnrelated {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            topic_a: Uuid::new_v4(),
+            topic_b: Uuid::new_v4(),
+            unrelated_at: Utc::now(),
+        }),
+        DialogDomainEvent::TurnFlagged(TurnFlagged {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            reason: "profanity".to_string(),
+            flagged_at: Utc::now(),
+        }),
+        DialogDomainEvent::ResolutionSet(ResolutionSet {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            resolution: ResolutionOutcome::Escalated,
+            set_at: Utc::now(),
+        }),
+        DialogDomainEvent::EmbeddingAttached(EmbeddingAttached {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            embeddings: vec![0.25, -0.5, 1.0],
+            attached_at: Utc::now(),
+        }),
+        DialogDomainEvent::TurnScheduled(TurnScheduled {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn: turn.clone(),
+            deliver_at: Utc::now() + chrono::Duration::hours(1),
+            scheduled_at: Utc::now(),
+        }),
+        DialogDomainEvent::MetricsUpdated(MetricsUpdated {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            metrics,
+            delta: MetricsDelta {
+                turn_count: 1,
+                ..Default::default()
+            },
+            updated_at: Utc::now(),
+        }),
+        DialogDomainEvent::ContextStateChanged(ContextStateChanged {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            previous_state: crate::ContextState::Normal,
+            new_state: crate::ContextState::AwaitingClarification,
+            changed_at: Utc::now(),
+        }),
+        DialogDomainEvent::PhaseChanged(PhaseChanged {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            previous_phase: Some(crate::ConversationPhase::Greeting),
+            new_phase: crate::ConversationPhase::Triage,
+            changed_at: Utc::now(),
+        }),
+        DialogDomainEvent::DialogAbandoned(DialogAbandoned {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            abandoned_at: Utc::now(),
+            reason: Some("timed out".to_string()),
+        }),
+        DialogDomainEvent::ResolutionTurnMarked(ResolutionTurnMarked {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            marked_at: Utc::now(),
+        }),
+        DialogDomainEvent::ContextVariablesExpired(ContextVariablesExpired {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            names: vec!["locale".to_string()],
+            expired_at: Utc::now(),
+        }),
+        DialogDomainEvent::TurnEdited(TurnEdited {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            new_message: Message::text("Corrected"),
+            edited_at: Utc::now(),
+        }),
+        DialogDomainEvent::ScheduledTurnRejected(ScheduledTurnRejected {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_id: turn.turn_id,
+            reason: "Participant not in dialog".to_string(),
+            rejected_at: Utc::now(),
+        }),
+    ]
+}
+
+/// Has no wildcard arm, so adding a variant fails to compile until it is
+/// listed here and in [`all_variants`]
+#[allow(dead_code)]
+fn listed(event: &DialogDomainEvent) {
+    match event {
+        DialogDomainEvent::DialogStarted(_)
+        | DialogDomainEvent::DialogEnded(_)
+        | DialogDomainEvent::DialogPaused(_)
+        | DialogDomainEvent::DialogResumed(_)
+        | DialogDomainEvent::TurnAdded(_)
+        | DialogDomainEvent::ParticipantAdded(_)
+        | DialogDomainEvent::ParticipantRemoved(_)
+        | DialogDomainEvent::ContextSwitched(_)
+        | DialogDomainEvent::ContextUpdated(_)
+        | DialogDomainEvent::ContextVariableAdded(_)
+        | DialogDomainEvent::DialogMetadataSet(_)
+        | DialogDomainEvent::TopicCompleted(_)
+        | DialogDomainEvent::TurnPinned(_)
+        | DialogDomainEvent::TurnUnpinned(_)
+        | DialogDomainEvent::TurnRetracted(_)
+        | DialogDomainEvent::TurnsArchived(_)
+        | DialogDomainEvent::DialogLocked(_)
+        | DialogDomainEvent::DialogUnlocked(_)
+        | DialogDomainEvent::TopicsRelated(_)
+        | DialogDomainEvent::TopicsUnrelated(_)
+        | DialogDomainEvent::TurnFlagged(_)
+        | DialogDomainEvent::ResolutionSet(_)
+        | DialogDomainEvent::EmbeddingAttached(_)
+        | DialogDomainEvent::TurnScheduled(_)
+        | DialogDomainEvent::MetricsUpdated(_)
+        | DialogDomainEvent::ContextStateChanged(_)
+        | DialogDomainEvent::PhaseChanged(_)
+        | DialogDomainEvent::DialogAbandoned(_)
+        | DialogDomainEvent::ResolutionTurnMarked(_)
+        | DialogDomainEvent::ContextVariablesExpired(_)
+        | DialogDomainEvent::TurnEdited(_)
+        | DialogDomainEvent::ScheduledTurnRejected(_) => {}
+    }
+}
@@ -6,11 +6,34 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
-use crate::value_objects::{ContextVariable, ConversationMetrics, Participant, Topic, Turn};
+use crate::value_objects::{
+    ContextVariable, ConversationMetrics, EndReason, Message, MetricsDelta, Participant,
+    ResolutionOutcome, Topic, Turn,
+};
+
+mod envelope;
+pub use envelope::DialogEventError;
+
+mod store;
+pub use store::{EventStore, InMemoryEventStore};
+
+mod stream;
+pub use stream::{
+    EVENT_STREAM_SCHEMA_VERSION, EventStreamError, export_event_stream, import_event_stream,
+};
+
+#[cfg(feature = "binary")]
+mod binary;
+#[cfg(test)]
+pub(crate) mod fixtures;
+#[cfg(feature = "binary")]
+pub use binary::EventCodecError;
 
 /// Dialog started event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DialogStarted {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub dialog_type: crate::DialogType,
     pub primary_participant: Participant,
@@ -34,9 +57,11 @@ impl DomainEvent for DialogStarted {
 /// Dialog ended event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DialogEnded {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub ended_at: DateTime<Utc>,
-    pub reason: Option<String>,
+    pub reason: Option<EndReason>,
     pub final_metrics: ConversationMetrics,
 }
 
@@ -57,6 +82,8 @@ impl DomainEvent for DialogEnded {
 /// Turn added to dialog
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TurnAdded {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub turn: Turn,
     pub turn_number: u32,
@@ -79,6 +106,8 @@ impl DomainEvent for TurnAdded {
 /// Context switched event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextSwitched {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub previous_topic: Option<Uuid>,
     pub new_topic: Topic,
@@ -102,6 +131,8 @@ impl DomainEvent for ContextSwitched {
 /// Context updated event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextUpdated {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub updated_variables: HashMap<String, serde_json::Value>,
     pub updated_at: DateTime<Utc>,
@@ -124,6 +155,8 @@ impl DomainEvent for ContextUpdated {
 /// Dialog paused event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DialogPaused {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub paused_at: DateTime<Utc>,
     pub context_snapshot: HashMap<String, ContextVariable>,
@@ -146,8 +179,14 @@ impl DomainEvent for DialogPaused {
 /// Dialog resumed event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DialogResumed {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub resumed_at: DateTime<Utc>,
+    /// Names of the context variables restored from the pause snapshot, or
+    /// `None` if the resume left the context as it was
+    #[serde(default)]
+    pub restored_variables: Option<Vec<String>>,
 }
 
 impl DomainEvent for DialogResumed {
@@ -167,6 +206,8 @@ impl DomainEvent for DialogResumed {
 /// Dialog metadata set event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DialogMetadataSet {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub key: String,
     pub value: serde_json::Value,
@@ -190,6 +231,8 @@ impl DomainEvent for DialogMetadataSet {
 /// Participant added event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParticipantAdded {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub participant: Participant,
     pub added_at: DateTime<Utc>,
@@ -212,6 +255,8 @@ impl DomainEvent for ParticipantAdded {
 /// Participant removed event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParticipantRemoved {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub participant_id: Uuid,
     pub removed_at: DateTime<Utc>,
@@ -235,6 +280,8 @@ impl DomainEvent for ParticipantRemoved {
 /// Topic completed event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TopicCompleted {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub topic_id: Uuid,
     pub completed_at: DateTime<Utc>,
@@ -258,6 +305,8 @@ impl DomainEvent for TopicCompleted {
 /// Context variable added event
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextVariableAdded {
+    #[serde(default)]
+    pub event_id: Uuid,
     pub dialog_id: Uuid,
     pub variable: ContextVariable,
     pub added_at: DateTime<Utc>,
@@ -277,6 +326,496 @@ impl DomainEvent for ContextVariableAdded {
     }
 }
 
+/// Turn pinned event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnPinned {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub pinned_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TurnPinned {
+    fn subject(&self) -> String {
+        "dialog.turn.pinned.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TurnPinned"
+    }
+}
+
+/// Turn unpinned event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnUnpinned {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub unpinned_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TurnUnpinned {
+    fn subject(&self) -> String {
+        "dialog.turn.unpinned.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TurnUnpinned"
+    }
+}
+
+/// Turn retracted event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnRetracted {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub retracted_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+impl DomainEvent for TurnRetracted {
+    fn subject(&self) -> String {
+        "dialog.turn.retracted.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TurnRetracted"
+    }
+}
+
+/// Turns archived event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnsArchived {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_ids: Vec<Uuid>,
+    pub archived_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TurnsArchived {
+    fn subject(&self) -> String {
+        "dialog.turns.archived.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TurnsArchived"
+    }
+}
+
+/// Dialog locked event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogLocked {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub locked_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+impl DomainEvent for DialogLocked {
+    fn subject(&self) -> String {
+        "dialog.locked.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "DialogLocked"
+    }
+}
+
+/// Dialog unlocked event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogUnlocked {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub unlocked_at: DateTime<Utc>,
+}
+
+impl DomainEvent for DialogUnlocked {
+    fn subject(&self) -> String {
+        "dialog.unlocked.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "DialogUnlocked"
+    }
+}
+
+/// Topics related event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicsRelated {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub topic_a: Uuid,
+    pub topic_b: Uuid,
+    pub related_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TopicsRelated {
+    fn subject(&self) -> String {
+        "dialog.topics.related.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TopicsRelated"
+    }
+}
+
+/// Topics unrelated event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicsUnrelated {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub topic_a: Uuid,
+    pub topic_b: Uuid,
+    pub unrelated_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TopicsUnrelated {
+    fn subject(&self) -> String {
+        "dialog.topics.unrelated.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TopicsUnrelated"
+    }
+}
+
+/// Turn flagged event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnFlagged {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub reason: String,
+    pub flagged_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TurnFlagged {
+    fn subject(&self) -> String {
+        "dialog.turn.flagged.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TurnFlagged"
+    }
+}
+
+/// Dialog resolution outcome recorded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolutionSet {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub resolution: ResolutionOutcome,
+    pub set_at: DateTime<Utc>,
+}
+
+impl DomainEvent for ResolutionSet {
+    fn subject(&self) -> String {
+        "dialog.resolution.set.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "ResolutionSet"
+    }
+}
+
+/// Embedding attached to an existing turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingAttached {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub embeddings: Vec<f32>,
+    pub attached_at: DateTime<Utc>,
+}
+
+impl DomainEvent for EmbeddingAttached {
+    fn subject(&self) -> String {
+        "dialog.turn.embedding.attached.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "EmbeddingAttached"
+    }
+}
+
+/// Turn queued for future delivery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnScheduled {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn: Turn,
+    pub deliver_at: DateTime<Utc>,
+    pub scheduled_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TurnScheduled {
+    fn subject(&self) -> String {
+        "dialog.turn.scheduled.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TurnScheduled"
+    }
+}
+
+/// Conversation metrics changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsUpdated {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub metrics: ConversationMetrics,
+    pub delta: MetricsDelta,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DomainEvent for MetricsUpdated {
+    fn subject(&self) -> String {
+        "dialog.metrics.updated.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "MetricsUpdated"
+    }
+}
+
+/// Conversation context moved to a different state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextStateChanged {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub previous_state: crate::ContextState,
+    pub new_state: crate::ContextState,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl DomainEvent for ContextStateChanged {
+    fn subject(&self) -> String {
+        "dialog.context.state.changed.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "ContextStateChanged"
+    }
+}
+
+/// Conversation phase changed event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseChanged {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub previous_phase: Option<crate::ConversationPhase>,
+    pub new_phase: crate::ConversationPhase,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl DomainEvent for PhaseChanged {
+    fn subject(&self) -> String {
+        "dialog.phase.changed.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "PhaseChanged"
+    }
+}
+
+/// Dialog abandoned without a graceful ending, e.g. the user walked away
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DialogAbandoned {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub abandoned_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+impl DomainEvent for DialogAbandoned {
+    fn subject(&self) -> String {
+        "dialog.abandoned.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "DialogAbandoned"
+    }
+}
+
+/// Turn marked as the one that resolved the user's issue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolutionTurnMarked {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub marked_at: DateTime<Utc>,
+}
+
+impl DomainEvent for ResolutionTurnMarked {
+    fn subject(&self) -> String {
+        "dialog.resolution_turn.marked.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "ResolutionTurnMarked"
+    }
+}
+
+/// Expired context variables were removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextVariablesExpired {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub names: Vec<String>,
+    pub expired_at: DateTime<Utc>,
+}
+
+impl DomainEvent for ContextVariablesExpired {
+    fn subject(&self) -> String {
+        "dialog.context.variables.expired.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "ContextVariablesExpired"
+    }
+}
+
+/// Turn message was replaced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnEdited {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub new_message: Message,
+    pub edited_at: DateTime<Utc>,
+}
+
+impl DomainEvent for TurnEdited {
+    fn subject(&self) -> String {
+        "dialog.turn.edited.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "TurnEdited"
+    }
+}
+
+/// Scheduled turn dropped from the queue because it could not be added when due
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTurnRejected {
+    #[serde(default)]
+    pub event_id: Uuid,
+    pub dialog_id: Uuid,
+    pub turn_id: Uuid,
+    pub reason: String,
+    pub rejected_at: DateTime<Utc>,
+}
+
+impl DomainEvent for ScheduledTurnRejected {
+    fn subject(&self) -> String {
+        "dialog.turn.scheduled.rejected.v1".to_string()
+    }
+
+    fn aggregate_id(&self) -> Uuid {
+        self.dialog_id
+    }
+
+    fn event_type(&self) -> &'static str {
+        "ScheduledTurnRejected"
+    }
+}
+
 /// Dialog domain event enum
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DialogDomainEvent {
@@ -292,6 +831,26 @@ pub enum DialogDomainEvent {
     ContextVariableAdded(ContextVariableAdded),
     DialogMetadataSet(DialogMetadataSet),
     TopicCompleted(TopicCompleted),
+    TurnPinned(TurnPinned),
+    TurnUnpinned(TurnUnpinned),
+    TurnRetracted(TurnRetracted),
+    TurnsArchived(TurnsArchived),
+    DialogLocked(DialogLocked),
+    DialogUnlocked(DialogUnlocked),
+    TopicsRelated(TopicsRelated),
+    TopicsUnrelated(TopicsUnrelated),
+    TurnFlagged(TurnFlagged),
+    ResolutionSet(ResolutionSet),
+    EmbeddingAttached(EmbeddingAttached),
+    TurnScheduled(TurnScheduled),
+    MetricsUpdated(MetricsUpdated),
+    ContextStateChanged(ContextStateChanged),
+    PhaseChanged(PhaseChanged),
+    DialogAbandoned(DialogAbandoned),
+    ResolutionTurnMarked(ResolutionTurnMarked),
+    ContextVariablesExpired(ContextVariablesExpired),
+    TurnEdited(TurnEdited),
+    ScheduledTurnRejected(ScheduledTurnRejected),
 }
 
 impl DomainEvent for DialogDomainEvent {
@@ -309,6 +868,26 @@ impl DomainEvent for DialogDomainEvent {
             Self::ContextVariableAdded(e) => e.subject(),
             Self::DialogMetadataSet(e) => e.subject(),
             Self::TopicCompleted(e) => e.subject(),
+            Self::TurnPinned(e) => e.subject(),
+            Self::TurnUnpinned(e) => e.subject(),
+            Self::TurnRetracted(e) => e.subject(),
+            Self::TurnsArchived(e) => e.subject(),
+            Self::DialogLocked(e) => e.subject(),
+            Self::DialogUnlocked(e) => e.subject(),
+            Self::TopicsRelated(e) => e.subject(),
+            Self::TopicsUnrelated(e) => e.subject(),
+            Self::TurnFlagged(e) => e.subject(),
+            Self::ResolutionSet(e) => e.subject(),
+            Self::EmbeddingAttached(e) => e.subject(),
+            Self::TurnScheduled(e) => e.subject(),
+            Self::MetricsUpdated(e) => e.subject(),
+            Self::ContextStateChanged(e) => e.subject(),
+            Self::PhaseChanged(e) => e.subject(),
+            Self::DialogAbandoned(e) => e.subject(),
+            Self::ResolutionTurnMarked(e) => e.subject(),
+            Self::ContextVariablesExpired(e) => e.subject(),
+            Self::TurnEdited(e) => e.subject(),
+            Self::ScheduledTurnRejected(e) => e.subject(),
         }
     }
 
@@ -326,6 +905,26 @@ impl DomainEvent for DialogDomainEvent {
             Self::ContextVariableAdded(e) => e.aggregate_id(),
             Self::DialogMetadataSet(e) => e.aggregate_id(),
             Self::TopicCompleted(e) => e.aggregate_id(),
+            Self::TurnPinned(e) => e.aggregate_id(),
+            Self::TurnUnpinned(e) => e.aggregate_id(),
+            Self::TurnRetracted(e) => e.aggregate_id(),
+            Self::TurnsArchived(e) => e.aggregate_id(),
+            Self::DialogLocked(e) => e.aggregate_id(),
+            Self::DialogUnlocked(e) => e.aggregate_id(),
+            Self::TopicsRelated(e) => e.aggregate_id(),
+            Self::TopicsUnrelated(e) => e.aggregate_id(),
+            Self::TurnFlagged(e) => e.aggregate_id(),
+            Self::ResolutionSet(e) => e.aggregate_id(),
+            Self::EmbeddingAttached(e) => e.aggregate_id(),
+            Self::TurnScheduled(e) => e.aggregate_id(),
+            Self::MetricsUpdated(e) => e.aggregate_id(),
+            Self::ContextStateChanged(e) => e.aggregate_id(),
+            Self::PhaseChanged(e) => e.aggregate_id(),
+            Self::DialogAbandoned(e) => e.aggregate_id(),
+            Self::ResolutionTurnMarked(e) => e.aggregate_id(),
+            Self::ContextVariablesExpired(e) => e.aggregate_id(),
+            Self::TurnEdited(e) => e.aggregate_id(),
+            Self::ScheduledTurnRejected(e) => e.aggregate_id(),
         }
     }
 
@@ -343,6 +942,108 @@ impl DomainEvent for DialogDomainEvent {
             Self::ContextVariableAdded(e) => e.event_type(),
             Self::DialogMetadataSet(e) => e.event_type(),
             Self::TopicCompleted(e) => e.event_type(),
+            Self::TurnPinned(e) => e.event_type(),
+            Self::TurnUnpinned(e) => e.event_type(),
+            Self::TurnRetracted(e) => e.event_type(),
+            Self::TurnsArchived(e) => e.event_type(),
+            Self::DialogLocked(e) => e.event_type(),
+            Self::DialogUnlocked(e) => e.event_type(),
+            Self::TopicsRelated(e) => e.event_type(),
+            Self::TopicsUnrelated(e) => e.event_type(),
+            Self::TurnFlagged(e) => e.event_type(),
+            Self::ResolutionSet(e) => e.event_type(),
+            Self::EmbeddingAttached(e) => e.event_type(),
+            Self::TurnScheduled(e) => e.event_type(),
+            Self::MetricsUpdated(e) => e.event_type(),
+            Self::ContextStateChanged(e) => e.event_type(),
+            Self::PhaseChanged(e) => e.event_type(),
+            Self::DialogAbandoned(e) => e.event_type(),
+            Self::ResolutionTurnMarked(e) => e.event_type(),
+            Self::ContextVariablesExpired(e) => e.event_type(),
+            Self::TurnEdited(e) => e.event_type(),
+            Self::ScheduledTurnRejected(e) => e.event_type(),
+        }
+    }
+}
+
+impl DialogDomainEvent {
+    /// Unique ID of the event
+    ///
+    /// Redelivered copies of an event keep its ID, so consumers can use it to
+    /// skip duplicates. Events recorded before IDs were introduced have the
+    /// nil ID.
+    pub fn event_id(&self) -> Uuid {
+        match self {
+            Self::DialogStarted(e) => e.event_id,
+            Self::DialogEnded(e) => e.event_id,
+            Self::DialogPaused(e) => e.event_id,
+            Self::DialogResumed(e) => e.event_id,
+            Self::TurnAdded(e) => e.event_id,
+            Self::ParticipantAdded(e) => e.event_id,
+            Self::ParticipantRemoved(e) => e.event_id,
+            Self::ContextSwitched(e) => e.event_id,
+            Self::ContextUpdated(e) => e.event_id,
+            Self::ContextVariableAdded(e) => e.event_id,
+            Self::DialogMetadataSet(e) => e.event_id,
+            Self::TopicCompleted(e) => e.event_id,
+            Self::TurnPinned(e) => e.event_id,
+            Self::TurnUnpinned(e) => e.event_id,
+            Self::TurnRetracted(e) => e.event_id,
+            Self::TurnsArchived(e) => e.event_id,
+            Self::DialogLocked(e) => e.event_id,
+            Self::DialogUnlocked(e) => e.event_id,
+            Self::TopicsRelated(e) => e.event_id,
+            Self::TopicsUnrelated(e) => e.event_id,
+            Self::TurnFlagged(e) => e.event_id,
+            Self::ResolutionSet(e) => e.event_id,
+            Self::EmbeddingAttached(e) => e.event_id,
+            Self::TurnScheduled(e) => e.event_id,
+            Self::MetricsUpdated(e) => e.event_id,
+            Self::ContextStateChanged(e) => e.event_id,
+            Self::PhaseChanged(e) => e.event_id,
+            Self::DialogAbandoned(e) => e.event_id,
+            Self::ResolutionTurnMarked(e) => e.event_id,
+            Self::ContextVariablesExpired(e) => e.event_id,
+            Self::TurnEdited(e) => e.event_id,
+            Self::ScheduledTurnRejected(e) => e.event_id,
+        }
+    }
+
+    /// When the event occurred
+    pub fn occurred_at(&self) -> DateTime<Utc> {
+        match self {
+            Self::DialogStarted(e) => e.started_at,
+            Self::DialogEnded(e) => e.ended_at,
+            Self::DialogPaused(e) => e.paused_at,
+            Self::DialogResumed(e) => e.resumed_at,
+            Self::TurnAdded(e) => e.turn.timestamp,
+            Self::ParticipantAdded(e) => e.added_at,
+            Self::ParticipantRemoved(e) => e.removed_at,
+            Self::ContextSwitched(e) => e.switched_at,
+            Self::ContextUpdated(e) => e.updated_at,
+            Self::ContextVariableAdded(e) => e.added_at,
+            Self::DialogMetadataSet(e) => e.set_at,
+            Self::TopicCompleted(e) => e.completed_at,
+            Self::TurnPinned(e) => e.pinned_at,
+            Self::TurnUnpinned(e) => e.unpinned_at,
+            Self::TurnRetracted(e) => e.retracted_at,
+            Self::TurnsArchived(e) => e.archived_at,
+            Self::DialogLocked(e) => e.locked_at,
+            Self::DialogUnlocked(e) => e.unlocked_at,
+            Self::TopicsRelated(e) => e.related_at,
+            Self::TopicsUnrelated(e) => e.unrelated_at,
+            Self::TurnFlagged(e) => e.flagged_at,
+            Self::ResolutionSet(e) => e.set_at,
+            Self::EmbeddingAttached(e) => e.attached_at,
+            Self::TurnScheduled(e) => e.scheduled_at,
+            Self::MetricsUpdated(e) => e.updated_at,
+            Self::ContextStateChanged(e) => e.changed_at,
+            Self::PhaseChanged(e) => e.changed_at,
+            Self::DialogAbandoned(e) => e.abandoned_at,
+            Self::ResolutionTurnMarked(e) => e.marked_at,
+            Self::ContextVariablesExpired(e) => e.expired_at,
+            Self::TurnEdited(e) => e.edited_at,
+            Self::ScheduledTurnRejected(e) => e.rejected_at,
         }
     }
 }
@@ -0,0 +1,142 @@
+//! Append-only event storage
+//!
+//! Every appended event is assigned a global sequence number, starting at 1
+//! and increasing by one per event across all dialogs. Consumers rebuilding
+//! projections page through the store with [`EventStore::read_all_paged`],
+//! checkpointing the last sequence they applied and resuming after it.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{DialogDomainEvent, DialogEventError};
+
+/// Append-only store of dialog events
+#[async_trait]
+pub trait EventStore: Send + Sync {
+    /// Append an event, returning its global sequence number
+    async fn append(&self, event: DialogDomainEvent) -> u64;
+
+    /// Read up to `limit` events with a sequence greater than `after_global_seq`,
+    /// in sequence order
+    ///
+    /// Pass 0 to read from the beginning, then the last returned sequence to
+    /// resume. An empty page means the consumer has caught up. Stores that
+    /// persist serialized events report payloads they cannot decode as errors.
+    async fn read_all_paged(
+        &self,
+        after_global_seq: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, DialogDomainEvent)>, DialogEventError>;
+}
+
+/// In-memory implementation of EventStore
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryEventStore {
+    /// Event with sequence `n` is stored at index `n - 1`
+    events: Arc<RwLock<Vec<DialogDomainEvent>>>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn append(&self, event: DialogDomainEvent) -> u64 {
+        let mut events = self.events.write().await;
+        events.push(event);
+        events.len() as u64
+    }
+
+    async fn read_all_paged(
+        &self,
+        after_global_seq: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, DialogDomainEvent)>, DialogEventError> {
+        let events = self.events.read().await;
+        let start = usize::try_from(after_global_seq)
+            .unwrap_or(usize::MAX)
+            .min(events.len());
+
+        Ok(events[start..]
+            .iter()
+            .take(limit)
+            .zip(after_global_seq + 1..)
+            .map(|(event, seq)| (seq, event.clone()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::TurnAdded;
+    use crate::value_objects::{Message, Turn, TurnType};
+    use cim_domain::DomainEvent;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_read_all_paged() {
+        let store = InMemoryEventStore::new();
+        let dialogs = [Uuid::new_v4(), Uuid::new_v4()];
+        let mut turn_ids = Vec::new();
+        for turn_number in 1..=25 {
+            let turn = Turn::new(
+                turn_number,
+                Uuid::new_v4(),
+                Message::text("Hello"),
+                TurnType::UserQuery,
+            );
+            turn_ids.push(turn.turn_id);
+            let seq = store
+                .append(DialogDomainEvent::TurnAdded(TurnAdded {
+                    event_id: Uuid::new_v4(),
+                    dialog_id: dialogs[turn_number as usize % 2],
+                    turn,
+                    turn_number,
+                }))
+                .await;
+            assert_eq!(seq, turn_number as u64);
+        }
+
+        // Page through in chunks, resuming after the last sequence read
+        let mut checkpoint = 0;
+        let mut read = Vec::new();
+        loop {
+            let page = store.read_all_paged(checkpoint, 7).await.unwrap();
+            if page.is_empty() {
+                break;
+            }
+            assert!(page.len() <= 7);
+            checkpoint = page.last().unwrap().0;
+            read.extend(page);
+        }
+
+        let seqs: Vec<u64> = read.iter().map(|(seq, _)| *seq).collect();
+        assert_eq!(seqs, (1..=25).collect::<Vec<_>>());
+        let read_turns: Vec<Uuid> = read
+            .iter()
+            .map(|(_, event)| match event {
+                DialogDomainEvent::TurnAdded(e) => e.turn.turn_id,
+                _ => panic!("Expected TurnAdded"),
+            })
+            .collect();
+        assert_eq!(read_turns, turn_ids);
+        assert_eq!(read[0].1.aggregate_id(), dialogs[1]);
+
+        // Resuming past the end or with no limit yields nothing
+        assert!(store.read_all_paged(25, 10).await.unwrap().is_empty());
+        assert!(store.read_all_paged(100, 10).await.unwrap().is_empty());
+        assert!(store.read_all_paged(0, 0).await.unwrap().is_empty());
+
+        // Newly appended events are picked up from the checkpoint
+        let seq = store.append(read[0].1.clone()).await;
+        assert_eq!(seq, 26);
+        let page = store.read_all_paged(checkpoint, 7).await.unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].0, 26);
+    }
+}
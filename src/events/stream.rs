@@ -0,0 +1,228 @@
+//! Portable event stream dumps
+//!
+//! A dialog's events can be exported into a self-contained, versioned JSON
+//! envelope and loaded back, e.g. to attach a reproducible event history to
+//! a bug report:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "dialog_id": "…",
+//!   "events": [
+//!     { "sequence": 0, "subject": "dialog.started.v1", "event": { "DialogStarted": { … } } }
+//!   ]
+//! }
+//! ```
+
+use cim_domain::DomainEvent;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::DialogDomainEvent;
+
+/// Current version of the event stream envelope
+pub const EVENT_STREAM_SCHEMA_VERSION: u64 = 1;
+
+/// Errors from importing an event stream envelope
+#[derive(Debug, Error)]
+pub enum EventStreamError {
+    #[error("missing or invalid field `{0}`")]
+    InvalidField(&'static str),
+    #[error("unsupported schema version {0}")]
+    UnsupportedVersion(u64),
+    #[error("event {index} could not be decoded: {source}")]
+    InvalidEvent {
+        index: usize,
+        source: serde_json::Error,
+    },
+    #[error("event {index} has sequence {found}, expected {index}")]
+    OutOfOrder { index: usize, found: u64 },
+    #[error("event {index} belongs to dialog {found}, not {expected}")]
+    ForeignEvent {
+        index: usize,
+        expected: Uuid,
+        found: Uuid,
+    },
+    #[error("event {index} has subject {found}, expected {expected}")]
+    SubjectMismatch {
+        index: usize,
+        expected: String,
+        found: String,
+    },
+}
+
+/// Export the events of one dialog as a versioned envelope
+///
+/// Events belonging to other dialogs are left out; the remaining events keep
+/// their order.
+pub fn export_event_stream(dialog_id: Uuid, events: &[DialogDomainEvent]) -> serde_json::Value {
+    let events: Vec<serde_json::Value> = events
+        .iter()
+        .filter(|e| e.aggregate_id() == dialog_id)
+        .enumerate()
+        .map(|(sequence, event)| {
+            serde_json::json!({
+                "sequence": sequence,
+                "subject": event.subject(),
+                "event": event,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "schema_version": EVENT_STREAM_SCHEMA_VERSION,
+        "dialog_id": dialog_id,
+        "events": events,
+    })
+}
+
+/// Validate an envelope produced by [`export_event_stream`] and return its events in order
+pub fn import_event_stream(
+    envelope: &serde_json::Value,
+) -> Result<Vec<DialogDomainEvent>, EventStreamError> {
+    let version = envelope
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .ok_or(EventStreamError::InvalidField("schema_version"))?;
+    if version != EVENT_STREAM_SCHEMA_VERSION {
+        return Err(EventStreamError::UnsupportedVersion(version));
+    }
+
+    let dialog_id = envelope
+        .get("dialog_id")
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .ok_or(EventStreamError::InvalidField("dialog_id"))?;
+
+    let entries = envelope
+        .get("events")
+        .and_then(|v| v.as_array())
+        .ok_or(EventStreamError::InvalidField("events"))?;
+
+    let mut events = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let sequence = entry
+            .get("sequence")
+            .and_then(|v| v.as_u64())
+            .ok_or(EventStreamError::InvalidField("sequence"))?;
+        if sequence != index as u64 {
+            return Err(EventStreamError::OutOfOrder {
+                index,
+                found: sequence,
+            });
+        }
+
+        let event: DialogDomainEvent =
+            serde_json::from_value(entry.get("event").cloned().unwrap_or_default())
+                .map_err(|source| EventStreamError::InvalidEvent { index, source })?;
+
+        if event.aggregate_id() != dialog_id {
+            return Err(EventStreamError::ForeignEvent {
+                index,
+                expected: dialog_id,
+                found: event.aggregate_id(),
+            });
+        }
+
+        let subject = entry
+            .get("subject")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default();
+        if subject != event.subject() {
+            return Err(EventStreamError::SubjectMismatch {
+                index,
+                expected: event.subject(),
+                found: subject.to_string(),
+            });
+        }
+
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::DialogType;
+    use crate::events::{DialogPaused, DialogResumed, DialogStarted, TurnAdded};
+    use crate::value_objects::{
+        Message, Participant, ParticipantRole, ParticipantType, Turn, TurnType,
+    };
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_event_stream_round_trip() {
+        let dialog_id = Uuid::new_v4();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+
+        let events = vec![
+            DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                dialog_type: DialogType::Support,
+                primary_participant: user.clone(),
+                started_at: Utc::now(),
+            }),
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: Turn::new(1, user.id, Message::text("Help"), TurnType::UserQuery),
+                turn_number: 1,
+            }),
+            // Events of other dialogs are not exported
+            DialogDomainEvent::DialogResumed(DialogResumed {
+                event_id: Uuid::new_v4(),
+                dialog_id: Uuid::new_v4(),
+                resumed_at: Utc::now(),
+                restored_variables: None,
+            }),
+            DialogDomainEvent::DialogPaused(DialogPaused {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                paused_at: Utc::now(),
+                context_snapshot: HashMap::new(),
+            }),
+        ];
+
+        let envelope = export_event_stream(dialog_id, &events);
+        assert_eq!(envelope["schema_version"], EVENT_STREAM_SCHEMA_VERSION);
+        assert_eq!(envelope["events"][1]["subject"], "dialog.turn.added.v1");
+
+        let imported = import_event_stream(&envelope).unwrap();
+        let expected: Vec<&DialogDomainEvent> = events
+            .iter()
+            .filter(|e| e.aggregate_id() == dialog_id)
+            .collect();
+        assert_eq!(imported.len(), 3);
+        for (imported, expected) in imported.iter().zip(expected) {
+            assert_eq!(
+                serde_json::to_value(imported).unwrap(),
+                serde_json::to_value(expected).unwrap()
+            );
+        }
+
+        // Invalid envelopes are rejected
+        let mut wrong_version = envelope.clone();
+        wrong_version["schema_version"] = serde_json::json!(99);
+        assert!(matches!(
+            import_event_stream(&wrong_version),
+            Err(EventStreamError::UnsupportedVersion(99))
+        ));
+
+        let mut reordered = envelope.clone();
+        reordered["events"].as_array_mut().unwrap().swap(0, 1);
+        assert!(matches!(
+            import_event_stream(&reordered),
+            Err(EventStreamError::OutOfOrder { index: 0, found: 1 })
+        ));
+    }
+}
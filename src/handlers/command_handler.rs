@@ -1,24 +1,46 @@
 //! Dialog command handler implementation
 
-use cim_domain::{
-    DomainError, DomainResult, EntityId, AggregateRepository,
-};
-use std::sync::Arc;
 use chrono::Utc;
+use cim_domain::{AggregateRepository, AggregateRoot, DomainError, DomainResult, EntityId};
+use std::sync::Arc;
+use uuid::Uuid;
 
+use super::{CommandInterceptor, ContentFilter, FilterVerdict, NoopContentFilter};
 use crate::{
-    aggregate::{Dialog, DialogMarker},
+    aggregate::{Dialog, DialogMarker, DialogStatus},
     commands::*,
     events::*,
-    value_objects::ConversationMetrics,
 };
 
+/// Events produced by a command together with the resulting aggregate state
+#[derive(Debug, Clone)]
+pub struct CommandOutcome {
+    /// Events emitted by the command
+    pub events: Vec<DialogDomainEvent>,
+    /// Aggregate version after the command
+    pub aggregate_version: u64,
+    /// Dialog status after the command
+    pub status: DialogStatus,
+}
+
+impl CommandOutcome {
+    fn new(events: Vec<DialogDomainEvent>, dialog: &Dialog) -> Self {
+        Self {
+            events,
+            aggregate_version: dialog.version(),
+            status: dialog.status(),
+        }
+    }
+}
+
 /// Handler for dialog commands
-pub struct DialogCommandHandler<R> 
+pub struct DialogCommandHandler<R>
 where
     R: AggregateRepository<Dialog> + Send + Sync,
 {
     repository: Arc<R>,
+    interceptors: Vec<Box<dyn CommandInterceptor>>,
+    content_filter: Box<dyn ContentFilter>,
 }
 
 impl<R> DialogCommandHandler<R>
@@ -29,408 +51,388 @@ where
     pub fn new(repository: Arc<R>) -> Self {
         Self {
             repository,
+            interceptors: Vec::new(),
+            content_filter: Box::new(NoopContentFilter),
         }
     }
 
-    /// Handle StartDialog command
-    pub fn handle_start_dialog(&self, cmd: StartDialog) -> DomainResult<Vec<DialogDomainEvent>> {
+    /// Set the filter consulted by `handle_add_turn`
+    pub fn set_content_filter(&mut self, filter: Box<dyn ContentFilter>) {
+        self.content_filter = filter;
+    }
+
+    /// Add an interceptor to run before commands passed to `dispatch`
+    ///
+    /// Interceptors run in the order they were added.
+    pub fn add_interceptor(&mut self, interceptor: Box<dyn CommandInterceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    /// Run the interceptor chain, then handle the command
+    ///
+    /// The first interceptor to return an error stops the chain and the
+    /// command is not handled.
+    pub fn dispatch(&self, cmd: DialogCommand) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.dispatch_with_outcome(cmd)
+            .map(|outcome| outcome.events)
+    }
+
+    /// Like `dispatch`, but also report the state of the dialog the command
+    /// left behind
+    ///
+    /// The version and status are those of the dialog as it was saved, so
+    /// callers do not need to load it again:
+    ///
+    /// ```ignore
+    /// let outcome = handler.dispatch_with_outcome(DialogCommand::EndDialog(cmd))?;
+    /// assert_eq!(outcome.status, DialogStatus::Ended);
+    /// ```
+    pub fn dispatch_with_outcome(&self, cmd: DialogCommand) -> DomainResult<CommandOutcome> {
+        for interceptor in &self.interceptors {
+            interceptor.before(&cmd)?;
+        }
+
+        self.execute(cmd)
+    }
+
+    /// Handle a command without running the interceptor chain
+    fn execute(&self, cmd: DialogCommand) -> DomainResult<CommandOutcome> {
+        match cmd {
+            DialogCommand::StartDialog(cmd) => self.start_dialog(cmd),
+            DialogCommand::EndDialog(cmd) => self.update(cmd.id, |dialog| {
+                dialog.end(cmd.reason).map_err(validation_error)
+            }),
+            DialogCommand::AbandonDialog(cmd) => {
+                self.update(cmd.id, |dialog| dialog.abandon(cmd.reason))
+            }
+            DialogCommand::AddTurn(cmd) => self.add_turn(cmd),
+            DialogCommand::SwitchContext(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog.switch_topic(cmd.topic).map_err(validation_error)
+            }),
+            DialogCommand::UpdateContext(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog
+                    .update_context(cmd.variables)
+                    .map_err(validation_error)
+            }),
+            DialogCommand::PauseDialog(cmd) => {
+                self.update(cmd.id, |dialog| dialog.pause().map_err(validation_error))
+            }
+            DialogCommand::ResumeDialog(cmd) => {
+                self.update(cmd.id, |dialog| dialog.resume().map_err(validation_error))
+            }
+            DialogCommand::ResumeDialogRestoring(cmd) => self.update(cmd.id, |dialog| {
+                dialog.resume_restoring().map_err(validation_error)
+            }),
+            DialogCommand::SetDialogMetadata(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog
+                    .set_metadata(cmd.key, cmd.value)
+                    .map_err(validation_error)
+            }),
+            DialogCommand::AddParticipant(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog
+                    .add_participant(cmd.participant)
+                    .map_err(validation_error)
+            }),
+            DialogCommand::RemoveParticipant(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog
+                    .remove_participant(cmd.participant_id, cmd.reason)
+                    .map_err(validation_error)
+            }),
+            // Subtopics a cascade completes come before the topic itself
+            DialogCommand::MarkTopicComplete(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog
+                    .mark_topic_complete(cmd.topic_id, cmd.resolution, cmd.cascade)
+                    .map_err(validation_error)
+            }),
+            // Already expired values are dropped without an event
+            DialogCommand::AddContextVariable(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog
+                    .add_context_variable(cmd.variable)
+                    .map_err(validation_error)
+            }),
+            DialogCommand::PinTurn(cmd) => {
+                self.update(cmd.dialog_id, |dialog| dialog.pin_turn(cmd.turn_id))
+            }
+            DialogCommand::UnpinTurn(cmd) => {
+                self.update(cmd.dialog_id, |dialog| dialog.unpin_turn(cmd.turn_id))
+            }
+            DialogCommand::LockDialog(cmd) => {
+                self.update(cmd.dialog_id, |dialog| dialog.lock(cmd.reason))
+            }
+            DialogCommand::UnlockDialog(cmd) => {
+                self.update(cmd.dialog_id, |dialog| dialog.unlock())
+            }
+            DialogCommand::SetResolution(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog.set_resolution(cmd.resolution)
+            }),
+            DialogCommand::MarkResolutionTurn(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog.mark_resolution_turn(cmd.turn_id)
+            }),
+            DialogCommand::PruneExpiredContext(cmd) => self.update(cmd.dialog_id, |dialog| {
+                Ok(dialog.expire_context(Utc::now()).into_iter().collect())
+            }),
+            DialogCommand::AttachEmbedding(cmd) => self.update(cmd.dialog_id, |dialog| {
+                dialog.attach_embedding(cmd.turn_id, cmd.embeddings)
+            }),
+        }
+    }
+
+    /// Load a dialog, run `command` on it and save it if anything changed
+    fn update<F>(&self, dialog_id: Uuid, command: F) -> DomainResult<CommandOutcome>
+    where
+        F: FnOnce(&mut Dialog) -> DomainResult<Vec<DialogDomainEvent>>,
+    {
+        // Load dialog aggregate
+        let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
+        let mut dialog = self
+            .repository
+            .load(entity_id)
+            .map_err(DomainError::Generic)?
+            .ok_or_else(|| DomainError::EntityNotFound {
+                entity_type: "Dialog".to_string(),
+                id: dialog_id.to_string(),
+            })?;
+
+        let events = command(&mut dialog)?;
+
+        // Save aggregate
+        if !events.is_empty() {
+            self.repository
+                .save(&dialog)
+                .map_err(DomainError::Generic)?;
+        }
+
+        Ok(CommandOutcome::new(events, &dialog))
+    }
+
+    fn start_dialog(&self, cmd: StartDialog) -> DomainResult<CommandOutcome> {
         // Create new dialog aggregate
-        let mut dialog = Dialog::new(
+        let mut dialog = Dialog::with_config(
             cmd.id,
             cmd.dialog_type,
             cmd.primary_participant.clone(),
+            cmd.config.unwrap_or_default(),
         );
 
-        let mut domain_events = vec![
-            DialogDomainEvent::DialogStarted(DialogStarted {
-                dialog_id: cmd.id,
-                dialog_type: cmd.dialog_type,
-                primary_participant: cmd.primary_participant,
-                started_at: Utc::now(),
-            })
-        ];
-        
+        // Creation is not a command on the aggregate, so its event is built here
+        let mut events = vec![DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id: cmd.id,
+            dialog_type: cmd.dialog_type,
+            primary_participant: cmd.primary_participant,
+            started_at: Utc::now(),
+        })];
+
         // Set metadata if provided
         if let Some(metadata) = cmd.metadata {
             for (key, value) in metadata {
-                let _events = dialog.set_metadata(key.clone(), value.clone())
-                    .map_err(|e| DomainError::ValidationError(e.to_string()))?;
-                    
-                // For now, we'll create the event manually since we can't downcast
-                domain_events.push(DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
-                    dialog_id: cmd.id,
-                    key,
-                    value,
-                    set_at: Utc::now(),
-                }));
+                events.extend(dialog.set_metadata(key, value).map_err(validation_error)?);
             }
         }
-        
+
         // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
+        self.repository
+            .save(&dialog)
+            .map_err(DomainError::Generic)?;
 
-        Ok(domain_events)
+        Ok(CommandOutcome::new(events, &dialog))
     }
 
-    /// Handle EndDialog command
-    pub fn handle_end_dialog(&self, cmd: EndDialog) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.id.to_string(),
-            })?;
+    fn add_turn(&self, cmd: AddTurn) -> DomainResult<CommandOutcome> {
+        // Check content before touching the aggregate
+        let verdict = self.content_filter.check(&cmd.turn.message.content);
+        if let FilterVerdict::Block(reason) = &verdict {
+            return Err(DomainError::ValidationError(format!(
+                "Turn blocked by content filter: {reason}"
+            )));
+        }
 
-        // End the dialog
-        let _events = dialog.end(cmd.reason.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
+        self.update(cmd.dialog_id, |dialog| {
+            let turn_id = cmd.turn.turn_id;
+            let mut events = dialog.add_turn(cmd.turn).map_err(validation_error)?;
 
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::DialogEnded(DialogEnded {
-                dialog_id: cmd.id,
-                ended_at: Utc::now(),
-                reason: cmd.reason,
-                final_metrics: ConversationMetrics {
-                    turn_count: dialog.turn_count() as u32,
-                    avg_response_time_ms: 0.0,
-                    topic_switches: 0,
-                    clarification_count: 0,
-                    sentiment_trend: 0.0,
-                    coherence_score: 1.0,
-                },
-            })
-        ];
-
-        Ok(domain_events)
+            // Flag it if the filter asked for review
+            if let FilterVerdict::Flag(reason) = verdict {
+                events.extend(dialog.flag_turn(turn_id, reason)?);
+            }
+
+            Ok(events)
+        })
     }
 
-    /// Handle AddTurn command
-    pub fn handle_add_turn(&self, cmd: AddTurn) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
+    /// Handle StartDialog command
+    pub fn handle_start_dialog(&self, cmd: StartDialog) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::StartDialog(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Get current turn count before adding
-        let turn_number = (dialog.turn_count() + 1) as u32;
-        
-        // Add the turn
-        let _events = dialog.add_turn(cmd.turn.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
+    /// Handle EndDialog command
+    pub fn handle_end_dialog(&self, cmd: EndDialog) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::EndDialog(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::TurnAdded(TurnAdded {
-                dialog_id: cmd.dialog_id,
-                turn: cmd.turn,
-                turn_number,
-            })
-        ];
+    /// Handle AbandonDialog command
+    pub fn handle_abandon_dialog(
+        &self,
+        cmd: AbandonDialog,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::AbandonDialog(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        Ok(domain_events)
+    /// Handle AddTurn command
+    pub fn handle_add_turn(&self, cmd: AddTurn) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::AddTurn(cmd))
+            .map(|outcome| outcome.events)
     }
 
     /// Handle SwitchContext command
-    pub fn handle_switch_context(&self, cmd: SwitchContext) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
-
-        // Get current topic before switching
-        let previous_topic = dialog.current_topic().map(|t| t.id);
-        
-        // Switch topic (context)
-        let _events = dialog.switch_topic(cmd.topic.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
-
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::ContextSwitched(ContextSwitched {
-                dialog_id: cmd.dialog_id,
-                previous_topic,
-                new_topic: cmd.topic,
-                switched_at: Utc::now(),
-            })
-        ];
-
-        Ok(domain_events)
+    pub fn handle_switch_context(
+        &self,
+        cmd: SwitchContext,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::SwitchContext(cmd))
+            .map(|outcome| outcome.events)
     }
 
     /// Handle UpdateContext command
-    pub fn handle_update_context(&self, cmd: UpdateContext) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
-
-        // Update context variables
-        let _events = dialog.update_context(cmd.variables.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
-
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::ContextUpdated(ContextUpdated {
-                dialog_id: cmd.dialog_id,
-                updated_variables: cmd.variables,
-                updated_at: Utc::now(),
-            })
-        ];
-
-        Ok(domain_events)
+    pub fn handle_update_context(
+        &self,
+        cmd: UpdateContext,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::UpdateContext(cmd))
+            .map(|outcome| outcome.events)
     }
 
     /// Handle PauseDialog command
     pub fn handle_pause_dialog(&self, cmd: PauseDialog) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.id.to_string(),
-            })?;
-
-        // Get current context snapshot
-        let context_snapshot = dialog.context().variables.clone();
-        
-        // Pause the dialog
-        let _events = dialog.pause()
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
-
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::DialogPaused(DialogPaused {
-                dialog_id: cmd.id,
-                paused_at: Utc::now(),
-                context_snapshot,
-            })
-        ];
-
-        Ok(domain_events)
+        self.execute(DialogCommand::PauseDialog(cmd))
+            .map(|outcome| outcome.events)
     }
 
     /// Handle ResumeDialog command
     pub fn handle_resume_dialog(&self, cmd: ResumeDialog) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.id.to_string(),
-            })?;
-
-        // Resume the dialog
-        let _events = dialog.resume()
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
-
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::DialogResumed(DialogResumed {
-                dialog_id: cmd.id,
-                resumed_at: Utc::now(),
-            })
-        ];
+        self.execute(DialogCommand::ResumeDialog(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        Ok(domain_events)
+    /// Handle ResumeDialogRestoring command
+    pub fn handle_resume_dialog_restoring(
+        &self,
+        cmd: ResumeDialogRestoring,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::ResumeDialogRestoring(cmd))
+            .map(|outcome| outcome.events)
     }
 
     /// Handle SetDialogMetadata command
-    pub fn handle_set_metadata(&self, cmd: SetDialogMetadata) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
-
-        // Set metadata
-        let _events = dialog.set_metadata(cmd.key.clone(), cmd.value.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
-
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
-                dialog_id: cmd.dialog_id,
-                key: cmd.key,
-                value: cmd.value,
-                set_at: Utc::now(),
-            })
-        ];
-
-        Ok(domain_events)
+    pub fn handle_set_metadata(
+        &self,
+        cmd: SetDialogMetadata,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::SetDialogMetadata(cmd))
+            .map(|outcome| outcome.events)
     }
 
     /// Handle AddParticipant command
-    pub fn handle_add_participant(&self, cmd: AddParticipant) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
+    pub fn handle_add_participant(
+        &self,
+        cmd: AddParticipant,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::AddParticipant(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Add participant
-        let _events = dialog.add_participant(cmd.participant.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
+    /// Handle RemoveParticipant command
+    pub fn handle_remove_participant(
+        &self,
+        cmd: RemoveParticipant,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::RemoveParticipant(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::ParticipantAdded(ParticipantAdded {
-                dialog_id: cmd.dialog_id,
-                participant: cmd.participant,
-                added_at: Utc::now(),
-            })
-        ];
+    /// Handle MarkTopicComplete command
+    pub fn handle_mark_topic_complete(
+        &self,
+        cmd: MarkTopicComplete,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::MarkTopicComplete(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        Ok(domain_events)
+    /// Handle AddContextVariable command
+    pub fn handle_add_context_variable(
+        &self,
+        cmd: AddContextVariable,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::AddContextVariable(cmd))
+            .map(|outcome| outcome.events)
     }
 
-    /// Handle RemoveParticipant command
-    pub fn handle_remove_participant(&self, cmd: RemoveParticipant) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
+    /// Handle PinTurn command
+    pub fn handle_pin_turn(&self, cmd: PinTurn) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::PinTurn(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Remove participant
-        let _events = dialog.remove_participant(cmd.participant_id, cmd.reason.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
+    /// Handle UnpinTurn command
+    pub fn handle_unpin_turn(&self, cmd: UnpinTurn) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::UnpinTurn(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::ParticipantRemoved(ParticipantRemoved {
-                dialog_id: cmd.dialog_id,
-                participant_id: cmd.participant_id,
-                removed_at: Utc::now(),
-                reason: cmd.reason,
-            })
-        ];
-
-        Ok(domain_events)
+    /// Handle LockDialog command
+    pub fn handle_lock_dialog(&self, cmd: LockDialog) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::LockDialog(cmd))
+            .map(|outcome| outcome.events)
     }
 
-    /// Handle MarkTopicComplete command
-    pub fn handle_mark_topic_complete(&self, cmd: MarkTopicComplete) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
+    /// Handle UnlockDialog command
+    pub fn handle_unlock_dialog(&self, cmd: UnlockDialog) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::UnlockDialog(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Mark topic complete
-        let _events = dialog.mark_topic_complete(cmd.topic_id, cmd.resolution.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
+    /// Handle SetResolution command
+    pub fn handle_set_resolution(
+        &self,
+        cmd: SetResolution,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::SetResolution(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::TopicCompleted(TopicCompleted {
-                dialog_id: cmd.dialog_id,
-                topic_id: cmd.topic_id,
-                completed_at: Utc::now(),
-                resolution: cmd.resolution,
-            })
-        ];
-
-        Ok(domain_events)
+    /// Handle PruneExpiredContext command
+    pub fn handle_prune_expired_context(
+        &self,
+        cmd: PruneExpiredContext,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::PruneExpiredContext(cmd))
+            .map(|outcome| outcome.events)
     }
 
-    /// Handle AddContextVariable command
-    pub fn handle_add_context_variable(&self, cmd: AddContextVariable) -> DomainResult<Vec<DialogDomainEvent>> {
-        // Load dialog aggregate
-        let entity_id = EntityId::<DialogMarker>::from_uuid(cmd.dialog_id);
-        let mut dialog = self.repository.load(entity_id)
-            .map_err(|e| DomainError::Generic(e))?
-            .ok_or_else(|| DomainError::EntityNotFound { 
-                entity_type: "Dialog".to_string(),
-                id: cmd.dialog_id.to_string(),
-            })?;
+    /// Handle MarkResolutionTurn command
+    pub fn handle_mark_resolution_turn(
+        &self,
+        cmd: MarkResolutionTurn,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::MarkResolutionTurn(cmd))
+            .map(|outcome| outcome.events)
+    }
 
-        // Add context variable
-        let _events = dialog.add_context_variable(cmd.variable.clone())
-            .map_err(|e| DomainError::ValidationError(e.to_string()))?;
+    /// Handle AttachEmbedding command
+    pub fn handle_attach_embedding(
+        &self,
+        cmd: AttachEmbedding,
+    ) -> DomainResult<Vec<DialogDomainEvent>> {
+        self.execute(DialogCommand::AttachEmbedding(cmd))
+            .map(|outcome| outcome.events)
+    }
+}
 
-        // Save aggregate
-        self.repository.save(&dialog)
-            .map_err(|e| DomainError::Generic(e))?;
-        
-        // Create event manually
-        let domain_events = vec![
-            DialogDomainEvent::ContextVariableAdded(ContextVariableAdded {
-                dialog_id: cmd.dialog_id,
-                variable: cmd.variable,
-                added_at: Utc::now(),
-            })
-        ];
-
-        Ok(domain_events)
-    }
-}
\ No newline at end of file
+/// Report an aggregate error as a validation failure
+fn validation_error(error: impl std::fmt::Display) -> DomainError {
+    DomainError::ValidationError(error.to_string())
+}
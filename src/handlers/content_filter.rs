@@ -0,0 +1,34 @@
+//! Content filtering for incoming turns
+//!
+//! [`DialogCommandHandler::handle_add_turn`](super::DialogCommandHandler::handle_add_turn)
+//! consults a [`ContentFilter`] before a turn is added, so deployments can
+//! block or flag inappropriate content at ingestion.
+
+use crate::value_objects::MessageContent;
+
+/// Result of checking message content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterVerdict {
+    /// Accept the turn as is
+    Allow,
+    /// Accept the turn but mark it for review
+    Flag(String),
+    /// Reject the turn
+    Block(String),
+}
+
+/// Check message content before a turn is added
+pub trait ContentFilter: Send + Sync {
+    /// Decide whether the content is allowed, flagged or blocked
+    fn check(&self, content: &MessageContent) -> FilterVerdict;
+}
+
+/// Filter that allows all content
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopContentFilter;
+
+impl ContentFilter for NoopContentFilter {
+    fn check(&self, _content: &MessageContent) -> FilterVerdict {
+        FilterVerdict::Allow
+    }
+}
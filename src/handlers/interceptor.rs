@@ -0,0 +1,16 @@
+//! Command interceptors
+//!
+//! Interceptors run before a command is handled by
+//! [`DialogCommandHandler::dispatch`](super::DialogCommandHandler::dispatch),
+//! for cross-cutting concerns such as authorization, rate limiting or
+//! auditing.
+
+use cim_domain::DomainError;
+
+use crate::commands::DialogCommand;
+
+/// Hook run before a dialog command is handled
+pub trait CommandInterceptor: Send + Sync {
+    /// Inspect the command; returning an error rejects it
+    fn before(&self, cmd: &DialogCommand) -> Result<(), DomainError>;
+}
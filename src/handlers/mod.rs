@@ -1,8 +1,12 @@
 //! Dialog command and event handlers
 
 pub mod command_handler;
+pub mod content_filter;
+pub mod interceptor;
 
-pub use command_handler::DialogCommandHandler;
+pub use command_handler::{CommandOutcome, DialogCommandHandler};
+pub use content_filter::{ContentFilter, FilterVerdict, NoopContentFilter};
+pub use interceptor::CommandInterceptor;
 
 /// Handler for dialog events
 pub struct DialogEventHandler;
@@ -21,4 +25,4 @@ impl Default for DialogEventHandler {
 }
 
 // Event handler implementations will process dialog events to update projections,
-// trigger workflows, and handle cross-domain integrations
\ No newline at end of file
+// trigger workflows, and handle cross-domain integrations
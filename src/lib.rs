@@ -13,36 +13,57 @@
 //! of interactions.
 
 pub mod aggregate;
+pub mod analytics;
 pub mod commands;
 pub mod events;
 pub mod handlers;
 pub mod projections;
 pub mod queries;
 pub mod routing;
+pub mod schema;
 pub mod value_objects;
 
 // Re-export main types
 pub use aggregate::{
-    ContextState, ConversationContext, Dialog, DialogMarker, DialogStatus, DialogType,
+    COHERENCE_REFERENCE_WINDOW, ClockSkewPolicy, ComputedVariable, ContextState,
+    ConversationContext, ConversationPhase, DEFAULT_MAX_PINNED_TURNS, Dialog, DialogConfig,
+    DialogError, DialogMarker, DialogResult, DialogStatus, DialogType, EmbeddingNormalization,
+    ExpressionError, FlowSpec, FlowViolation, HANDOFF_RECENT_TURNS, HandoffBriefing,
+    IncompleteSubtopicsError, MAX_EXPRESSION_DEPTH, ParticipantExport, PhaseTransition, RateLimit,
+    TopicBriefing, TurnReferenceError, ValidationReport, ValidationWarning,
 };
 
 pub use commands::{
-    AddContextVariable, AddParticipant, AddTurn, EndDialog, MarkTopicComplete, PauseDialog,
-    RemoveParticipant, ResumeDialog, SetDialogMetadata, StartDialog, SwitchContext, UpdateContext,
+    AbandonDialog, AddContextVariable, AddParticipant, AddTurn, AttachEmbedding, DialogCommand,
+    EndDialog, LockDialog, MarkResolutionTurn, MarkTopicComplete, PauseDialog, PinTurn,
+    PruneExpiredContext, RemoveParticipant, ResumeDialog, ResumeDialogRestoring, SetDialogMetadata,
+    SetResolution, StartDialog, SwitchContext, UnlockDialog, UnpinTurn, UpdateContext,
 };
 
 pub use events::{
-    ContextSwitched, ContextUpdated, ContextVariableAdded, DialogDomainEvent, DialogEnded, 
-    DialogMetadataSet, DialogPaused, DialogResumed, DialogStarted, ParticipantAdded, 
-    ParticipantRemoved, TopicCompleted, TurnAdded,
+    ContextStateChanged, ContextSwitched, ContextUpdated, ContextVariableAdded,
+    ContextVariablesExpired, DialogAbandoned, DialogDomainEvent, DialogEnded, DialogEventError,
+    DialogLocked, DialogMetadataSet, DialogPaused, DialogResumed, DialogStarted, DialogUnlocked,
+    EmbeddingAttached, EventStore, InMemoryEventStore, MetricsUpdated, ParticipantAdded,
+    ParticipantRemoved, PhaseChanged, ResolutionSet, ResolutionTurnMarked, ScheduledTurnRejected,
+    TopicCompleted, TopicsRelated, TopicsUnrelated, TurnAdded, TurnEdited, TurnFlagged, TurnPinned,
+    TurnRetracted, TurnScheduled, TurnUnpinned, TurnsArchived,
 };
 
-pub use handlers::{DialogCommandHandler, DialogEventHandler};
-pub use projections::{SimpleDialogView, SimpleProjectionUpdater};
+pub use handlers::{
+    CommandInterceptor, CommandOutcome, ContentFilter, DialogCommandHandler, DialogEventHandler,
+    FilterVerdict,
+};
+pub use projections::{
+    BufferedProjectionUpdater, ConversationTreeProjection, RelationKind, RelationshipProjection,
+    SimpleDialogView, SimpleProjectionUpdater, SimpleTokenizer, Tokenizer,
+};
 pub use queries::{DialogQuery, DialogQueryHandler};
 
 pub use value_objects::{
-    ContextScope, ContextVariable, ConversationMetrics, EngagementMetrics, Message, MessageContent,
-    MessageIntent, Participant, ParticipantRole, ParticipantType, Topic, TopicRelevance,
-    TopicStatus, Turn, TurnMetadata, TurnType,
+    ContextScope, ContextVariable, ConversationMetrics, DefaultSanitizer, DisplayNameResolver,
+    EndReason, EndReasonCode, EngagementMetrics, Message, MessageContent, MessageIntent,
+    MessageSegment, MetricsDelta, Participant, ParticipantRole, ParticipantType, ResolutionOutcome,
+    Sanitizer, StoredNameResolver, SubtopicLink, Topic, TopicRelevance, TopicStatus, Turn,
+    TurnMetadata, TurnType,
 };
@@ -0,0 +1,182 @@
+//! Buffered projection updates
+//!
+//! Lets event publishers hand events to the projections without waiting for
+//! them to be applied, while still giving shutdown a point at which nothing is
+//! left in flight: events are queued on a channel and applied in order by a
+//! background task.
+
+use super::SimpleProjectionUpdater;
+use crate::events::DialogDomainEvent;
+use cim_domain::DomainEvent;
+use std::sync::Arc;
+use tokio::sync::{RwLock, mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+enum Queued {
+    Event(Box<DialogDomainEvent>),
+    /// Answered once every event queued before it has been applied
+    Flush(oneshot::Sender<()>),
+}
+
+/// Applies queued events to a [`SimpleProjectionUpdater`] on a background task
+///
+/// Must be created inside a Tokio runtime.
+pub struct BufferedProjectionUpdater {
+    updater: Arc<RwLock<SimpleProjectionUpdater>>,
+    sender: mpsc::UnboundedSender<Queued>,
+    task: JoinHandle<()>,
+}
+
+impl BufferedProjectionUpdater {
+    /// Start applying queued events to `updater`
+    pub fn new(updater: Arc<RwLock<SimpleProjectionUpdater>>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let target = updater.clone();
+        let task = tokio::spawn(async move {
+            while let Some(queued) = receiver.recv().await {
+                match queued {
+                    Queued::Event(event) => {
+                        let event_type = event.event_type();
+                        if let Err(e) = target.write().await.handle_event(*event).await {
+                            warn!("Failed to apply {} to projections: {}", event_type, e);
+                        }
+                    }
+                    // The caller may have stopped waiting
+                    Queued::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+
+        Self {
+            updater,
+            sender,
+            task,
+        }
+    }
+
+    /// The updater events are applied to, e.g. for a `DialogQueryHandler`
+    pub fn updater(&self) -> Arc<RwLock<SimpleProjectionUpdater>> {
+        self.updater.clone()
+    }
+
+    /// Queue an event to be applied
+    pub fn enqueue(&self, event: DialogDomainEvent) {
+        // The receiver lives until `shutdown` consumes self, so this only
+        // fails if the task panicked
+        if self.sender.send(Queued::Event(Box::new(event))).is_err() {
+            warn!("Projection task stopped; dropping event");
+        }
+    }
+
+    /// Wait until every event queued so far has been applied
+    pub async fn flush(&self) {
+        let (done, applied) = oneshot::channel();
+        if self.sender.send(Queued::Flush(done)).is_ok() {
+            let _ = applied.await;
+        }
+    }
+
+    /// Apply every queued event, then stop the background task
+    pub async fn shutdown(self) {
+        drop(self.sender);
+        if let Err(e) = self.task.await {
+            warn!("Projection task failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::DialogType;
+    use crate::events::{DialogStarted, TurnAdded};
+    use crate::value_objects::{
+        Message, Participant, ParticipantRole, ParticipantType, Turn, TurnType,
+    };
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn events(dialog_id: Uuid, turns: u32) -> Vec<DialogDomainEvent> {
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let mut events = vec![DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            started_at: Utc::now(),
+        })];
+        events.extend((1..=turns).map(|turn_number| {
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: Turn::new(
+                    turn_number,
+                    user.id,
+                    Message::text("Hello"),
+                    TurnType::UserQuery,
+                ),
+                turn_number,
+            })
+        }));
+        events
+    }
+
+    #[tokio::test]
+    async fn test_flush_applies_queued_events() {
+        let buffered =
+            BufferedProjectionUpdater::new(Arc::new(RwLock::new(SimpleProjectionUpdater::new())));
+        let dialog_id = Uuid::new_v4();
+        for event in events(dialog_id, 50) {
+            buffered.enqueue(event);
+        }
+
+        buffered.flush().await;
+        let updater = buffered.updater();
+        assert_eq!(
+            updater
+                .read()
+                .await
+                .get_view(&dialog_id)
+                .unwrap()
+                .turns
+                .len(),
+            50
+        );
+
+        // Flushing with nothing queued returns straight away
+        buffered.flush().await;
+        buffered.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queue() {
+        let updater = Arc::new(RwLock::new(SimpleProjectionUpdater::new()));
+        let buffered = BufferedProjectionUpdater::new(updater.clone());
+        let dialog_id = Uuid::new_v4();
+        for event in events(dialog_id, 20) {
+            buffered.enqueue(event);
+        }
+
+        buffered.shutdown().await;
+        assert_eq!(
+            updater
+                .read()
+                .await
+                .get_view(&dialog_id)
+                .unwrap()
+                .turns
+                .len(),
+            20
+        );
+    }
+}
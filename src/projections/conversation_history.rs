@@ -4,6 +4,7 @@
 //! with efficient pagination and filtering capabilities.
 
 use super::DialogProjection;
+use super::tokenizer::{SimpleTokenizer, Tokenizer};
 use crate::events::*;
 use crate::value_objects::*;
 use async_trait::async_trait;
@@ -20,10 +21,13 @@ pub struct HistoryEntry {
     pub entry_id: Uuid,
     pub dialog_id: Uuid,
     pub turn_id: Uuid,
+    /// Position of the entry in [`ConversationHistory::entries`]
     pub message_index: usize,
     pub participant_id: String,
     pub participant_name: String,
-    pub participant_type: ParticipantType,
+    /// None for participants the projection never saw join, e.g. ones added
+    /// before it started listening; their name is a placeholder
+    pub participant_type: Option<ParticipantType>,
     pub message: Message,
     pub timestamp: DateTime<Utc>,
     pub context_id: String,
@@ -41,8 +45,14 @@ pub struct ConversationHistory {
     pub participant_index: HashMap<String, Vec<usize>>,
     pub topic_index: HashMap<String, Vec<usize>>,
     pub context_index: HashMap<String, Vec<usize>>,
+    /// Entries containing each keyword, as split by the tokenizer
+    pub keyword_index: HashMap<String, Vec<usize>>,
     pub total_messages: u64,
     pub last_sequence: u64,
+    participants: HashMap<Uuid, Participant>,
+    current_topic: Option<Topic>,
+    projection_id: String,
+    tokenizer: Arc<dyn Tokenizer>,
 }
 
 impl ConversationHistory {
@@ -53,58 +63,147 @@ impl ConversationHistory {
             participant_index: HashMap::new(),
             topic_index: HashMap::new(),
             context_index: HashMap::new(),
+            keyword_index: HashMap::new(),
             total_messages: 0,
             last_sequence: 0,
+            participants: HashMap::new(),
+            current_topic: None,
+            projection_id: format!("conversation_history:{dialog_id}"),
+            tokenizer: Arc::new(SimpleTokenizer::default()),
         }
     }
-    
+
+    /// Split messages into keywords with `tokenizer` instead of the default
+    /// [`SimpleTokenizer`]
+    ///
+    /// Only messages added afterwards are indexed with the new tokenizer.
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
     /// Get messages for a specific participant
     pub fn get_by_participant(&self, participant_id: &str) -> Vec<&HistoryEntry> {
-        self.participant_index.get(participant_id)
+        self.participant_index
+            .get(participant_id)
             .map(|indices| {
-                indices.iter()
+                indices
+                    .iter()
                     .filter_map(|&idx| self.entries.get(idx))
                     .collect()
             })
             .unwrap_or_default()
     }
-    
+
     /// Get messages for a specific topic
     pub fn get_by_topic(&self, topic_id: &str) -> Vec<&HistoryEntry> {
-        self.topic_index.get(topic_id)
+        self.topic_index
+            .get(topic_id)
             .map(|indices| {
-                indices.iter()
+                indices
+                    .iter()
                     .filter_map(|&idx| self.entries.get(idx))
                     .collect()
             })
             .unwrap_or_default()
     }
-    
+
     /// Get messages in a time range
-    pub fn get_by_time_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<&HistoryEntry> {
-        self.entries.iter()
+    pub fn get_by_time_range(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<&HistoryEntry> {
+        self.entries
+            .iter()
             .filter(|entry| entry.timestamp >= start && entry.timestamp <= end)
             .collect()
     }
-    
+
     /// Get paginated messages
     pub fn get_page(&self, offset: usize, limit: usize) -> &[HistoryEntry] {
         let start = offset.min(self.entries.len());
         let end = (start + limit).min(self.entries.len());
         &self.entries[start..end]
     }
-    
+
     /// Search messages by content
     pub fn search(&self, query: &str) -> Vec<&HistoryEntry> {
         let query_lower = query.to_lowercase();
-        self.entries.iter()
+        self.entries
+            .iter()
             .filter(|entry| {
-                match &entry.message.content {
-                    MessageContent::Text(text) => text.to_lowercase().contains(&query_lower),
-                    MessageContent::Code { code, .. } => code.to_lowercase().contains(&query_lower),
-                    MessageContent::Structured { template, .. } => template.to_lowercase().contains(&query_lower),
-                    _ => false,
+                entry
+                    .message
+                    .content
+                    .to_searchable_string()
+                    .to_lowercase()
+                    .contains(&query_lower)
+            })
+            .collect()
+    }
+
+    /// Get messages containing every keyword of `query`, using the keyword index
+    ///
+    /// The query is split with the same tokenizer as the messages, so stop
+    /// words and punctuation in it are ignored. Returns nothing if the query
+    /// has no keywords.
+    pub fn search_keywords(&self, query: &str) -> Vec<&HistoryEntry> {
+        let keywords = self.tokenizer.tokenize(query);
+        let Some((first, rest)) = keywords.split_first() else {
+            return Vec::new();
+        };
+
+        self.keyword_index
+            .get(first)
+            .into_iter()
+            .flatten()
+            .filter(|&&idx| {
+                rest.iter()
+                    .all(|k| self.keyword_index.get(k).is_some_and(|i| i.contains(&idx)))
+            })
+            .filter_map(|&idx| self.entries.get(idx))
+            .collect()
+    }
+
+    /// Search messages by content, reporting where each match is
+    ///
+    /// Returns the index of every matching entry together with the
+    /// `(start, end)` char ranges of its non-overlapping matches. Matching is
+    /// case-insensitive; ranges are char offsets into the message's
+    /// [`MessageContent::to_searchable_string`].
+    pub fn search_highlighted(&self, query: &str) -> Vec<(usize, Vec<(usize, usize)>)> {
+        let query: Vec<char> = query.chars().collect();
+        if query.is_empty() {
+            return Vec::new();
+        }
+
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let text: Vec<char> = entry
+                    .message
+                    .content
+                    .to_searchable_string()
+                    .chars()
+                    .collect();
+                let mut ranges = Vec::new();
+                let mut start = 0;
+                while start + query.len() <= text.len() {
+                    let window = &text[start..start + query.len()];
+                    if window
+                        .iter()
+                        .zip(&query)
+                        .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+                    {
+                        ranges.push((start, start + query.len()));
+                        start += query.len();
+                    } else {
+                        start += 1;
+                    }
                 }
+                (!ranges.is_empty()).then_some((index, ranges))
             })
             .collect()
     }
@@ -113,72 +212,91 @@ impl ConversationHistory {
 impl DialogProjection for ConversationHistory {
     fn apply_event(&mut self, event: &DialogDomainEvent) {
         match event {
-            DialogDomainEvent::TurnAdded(e) => {
+            DialogDomainEvent::DialogStarted(e) if e.dialog_id == self.dialog_id => {
+                self.participants
+                    .insert(e.primary_participant.id, e.primary_participant.clone());
+            }
+            DialogDomainEvent::ParticipantAdded(e) if e.dialog_id == self.dialog_id => {
+                self.participants
+                    .insert(e.participant.id, e.participant.clone());
+            }
+            DialogDomainEvent::ContextSwitched(e) if e.dialog_id == self.dialog_id => {
+                self.current_topic = Some(e.new_topic.clone());
+            }
+            DialogDomainEvent::TurnAdded(e) if e.dialog_id == self.dialog_id => {
                 let turn = &e.turn;
-                let current_context = e.current_context.as_deref().unwrap_or("default");
-                
-                // Get participant info (would be enriched from participant store in production)
-                let participant_name = format!("Participant {}", &turn.participant_id);
-                let participant_type = ParticipantType::User; // Default, would be looked up
-                
-                // Get topic info if available
-                let (topic_id, topic_name) = if let Some(tid) = &turn.topic_id {
-                    (Some(tid.clone()), Some(format!("Topic {}", tid)))
-                } else {
-                    (None, None)
-                };
-                
-                // Add each message as a history entry
-                for (idx, message) in turn.messages.iter().enumerate() {
-                    self.last_sequence += 1;
-                    
-                    let entry = HistoryEntry {
-                        entry_id: Uuid::new_v4(),
-                        dialog_id: e.dialog_id,
-                        turn_id: turn.turn_id,
-                        message_index: idx,
-                        participant_id: turn.participant_id.clone(),
-                        participant_name: participant_name.clone(),
-                        participant_type: participant_type.clone(),
-                        message: message.clone(),
-                        timestamp: e.timestamp,
-                        context_id: current_context.to_string(),
-                        topic_id: topic_id.clone(),
-                        topic_name: topic_name.clone(),
-                        metadata: turn.metadata.clone(),
-                        sequence_number: self.last_sequence,
+                let participant_id = turn.participant_id.to_string();
+
+                // Participants that joined before this projection started listening are unknown
+                let (participant_name, participant_type) =
+                    match self.participants.get(&turn.participant_id) {
+                        Some(participant) => {
+                            (participant.name.clone(), Some(participant.participant_type))
+                        }
+                        None => (format!("Participant {}", turn.participant_id), None),
                     };
-                    
-                    let entry_index = self.entries.len();
-                    
-                    // Update indices
-                    self.participant_index
-                        .entry(turn.participant_id.clone())
-                        .or_insert_with(Vec::new)
-                        .push(entry_index);
-                    
-                    if let Some(tid) = &topic_id {
-                        self.topic_index
-                            .entry(tid.clone())
-                            .or_insert_with(Vec::new)
-                            .push(entry_index);
-                    }
-                    
-                    self.context_index
-                        .entry(current_context.to_string())
-                        .or_insert_with(Vec::new)
+
+                let topic_id = self.current_topic.as_ref().map(|t| t.id.to_string());
+                let topic_name = self.current_topic.as_ref().map(|t| t.name.clone());
+                let context_id = topic_id.clone().unwrap_or_else(|| "default".to_string());
+
+                self.last_sequence += 1;
+                let entry_index = self.entries.len();
+
+                let entry = HistoryEntry {
+                    entry_id: Uuid::new_v4(),
+                    dialog_id: e.dialog_id,
+                    turn_id: turn.turn_id,
+                    message_index: entry_index,
+                    participant_id: participant_id.clone(),
+                    participant_name,
+                    participant_type,
+                    message: turn.message.clone(),
+                    timestamp: turn.timestamp,
+                    context_id: context_id.clone(),
+                    topic_id: topic_id.clone(),
+                    topic_name,
+                    metadata: turn.metadata.clone(),
+                    sequence_number: self.last_sequence,
+                };
+
+                // Update indices
+                self.participant_index
+                    .entry(participant_id)
+                    .or_default()
+                    .push(entry_index);
+
+                if let Some(tid) = topic_id {
+                    self.topic_index.entry(tid).or_default().push(entry_index);
+                }
+
+                self.context_index
+                    .entry(context_id)
+                    .or_default()
+                    .push(entry_index);
+
+                for keyword in self.tokenizer.keywords(&turn.message.content) {
+                    self.keyword_index
+                        .entry(keyword)
+                        .or_default()
                         .push(entry_index);
-                    
-                    self.entries.push(entry);
-                    self.total_messages += 1;
                 }
+
+                self.entries.push(entry);
+                self.total_messages += 1;
             }
             _ => {} // Other events don't affect history
         }
     }
-    
+
     fn id(&self) -> &str {
-        &self.dialog_id.to_string()
+        &self.projection_id
+    }
+
+    fn reset(&mut self) {
+        let tokenizer = self.tokenizer.clone();
+        *self = Self::new(self.dialog_id);
+        self.tokenizer = tokenizer;
     }
 }
 
@@ -187,17 +305,20 @@ impl DialogProjection for ConversationHistory {
 pub trait ConversationHistoryRepository: Send + Sync {
     /// Save or update conversation history
     async fn save(&self, history: ConversationHistory) -> Result<(), Box<dyn std::error::Error>>;
-    
+
     /// Get conversation history by dialog ID
-    async fn get(&self, dialog_id: &Uuid) -> Result<Option<ConversationHistory>, Box<dyn std::error::Error>>;
-    
+    async fn get(
+        &self,
+        dialog_id: &Uuid,
+    ) -> Result<Option<ConversationHistory>, Box<dyn std::error::Error>>;
+
     /// Get history entries across all dialogs for a participant
     async fn get_participant_history(
-        &self, 
+        &self,
         participant_id: &str,
         limit: usize,
     ) -> Result<Vec<HistoryEntry>, Box<dyn std::error::Error>>;
-    
+
     /// Search across all conversation histories
     async fn search_all(
         &self,
@@ -207,6 +328,7 @@ pub trait ConversationHistoryRepository: Send + Sync {
 }
 
 /// In-memory implementation
+#[derive(Default)]
 pub struct InMemoryConversationHistoryRepository {
     histories: Arc<RwLock<HashMap<Uuid, ConversationHistory>>>,
 }
@@ -226,45 +348,50 @@ impl ConversationHistoryRepository for InMemoryConversationHistoryRepository {
         histories.insert(history.dialog_id, history);
         Ok(())
     }
-    
-    async fn get(&self, dialog_id: &Uuid) -> Result<Option<ConversationHistory>, Box<dyn std::error::Error>> {
+
+    async fn get(
+        &self,
+        dialog_id: &Uuid,
+    ) -> Result<Option<ConversationHistory>, Box<dyn std::error::Error>> {
         let histories = self.histories.read().await;
         Ok(histories.get(dialog_id).cloned())
     }
-    
+
     async fn get_participant_history(
-        &self, 
+        &self,
         participant_id: &str,
         limit: usize,
     ) -> Result<Vec<HistoryEntry>, Box<dyn std::error::Error>> {
         let histories = self.histories.read().await;
-        let mut all_entries: Vec<HistoryEntry> = histories.values()
+        let mut all_entries: Vec<HistoryEntry> = histories
+            .values()
             .flat_map(|h| h.get_by_participant(participant_id))
             .cloned()
             .collect();
-        
+
         // Sort by timestamp descending
         all_entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
         all_entries.truncate(limit);
-        
+
         Ok(all_entries)
     }
-    
+
     async fn search_all(
         &self,
         query: &str,
         limit: usize,
     ) -> Result<Vec<HistoryEntry>, Box<dyn std::error::Error>> {
         let histories = self.histories.read().await;
-        let mut all_results: Vec<HistoryEntry> = histories.values()
+        let mut all_results: Vec<HistoryEntry> = histories
+            .values()
             .flat_map(|h| h.search(query))
             .cloned()
             .collect();
-        
+
         // Sort by timestamp descending
         all_results.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
         all_results.truncate(limit);
-        
+
         Ok(all_results)
     }
 }
@@ -272,51 +399,154 @@ impl ConversationHistoryRepository for InMemoryConversationHistoryRepository {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
-    #[test]
-    fn test_conversation_history() {
+    use crate::aggregate::DialogType;
+
+    fn history_with(texts: &[&str]) -> (ConversationHistory, Participant, Topic) {
         let dialog_id = Uuid::new_v4();
         let mut history = ConversationHistory::new(dialog_id);
-        
-        // Create a turn added event
-        let turn = Turn {
-            turn_id: Uuid::new_v4(),
-            turn_type: TurnType::Message,
-            participant_id: "user1".to_string(),
-            messages: vec![
-                Message {
-                    content: MessageContent::Text("Hello world".to_string()),
-                    intent: MessageIntent::Statement,
-                    confidence: 1.0,
-                    metadata: HashMap::new(),
-                }
-            ],
-            topic_id: Some("topic1".to_string()),
-            metadata: TurnMetadata {
-                duration_ms: Some(100),
-                tokens_used: Some(10),
-                model_used: None,
-                error: None,
-            },
-            timestamp: Utc::now(),
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
         };
-        
-        let event = DialogDomainEvent::TurnAdded(TurnAdded {
+        let topic = Topic::new("Greetings", vec!["hello".to_string()]);
+
+        history.apply_event(&DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
             dialog_id,
-            turn: turn.clone(),
-            turn_number: 1,
-            current_context: Some("default".to_string()),
-            timestamp: Utc::now(),
-        });
-        
-        history.apply_event(&event);
-        
+            dialog_type: DialogType::Direct,
+            primary_participant: user.clone(),
+            started_at: Utc::now(),
+        }));
+        history.apply_event(&DialogDomainEvent::ContextSwitched(ContextSwitched {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            previous_topic: None,
+            new_topic: topic.clone(),
+            switched_at: Utc::now(),
+        }));
+        for (i, text) in texts.iter().enumerate() {
+            history.apply_event(&DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: Turn::new(
+                    i as u32 + 1,
+                    user.id,
+                    Message::text(*text),
+                    TurnType::UserQuery,
+                ),
+                turn_number: i as u32 + 1,
+            }));
+        }
+
+        (history, user, topic)
+    }
+
+    #[test]
+    fn test_conversation_history() {
+        let (history, user, topic) = history_with(&["Hello world"]);
+
         assert_eq!(history.total_messages, 1);
         assert_eq!(history.entries.len(), 1);
-        assert_eq!(history.get_by_participant("user1").len(), 1);
-        assert_eq!(history.get_by_topic("topic1").len(), 1);
-        
+        assert_eq!(history.entries[0].participant_name, "User");
+        assert_eq!(
+            history.entries[0].participant_type,
+            Some(ParticipantType::Human)
+        );
+        assert_eq!(history.get_by_participant(&user.id.to_string()).len(), 1);
+        assert_eq!(history.get_by_topic(&topic.id.to_string()).len(), 1);
+
         let search_results = history.search("hello");
         assert_eq!(search_results.len(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_entry_positions_and_unknown_participants() {
+        let (mut history, _, _) = history_with(&["First", "Second"]);
+        let stranger = Uuid::new_v4();
+        history.apply_event(&DialogDomainEvent::TurnAdded(TurnAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id: history.dialog_id,
+            turn: Turn::new(3, stranger, Message::text("Who am I?"), TurnType::UserQuery),
+            turn_number: 3,
+        }));
+
+        let indices: Vec<usize> = history.entries.iter().map(|e| e.message_index).collect();
+        assert_eq!(indices, vec![0, 1, 2]);
+
+        // An author the projection never saw join is flagged rather than
+        // assumed to be human
+        let entry = &history.entries[2];
+        assert_eq!(entry.participant_type, None);
+        assert_eq!(entry.participant_name, format!("Participant {stranger}"));
+    }
+
+    #[test]
+    fn test_search_segmented_messages() {
+        let (mut history, user, _) = history_with(&[]);
+        let mut message = Message::text("");
+        message.content = MessageContent::Segmented(vec![
+            MessageSegment::Text("Try this:".to_string()),
+            MessageSegment::Code {
+                lang: "sh".to_string(),
+                code: "cargo update".to_string(),
+            },
+        ]);
+        history.apply_event(&DialogDomainEvent::TurnAdded(TurnAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id: history.dialog_id,
+            turn: Turn::new(1, user.id, message, TurnType::AgentResponse),
+            turn_number: 1,
+        }));
+
+        assert_eq!(history.search("cargo update").len(), 1);
+        assert_eq!(
+            history.search_highlighted("CARGO"),
+            vec![(0, vec![(10, 15)])]
+        );
+        assert_eq!(history.search_keywords("update").len(), 1);
+    }
+
+    #[test]
+    fn test_search_highlighted() {
+        let (history, _, _) = history_with(&["No match here", "Hello, hello again", "Say HELLO"]);
+
+        assert_eq!(
+            history.search_highlighted("hello"),
+            vec![(1, vec![(0, 5), (7, 12)]), (2, vec![(4, 9)])]
+        );
+
+        // Offsets count chars, not bytes
+        let (history, _, _) = history_with(&["Grüße, grüße"]);
+        assert_eq!(history.search_highlighted("GRÜSSE"), vec![]);
+        assert_eq!(
+            history.search_highlighted("GRÜßE"),
+            vec![(0, vec![(0, 5), (7, 12)])]
+        );
+
+        assert!(history.search_highlighted("").is_empty());
+    }
+
+    #[test]
+    fn test_keyword_index() {
+        let (history, _, _) = history_with(&[
+            "Where is my refund?",
+            "The refund was sent to the wrong account.",
+            "Thanks, that's all!",
+        ]);
+
+        assert_eq!(history.keyword_index["refund"], vec![0, 1]);
+        assert!(!history.keyword_index.contains_key("the"));
+        assert!(!history.keyword_index.contains_key("refund?"));
+
+        let found: Vec<Uuid> = history
+            .search_keywords("the wrong refund!")
+            .iter()
+            .map(|e| e.turn_id)
+            .collect();
+        assert_eq!(found, vec![history.entries[1].turn_id]);
+        assert!(history.search_keywords("the and").is_empty());
+    }
+}
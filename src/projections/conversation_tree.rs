@@ -0,0 +1,210 @@
+//! Conversation tree projection for branched dialogs
+//!
+//! Dialogs that are forked or reopened from another dialog record the parent
+//! dialog ID in their metadata (`forked_from` / `reopened_from`). This
+//! projection follows those links so branch explorers can walk from a root
+//! dialog down to every fork, or from a fork back up to its root.
+
+use super::DialogProjection;
+use crate::events::DialogDomainEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Metadata key recording the dialog a fork was created from
+pub const FORKED_FROM_KEY: &str = "forked_from";
+
+/// Metadata key recording the dialog a reopened dialog continues
+pub const REOPENED_FROM_KEY: &str = "reopened_from";
+
+/// How a dialog branched off its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BranchKind {
+    /// Dialog was forked from the parent
+    Fork,
+    /// Dialog reopened the parent
+    Reopen,
+}
+
+/// A node in a conversation tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeNode {
+    /// Dialog at this node
+    pub dialog_id: Uuid,
+    /// How this dialog branched off its parent (None for the root)
+    pub branch: Option<BranchKind>,
+    /// Child dialogs in the order they were linked
+    pub children: Vec<TreeNode>,
+}
+
+/// Projection tracking parent/child links between dialogs
+#[derive(Debug, Clone, Default)]
+pub struct ConversationTreeProjection {
+    parents: HashMap<Uuid, (Uuid, BranchKind)>,
+    children: HashMap<Uuid, Vec<Uuid>>,
+}
+
+impl ConversationTreeProjection {
+    /// Create an empty conversation tree projection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the direct children of a dialog
+    pub fn children(&self, dialog_id: Uuid) -> Vec<Uuid> {
+        self.children.get(&dialog_id).cloned().unwrap_or_default()
+    }
+
+    /// Get the parent of a dialog and how it branched off
+    pub fn parent(&self, dialog_id: Uuid) -> Option<(Uuid, BranchKind)> {
+        self.parents.get(&dialog_id).copied()
+    }
+
+    /// Get the ancestors of a dialog, nearest parent first and root last
+    pub fn ancestry(&self, dialog_id: Uuid) -> Vec<Uuid> {
+        let mut ancestry = Vec::new();
+        let mut current = dialog_id;
+
+        while let Some((parent, _)) = self.parents.get(&current) {
+            if *parent == dialog_id || ancestry.contains(parent) {
+                break;
+            }
+            ancestry.push(*parent);
+            current = *parent;
+        }
+
+        ancestry
+    }
+
+    /// Build the tree of dialogs descending from `root`
+    pub fn tree_from_root(&self, root: Uuid) -> TreeNode {
+        let mut visited = HashSet::new();
+        self.build_node(root, None, &mut visited)
+    }
+
+    fn build_node(
+        &self,
+        dialog_id: Uuid,
+        branch: Option<BranchKind>,
+        visited: &mut HashSet<Uuid>,
+    ) -> TreeNode {
+        visited.insert(dialog_id);
+
+        let mut children = Vec::new();
+        for child in self.children(dialog_id) {
+            if visited.contains(&child) {
+                continue;
+            }
+            let branch = self.parents.get(&child).map(|(_, kind)| *kind);
+            children.push(self.build_node(child, branch, visited));
+        }
+
+        TreeNode {
+            dialog_id,
+            branch,
+            children,
+        }
+    }
+
+    pub(crate) fn link(&mut self, child: Uuid, parent: Uuid, kind: BranchKind) {
+        if child == parent || self.ancestry(parent).contains(&child) {
+            warn!(
+                "Ignoring {:?} link from {} to {}: it would create a cycle",
+                kind, child, parent
+            );
+            return;
+        }
+
+        // A dialog has a single parent; relinking moves it
+        if let Some((old_parent, _)) = self.parents.insert(child, (parent, kind))
+            && let Some(siblings) = self.children.get_mut(&old_parent)
+        {
+            siblings.retain(|id| *id != child);
+        }
+
+        let siblings = self.children.entry(parent).or_default();
+        if !siblings.contains(&child) {
+            siblings.push(child);
+        }
+    }
+}
+
+impl DialogProjection for ConversationTreeProjection {
+    fn apply_event(&mut self, event: &DialogDomainEvent) {
+        if let DialogDomainEvent::DialogMetadataSet(e) = event {
+            let kind = match e.key.as_str() {
+                FORKED_FROM_KEY => BranchKind::Fork,
+                REOPENED_FROM_KEY => BranchKind::Reopen,
+                _ => return,
+            };
+
+            match e.value.as_str().and_then(|s| Uuid::parse_str(s).ok()) {
+                Some(parent) => self.link(e.dialog_id, parent, kind),
+                None => warn!(
+                    "Invalid {} value on dialog {}: {}",
+                    e.key, e.dialog_id, e.value
+                ),
+            }
+        }
+    }
+
+    fn id(&self) -> &str {
+        "conversation_tree"
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DialogMetadataSet;
+    use chrono::Utc;
+
+    fn fork(projection: &mut ConversationTreeProjection, child: Uuid, parent: Uuid) {
+        projection.apply_event(&DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
+            event_id: Uuid::new_v4(),
+            dialog_id: child,
+            key: FORKED_FROM_KEY.to_string(),
+            value: serde_json::json!(parent.to_string()),
+            set_at: Utc::now(),
+        }));
+    }
+
+    #[test]
+    fn test_conversation_tree() {
+        let mut projection = ConversationTreeProjection::new();
+        let root = Uuid::new_v4();
+        let first_fork = Uuid::new_v4();
+        let second_fork = Uuid::new_v4();
+        let nested_fork = Uuid::new_v4();
+
+        // Fork the root twice, then fork the second fork
+        fork(&mut projection, first_fork, root);
+        fork(&mut projection, second_fork, root);
+        fork(&mut projection, nested_fork, second_fork);
+
+        assert_eq!(projection.children(root), vec![first_fork, second_fork]);
+        assert_eq!(projection.children(second_fork), vec![nested_fork]);
+        assert!(projection.children(first_fork).is_empty());
+
+        assert_eq!(projection.ancestry(nested_fork), vec![second_fork, root]);
+        assert_eq!(projection.ancestry(first_fork), vec![root]);
+        assert!(projection.ancestry(root).is_empty());
+
+        let tree = projection.tree_from_root(root);
+        assert_eq!(tree.dialog_id, root);
+        assert_eq!(tree.branch, None);
+        assert_eq!(tree.children.len(), 2);
+        assert_eq!(tree.children[1].dialog_id, second_fork);
+        assert_eq!(tree.children[1].branch, Some(BranchKind::Fork));
+        assert_eq!(tree.children[1].children[0].dialog_id, nested_fork);
+
+        // Linking the root under its own descendant is ignored
+        fork(&mut projection, root, nested_fork);
+        assert!(projection.ancestry(root).is_empty());
+    }
+}
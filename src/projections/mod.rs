@@ -8,15 +8,31 @@ use crate::value_objects::*;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+pub mod buffered;
+pub mod conversation_history;
+pub mod conversation_tree;
+pub mod registry;
+pub mod relationships;
 pub mod simple_projection;
+pub mod tokenizer;
 // pub mod dialog_view;
-// pub mod conversation_history;
 // pub mod active_dialogs;
 // pub mod projection_updater;
 
-pub use simple_projection::{SimpleDialogView, SimpleProjectionUpdater};
+pub use buffered::BufferedProjectionUpdater;
+pub use conversation_history::{
+    ConversationHistory, ConversationHistoryRepository, HistoryEntry,
+    InMemoryConversationHistoryRepository,
+};
+pub use conversation_tree::{BranchKind, ConversationTreeProjection, TreeNode};
+pub use registry::ProjectionRegistry;
+pub use relationships::{RelationKind, RelationshipProjection};
+pub use simple_projection::{
+    MembershipChange, MembershipChangeKind, PhaseEntry, ProjectionHealth, SimpleDialogView,
+    SimpleProjectionUpdater,
+};
+pub use tokenizer::{SimpleTokenizer, Tokenizer};
 // pub use dialog_view::{DialogView, DialogViewRepository};
-// pub use conversation_history::{ConversationHistory, ConversationHistoryRepository};
 // pub use active_dialogs::{ActiveDialogs, ActiveDialogsRepository};
 // pub use projection_updater::DialogProjectionUpdater;
 
@@ -24,9 +40,15 @@ pub use simple_projection::{SimpleDialogView, SimpleProjectionUpdater};
 pub trait DialogProjection: Send + Sync {
     /// Update the projection based on an event
     fn apply_event(&mut self, event: &DialogDomainEvent);
-    
+
     /// Get the projection ID
     fn id(&self) -> &str;
+
+    /// Clear the projection before a rebuild replays events into it
+    ///
+    /// Projections that keep state should override this, or a rebuild applies
+    /// the replayed events on top of what they already hold.
+    fn reset(&mut self) {}
 }
 
 /// Summary statistics for a dialog
@@ -90,4 +112,4 @@ pub struct ContextSummary {
     pub switches_to: usize,
     pub switches_from: usize,
     pub total_duration_seconds: u64,
-}
\ No newline at end of file
+}
@@ -0,0 +1,153 @@
+//! Registry of dialog projections
+//!
+//! Lets applications plug custom read models into the projection pipeline
+//! without modifying the updater: every registered projection receives each
+//! dispatched event in registration order.
+
+use super::DialogProjection;
+use crate::events::DialogDomainEvent;
+
+/// Registry holding multiple projections keyed by their ID
+#[derive(Default)]
+pub struct ProjectionRegistry {
+    projections: Vec<Box<dyn DialogProjection>>,
+}
+
+impl ProjectionRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a projection, returning any projection previously registered under the same ID
+    pub fn register(
+        &mut self,
+        projection: Box<dyn DialogProjection>,
+    ) -> Option<Box<dyn DialogProjection>> {
+        match self.position(projection.id()) {
+            Some(index) => Some(std::mem::replace(&mut self.projections[index], projection)),
+            None => {
+                self.projections.push(projection);
+                None
+            }
+        }
+    }
+
+    /// Remove a projection by ID
+    pub fn unregister(&mut self, id: &str) -> Option<Box<dyn DialogProjection>> {
+        self.position(id)
+            .map(|index| self.projections.remove(index))
+    }
+
+    /// Look up a projection by ID
+    pub fn get(&self, id: &str) -> Option<&dyn DialogProjection> {
+        self.projections
+            .iter()
+            .find(|p| p.id() == id)
+            .map(|p| p.as_ref())
+    }
+
+    /// Look up a projection by ID for mutation
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Box<dyn DialogProjection>> {
+        self.projections.iter_mut().find(|p| p.id() == id)
+    }
+
+    /// IDs of all registered projections in registration order
+    pub fn ids(&self) -> Vec<&str> {
+        self.projections.iter().map(|p| p.id()).collect()
+    }
+
+    /// Number of registered projections
+    pub fn len(&self) -> usize {
+        self.projections.len()
+    }
+
+    /// Check whether no projections are registered
+    pub fn is_empty(&self) -> bool {
+        self.projections.is_empty()
+    }
+
+    /// Reset every registered projection ahead of a rebuild
+    pub fn reset(&mut self) {
+        for projection in &mut self.projections {
+            projection.reset();
+        }
+    }
+
+    /// Apply an event to every registered projection
+    pub fn dispatch(&mut self, event: &DialogDomainEvent) {
+        for projection in &mut self.projections {
+            projection.apply_event(event);
+        }
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.projections.iter().position(|p| p.id() == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DialogResumed;
+    use chrono::Utc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    struct CountingProjection {
+        id: String,
+        applied: Arc<AtomicUsize>,
+    }
+
+    impl DialogProjection for CountingProjection {
+        fn apply_event(&mut self, _event: &DialogDomainEvent) {
+            self.applied.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn id(&self) -> &str {
+            &self.id
+        }
+    }
+
+    #[test]
+    fn test_projection_registry() {
+        let first = Arc::new(AtomicUsize::new(0));
+        let second = Arc::new(AtomicUsize::new(0));
+
+        let mut registry = ProjectionRegistry::new();
+        registry.register(Box::new(CountingProjection {
+            id: "first".to_string(),
+            applied: first.clone(),
+        }));
+        registry.register(Box::new(CountingProjection {
+            id: "second".to_string(),
+            applied: second.clone(),
+        }));
+
+        assert_eq!(registry.ids(), vec!["first", "second"]);
+        assert!(registry.get("second").is_some());
+        assert!(registry.get("missing").is_none());
+
+        registry.dispatch(&DialogDomainEvent::DialogResumed(DialogResumed {
+            event_id: Uuid::new_v4(),
+            dialog_id: Uuid::new_v4(),
+            resumed_at: Utc::now(),
+            restored_variables: None,
+        }));
+
+        assert_eq!(first.load(Ordering::SeqCst), 1);
+        assert_eq!(second.load(Ordering::SeqCst), 1);
+
+        // Unregistered projections stop receiving events
+        assert!(registry.unregister("first").is_some());
+        registry.dispatch(&DialogDomainEvent::DialogResumed(DialogResumed {
+            event_id: Uuid::new_v4(),
+            dialog_id: Uuid::new_v4(),
+            resumed_at: Utc::now(),
+            restored_variables: None,
+        }));
+        assert_eq!(first.load(Ordering::SeqCst), 1);
+        assert_eq!(second.load(Ordering::SeqCst), 2);
+    }
+}
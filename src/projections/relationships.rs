@@ -0,0 +1,224 @@
+//! Relationship projection linking dialogs across a participant's journey
+//!
+//! Two dialogs are related when one was forked or reopened from the other,
+//! when they share a participant, or when their topics share a keyword. The
+//! projection keeps only what it needs to answer "which dialogs are related
+//! to this one", so it can be fed the full event stream cheaply.
+
+use super::DialogProjection;
+use super::conversation_tree::{FORKED_FROM_KEY, REOPENED_FROM_KEY};
+use crate::events::DialogDomainEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::warn;
+use uuid::Uuid;
+
+/// How two dialogs are related
+///
+/// Fork and reopen links are reported from both ends: a fork lists its parent
+/// as `ForkedFrom`, and the parent lists the fork the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RelationKind {
+    /// One dialog was forked from the other
+    ForkedFrom,
+    /// One dialog reopened the other
+    ReopenedFrom,
+    /// Both dialogs have (or had) the same participant
+    SharedParticipant,
+    /// Topics in both dialogs share a keyword (case-insensitive)
+    SharedTopic,
+}
+
+/// Projection of relationships between dialogs
+#[derive(Debug, Clone, Default)]
+pub struct RelationshipProjection {
+    links: HashMap<Uuid, Vec<(Uuid, RelationKind)>>,
+    participants: HashMap<Uuid, HashSet<Uuid>>,
+    keywords: HashMap<Uuid, HashSet<String>>,
+}
+
+impl RelationshipProjection {
+    /// Create an empty relationship projection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the dialogs related to `dialog_id`
+    ///
+    /// A dialog related in several ways appears once per relation kind.
+    /// Results are ordered by kind, then by dialog ID.
+    pub fn related_dialogs(&self, dialog_id: Uuid) -> Vec<(Uuid, RelationKind)> {
+        let mut related = BTreeSet::new();
+
+        for (other, kind) in self.links.get(&dialog_id).into_iter().flatten() {
+            related.insert((*kind, *other));
+        }
+
+        if let Some(mine) = self.participants.get(&dialog_id) {
+            for (other, theirs) in &self.participants {
+                if *other != dialog_id && !mine.is_disjoint(theirs) {
+                    related.insert((RelationKind::SharedParticipant, *other));
+                }
+            }
+        }
+
+        if let Some(mine) = self.keywords.get(&dialog_id) {
+            for (other, theirs) in &self.keywords {
+                if *other != dialog_id && !mine.is_disjoint(theirs) {
+                    related.insert((RelationKind::SharedTopic, *other));
+                }
+            }
+        }
+
+        related
+            .into_iter()
+            .map(|(kind, other)| (other, kind))
+            .collect()
+    }
+
+    fn link(&mut self, child: Uuid, parent: Uuid, kind: RelationKind) {
+        if child == parent {
+            return;
+        }
+        for (from, to) in [(child, parent), (parent, child)] {
+            let links = self.links.entry(from).or_default();
+            if !links.contains(&(to, kind)) {
+                links.push((to, kind));
+            }
+        }
+    }
+}
+
+impl DialogProjection for RelationshipProjection {
+    fn apply_event(&mut self, event: &DialogDomainEvent) {
+        match event {
+            DialogDomainEvent::DialogStarted(e) => {
+                self.participants
+                    .entry(e.dialog_id)
+                    .or_default()
+                    .insert(e.primary_participant.id);
+            }
+            DialogDomainEvent::ParticipantAdded(e) => {
+                self.participants
+                    .entry(e.dialog_id)
+                    .or_default()
+                    .insert(e.participant.id);
+            }
+            DialogDomainEvent::ContextSwitched(e) => {
+                self.keywords
+                    .entry(e.dialog_id)
+                    .or_default()
+                    .extend(e.new_topic.keywords.iter().map(|k| k.to_lowercase()));
+            }
+            DialogDomainEvent::DialogMetadataSet(e) => {
+                let kind = match e.key.as_str() {
+                    FORKED_FROM_KEY => RelationKind::ForkedFrom,
+                    REOPENED_FROM_KEY => RelationKind::ReopenedFrom,
+                    _ => return,
+                };
+
+                match e.value.as_str().and_then(|s| Uuid::parse_str(s).ok()) {
+                    Some(parent) => self.link(e.dialog_id, parent, kind),
+                    None => warn!(
+                        "Invalid {} value on dialog {}: {}",
+                        e.key, e.dialog_id, e.value
+                    ),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn id(&self) -> &str {
+        "relationships"
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::DialogType;
+    use crate::events::{DialogMetadataSet, DialogStarted, ParticipantAdded};
+    use crate::value_objects::{Participant, ParticipantRole, ParticipantType};
+    use chrono::Utc;
+
+    fn participant(name: &str) -> Participant {
+        Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: name.to_string(),
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn start(projection: &mut RelationshipProjection, dialog_id: Uuid, primary: &Participant) {
+        projection.apply_event(&DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            dialog_type: DialogType::Direct,
+            primary_participant: primary.clone(),
+            started_at: Utc::now(),
+        }));
+    }
+
+    #[test]
+    fn test_related_dialogs() {
+        let mut projection = RelationshipProjection::new();
+        let (alice, bob, carol) = (
+            participant("Alice"),
+            participant("Bob"),
+            participant("Carol"),
+        );
+        let (first, second, original, fork, unrelated) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+
+        // Bob joins both of Alice's and Carol's dialogs
+        start(&mut projection, first, &alice);
+        start(&mut projection, second, &carol);
+        for dialog_id in [first, second] {
+            projection.apply_event(&DialogDomainEvent::ParticipantAdded(ParticipantAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                participant: bob.clone(),
+                added_at: Utc::now(),
+            }));
+        }
+
+        // A forked pair with no participants in common with the others
+        start(&mut projection, original, &participant("Dave"));
+        start(&mut projection, fork, &participant("Erin"));
+        projection.apply_event(&DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
+            event_id: Uuid::new_v4(),
+            dialog_id: fork,
+            key: FORKED_FROM_KEY.to_string(),
+            value: serde_json::json!(original.to_string()),
+            set_at: Utc::now(),
+        }));
+
+        start(&mut projection, unrelated, &participant("Frank"));
+
+        assert_eq!(
+            projection.related_dialogs(first),
+            vec![(second, RelationKind::SharedParticipant)]
+        );
+        assert_eq!(
+            projection.related_dialogs(fork),
+            vec![(original, RelationKind::ForkedFrom)]
+        );
+        assert_eq!(
+            projection.related_dialogs(original),
+            vec![(fork, RelationKind::ForkedFrom)]
+        );
+        assert!(projection.related_dialogs(unrelated).is_empty());
+    }
+}
@@ -2,27 +2,94 @@
 //!
 //! This provides a working projection system that matches the actual event structure
 
+use super::{BranchKind, ConversationTreeProjection, DialogProjection, ProjectionRegistry};
+use crate::aggregate::{ConversationPhase, DialogStatus, DialogType};
 use crate::events::*;
-use crate::aggregate::{DialogStatus, DialogType};
-use crate::value_objects::{Participant, Turn, ConversationMetrics};
-use cim_domain::DomainEvent;
+use crate::value_objects::{
+    ConversationMetrics, DisplayNameResolver, EndReason, FLAGGED_PROPERTY, MessageContent,
+    MessageIntent, MessageSegment, Participant, ParticipantType, ResolutionOutcome,
+    StoredNameResolver, Topic, Turn, TurnType,
+};
 use chrono::{DateTime, Utc};
+use cim_domain::DomainEvent;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::io;
+use tracing::warn;
 use uuid::Uuid;
 
+/// Number of most recent turns inspected when detecting a clarification loop
+pub const CLARIFICATION_LOOP_WINDOW: usize = 4;
+
+/// Clarification turns within the window that constitute a loop
+pub const CLARIFICATION_LOOP_THRESHOLD: usize = 2;
+
+/// Number of most recent event IDs remembered per dialog to skip redeliveries
+pub const DEDUP_WINDOW: usize = 256;
+
 /// Simple dialog view projection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimpleDialogView {
     pub dialog_id: Uuid,
     pub dialog_type: DialogType,
     pub status: DialogStatus,
+    pub locked: bool,
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
+    /// When the dialog was abandoned; abandoned dialogs have no `ended_at`
+    #[serde(default)]
+    pub abandoned_at: Option<DateTime<Utc>>,
+    pub end_reason: Option<EndReason>,
+    pub resolution: Option<ResolutionOutcome>,
+    /// Turn marked as the one that resolved the user's issue
+    #[serde(default)]
+    pub resolution_turn: Option<Uuid>,
     pub primary_participant: Participant,
     pub participants: HashMap<String, Participant>,
     pub turns: Vec<Turn>,
+    pub pinned_turns: Vec<Uuid>,
+    pub membership: Vec<MembershipChange>,
+    /// Topics in the order they were introduced
+    #[serde(default)]
+    pub topics: Vec<Topic>,
+    /// Resolution text of completed topics, keyed by topic ID
+    pub topic_resolutions: HashMap<Uuid, String>,
     pub metrics: Option<ConversationMetrics>,
+    /// Number of dialogs opened to reopen this one
+    #[serde(default)]
+    pub reopen_count: usize,
+    /// Dialog this one was forked or reopened from, as linked in the conversation tree
+    #[serde(default)]
+    pub branched_from: Option<(Uuid, BranchKind)>,
+    /// Number of `ContextSwitched` events applied
+    #[serde(default)]
+    pub topic_switches: u32,
+    /// Conversation phases entered, oldest first
+    #[serde(default)]
+    pub phase_history: Vec<PhaseEntry>,
+}
+
+/// A conversation phase the dialog entered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseEntry {
+    pub phase: ConversationPhase,
+    pub entered_at: DateTime<Utc>,
+}
+
+/// Kind of participant membership change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MembershipChangeKind {
+    Joined,
+    Left,
+}
+
+/// A participant joining or leaving a dialog after it started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipChange {
+    pub participant_id: Uuid,
+    pub kind: MembershipChangeKind,
+    pub at: DateTime<Utc>,
 }
 
 impl SimpleDialogView {
@@ -38,12 +105,25 @@ impl SimpleDialogView {
             dialog_id: event.dialog_id,
             dialog_type: event.dialog_type.clone(),
             status: DialogStatus::Active,
+            locked: false,
             started_at: event.started_at,
             ended_at: None,
+            abandoned_at: None,
+            end_reason: None,
+            resolution: None,
+            resolution_turn: None,
             primary_participant: event.primary_participant.clone(),
             participants,
             turns: Vec::new(),
+            pinned_turns: Vec::new(),
+            membership: Vec::new(),
+            topics: Vec::new(),
+            topic_resolutions: HashMap::new(),
             metrics: None,
+            reopen_count: 0,
+            branched_from: None,
+            topic_switches: 0,
+            phase_history: Vec::new(),
         }
     }
 
@@ -56,65 +136,739 @@ impl SimpleDialogView {
             DialogDomainEvent::DialogEnded(e) => {
                 self.status = DialogStatus::Ended;
                 self.ended_at = Some(e.ended_at);
+                self.end_reason = e.reason.clone();
                 self.metrics = Some(e.final_metrics.clone());
             }
+            DialogDomainEvent::DialogAbandoned(e) => {
+                self.status = DialogStatus::Abandoned;
+                self.abandoned_at = Some(e.abandoned_at);
+            }
             DialogDomainEvent::DialogPaused(_) => {
                 self.status = DialogStatus::Paused;
             }
             DialogDomainEvent::DialogResumed(_) => {
                 self.status = DialogStatus::Active;
             }
+            DialogDomainEvent::DialogLocked(_) => {
+                self.locked = true;
+            }
+            DialogDomainEvent::DialogUnlocked(_) => {
+                self.locked = false;
+            }
+            DialogDomainEvent::ResolutionSet(e) => {
+                self.resolution = Some(e.resolution);
+            }
+            DialogDomainEvent::ResolutionTurnMarked(e) => {
+                self.resolution_turn = Some(e.turn_id);
+            }
+            DialogDomainEvent::PhaseChanged(e) => {
+                self.phase_history.push(PhaseEntry {
+                    phase: e.new_phase,
+                    entered_at: e.changed_at,
+                });
+            }
             DialogDomainEvent::TurnAdded(e) => {
                 self.turns.push(e.turn.clone());
             }
+            DialogDomainEvent::MetricsUpdated(e) => {
+                self.metrics = Some(e.metrics.clone());
+            }
             DialogDomainEvent::ParticipantAdded(e) => {
-                self.participants.insert(
-                    e.participant.id.to_string(),
-                    e.participant.clone(),
-                );
+                self.participants
+                    .insert(e.participant.id.to_string(), e.participant.clone());
+                self.membership.push(MembershipChange {
+                    participant_id: e.participant.id,
+                    kind: MembershipChangeKind::Joined,
+                    at: e.added_at,
+                });
             }
             DialogDomainEvent::ParticipantRemoved(e) => {
                 self.participants.remove(&e.participant_id.to_string());
+                self.membership.push(MembershipChange {
+                    participant_id: e.participant_id,
+                    kind: MembershipChangeKind::Left,
+                    at: e.removed_at,
+                });
+            }
+            DialogDomainEvent::ContextSwitched(e) => {
+                self.topic_switches += 1;
+                match self.topics.iter_mut().find(|t| t.id == e.new_topic.id) {
+                    Some(topic) => *topic = e.new_topic.clone(),
+                    None => self.topics.push(e.new_topic.clone()),
+                }
+            }
+            DialogDomainEvent::TopicCompleted(e) => {
+                if let Some(resolution) = &e.resolution {
+                    self.topic_resolutions
+                        .insert(e.topic_id, resolution.clone());
+                }
             }
-            DialogDomainEvent::TopicCompleted(_) => {
-                // Topic tracking could be added here
+            DialogDomainEvent::TurnPinned(e) if !self.pinned_turns.contains(&e.turn_id) => {
+                self.pinned_turns.push(e.turn_id);
+            }
+            DialogDomainEvent::TurnUnpinned(e) => {
+                self.pinned_turns.retain(|id| *id != e.turn_id);
+            }
+            DialogDomainEvent::TurnFlagged(e) => {
+                if let Some(turn) = self.turns.iter_mut().find(|t| t.turn_id == e.turn_id) {
+                    turn.metadata.properties.insert(
+                        FLAGGED_PROPERTY.to_string(),
+                        serde_json::Value::String(e.reason.clone()),
+                    );
+                }
+            }
+            DialogDomainEvent::EmbeddingAttached(e) => {
+                if let Some(turn) = self.turns.iter_mut().find(|t| t.turn_id == e.turn_id) {
+                    turn.message.embeddings = Some(e.embeddings.clone());
+                }
+            }
+            DialogDomainEvent::TurnRetracted(e) => {
+                self.turns.retain(|t| t.turn_id != e.turn_id);
+                self.pinned_turns.retain(|id| *id != e.turn_id);
+                if self.resolution_turn == Some(e.turn_id) {
+                    self.resolution_turn = None;
+                }
+            }
+            DialogDomainEvent::TurnEdited(e) => {
+                if let Some(turn) = self.turns.iter_mut().find(|t| t.turn_id == e.turn_id) {
+                    turn.message = e.new_message.clone();
+                }
+            }
+            DialogDomainEvent::TurnsArchived(e) => {
+                // The view keeps the whole transcript; as on the aggregate,
+                // archived turns are no longer pinned
+                self.pinned_turns.retain(|id| !e.turn_ids.contains(id));
             }
             _ => {
                 // Handle other events as needed
             }
         }
     }
+
+    /// Copy of the view with only the requested sections filled in
+    ///
+    /// Summary fields are always copied. Excluded sections are left empty
+    /// without being cloned: turns covers `turns` and `pinned_turns`,
+    /// participants covers `participants` and `membership`, and metadata
+    /// covers `metrics`, `topics`, `topic_resolutions` and `phase_history`.
+    pub fn projected(
+        &self,
+        include_turns: bool,
+        include_participants: bool,
+        include_metadata: bool,
+    ) -> Self {
+        Self {
+            dialog_id: self.dialog_id,
+            dialog_type: self.dialog_type,
+            status: self.status,
+            locked: self.locked,
+            started_at: self.started_at,
+            ended_at: self.ended_at,
+            abandoned_at: self.abandoned_at,
+            end_reason: self.end_reason.clone(),
+            resolution: self.resolution,
+            resolution_turn: self.resolution_turn,
+            primary_participant: self.primary_participant.clone(),
+            participants: if include_participants {
+                self.participants.clone()
+            } else {
+                HashMap::new()
+            },
+            turns: if include_turns {
+                self.turns.clone()
+            } else {
+                Vec::new()
+            },
+            pinned_turns: if include_turns {
+                self.pinned_turns.clone()
+            } else {
+                Vec::new()
+            },
+            membership: if include_participants {
+                self.membership.clone()
+            } else {
+                Vec::new()
+            },
+            topics: if include_metadata {
+                self.topics.clone()
+            } else {
+                Vec::new()
+            },
+            topic_resolutions: if include_metadata {
+                self.topic_resolutions.clone()
+            } else {
+                HashMap::new()
+            },
+            metrics: if include_metadata {
+                self.metrics.clone()
+            } else {
+                None
+            },
+            reopen_count: self.reopen_count,
+            branched_from: self.branched_from,
+            topic_switches: self.topic_switches,
+            phase_history: if include_metadata {
+                self.phase_history.clone()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    /// The phase the dialog is currently in
+    pub fn current_phase(&self) -> Option<ConversationPhase> {
+        self.phase_history.last().map(|entry| entry.phase)
+    }
+
+    /// The first topic introduced in the dialog
+    pub fn initial_topic(&self) -> Option<&Topic> {
+        self.topics.first()
+    }
+
+    /// Check whether a participant has ever taken part in the dialog, even if they left
+    pub fn has_participated(&self, participant_id: &str) -> bool {
+        self.participants.contains_key(participant_id)
+            || self.primary_participant.id.to_string() == participant_id
+            || self
+                .membership
+                .iter()
+                .any(|change| change.participant_id.to_string() == participant_id)
+    }
+
+    /// Text turns without embeddings, as (turn ID, text) in conversation order
+    pub fn turns_missing_embeddings(&self) -> Vec<(Uuid, String)> {
+        self.turns
+            .iter()
+            .filter(|t| t.message.embeddings.as_ref().is_none_or(|e| e.is_empty()))
+            .filter_map(|t| {
+                t.message
+                    .content
+                    .text_content()
+                    .map(|text| (t.turn_id, text))
+            })
+            .collect()
+    }
+
+    /// Turns per minute from the start of the dialog to its end (or latest turn)
+    ///
+    /// Returns `None` for dialogs without turns or whose span is zero.
+    pub fn turns_per_minute(&self) -> Option<f64> {
+        let last_turn = self.turns.iter().map(|t| t.timestamp).max()?;
+        let end = self
+            .ended_at
+            .or(self.abandoned_at)
+            .map_or(last_turn, |ended| ended.max(last_turn));
+        let minutes = (end - self.started_at).num_milliseconds() as f64 / 60_000.0;
+        if minutes <= 0.0 {
+            return None;
+        }
+        Some(self.turns.len() as f64 / minutes)
+    }
+
+    /// Time from the start of the dialog to its end, if it has ended
+    ///
+    /// Abandoned dialogs were never resolved and have none.
+    pub fn resolution_time(&self) -> Option<chrono::Duration> {
+        self.ended_at.map(|ended| ended - self.started_at)
+    }
+
+    /// Number of joins and leaves since the dialog started
+    pub fn membership_churn(&self) -> usize {
+        self.membership.len()
+    }
+
+    /// Check whether a turn was produced by an agent
+    pub fn is_agent_turn(&self, turn: &Turn) -> bool {
+        turn.metadata.turn_type == TurnType::AgentResponse
+            || self
+                .participants
+                .get(&turn.participant_id.to_string())
+                .is_some_and(|p| p.participant_type == ParticipantType::AIAgent)
+    }
+
+    /// Get the first turn produced by an agent
+    pub fn first_agent_turn(&self) -> Option<&Turn> {
+        self.turns.iter().find(|turn| self.is_agent_turn(turn))
+    }
+
+    /// Check whether the last turn is a user question no agent has answered yet
+    pub fn has_unanswered_question(&self) -> bool {
+        self.turns.last().is_some_and(|turn| {
+            turn.message.intent == Some(MessageIntent::Question) && !self.is_agent_turn(turn)
+        })
+    }
+
+    /// Check whether recent turns keep asking for clarification
+    pub fn in_clarification_loop(&self) -> bool {
+        let start = self.turns.len().saturating_sub(CLARIFICATION_LOOP_WINDOW);
+        self.turns[start..]
+            .iter()
+            .filter(|turn| {
+                turn.metadata.turn_type == TurnType::Clarification
+                    || turn.message.intent == Some(MessageIntent::Clarification)
+            })
+            .count()
+            >= CLARIFICATION_LOOP_THRESHOLD
+    }
+
+    /// Mean sentiment of turns that carry a sentiment score
+    pub fn average_sentiment(&self) -> Option<f32> {
+        let sentiments: Vec<f32> = self
+            .turns
+            .iter()
+            .filter_map(|t| t.message.sentiment)
+            .collect();
+        if sentiments.is_empty() {
+            None
+        } else {
+            Some(sentiments.iter().sum::<f32>() / sentiments.len() as f32)
+        }
+    }
+
+    /// Display name of the participant who produced a turn
+    pub fn speaker_name(&self, participant_id: Uuid) -> String {
+        self.speaker_name_with(participant_id, &StoredNameResolver)
+    }
+
+    /// Display name of the participant who produced a turn, as resolved by `resolver`
+    ///
+    /// Unknown participants are shown by ID.
+    pub fn speaker_name_with(
+        &self,
+        participant_id: Uuid,
+        resolver: &dyn DisplayNameResolver,
+    ) -> String {
+        self.participants
+            .get(&participant_id.to_string())
+            .or(Some(&self.primary_participant).filter(|p| p.id == participant_id))
+            .map(|p| resolver.display_name(p))
+            .unwrap_or_else(|| participant_id.to_string())
+    }
+
+    /// Render the dialog as a markdown transcript
+    ///
+    /// Structured content with a `code` string field is rendered as a fenced
+    /// code block (using its `language` field, if any); other structured
+    /// content is rendered as fenced JSON. Multimodal attachments with string
+    /// values are rendered as links.
+    pub fn to_markdown(&self) -> String {
+        self.to_markdown_with(&StoredNameResolver)
+    }
+
+    /// Render the dialog as a markdown transcript, naming speakers with `resolver`
+    pub fn to_markdown_with(&self, resolver: &dyn DisplayNameResolver) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {:?} dialog {}", self.dialog_type, self.dialog_id);
+        let _ = writeln!(
+            out,
+            "\n_Started {}, status: {:?}_",
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            self.status
+        );
+
+        for turn in &self.turns {
+            let _ = writeln!(
+                out,
+                "\n### {} — {}\n",
+                self.speaker_name_with(turn.participant_id, resolver),
+                turn.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+
+            match &turn.message.content {
+                MessageContent::Text(text) => {
+                    let _ = writeln!(out, "{text}");
+                }
+                MessageContent::Structured(value) => {
+                    let _ = writeln!(out, "{}", Self::structured_markdown(value));
+                }
+                MessageContent::Multimodal { text, data } => {
+                    if let Some(text) = text {
+                        let _ = writeln!(out, "{text}\n");
+                    }
+                    let mut keys: Vec<&String> = data.keys().collect();
+                    keys.sort();
+                    for key in keys {
+                        match &data[key] {
+                            serde_json::Value::String(url) => {
+                                let _ = writeln!(out, "- [{key}]({url})");
+                            }
+                            other => {
+                                let _ = writeln!(out, "- {key}: `{other}`");
+                            }
+                        }
+                    }
+                }
+                MessageContent::Segmented(segments) => {
+                    let parts: Vec<String> = segments
+                        .iter()
+                        .map(|segment| match segment {
+                            MessageSegment::Text(text) => text.clone(),
+                            MessageSegment::Code { lang, code } => {
+                                format!("```{lang}\n{code}\n```")
+                            }
+                            MessageSegment::Json(value) => Self::json_markdown(value),
+                        })
+                        .collect();
+                    let _ = writeln!(out, "{}", parts.join("\n\n"));
+                }
+            }
+        }
+
+        out
+    }
+
+    fn structured_markdown(value: &serde_json::Value) -> String {
+        if let Some(code) = value.get("code").and_then(|c| c.as_str()) {
+            let language = value.get("language").and_then(|l| l.as_str()).unwrap_or("");
+            return format!("```{language}\n{code}\n```");
+        }
+
+        Self::json_markdown(value)
+    }
+
+    fn json_markdown(value: &serde_json::Value) -> String {
+        let json = serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string());
+        format!("```json\n{json}\n```")
+    }
 }
 
 /// Simple projection updater
 pub struct SimpleProjectionUpdater {
     views: HashMap<Uuid, SimpleDialogView>,
+    tree: ConversationTreeProjection,
+    registry: ProjectionRegistry,
+    last_event_at: Option<DateTime<Utc>>,
+    generation: u64,
+    /// Revision of each view, taken from `revision` when the view last changed
+    revisions: HashMap<Uuid, u64>,
+    /// Increases on every view change and is never reset, so a revision is
+    /// never reused for a different state of a view
+    revision: u64,
+    /// IDs of the events last applied to each view, so redelivered events are skipped
+    applied_events: HashMap<Uuid, RecentEvents>,
+}
+
+/// The last [`DEDUP_WINDOW`] event IDs applied to a view
+#[derive(Debug, Default)]
+struct RecentEvents {
+    order: VecDeque<Uuid>,
+    ids: HashSet<Uuid>,
+}
+
+impl RecentEvents {
+    fn contains(&self, event_id: &Uuid) -> bool {
+        self.ids.contains(event_id)
+    }
+
+    fn insert(&mut self, event_id: Uuid) {
+        if !self.ids.insert(event_id) {
+            return;
+        }
+        self.order.push_back(event_id);
+        if self.order.len() > DEDUP_WINDOW
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.ids.remove(&oldest);
+        }
+    }
+}
+
+/// Snapshot of the projection's state for monitoring
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectionHealth {
+    pub total_views: usize,
+    pub active: usize,
+    pub paused: usize,
+    /// Ended and abandoned dialogs
+    pub ended: usize,
+    /// Latest event timestamp applied since the views were last reset
+    pub last_event_at: Option<DateTime<Utc>>,
+    /// Number of times the views were rebuilt or reloaded
+    pub generation: u64,
 }
 
 impl SimpleProjectionUpdater {
     pub fn new() -> Self {
         Self {
             views: HashMap::new(),
+            tree: ConversationTreeProjection::new(),
+            registry: ProjectionRegistry::new(),
+            last_event_at: None,
+            generation: 0,
+            revisions: HashMap::new(),
+            revision: 0,
+            applied_events: HashMap::new(),
         }
     }
 
+    /// Register an additional projection to receive every handled event
+    pub fn register_projection(
+        &mut self,
+        projection: Box<dyn DialogProjection>,
+    ) -> Option<Box<dyn DialogProjection>> {
+        self.registry.register(projection)
+    }
+
+    /// Get the registry of additional projections
+    pub fn projections(&self) -> &ProjectionRegistry {
+        &self.registry
+    }
+
     /// Handle a domain event
-    pub async fn handle_event(&mut self, event: DialogDomainEvent) -> Result<(), Box<dyn std::error::Error>> {
+    ///
+    /// The views, their revisions and the conversation tree are all updated
+    /// before this returns, so callers sharing the updater behind a lock never
+    /// observe an event half applied. An event whose ID was already applied
+    /// to its dialog's view, e.g. one redelivered by an at-least-once
+    /// transport, is ignored, including by registered projections. Only the
+    /// last [`DEDUP_WINDOW`] event IDs of each dialog are remembered.
+    pub async fn handle_event(
+        &mut self,
+        event: DialogDomainEvent,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.apply_to_views(&event) {
+            self.registry.dispatch(&event);
+        }
+        debug_assert_eq!(
+            self.dialog_invariant_violations(event.aggregate_id()),
+            Vec::<String>::new()
+        );
+
+        Ok(())
+    }
+
+    /// Rebuild the views from scratch using only events that occurred at or before `as_of`
+    ///
+    /// Registered projections are reset and receive the replayed events too.
+    pub async fn rebuild_as_of(
+        &mut self,
+        events: impl IntoIterator<Item = DialogDomainEvent>,
+        as_of: DateTime<Utc>,
+    ) {
+        self.reset();
+        self.registry.reset();
+        for event in events.into_iter().filter(|e| e.occurred_at() <= as_of) {
+            self.replay(&event);
+        }
+    }
+
+    /// Rebuild the views of the dialogs active within `[from, to]`
+    ///
+    /// A dialog is active in the window if it has an event inside it or was
+    /// not yet ended or abandoned when the window opened. Each such dialog is
+    /// rebuilt from all of its events up to `to`, so dialogs started before
+    /// the window keep their earlier history. Registered projections are reset
+    /// and receive the replayed events too.
+    pub async fn rebuild_window(
+        &mut self,
+        events: impl IntoIterator<Item = DialogDomainEvent>,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) {
+        let events: Vec<DialogDomainEvent> = events
+            .into_iter()
+            .filter(|e| e.occurred_at() <= to)
+            .collect();
+        let mut in_window = HashSet::new();
+        let mut closed_before = HashSet::new();
+        for event in &events {
+            if event.occurred_at() >= from {
+                in_window.insert(event.aggregate_id());
+            } else if matches!(
+                event,
+                DialogDomainEvent::DialogEnded(_) | DialogDomainEvent::DialogAbandoned(_)
+            ) {
+                closed_before.insert(event.aggregate_id());
+            }
+        }
+
+        self.reset();
+        self.registry.reset();
+        for event in &events {
+            let dialog_id = event.aggregate_id();
+            if in_window.contains(&dialog_id) || !closed_before.contains(&dialog_id) {
+                self.replay(event);
+            }
+        }
+    }
+
+    /// Apply a replayed event to the views and registered projections
+    fn replay(&mut self, event: &DialogDomainEvent) {
+        if self.apply_to_views(event) {
+            self.registry.dispatch(event);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.views.clear();
+        self.revisions.clear();
+        self.applied_events.clear();
+        self.tree = ConversationTreeProjection::new();
+        self.last_event_at = None;
+        self.generation += 1;
+    }
+
+    /// Apply an event to the views, returning false if it was a duplicate
+    ///
+    /// Events with the nil ID cannot be told apart and are always applied.
+    fn apply_to_views(&mut self, event: &DialogDomainEvent) -> bool {
         let dialog_id = event.aggregate_id();
+        let event_id = event.event_id();
+        if !event_id.is_nil()
+            && self
+                .applied_events
+                .get(&dialog_id)
+                .is_some_and(|applied| applied.contains(&event_id))
+        {
+            return false;
+        }
 
-        match &event {
+        let parent_before = self.tree.parent(dialog_id);
+        self.tree.apply_event(event);
+        let parent_after = self.tree.parent(dialog_id);
+
+        // Move the reopen count when a dialog is (re)linked as a reopening
+        if parent_before != parent_after {
+            if let Some(view) = self.views.get_mut(&dialog_id) {
+                view.branched_from = parent_after;
+            }
+            if let Some((parent, BranchKind::Reopen)) = parent_before
+                && let Some(view) = self.views.get_mut(&parent)
+            {
+                view.reopen_count = view.reopen_count.saturating_sub(1);
+                self.mark_changed(parent);
+            }
+            if let Some((parent, BranchKind::Reopen)) = parent_after
+                && let Some(view) = self.views.get_mut(&parent)
+            {
+                view.reopen_count += 1;
+                self.mark_changed(parent);
+            }
+        }
+        self.last_event_at = self.last_event_at.max(Some(event.occurred_at()));
+
+        match event {
             DialogDomainEvent::DialogStarted(e) => {
-                let view = SimpleDialogView::from_started(e);
+                // Reopenings may have been linked before this dialog started
+                let mut view = SimpleDialogView::from_started(e);
+                view.reopen_count = self.reopen_links(dialog_id);
+                view.branched_from = self.tree.parent(dialog_id);
                 self.views.insert(dialog_id, view);
             }
             _ => {
                 if let Some(view) = self.views.get_mut(&dialog_id) {
-                    view.apply_event(&event);
+                    view.apply_event(event);
                 }
             }
         }
+        self.mark_changed(dialog_id);
 
-        Ok(())
+        if !event_id.is_nil() && self.views.contains_key(&dialog_id) {
+            self.applied_events
+                .entry(dialog_id)
+                .or_default()
+                .insert(event_id);
+        }
+        true
+    }
+
+    /// Number of dialogs linked in the tree as reopenings of `dialog_id`
+    fn reopen_links(&self, dialog_id: Uuid) -> usize {
+        self.tree
+            .children(dialog_id)
+            .into_iter()
+            .filter(|child| matches!(self.tree.parent(*child), Some((_, BranchKind::Reopen))))
+            .count()
+    }
+
+    /// Ways in which the views, their indices and the conversation tree
+    /// disagree (empty when consistent)
+    ///
+    /// Scans every dialog, so it is only meant for tests; registered
+    /// projections are opaque and not checked.
+    #[cfg(test)]
+    fn invariant_violations(&self) -> Vec<String> {
+        let dialog_ids: HashSet<Uuid> = self
+            .views
+            .keys()
+            .chain(self.revisions.keys())
+            .copied()
+            .collect();
+        let mut violations: Vec<String> = dialog_ids
+            .into_iter()
+            .flat_map(|dialog_id| self.dialog_invariant_violations(dialog_id))
+            .collect();
+
+        let mut revisions: Vec<u64> = self.revisions.values().copied().collect();
+        revisions.sort();
+        if revisions.windows(2).any(|pair| pair[0] == pair[1]) {
+            violations.push("revision shared by several views".to_string());
+        }
+
+        violations
+    }
+
+    /// Ways in which one dialog's view, revision and tree links disagree
+    fn dialog_invariant_violations(&self, dialog_id: Uuid) -> Vec<String> {
+        let mut violations = Vec::new();
+        let revision = self.revisions.get(&dialog_id);
+
+        match self.views.get(&dialog_id) {
+            Some(view) => {
+                if view.dialog_id != dialog_id {
+                    violations.push(format!(
+                        "view {} stored under {}",
+                        view.dialog_id, dialog_id
+                    ));
+                }
+                if revision.is_none() {
+                    violations.push(format!("view {dialog_id} has no revision"));
+                }
+                if view.branched_from != self.tree.parent(dialog_id) {
+                    violations.push(format!(
+                        "view {dialog_id} disagrees with the tree about its parent"
+                    ));
+                }
+                if view.reopen_count != self.reopen_links(dialog_id) {
+                    violations.push(format!(
+                        "view {dialog_id} disagrees with the tree about its reopenings"
+                    ));
+                }
+            }
+            None => {
+                if let Some(revision) = revision {
+                    violations.push(format!(
+                        "revision {revision} recorded for missing view {dialog_id}"
+                    ));
+                }
+            }
+        }
+        if let Some(revision) = revision
+            && *revision > self.revision
+        {
+            violations.push(format!(
+                "revision {revision} of {dialog_id} is ahead of {}",
+                self.revision
+            ));
+        }
+
+        violations
+    }
+
+    fn mark_changed(&mut self, dialog_id: Uuid) {
+        if self.views.contains_key(&dialog_id) {
+            self.revision += 1;
+            self.revisions.insert(dialog_id, self.revision);
+        }
+    }
+
+    /// Get the revision of a dialog view
+    ///
+    /// The revision changes whenever the view does (including when the views
+    /// are rebuilt or reloaded), so callers holding a copy of a view can tell
+    /// whether it is stale by comparing revisions.
+    pub fn view_revision(&self, dialog_id: &Uuid) -> Option<u64> {
+        self.revisions.get(dialog_id).copied()
     }
 
     /// Get a dialog view
@@ -122,6 +876,25 @@ impl SimpleProjectionUpdater {
         self.views.get(dialog_id)
     }
 
+    /// Get the parent/child links between forked and reopened dialogs
+    pub fn conversation_tree(&self) -> &ConversationTreeProjection {
+        &self.tree
+    }
+
+    /// Summarize the projection's state for health checks
+    pub fn health(&self) -> ProjectionHealth {
+        let count = |status| self.views.values().filter(|v| v.status == status).count();
+
+        ProjectionHealth {
+            total_views: self.views.len(),
+            active: count(DialogStatus::Active),
+            paused: count(DialogStatus::Paused),
+            ended: count(DialogStatus::Ended) + count(DialogStatus::Abandoned),
+            last_event_at: self.last_event_at,
+            generation: self.generation,
+        }
+    }
+
     /// Get all active dialogs
     pub fn get_active_dialogs(&self) -> Vec<&SimpleDialogView> {
         self.views
@@ -129,16 +902,104 @@ impl SimpleProjectionUpdater {
             .filter(|v| v.status == DialogStatus::Active)
             .collect()
     }
-    
+
     /// Get all dialogs
     pub fn get_all_dialogs(&self) -> Vec<&SimpleDialogView> {
         self.views.values().collect()
     }
+
+    /// Up to `limit` text turns without embeddings, as (dialog ID, turn ID, text)
+    ///
+    /// Dialogs are visited oldest first so repeated calls walk the backlog in a
+    /// stable order as embeddings are attached.
+    pub fn all_turns_missing_embeddings(&self, limit: usize) -> Vec<(Uuid, Uuid, String)> {
+        let mut views: Vec<&SimpleDialogView> = self.views.values().collect();
+        views.sort_by_key(|v| (v.started_at, v.dialog_id));
+
+        views
+            .into_iter()
+            .flat_map(|view| {
+                view.turns_missing_embeddings()
+                    .into_iter()
+                    .map(move |(turn_id, text)| (view.dialog_id, turn_id, text))
+            })
+            .take(limit)
+            .collect()
+    }
+
+    /// Stream every view to `writer` as one JSON object per line
+    ///
+    /// Returns the number of views written.
+    pub fn export_jsonl(&self, mut writer: impl io::Write) -> io::Result<usize> {
+        let mut written = 0;
+        for view in self.views.values() {
+            serde_json::to_writer(&mut writer, view)?;
+            writer.write_all(b"\n")?;
+            written += 1;
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Replace all views with those read from a JSON lines stream
+    ///
+    /// Blank lines are ignored and malformed lines are logged and skipped.
+    /// The conversation tree is relinked from each view's `branched_from`.
+    /// Views carry no event IDs, so redeliveries of events applied before the
+    /// export are not recognised, and registered projections are reset since
+    /// they cannot be rebuilt from views. Returns the number of views imported.
+    pub fn import_jsonl(&mut self, reader: impl io::BufRead) -> io::Result<usize> {
+        self.reset();
+        self.registry.reset();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<SimpleDialogView>(&line) {
+                Ok(view) => {
+                    let dialog_id = view.dialog_id;
+                    self.views.insert(dialog_id, view);
+                    self.mark_changed(dialog_id);
+                }
+                Err(e) => warn!(
+                    "Skipping malformed dialog view on line {}: {}",
+                    index + 1,
+                    e
+                ),
+            }
+        }
+
+        // Link oldest first so siblings keep a stable order
+        let mut links: Vec<_> = self
+            .views
+            .values()
+            .filter_map(|view| Some((view.started_at, view.dialog_id, view.branched_from?)))
+            .collect();
+        links.sort_by_key(|(started_at, dialog_id, _)| (*started_at, *dialog_id));
+        for (_, child, (parent, kind)) in links {
+            self.tree.link(child, parent, kind);
+        }
+
+        // Reopenings whose own view was not exported are no longer counted
+        let dialog_ids: Vec<Uuid> = self.views.keys().copied().collect();
+        for dialog_id in dialog_ids {
+            let reopen_count = self.reopen_links(dialog_id);
+            if let Some(view) = self.views.get_mut(&dialog_id) {
+                view.reopen_count = reopen_count;
+            }
+        }
+
+        Ok(self.views.len())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::value_objects::{Message, ParticipantRole};
 
     #[tokio::test]
     async fn test_simple_projection() {
@@ -147,6 +1008,7 @@ mod tests {
         // Create a dialog started event
         let dialog_id = Uuid::new_v4();
         let event = DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
             dialog_id,
             dialog_type: DialogType::Support,
             primary_participant: Participant {
@@ -168,4 +1030,747 @@ mod tests {
         assert_eq!(view.status, DialogStatus::Active);
         assert_eq!(view.participants.len(), 1);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_to_markdown() {
+        let mut updater = SimpleProjectionUpdater::new();
+        let dialog_id = Uuid::new_v4();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "Alice".to_string(),
+            metadata: HashMap::new(),
+        };
+        let agent = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::AIAgent,
+            role: ParticipantRole::Assistant,
+            name: "Helper".to_string(),
+            metadata: HashMap::new(),
+        };
+
+        let code = Message {
+            content: MessageContent::Structured(serde_json::json!({
+                "language": "rust",
+                "code": "fn main() {}",
+            })),
+            ..Message::text("")
+        };
+
+        let events = vec![
+            DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                dialog_type: DialogType::Support,
+                primary_participant: user.clone(),
+                started_at: Utc::now(),
+            }),
+            DialogDomainEvent::ParticipantAdded(ParticipantAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                participant: agent.clone(),
+                added_at: Utc::now(),
+            }),
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: Turn::new(
+                    1,
+                    user.id,
+                    Message::text("How do I start?"),
+                    TurnType::UserQuery,
+                ),
+                turn_number: 1,
+            }),
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: Turn::new(2, agent.id, code, TurnType::AgentResponse),
+                turn_number: 2,
+            }),
+        ];
+        for event in events {
+            updater.handle_event(event).await.unwrap();
+        }
+
+        let markdown = updater.get_view(&dialog_id).unwrap().to_markdown();
+        assert!(markdown.contains("### Alice"));
+        assert!(markdown.contains("### Helper"));
+        assert!(markdown.contains("How do I start?"));
+        assert!(markdown.contains("```rust\nfn main() {}\n```"));
+
+        // A resolver can override stored names, e.g. from a user directory
+        struct Directory(Uuid);
+        impl DisplayNameResolver for Directory {
+            fn display_name(&self, participant: &Participant) -> String {
+                if participant.id == self.0 {
+                    "Alice Smith".to_string()
+                } else {
+                    participant.name.clone()
+                }
+            }
+        }
+        let markdown = updater
+            .get_view(&dialog_id)
+            .unwrap()
+            .to_markdown_with(&Directory(user.id));
+        assert!(markdown.contains("### Alice Smith"));
+        assert!(markdown.contains("### Helper"));
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_round_trip() {
+        let mut updater = SimpleProjectionUpdater::new();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+
+        for i in 0..50 {
+            let dialog_id = Uuid::new_v4();
+            updater
+                .handle_event(DialogDomainEvent::DialogStarted(DialogStarted {
+                    event_id: Uuid::new_v4(),
+                    dialog_id,
+                    dialog_type: DialogType::Direct,
+                    primary_participant: user.clone(),
+                    started_at: Utc::now(),
+                }))
+                .await
+                .unwrap();
+            updater
+                .handle_event(DialogDomainEvent::TurnAdded(TurnAdded {
+                    event_id: Uuid::new_v4(),
+                    dialog_id,
+                    turn: Turn::new(
+                        1,
+                        user.id,
+                        Message::text(format!("Message {i}")),
+                        TurnType::UserQuery,
+                    ),
+                    turn_number: 1,
+                }))
+                .await
+                .unwrap();
+        }
+
+        let mut buffer = Vec::new();
+        assert_eq!(updater.export_jsonl(&mut buffer).unwrap(), 50);
+
+        // Malformed and blank lines are skipped on import
+        buffer.extend_from_slice(b"{not json}\n\n");
+
+        let mut restored = SimpleProjectionUpdater::new();
+        assert_eq!(restored.import_jsonl(buffer.as_slice()).unwrap(), 50);
+
+        for view in updater.get_all_dialogs() {
+            let copy = restored.get_view(&view.dialog_id).unwrap();
+            assert_eq!(
+                serde_json::to_value(copy).unwrap(),
+                serde_json::to_value(view).unwrap()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_relinks_conversation_tree() {
+        use super::super::conversation_tree::REOPENED_FROM_KEY;
+
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let reopen = |child: Uuid, parent: Uuid| {
+            DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
+                event_id: Uuid::new_v4(),
+                dialog_id: child,
+                key: REOPENED_FROM_KEY.to_string(),
+                value: serde_json::json!(parent.to_string()),
+                set_at: Utc::now(),
+            })
+        };
+        let (first, second, reopening) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let mut updater = SimpleProjectionUpdater::new();
+        for dialog_id in [first, second, reopening] {
+            updater
+                .handle_event(DialogDomainEvent::DialogStarted(DialogStarted {
+                    event_id: Uuid::new_v4(),
+                    dialog_id,
+                    dialog_type: DialogType::Support,
+                    primary_participant: user.clone(),
+                    started_at: Utc::now(),
+                }))
+                .await
+                .unwrap();
+        }
+        updater
+            .handle_event(reopen(reopening, first))
+            .await
+            .unwrap();
+        assert_eq!(
+            updater.get_view(&reopening).unwrap().branched_from,
+            Some((first, BranchKind::Reopen))
+        );
+
+        let mut buffer = Vec::new();
+        updater.export_jsonl(&mut buffer).unwrap();
+        let mut restored = SimpleProjectionUpdater::new();
+        restored.import_jsonl(buffer.as_slice()).unwrap();
+
+        assert_eq!(
+            restored.conversation_tree().children(first),
+            vec![reopening]
+        );
+        assert_eq!(restored.get_view(&first).unwrap().reopen_count, 1);
+
+        // Relinking after the import moves the reopen count
+        restored
+            .handle_event(reopen(reopening, second))
+            .await
+            .unwrap();
+        assert_eq!(restored.get_view(&first).unwrap().reopen_count, 0);
+        assert_eq!(restored.get_view(&second).unwrap().reopen_count, 1);
+        assert!(restored.conversation_tree().children(first).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_as_of_and_window() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let early = Uuid::new_v4();
+        let late = Uuid::new_v4();
+
+        let mut turn = Turn::new(1, user.id, Message::text("Hello"), TurnType::UserQuery);
+        turn.timestamp = at(10);
+        let events = vec![
+            DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id: early,
+                dialog_type: DialogType::Direct,
+                primary_participant: user.clone(),
+                started_at: at(0),
+            }),
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id: early,
+                turn,
+                turn_number: 1,
+            }),
+            DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id: late,
+                dialog_type: DialogType::Direct,
+                primary_participant: user.clone(),
+                started_at: at(30),
+            }),
+            DialogDomainEvent::DialogEnded(DialogEnded {
+                event_id: Uuid::new_v4(),
+                dialog_id: early,
+                ended_at: at(40),
+                reason: None,
+                final_metrics: ConversationMetrics {
+                    turn_count: 1,
+                    avg_response_time_ms: 0.0,
+                    topic_switches: 0,
+                    clarification_count: 0,
+                    sentiment_trend: 0.0,
+                    coherence_score: 1.0,
+                },
+            }),
+        ];
+
+        // Registered projections are reset and replayed along with the views
+        struct Counting(Arc<AtomicUsize>);
+        impl DialogProjection for Counting {
+            fn apply_event(&mut self, _event: &DialogDomainEvent) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn id(&self) -> &str {
+                "counting"
+            }
+
+            fn reset(&mut self) {
+                self.0.store(0, Ordering::SeqCst);
+            }
+        }
+        let replayed = Arc::new(AtomicUsize::new(0));
+        let mut updater = SimpleProjectionUpdater::new();
+        updater.register_projection(Box::new(Counting(replayed.clone())));
+
+        updater.rebuild_as_of(events.clone(), at(20)).await;
+        let view = updater.get_view(&early).unwrap();
+        assert_eq!(view.status, DialogStatus::Active);
+        assert_eq!(view.turns.len(), 1);
+        assert!(updater.get_view(&late).is_none());
+        assert_eq!(replayed.load(Ordering::SeqCst), 2);
+
+        updater.rebuild_as_of(events.clone(), at(45)).await;
+        assert_eq!(
+            updater.get_view(&early).unwrap().status,
+            DialogStatus::Ended
+        );
+        assert!(updater.get_view(&late).is_some());
+        assert_eq!(replayed.load(Ordering::SeqCst), 4);
+
+        // A dialog started before the window but ending inside it is rebuilt
+        // from its whole history
+        updater.rebuild_window(events.clone(), at(25), at(50)).await;
+        let view = updater.get_view(&early).unwrap();
+        assert_eq!(view.status, DialogStatus::Ended);
+        assert_eq!(view.turns.len(), 1);
+        assert!(updater.get_view(&late).is_some());
+        assert_eq!(replayed.load(Ordering::SeqCst), 4);
+
+        // One that ended before the window is left out; one still open is kept
+        updater.rebuild_window(events.clone(), at(45), at(50)).await;
+        assert!(updater.get_view(&early).is_none());
+        assert!(updater.get_view(&late).is_some());
+        assert_eq!(replayed.load(Ordering::SeqCst), 1);
+
+        // Dialogs that had not started by the end of the window are left out
+        updater.rebuild_window(events, at(5), at(20)).await;
+        assert!(updater.get_view(&early).is_some());
+        assert!(updater.get_view(&late).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_turns_missing_embeddings() {
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let older = Uuid::new_v4();
+        let newer = Uuid::new_v4();
+
+        let mut updater = SimpleProjectionUpdater::new();
+        for (dialog_id, minutes) in [(newer, 30), (older, 0)] {
+            updater
+                .handle_event(DialogDomainEvent::DialogStarted(DialogStarted {
+                    event_id: Uuid::new_v4(),
+                    dialog_id,
+                    dialog_type: DialogType::Direct,
+                    primary_participant: user.clone(),
+                    started_at: start + chrono::Duration::minutes(minutes),
+                }))
+                .await
+                .unwrap();
+        }
+
+        let structured = Message {
+            content: MessageContent::Structured(serde_json::json!({"no": "text"})),
+            ..Message::text("")
+        };
+        let turns = [
+            (older, Message::text("first")),
+            (
+                older,
+                Message::text("embedded").with_embeddings(vec![0.1, 0.2]),
+            ),
+            (older, structured),
+            (newer, Message::text("second")),
+            (newer, Message::text("third")),
+        ];
+        let mut turn_ids = Vec::new();
+        for (dialog_id, message) in turns {
+            let turn = Turn::new(1, user.id, message, TurnType::UserQuery);
+            turn_ids.push(turn.turn_id);
+            updater
+                .handle_event(DialogDomainEvent::TurnAdded(TurnAdded {
+                    event_id: Uuid::new_v4(),
+                    dialog_id,
+                    turn,
+                    turn_number: 1,
+                }))
+                .await
+                .unwrap();
+        }
+        let missing = vec![
+            (older, turn_ids[0], "first".to_string()),
+            (newer, turn_ids[3], "second".to_string()),
+            (newer, turn_ids[4], "third".to_string()),
+        ];
+
+        let view = updater.get_view(&older).unwrap();
+        assert_eq!(
+            view.turns_missing_embeddings(),
+            vec![(missing[0].1, "first".to_string())]
+        );
+
+        // Oldest dialog first, capped at the limit
+        assert_eq!(
+            updater.all_turns_missing_embeddings(2),
+            missing[..2].to_vec()
+        );
+        assert_eq!(updater.all_turns_missing_embeddings(10), missing);
+
+        // Attaching an embedding removes the turn from the backlog
+        updater
+            .handle_event(DialogDomainEvent::EmbeddingAttached(EmbeddingAttached {
+                event_id: Uuid::new_v4(),
+                dialog_id: older,
+                turn_id: missing[0].1,
+                embeddings: vec![0.3, 0.4],
+                attached_at: Utc::now(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            updater.all_turns_missing_embeddings(10),
+            missing[1..].to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let (active, paused, ended) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let mut events: Vec<DialogDomainEvent> = [active, paused, ended]
+            .into_iter()
+            .map(|dialog_id| {
+                DialogDomainEvent::DialogStarted(DialogStarted {
+                    event_id: Uuid::new_v4(),
+                    dialog_id,
+                    dialog_type: DialogType::Direct,
+                    primary_participant: user.clone(),
+                    started_at: at(0),
+                })
+            })
+            .collect();
+        events.push(DialogDomainEvent::DialogPaused(DialogPaused {
+            event_id: Uuid::new_v4(),
+            dialog_id: paused,
+            paused_at: at(20),
+            context_snapshot: HashMap::new(),
+        }));
+        events.push(DialogDomainEvent::DialogEnded(DialogEnded {
+            event_id: Uuid::new_v4(),
+            dialog_id: ended,
+            ended_at: at(10),
+            reason: None,
+            final_metrics: ConversationMetrics {
+                turn_count: 0,
+                avg_response_time_ms: 0.0,
+                topic_switches: 0,
+                clarification_count: 0,
+                sentiment_trend: 0.0,
+                coherence_score: 1.0,
+            },
+        }));
+
+        let mut updater = SimpleProjectionUpdater::new();
+        let empty = updater.health();
+        assert_eq!(empty.total_views, 0);
+        assert_eq!(empty.last_event_at, None);
+        assert_eq!(empty.generation, 0);
+
+        for event in events.clone() {
+            updater.handle_event(event).await.unwrap();
+        }
+        assert_eq!(
+            updater.health(),
+            ProjectionHealth {
+                total_views: 3,
+                active: 1,
+                paused: 1,
+                ended: 1,
+                // Latest timestamp seen, not the last event handled
+                last_event_at: Some(at(20)),
+                generation: 0,
+            }
+        );
+
+        updater.rebuild_as_of(events, at(15)).await;
+        let health = updater.health();
+        assert_eq!((health.active, health.paused, health.ended), (2, 0, 1));
+        assert_eq!(health.last_event_at, Some(at(10)));
+        assert_eq!(health.generation, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_writers_keep_indices_consistent() {
+        use super::super::conversation_tree::REOPENED_FROM_KEY;
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+
+        const DIALOGS: usize = 32;
+        const TURNS: u32 = 10;
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let dialog_ids: Vec<Uuid> = (0..DIALOGS).map(|_| Uuid::new_v4()).collect();
+        let updater = Arc::new(RwLock::new(SimpleProjectionUpdater::new()));
+
+        // Each writer owns one dialog; every odd dialog reopens the one before
+        // it, so links may arrive before or after the parent's view exists
+        let writers: Vec<_> = dialog_ids
+            .iter()
+            .enumerate()
+            .map(|(index, &dialog_id)| {
+                let mut events = vec![DialogDomainEvent::DialogStarted(DialogStarted {
+                    event_id: Uuid::new_v4(),
+                    dialog_id,
+                    dialog_type: DialogType::Support,
+                    primary_participant: user.clone(),
+                    started_at: Utc::now(),
+                })];
+                if index % 2 == 1 {
+                    events.push(DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
+                        event_id: Uuid::new_v4(),
+                        dialog_id,
+                        key: REOPENED_FROM_KEY.to_string(),
+                        value: serde_json::json!(dialog_ids[index - 1].to_string()),
+                        set_at: Utc::now(),
+                    }));
+                }
+                events.extend((1..=TURNS).map(|turn_number| {
+                    DialogDomainEvent::TurnAdded(TurnAdded {
+                        event_id: Uuid::new_v4(),
+                        dialog_id,
+                        turn: Turn::new(
+                            turn_number,
+                            user.id,
+                            Message::text("Hello"),
+                            TurnType::UserQuery,
+                        ),
+                        turn_number,
+                    })
+                }));
+
+                let updater = updater.clone();
+                tokio::spawn(async move {
+                    for event in events {
+                        updater.write().await.handle_event(event).await.unwrap();
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        let updater = updater.read().await;
+        assert_eq!(updater.invariant_violations(), Vec::<String>::new());
+        assert_eq!(updater.health().total_views, DIALOGS);
+        for (index, dialog_id) in dialog_ids.iter().enumerate() {
+            let view = updater.get_view(dialog_id).unwrap();
+            assert_eq!(view.turns.len(), TURNS as usize);
+            assert!(updater.view_revision(dialog_id).is_some());
+            assert_eq!(view.reopen_count, updater.reopen_links(*dialog_id));
+            assert_eq!(view.reopen_count, usize::from(index % 2 == 0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_events_are_applied_once() {
+        let mut updater = SimpleProjectionUpdater::new();
+        let dialog_id = Uuid::new_v4();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let started = DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            started_at: Utc::now(),
+        });
+        let turn_added = DialogDomainEvent::TurnAdded(TurnAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn: Turn::new(1, user.id, Message::text("Hello"), TurnType::UserQuery),
+            turn_number: 1,
+        });
+
+        // A redelivered start must not reset the view either
+        for event in [started.clone(), turn_added.clone(), turn_added, started] {
+            updater.handle_event(event).await.unwrap();
+        }
+        assert_eq!(updater.get_view(&dialog_id).unwrap().turns.len(), 1);
+
+        // Only recent events are remembered
+        let turn = |number| {
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: Turn::new(number, user.id, Message::text("More"), TurnType::UserQuery),
+                turn_number: number,
+            })
+        };
+        let oldest = turn(2);
+        updater.handle_event(oldest.clone()).await.unwrap();
+        for number in 0..DEDUP_WINDOW as u32 {
+            updater.handle_event(turn(number + 3)).await.unwrap();
+        }
+        assert_eq!(updater.applied_events[&dialog_id].ids.len(), DEDUP_WINDOW);
+        updater.handle_event(oldest).await.unwrap();
+        assert_eq!(
+            updater.get_view(&dialog_id).unwrap().turns.len(),
+            DEDUP_WINDOW + 3
+        );
+
+        // Events without an ID cannot be deduplicated
+        let legacy = DialogDomainEvent::TurnAdded(TurnAdded {
+            event_id: Uuid::nil(),
+            dialog_id,
+            turn: Turn::new(2, user.id, Message::text("Again"), TurnType::UserQuery),
+            turn_number: 2,
+        });
+        updater.handle_event(legacy.clone()).await.unwrap();
+        updater.handle_event(legacy).await.unwrap();
+        assert_eq!(
+            updater.get_view(&dialog_id).unwrap().turns.len(),
+            DEDUP_WINDOW + 5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_turn_edited() {
+        let mut updater = SimpleProjectionUpdater::new();
+        let dialog_id = Uuid::new_v4();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let first = Turn::new(
+            1,
+            user.id,
+            Message::text("Partial respo"),
+            TurnType::AgentResponse,
+        );
+        let second = Turn::new(2, user.id, Message::text("Thanks"), TurnType::UserQuery);
+        let events = vec![
+            DialogDomainEvent::DialogStarted(DialogStarted {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                dialog_type: DialogType::Support,
+                primary_participant: user.clone(),
+                started_at: Utc::now(),
+            }),
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: first.clone(),
+                turn_number: 1,
+            }),
+            DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: second,
+                turn_number: 2,
+            }),
+            DialogDomainEvent::TurnEdited(TurnEdited {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn_id: first.turn_id,
+                new_message: Message::text("Full response"),
+                edited_at: Utc::now(),
+            }),
+        ];
+        for event in events {
+            updater.handle_event(event).await.unwrap();
+        }
+
+        let view = updater.get_view(&dialog_id).unwrap();
+        assert_eq!(view.turns.len(), 2);
+        assert_eq!(view.turns[0].turn_id, first.turn_id);
+        assert_eq!(
+            view.turns[0].message.content.as_text(),
+            Some("Full response")
+        );
+        assert_eq!(view.turns[1].message.content.as_text(), Some("Thanks"));
+    }
+
+    #[tokio::test]
+    async fn test_turns_archived() {
+        let mut updater = SimpleProjectionUpdater::new();
+        let dialog_id = Uuid::new_v4();
+        let user = Participant {
+            id: Uuid::new_v4(),
+            participant_type: ParticipantType::Human,
+            role: ParticipantRole::Primary,
+            name: "User".to_string(),
+            metadata: HashMap::new(),
+        };
+        let old = Turn::new(1, user.id, Message::text("Old"), TurnType::UserQuery);
+        let recent = Turn::new(2, user.id, Message::text("Recent"), TurnType::UserQuery);
+        let mut events = vec![DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            started_at: Utc::now(),
+        })];
+        for (number, turn) in [(1, &old), (2, &recent)] {
+            events.push(DialogDomainEvent::TurnAdded(TurnAdded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn: turn.clone(),
+                turn_number: number,
+            }));
+            events.push(DialogDomainEvent::TurnPinned(TurnPinned {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                turn_id: turn.turn_id,
+                pinned_at: Utc::now(),
+            }));
+        }
+        events.push(DialogDomainEvent::TurnsArchived(TurnsArchived {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn_ids: vec![old.turn_id],
+            archived_at: Utc::now(),
+        }));
+        for event in events {
+            updater.handle_event(event).await.unwrap();
+        }
+
+        // Archived turns stay in the transcript but are unpinned
+        let view = updater.get_view(&dialog_id).unwrap();
+        assert_eq!(view.turns.len(), 2);
+        assert_eq!(view.pinned_turns, vec![recent.turn_id]);
+    }
+}
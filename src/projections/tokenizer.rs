@@ -0,0 +1,118 @@
+//! Tokenizers for keyword extraction
+//!
+//! Projections that index or summarize message text split it into keywords
+//! through a [`Tokenizer`], so deployments can plug in stemming or
+//! language-specific rules without touching the projections.
+
+use crate::value_objects::MessageContent;
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+/// English stop words dropped by [`SimpleTokenizer::default`]
+pub const DEFAULT_STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at", "be",
+    "because", "been", "but", "by", "can", "could", "did", "do", "does", "for", "from", "had",
+    "has", "have", "he", "her", "here", "him", "his", "how", "i", "if", "in", "into", "is", "it",
+    "its", "just", "me", "my", "no", "not", "of", "on", "or", "our", "she", "so", "some", "than",
+    "that", "the", "their", "them", "then", "there", "these", "they", "this", "to", "too", "up",
+    "us", "was", "we", "were", "what", "when", "where", "which", "who", "why", "will", "with",
+    "would", "you", "your",
+];
+
+/// Splits text into keywords
+pub trait Tokenizer: Send + Sync + Debug {
+    /// Keywords of `text`, in order of appearance (repeats included)
+    fn tokenize(&self, text: &str) -> Vec<String>;
+
+    /// Distinct keywords of a message's searchable text, segments included
+    fn keywords(&self, content: &MessageContent) -> HashSet<String> {
+        self.tokenize(&content.to_searchable_string())
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Lowercases words, strips surrounding punctuation, and drops stop words
+/// and words shorter than `min_length` chars
+#[derive(Debug, Clone)]
+pub struct SimpleTokenizer {
+    pub stop_words: HashSet<String>,
+    pub min_length: usize,
+}
+
+impl SimpleTokenizer {
+    /// Create a tokenizer with a custom stop-word list
+    pub fn new(stop_words: impl IntoIterator<Item = impl Into<String>>, min_length: usize) -> Self {
+        Self {
+            stop_words: stop_words
+                .into_iter()
+                .map(|w| w.into().to_lowercase())
+                .collect(),
+            min_length,
+        }
+    }
+}
+
+impl Default for SimpleTokenizer {
+    fn default() -> Self {
+        Self::new(DEFAULT_STOP_WORDS.iter().copied(), 3)
+    }
+}
+
+impl Tokenizer for SimpleTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        text.split_whitespace()
+            .map(|word| {
+                word.trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase()
+            })
+            .filter(|word| {
+                word.chars().count() >= self.min_length && !self.stop_words.contains(word)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value_objects::MessageSegment;
+
+    #[test]
+    fn test_simple_tokenizer() {
+        let tokenizer = SimpleTokenizer::default();
+
+        // Stop words and short words are dropped, punctuation is stripped
+        assert_eq!(
+            tokenizer.tokenize("The invoice, for (my) REFUND... was wrong?!"),
+            vec!["invoice", "refund", "wrong"]
+        );
+        // Inner punctuation is kept
+        assert_eq!(
+            tokenizer.tokenize("a follow-up e-mail"),
+            vec!["follow-up", "e-mail"]
+        );
+
+        let keywords = tokenizer.keywords(&MessageContent::Text("Refund the refund!".to_string()));
+        assert_eq!(keywords, HashSet::from(["refund".to_string()]));
+        assert!(
+            tokenizer
+                .keywords(&MessageContent::Structured(serde_json::json!({})))
+                .is_empty()
+        );
+        let segmented = MessageContent::Segmented(vec![
+            MessageSegment::Text("Refund failed".to_string()),
+            MessageSegment::Code {
+                lang: "sh".to_string(),
+                code: "retry payment".to_string(),
+            },
+        ]);
+        assert_eq!(
+            tokenizer.keywords(&segmented),
+            HashSet::from(["refund", "failed", "retry", "payment"].map(String::from))
+        );
+
+        let custom = SimpleTokenizer::new(["Invoice"], 1);
+        assert_eq!(custom.tokenize("The invoice"), vec!["the"]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Bounded LRU cache of dialog views
+//!
+//! Entries remember the projection revision they were read at (see
+//! [`SimpleProjectionUpdater::view_revision`]). A lookup with a newer
+//! revision drops the entry, so a view is never served after the projection
+//! has changed it.
+//!
+//! [`SimpleProjectionUpdater::view_revision`]: crate::projections::SimpleProjectionUpdater::view_revision
+
+use crate::projections::SimpleDialogView;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug)]
+struct CachedView {
+    revision: u64,
+    view: Arc<SimpleDialogView>,
+    last_used: u64,
+}
+
+/// Least-recently-used cache of dialog views keyed by dialog ID
+#[derive(Debug)]
+pub(crate) struct ViewCache {
+    capacity: usize,
+    entries: HashMap<Uuid, CachedView>,
+    clock: u64,
+}
+
+impl ViewCache {
+    /// Create a cache holding at most `capacity` views
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            clock: 0,
+        }
+    }
+
+    /// Get the cached view if it is still at `revision`
+    pub(crate) fn get(&mut self, dialog_id: Uuid, revision: u64) -> Option<Arc<SimpleDialogView>> {
+        self.clock += 1;
+        match self.entries.get_mut(&dialog_id) {
+            Some(entry) if entry.revision == revision => {
+                entry.last_used = self.clock;
+                Some(entry.view.clone())
+            }
+            Some(_) => {
+                self.entries.remove(&dialog_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache a view read at `revision`, evicting the least recently used
+    /// entry if the cache is full
+    pub(crate) fn insert(&mut self, dialog_id: Uuid, revision: u64, view: Arc<SimpleDialogView>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&dialog_id)
+            && self.entries.len() >= self.capacity
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| *id)
+        {
+            self.entries.remove(&oldest);
+        }
+
+        self.clock += 1;
+        self.entries.insert(
+            dialog_id,
+            CachedView {
+                revision,
+                view,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Number of cached views
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
@@ -3,11 +3,17 @@
 //! This module provides query capabilities for the Dialog domain,
 //! enabling efficient search and retrieval of dialog data.
 
-use crate::aggregate::{DialogStatus, DialogType};
-use crate::projections::{SimpleDialogView, SimpleProjectionUpdater};
-use chrono::{DateTime, Utc};
+mod cache;
+
+use crate::aggregate::{ConversationPhase, DialogStatus, DialogType};
+use crate::projections::{SimpleDialogView, SimpleProjectionUpdater, TreeNode};
+use crate::value_objects::{
+    EndReasonCode, MessageIntent, ParticipantRole, ParticipantType, ResolutionOutcome, TurnType,
+};
+use cache::ViewCache;
+use chrono::{DateTime, Datelike, FixedOffset, Utc, Weekday};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -16,30 +22,149 @@ use uuid::Uuid;
 pub enum DialogQuery {
     /// Get a specific dialog by ID
     GetDialogById { dialog_id: Uuid },
-    
+
+    /// Get a specific dialog with only the requested sections filled in
+    ///
+    /// The summary (ID, type, status, timestamps, primary participant) is always
+    /// included. Excluded sections are left empty: `include_turns` covers the
+    /// turns and pinned turns, `include_participants` the participant map and
+    /// membership history, and `include_metadata` the metrics, topics and
+    /// topic resolutions.
+    GetDialogByIdProjected {
+        dialog_id: Uuid,
+        include_turns: bool,
+        include_participants: bool,
+        include_metadata: bool,
+    },
+
     /// Get all active dialogs
     GetActiveDialogs,
-    
+
     /// Get dialogs by participant
     GetDialogsByParticipant { participant_id: String },
-    
+
     /// Get dialogs by type
     GetDialogsByType { dialog_type: DialogType },
-    
+
     /// Get dialogs by status
     GetDialogsByStatus { status: DialogStatus },
-    
+
     /// Get dialogs in date range
     GetDialogsInDateRange {
         start_date: DateTime<Utc>,
         end_date: DateTime<Utc>,
     },
-    
+
     /// Search dialogs by text in messages
     SearchDialogsByText { search_text: String },
-    
+
     /// Get dialog statistics
     GetDialogStatistics,
+
+    /// Get the latency between dialog start and the first agent turn
+    GetOpeningLatencies { dialog_type: Option<DialogType> },
+
+    /// Get dialogs with at least `min_joins_leaves` participant joins and leaves
+    GetHighChurnDialogs { min_joins_leaves: usize },
+
+    /// Get the fraction of text turns that carry embeddings
+    GetEmbeddingCoverage,
+
+    /// Get the longest gaps between consecutive turns of a dialog
+    GetLongestGaps { dialog_id: Uuid, top_k: usize },
+
+    /// Get open dialogs that need an agent's attention
+    GetDialogsNeedingAttention { sentiment_floor: f32 },
+
+    /// Get average sentiment per participant and the largest gap between them
+    GetSentimentContrast { dialog_id: Uuid },
+
+    /// Get dialogs with a topic resolution containing a keyword
+    GetResolutionsContaining { keyword: String },
+
+    /// Get the earliest dialog a participant took part in
+    GetFirstContactDialogs { participant_id: String },
+
+    /// Get dialogs whose turns per minute fall within a range (bounds inclusive)
+    GetDialogsByVelocity {
+        min_turns_per_min: Option<f64>,
+        max: Option<f64>,
+    },
+
+    /// Get ended dialogs by end reason code
+    GetDialogsByEndCode { code: EndReasonCode },
+
+    /// Get participants who took part in at least `min_dialogs` distinct dialogs
+    GetReturningParticipants { min_dialogs: usize },
+
+    /// Get dialogs with a recorded resolution outcome
+    GetDialogsByResolution { resolution: ResolutionOutcome },
+
+    /// Get dialogs whose first topic has a keyword matching `keyword` (case-insensitive)
+    GetDialogsByInitialTopic { keyword: String },
+
+    /// Get dialogs by the mix of participant types currently in them
+    GetDialogsByComposition { composition: DialogComposition },
+
+    /// Get dialogs where the most active participant's turn count exceeds
+    /// the least active participant's by more than `max_ratio`
+    GetUnbalancedDialogs { max_ratio: f32 },
+
+    /// Get dialogs reopened at least `min_reopens` times
+    GetFrequentlyReopenedDialogs { min_reopens: usize },
+
+    /// Get dialogs that switched topic at least `min_switches` times
+    GetHighTopicSwitchDialogs { min_switches: u32 },
+
+    /// Get dialogs with at least one current participant in each required role
+    GetDialogsByRoleComposition { require_roles: Vec<ParticipantRole> },
+
+    /// Get dialogs with structured or multimodal turns, optionally only those
+    /// whose content names the given format (see `MessageContent::format`)
+    GetDialogsWithStructuredContent { format: Option<String> },
+
+    /// Suggest moving active dialogs from AI agents in more than
+    /// `target_concurrency` of them to agents in fewer
+    GetRebalancingSuggestions { target_concurrency: usize },
+
+    /// Get dialogs whose first agent turn came from the given agent
+    GetDialogsByFirstResponder { agent_id: String },
+
+    /// Get p50/p90/p99 resolution times of ended dialogs, optionally of one type
+    GetResolutionTimePercentiles { dialog_type: Option<DialogType> },
+
+    /// Get dialogs with at least `min_system_messages` system message turns,
+    /// which may indicate automation noise
+    GetNoisyDialogs { min_system_messages: usize },
+
+    /// Get dialogs currently in a conversation phase
+    GetDialogsInPhase { phase: ConversationPhase },
+
+    /// Get open dialogs with user turns but no agent turn between
+    /// `now - since` and `now`, i.e. customers waiting for an answer
+    GetDialogsWithoutAgentResponse {
+        since: std::time::Duration,
+        now: DateTime<Utc>,
+    },
+
+    /// Search dialogs for a current participant whose name contains `query`,
+    /// ignoring case
+    SearchByParticipantName { query: String },
+
+    /// Get dialogs started on a weekend (`weekend: true`) or on a weekday
+    ///
+    /// The start date is taken in `timezone`, a fixed UTC offset such as
+    /// `"+02:00"` or `"UTC"`; `None` means UTC.
+    GetDialogsByDayType {
+        weekend: bool,
+        timezone: Option<String>,
+    },
+
+    /// Get the average duration of ended dialogs by dialog type
+    GetAverageDurationByType,
+
+    /// Get a dialog and its forks and reopenings as a tree
+    GetConversationTree { root_id: Uuid },
 }
 
 /// Query result for dialog queries
@@ -47,13 +172,43 @@ pub enum DialogQuery {
 pub enum DialogQueryResult {
     /// Single dialog result
     Dialog(Option<SimpleDialogView>),
-    
+
     /// Multiple dialogs result
     Dialogs(Vec<SimpleDialogView>),
-    
+
     /// Statistics result
     Statistics(DialogStatistics),
-    
+
+    /// Opening latency result
+    OpeningLatencies(Vec<OpeningLatency>),
+
+    /// Embedding coverage result
+    EmbeddingCoverage(EmbeddingCoverageReport),
+
+    /// Turn gap result, longest first
+    TurnGaps(Vec<TurnGap>),
+
+    /// Dialogs needing attention with the reasons they were flagged
+    NeedsAttention(Vec<AttentionItem>),
+
+    /// Sentiment contrast result
+    SentimentContrast(SentimentContrast),
+
+    /// Returning participants, most dialogs first
+    ReturningParticipants(Vec<ReturningParticipant>),
+
+    /// Conversation tree rooted at the requested dialog
+    ConversationTree(TreeNode),
+
+    /// Suggested dialog moves between agents
+    RebalancingSuggestions(Vec<RebalancingSuggestion>),
+
+    /// Resolution time percentiles
+    ResolutionTimePercentiles(ResolutionTimePercentiles),
+
+    /// Average duration in seconds and sample count by dialog type, longest first
+    AverageDurations(Vec<(DialogType, f64, usize)>),
+
     /// Error result
     Error(String),
 }
@@ -68,155 +223,434 @@ pub struct DialogStatistics {
     pub dialogs_by_type: Vec<(DialogType, usize)>,
     pub average_turn_count: f64,
     pub total_participants: usize,
+    /// Turn counts by message intent (turns without an intent are skipped)
+    pub intents: std::collections::HashMap<MessageIntent, usize>,
+}
+
+/// Latency until an agent first responded in a dialog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpeningLatency {
+    pub dialog_id: Uuid,
+    pub dialog_type: DialogType,
+    /// Milliseconds from dialog start to the first agent turn (None if no agent turn yet)
+    pub latency_ms: Option<i64>,
+}
+
+/// Embedding coverage over a set of text turns
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingCoverage {
+    pub text_turns: usize,
+    pub embedded_turns: usize,
+    /// Fraction of text turns with embeddings (0.0 when there are no text turns)
+    pub coverage: f64,
+}
+
+impl EmbeddingCoverage {
+    fn record(&mut self, embedded: bool) {
+        self.text_turns += 1;
+        if embedded {
+            self.embedded_turns += 1;
+        }
+        self.coverage = self.embedded_turns as f64 / self.text_turns as f64;
+    }
+}
+
+/// Embedding coverage across all dialogs and per dialog type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingCoverageReport {
+    pub overall: EmbeddingCoverage,
+    pub by_type: Vec<(DialogType, EmbeddingCoverage)>,
+}
+
+/// Silence between two consecutive turns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnGap {
+    pub before_turn_id: Uuid,
+    pub after_turn_id: Uuid,
+    pub gap_secs: f64,
+}
+
+/// Why a dialog was flagged as needing attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttentionReason {
+    /// The last turn is a user question without an answer
+    UnansweredQuestion,
+    /// Average sentiment is below the requested floor
+    NegativeSentiment,
+    /// Recent turns keep asking for clarification
+    ClarificationLoop,
+}
+
+/// A dialog flagged as needing attention
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttentionItem {
+    pub dialog: SimpleDialogView,
+    pub reasons: Vec<AttentionReason>,
+}
+
+/// Sentiment per participant within a dialog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentimentContrast {
+    pub dialog_id: Uuid,
+    /// Average sentiment of each participant with scored turns, most negative first
+    pub per_participant: Vec<(Uuid, f32)>,
+    /// Largest pairwise difference between participant averages (0.0 with fewer than two)
+    pub max_difference: f32,
+}
+
+/// A participant and the number of distinct dialogs they took part in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReturningParticipant {
+    pub participant_id: Uuid,
+    pub dialog_count: usize,
+}
+
+/// Resolution time percentiles of ended dialogs, by nearest rank
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolutionTimePercentiles {
+    /// Number of ended dialogs measured
+    pub dialog_count: usize,
+    /// Seconds from start to end (None when no dialog has ended)
+    pub p50_secs: Option<f64>,
+    pub p90_secs: Option<f64>,
+    pub p99_secs: Option<f64>,
+}
+
+/// Suggestion to hand an active dialog from one AI agent to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebalancingSuggestion {
+    pub dialog_id: Uuid,
+    /// Participant ID of the overloaded agent
+    pub from: String,
+    /// Participant ID of the agent to take the dialog over
+    pub to: String,
+}
+
+/// Mix of participant types in a dialog
+///
+/// Compositions can overlap: a dialog with a human and two agents is both
+/// `HumanAgent` and `MultiAgent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DialogComposition {
+    /// Every participant is human
+    HumanOnly,
+    /// At least one human and at least one AI agent
+    HumanAgent,
+    /// Two or more AI agents
+    MultiAgent,
+    /// At least one system participant
+    HasSystem,
+}
+
+impl DialogComposition {
+    /// Check whether a dialog's current participants match this composition
+    pub fn matches(&self, dialog: &SimpleDialogView) -> bool {
+        let count = |participant_type| {
+            dialog
+                .participants
+                .values()
+                .filter(|p| p.participant_type == participant_type)
+                .count()
+        };
+
+        match self {
+            Self::HumanOnly => {
+                !dialog.participants.is_empty()
+                    && count(ParticipantType::Human) == dialog.participants.len()
+            }
+            Self::HumanAgent => {
+                count(ParticipantType::Human) > 0 && count(ParticipantType::AIAgent) > 0
+            }
+            Self::MultiAgent => count(ParticipantType::AIAgent) >= 2,
+            Self::HasSystem => count(ParticipantType::System) > 0,
+        }
+    }
 }
 
 /// Dialog query handler
 pub struct DialogQueryHandler {
     projection_updater: Arc<RwLock<SimpleProjectionUpdater>>,
+    view_cache: Option<Mutex<ViewCache>>,
 }
 
 impl DialogQueryHandler {
     /// Create a new query handler
     pub fn new(projection_updater: Arc<RwLock<SimpleProjectionUpdater>>) -> Self {
-        Self { projection_updater }
+        Self {
+            projection_updater,
+            view_cache: None,
+        }
+    }
+
+    /// Cache up to `capacity` recently read dialog views
+    ///
+    /// Cached views are shared rather than cloned from the projection on
+    /// every read, and are dropped as soon as the projection changes them.
+    /// Only `get_view` reads through the cache; `GetDialogById` hands back an
+    /// owned view and clones it from the projection as before.
+    pub fn with_view_cache(mut self, capacity: usize) -> Self {
+        self.view_cache = Some(Mutex::new(ViewCache::new(capacity)));
+        self
     }
-    
+
+    /// Get a dialog view, from the view cache if enabled
+    pub async fn get_view(&self, dialog_id: Uuid) -> Option<Arc<SimpleDialogView>> {
+        let updater = self.projection_updater.read().await;
+        let Some(cache) = &self.view_cache else {
+            return updater.get_view(&dialog_id).cloned().map(Arc::new);
+        };
+
+        let revision = updater.view_revision(&dialog_id)?;
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(view) = cache.get(dialog_id, revision) {
+            return Some(view);
+        }
+
+        let view = Arc::new(updater.get_view(&dialog_id)?.clone());
+        cache.insert(dialog_id, revision, view.clone());
+        Some(view)
+    }
+
     /// Execute a query
     pub async fn execute(&self, query: DialogQuery) -> DialogQueryResult {
         match query {
-            DialogQuery::GetDialogById { dialog_id } => {
-                self.get_dialog_by_id(dialog_id).await
-            }
-            DialogQuery::GetActiveDialogs => {
-                self.get_active_dialogs().await
+            DialogQuery::GetDialogById { dialog_id } => self.get_dialog_by_id(dialog_id).await,
+            DialogQuery::GetDialogByIdProjected {
+                dialog_id,
+                include_turns,
+                include_participants,
+                include_metadata,
+            } => {
+                self.get_dialog_by_id_projected(
+                    dialog_id,
+                    include_turns,
+                    include_participants,
+                    include_metadata,
+                )
+                .await
             }
+            DialogQuery::GetActiveDialogs => self.get_active_dialogs().await,
             DialogQuery::GetDialogsByParticipant { participant_id } => {
                 self.get_dialogs_by_participant(&participant_id).await
             }
             DialogQuery::GetDialogsByType { dialog_type } => {
                 self.get_dialogs_by_type(dialog_type).await
             }
-            DialogQuery::GetDialogsByStatus { status } => {
-                self.get_dialogs_by_status(status).await
-            }
-            DialogQuery::GetDialogsInDateRange { start_date, end_date } => {
-                self.get_dialogs_in_date_range(start_date, end_date).await
-            }
+            DialogQuery::GetDialogsByStatus { status } => self.get_dialogs_by_status(status).await,
+            DialogQuery::GetDialogsInDateRange {
+                start_date,
+                end_date,
+            } => self.get_dialogs_in_date_range(start_date, end_date).await,
             DialogQuery::SearchDialogsByText { search_text } => {
                 self.search_dialogs_by_text(&search_text).await
             }
-            DialogQuery::GetDialogStatistics => {
-                self.get_dialog_statistics().await
+            DialogQuery::GetDialogStatistics => self.get_dialog_statistics().await,
+            DialogQuery::GetOpeningLatencies { dialog_type } => {
+                self.get_opening_latencies(dialog_type).await
+            }
+            DialogQuery::GetHighChurnDialogs { min_joins_leaves } => {
+                self.get_high_churn_dialogs(min_joins_leaves).await
+            }
+            DialogQuery::GetEmbeddingCoverage => self.get_embedding_coverage().await,
+            DialogQuery::GetLongestGaps { dialog_id, top_k } => {
+                self.get_longest_gaps(dialog_id, top_k).await
+            }
+            DialogQuery::GetDialogsNeedingAttention { sentiment_floor } => {
+                self.get_dialogs_needing_attention(sentiment_floor).await
+            }
+            DialogQuery::GetSentimentContrast { dialog_id } => {
+                self.get_sentiment_contrast(dialog_id).await
+            }
+            DialogQuery::GetResolutionsContaining { keyword } => {
+                self.get_resolutions_containing(&keyword).await
+            }
+            DialogQuery::GetFirstContactDialogs { participant_id } => {
+                self.get_first_contact_dialog(&participant_id).await
+            }
+            DialogQuery::GetDialogsByVelocity {
+                min_turns_per_min,
+                max,
+            } => self.get_dialogs_by_velocity(min_turns_per_min, max).await,
+            DialogQuery::GetDialogsByEndCode { code } => self.get_dialogs_by_end_code(code).await,
+            DialogQuery::GetReturningParticipants { min_dialogs } => {
+                self.get_returning_participants(min_dialogs).await
+            }
+            DialogQuery::GetDialogsByResolution { resolution } => {
+                self.get_dialogs_by_resolution(resolution).await
+            }
+            DialogQuery::GetDialogsByInitialTopic { keyword } => {
+                self.get_dialogs_by_initial_topic(&keyword).await
+            }
+            DialogQuery::GetDialogsByComposition { composition } => {
+                self.get_dialogs_by_composition(composition).await
+            }
+            DialogQuery::GetUnbalancedDialogs { max_ratio } => {
+                self.get_unbalanced_dialogs(max_ratio).await
+            }
+            DialogQuery::GetFrequentlyReopenedDialogs { min_reopens } => {
+                self.get_frequently_reopened_dialogs(min_reopens).await
+            }
+            DialogQuery::GetHighTopicSwitchDialogs { min_switches } => {
+                self.get_high_topic_switch_dialogs(min_switches).await
+            }
+            DialogQuery::GetDialogsByRoleComposition { require_roles } => {
+                self.get_dialogs_by_role_composition(&require_roles).await
+            }
+            DialogQuery::GetDialogsWithStructuredContent { format } => {
+                self.get_dialogs_with_structured_content(format.as_deref())
+                    .await
+            }
+            DialogQuery::GetRebalancingSuggestions { target_concurrency } => {
+                self.get_rebalancing_suggestions(target_concurrency).await
+            }
+            DialogQuery::GetDialogsByFirstResponder { agent_id } => {
+                self.get_dialogs_by_first_responder(&agent_id).await
+            }
+            DialogQuery::GetResolutionTimePercentiles { dialog_type } => {
+                self.get_resolution_time_percentiles(dialog_type).await
+            }
+            DialogQuery::GetNoisyDialogs {
+                min_system_messages,
+            } => self.get_noisy_dialogs(min_system_messages).await,
+            DialogQuery::GetDialogsInPhase { phase } => self.get_dialogs_in_phase(phase).await,
+            DialogQuery::GetDialogsWithoutAgentResponse { since, now } => {
+                self.get_dialogs_without_agent_response(since, now).await
+            }
+            DialogQuery::SearchByParticipantName { query } => {
+                self.search_by_participant_name(&query).await
+            }
+            DialogQuery::GetDialogsByDayType { weekend, timezone } => {
+                self.get_dialogs_by_day_type(weekend, timezone.as_deref())
+                    .await
+            }
+            DialogQuery::GetAverageDurationByType => self.get_average_duration_by_type().await,
+            DialogQuery::GetConversationTree { root_id } => {
+                self.get_conversation_tree(root_id).await
             }
         }
     }
-    
+
     async fn get_dialog_by_id(&self, dialog_id: Uuid) -> DialogQueryResult {
         let updater = self.projection_updater.read().await;
-        let dialog = updater.get_view(&dialog_id).cloned();
+        DialogQueryResult::Dialog(updater.get_view(&dialog_id).cloned())
+    }
+
+    async fn get_dialog_by_id_projected(
+        &self,
+        dialog_id: Uuid,
+        include_turns: bool,
+        include_participants: bool,
+        include_metadata: bool,
+    ) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialog = updater
+            .get_view(&dialog_id)
+            .map(|view| view.projected(include_turns, include_participants, include_metadata));
         DialogQueryResult::Dialog(dialog)
     }
-    
+
     async fn get_active_dialogs(&self) -> DialogQueryResult {
         let updater = self.projection_updater.read().await;
-        let dialogs = updater.get_active_dialogs()
-            .into_iter()
-            .cloned()
-            .collect();
+        let dialogs = updater.get_active_dialogs().into_iter().cloned().collect();
         DialogQueryResult::Dialogs(dialogs)
     }
-    
+
     async fn get_dialogs_by_participant(&self, participant_id: &str) -> DialogQueryResult {
         let updater = self.projection_updater.read().await;
-        let dialogs = updater.get_all_dialogs()
+        let dialogs = updater
+            .get_all_dialogs()
             .into_iter()
             .filter(|d| d.participants.contains_key(participant_id))
             .cloned()
             .collect();
         DialogQueryResult::Dialogs(dialogs)
     }
-    
+
     async fn get_dialogs_by_type(&self, dialog_type: DialogType) -> DialogQueryResult {
         let updater = self.projection_updater.read().await;
-        let dialogs = updater.get_all_dialogs()
+        let dialogs = updater
+            .get_all_dialogs()
             .into_iter()
             .filter(|d| d.dialog_type == dialog_type)
             .cloned()
             .collect();
         DialogQueryResult::Dialogs(dialogs)
     }
-    
+
     async fn get_dialogs_by_status(&self, status: DialogStatus) -> DialogQueryResult {
         let updater = self.projection_updater.read().await;
-        let dialogs = updater.get_all_dialogs()
+        let dialogs = updater
+            .get_all_dialogs()
             .into_iter()
             .filter(|d| d.status == status)
             .cloned()
             .collect();
         DialogQueryResult::Dialogs(dialogs)
     }
-    
+
     async fn get_dialogs_in_date_range(
         &self,
         start_date: DateTime<Utc>,
         end_date: DateTime<Utc>,
     ) -> DialogQueryResult {
         let updater = self.projection_updater.read().await;
-        let dialogs = updater.get_all_dialogs()
+        let dialogs = updater
+            .get_all_dialogs()
             .into_iter()
             .filter(|d| d.started_at >= start_date && d.started_at <= end_date)
             .cloned()
             .collect();
         DialogQueryResult::Dialogs(dialogs)
     }
-    
+
     async fn search_dialogs_by_text(&self, search_text: &str) -> DialogQueryResult {
         let search_lower = search_text.to_lowercase();
         let updater = self.projection_updater.read().await;
-        
-        let dialogs = updater.get_all_dialogs()
+
+        let dialogs = updater
+            .get_all_dialogs()
             .into_iter()
             .filter(|d| {
                 // Search in turn messages
                 d.turns.iter().any(|turn| {
-                    match &turn.message.content {
-                        crate::value_objects::MessageContent::Text(text) => 
-                            text.to_lowercase().contains(&search_lower),
-                        crate::value_objects::MessageContent::Structured(value) => 
-                            value.to_string().to_lowercase().contains(&search_lower),
-                        crate::value_objects::MessageContent::Multimodal { text, .. } => 
-                            text.as_ref().map_or(false, |t| t.to_lowercase().contains(&search_lower)),
-                    }
+                    turn.message
+                        .content
+                        .to_searchable_string()
+                        .to_lowercase()
+                        .contains(&search_lower)
                 })
             })
             .cloned()
             .collect();
-            
+
         DialogQueryResult::Dialogs(dialogs)
     }
-    
+
     async fn get_dialog_statistics(&self) -> DialogQueryResult {
         let updater = self.projection_updater.read().await;
         let all_dialogs = updater.get_all_dialogs();
-        
+
         let total_dialogs = all_dialogs.len();
-        let active_dialogs = all_dialogs.iter()
+        let active_dialogs = all_dialogs
+            .iter()
             .filter(|d| d.status == DialogStatus::Active)
             .count();
-        let completed_dialogs = all_dialogs.iter()
+        let completed_dialogs = all_dialogs
+            .iter()
             .filter(|d| d.status == DialogStatus::Ended)
             .count();
-        let paused_dialogs = all_dialogs.iter()
+        let paused_dialogs = all_dialogs
+            .iter()
             .filter(|d| d.status == DialogStatus::Paused)
             .count();
-            
+
         // Count by type
         let mut type_counts = std::collections::HashMap::new();
         for dialog in &all_dialogs {
             *type_counts.entry(dialog.dialog_type.clone()).or_insert(0) += 1;
         }
         let dialogs_by_type: Vec<(DialogType, usize)> = type_counts.into_iter().collect();
-        
+
         // Calculate average turn count
         let total_turns: usize = all_dialogs.iter().map(|d| d.turns.len()).sum();
         let average_turn_count = if total_dialogs > 0 {
@@ -224,7 +658,7 @@ impl DialogQueryHandler {
         } else {
             0.0
         };
-        
+
         // Count unique participants
         let mut unique_participants = std::collections::HashSet::new();
         for dialog in &all_dialogs {
@@ -233,7 +667,15 @@ impl DialogQueryHandler {
             }
         }
         let total_participants = unique_participants.len();
-        
+
+        // Count turns by intent
+        let mut intents = std::collections::HashMap::new();
+        for dialog in &all_dialogs {
+            for intent in dialog.turns.iter().filter_map(|t| t.message.intent.clone()) {
+                *intents.entry(intent).or_insert(0) += 1;
+            }
+        }
+
         DialogQueryResult::Statistics(DialogStatistics {
             total_dialogs,
             active_dialogs,
@@ -242,69 +684,2822 @@ impl DialogQueryHandler {
             dialogs_by_type,
             average_turn_count,
             total_participants,
+            intents,
         })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::events::{DialogDomainEvent, DialogStarted};
-    use crate::value_objects::{Participant, ParticipantType, ParticipantRole};
-    
-    #[tokio::test]
-    async fn test_query_handler() {
-        // Create projection updater
-        let mut updater = SimpleProjectionUpdater::new();
-        
-        // Create a test dialog
-        let dialog_id = Uuid::new_v4();
-        let event = DialogDomainEvent::DialogStarted(DialogStarted {
-            dialog_id,
-            dialog_type: DialogType::Support,
-            primary_participant: Participant {
-                id: Uuid::new_v4(),
-                participant_type: ParticipantType::Human,
-                role: ParticipantRole::Primary,
-                name: "Test User".to_string(),
-                metadata: std::collections::HashMap::new(),
-            },
-            started_at: Utc::now(),
-        });
-        
-        // Handle the event
-        updater.handle_event(event).await.unwrap();
-        
-        // Create query handler
-        let updater_arc = Arc::new(RwLock::new(updater));
-        let handler = DialogQueryHandler::new(updater_arc);
-        
-        // Test get by ID
-        let result = handler.execute(DialogQuery::GetDialogById { dialog_id }).await;
-        match result {
-            DialogQueryResult::Dialog(Some(dialog)) => {
-                assert_eq!(dialog.dialog_id, dialog_id);
-            }
-            _ => panic!("Expected dialog result"),
-        }
-        
-        // Test get active dialogs
-        let result = handler.execute(DialogQuery::GetActiveDialogs).await;
-        match result {
-            DialogQueryResult::Dialogs(dialogs) => {
-                assert_eq!(dialogs.len(), 1);
+    async fn get_opening_latencies(&self, dialog_type: Option<DialogType>) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let latencies = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| dialog_type.is_none_or(|t| d.dialog_type == t))
+            .map(|d| OpeningLatency {
+                dialog_id: d.dialog_id,
+                dialog_type: d.dialog_type,
+                latency_ms: d
+                    .first_agent_turn()
+                    .map(|turn| (turn.timestamp - d.started_at).num_milliseconds()),
+            })
+            .collect();
+        DialogQueryResult::OpeningLatencies(latencies)
+    }
+
+    async fn get_high_churn_dialogs(&self, min_joins_leaves: usize) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| d.membership_churn() >= min_joins_leaves)
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_embedding_coverage(&self) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let mut overall = EmbeddingCoverage::default();
+        let mut by_type: std::collections::HashMap<DialogType, EmbeddingCoverage> =
+            std::collections::HashMap::new();
+
+        for dialog in updater.get_all_dialogs() {
+            for turn in &dialog.turns {
+                if !turn.message.content.has_text() {
+                    continue;
+                }
+
+                let embedded = turn
+                    .message
+                    .embeddings
+                    .as_ref()
+                    .is_some_and(|e| !e.is_empty());
+                overall.record(embedded);
+                by_type
+                    .entry(dialog.dialog_type)
+                    .or_default()
+                    .record(embedded);
             }
-            _ => panic!("Expected dialogs result"),
         }
-        
-        // Test statistics
-        let result = handler.execute(DialogQuery::GetDialogStatistics).await;
-        match result {
-            DialogQueryResult::Statistics(stats) => {
-                assert_eq!(stats.total_dialogs, 1);
-                assert_eq!(stats.active_dialogs, 1);
+
+        DialogQueryResult::EmbeddingCoverage(EmbeddingCoverageReport {
+            overall,
+            by_type: by_type.into_iter().collect(),
+        })
+    }
+
+    async fn get_longest_gaps(&self, dialog_id: Uuid, top_k: usize) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let Some(dialog) = updater.get_view(&dialog_id) else {
+            return DialogQueryResult::Error(format!("Dialog {dialog_id} not found"));
+        };
+
+        let mut gaps: Vec<TurnGap> = dialog
+            .turns
+            .windows(2)
+            .map(|pair| TurnGap {
+                before_turn_id: pair[0].turn_id,
+                after_turn_id: pair[1].turn_id,
+                gap_secs: (pair[1].timestamp - pair[0].timestamp).num_milliseconds() as f64
+                    / 1000.0,
+            })
+            .collect();
+        gaps.sort_by(|a, b| b.gap_secs.total_cmp(&a.gap_secs));
+        gaps.truncate(top_k);
+
+        DialogQueryResult::TurnGaps(gaps)
+    }
+
+    async fn get_dialogs_needing_attention(&self, sentiment_floor: f32) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let items = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| matches!(d.status, DialogStatus::Active | DialogStatus::Paused))
+            .filter_map(|d| {
+                let mut reasons = Vec::new();
+                if d.has_unanswered_question() {
+                    reasons.push(AttentionReason::UnansweredQuestion);
+                }
+                if d.average_sentiment().is_some_and(|s| s < sentiment_floor) {
+                    reasons.push(AttentionReason::NegativeSentiment);
+                }
+                if d.in_clarification_loop() {
+                    reasons.push(AttentionReason::ClarificationLoop);
+                }
+
+                (!reasons.is_empty()).then(|| AttentionItem {
+                    dialog: d.clone(),
+                    reasons,
+                })
+            })
+            .collect();
+        DialogQueryResult::NeedsAttention(items)
+    }
+
+    async fn get_sentiment_contrast(&self, dialog_id: Uuid) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let Some(dialog) = updater.get_view(&dialog_id) else {
+            return DialogQueryResult::Error(format!("Dialog {dialog_id} not found"));
+        };
+
+        let mut totals: std::collections::HashMap<Uuid, (f32, usize)> =
+            std::collections::HashMap::new();
+        for turn in &dialog.turns {
+            if let Some(sentiment) = turn.message.sentiment {
+                let entry = totals.entry(turn.participant_id).or_default();
+                entry.0 += sentiment;
+                entry.1 += 1;
             }
-            _ => panic!("Expected statistics result"),
         }
+
+        let mut per_participant: Vec<(Uuid, f32)> = totals
+            .into_iter()
+            .map(|(id, (sum, count))| (id, sum / count as f32))
+            .collect();
+        per_participant.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let max_difference = match (per_participant.first(), per_participant.last()) {
+            (Some(lowest), Some(highest)) => highest.1 - lowest.1,
+            _ => 0.0,
+        };
+
+        DialogQueryResult::SentimentContrast(SentimentContrast {
+            dialog_id,
+            per_participant,
+            max_difference,
+        })
     }
-}
\ No newline at end of file
+
+    async fn get_resolutions_containing(&self, keyword: &str) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let keyword = keyword.to_lowercase();
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                d.topic_resolutions
+                    .values()
+                    .any(|resolution| resolution.to_lowercase().contains(&keyword))
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_first_contact_dialog(&self, participant_id: &str) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialog = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| d.has_participated(participant_id))
+            .min_by_key(|d| d.started_at)
+            .cloned();
+        DialogQueryResult::Dialog(dialog)
+    }
+
+    async fn get_dialogs_by_velocity(
+        &self,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                // Dialogs without a measurable span have no velocity
+                d.turns_per_minute().is_some_and(|velocity| {
+                    min.is_none_or(|min| velocity >= min) && max.is_none_or(|max| velocity <= max)
+                })
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_by_end_code(&self, code: EndReasonCode) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                d.end_reason
+                    .as_ref()
+                    .is_some_and(|reason| reason.code == code)
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_returning_participants(&self, min_dialogs: usize) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let mut counts: std::collections::HashMap<Uuid, usize> = std::collections::HashMap::new();
+        for dialog in updater.get_all_dialogs() {
+            // Include participants who have since left
+            let ids: std::collections::HashSet<Uuid> =
+                std::iter::once(dialog.primary_participant.id)
+                    .chain(dialog.participants.values().map(|p| p.id))
+                    .chain(dialog.membership.iter().map(|change| change.participant_id))
+                    .collect();
+            for id in ids {
+                *counts.entry(id).or_default() += 1;
+            }
+        }
+
+        let mut returning: Vec<ReturningParticipant> = counts
+            .into_iter()
+            .filter(|(_, count)| *count >= min_dialogs)
+            .map(|(participant_id, dialog_count)| ReturningParticipant {
+                participant_id,
+                dialog_count,
+            })
+            .collect();
+        returning.sort_by(|a, b| {
+            b.dialog_count
+                .cmp(&a.dialog_count)
+                .then(a.participant_id.cmp(&b.participant_id))
+        });
+        DialogQueryResult::ReturningParticipants(returning)
+    }
+
+    async fn get_dialogs_by_resolution(&self, resolution: ResolutionOutcome) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| d.resolution == Some(resolution))
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_by_initial_topic(&self, keyword: &str) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                d.initial_topic().is_some_and(|topic| {
+                    topic
+                        .keywords
+                        .iter()
+                        .any(|k| k.eq_ignore_ascii_case(keyword))
+                })
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_by_composition(
+        &self,
+        composition: DialogComposition,
+    ) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| composition.matches(d))
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_unbalanced_dialogs(&self, max_ratio: f32) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| d.participants.len() > 1)
+            .filter(|d| {
+                let counts: Vec<usize> = d
+                    .participants
+                    .values()
+                    .map(|p| d.turns.iter().filter(|t| t.participant_id == p.id).count())
+                    .collect();
+                let most = counts.iter().copied().max().unwrap_or(0);
+                let least = counts.iter().copied().min().unwrap_or(0);
+                // A silent participant next to an active one is always unbalanced
+                most > 0 && (least == 0 || most as f32 / least as f32 > max_ratio)
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_frequently_reopened_dialogs(&self, min_reopens: usize) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| d.reopen_count >= min_reopens)
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_high_topic_switch_dialogs(&self, min_switches: u32) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| d.topic_switches >= min_switches)
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_by_role_composition(
+        &self,
+        require_roles: &[ParticipantRole],
+    ) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                require_roles
+                    .iter()
+                    .all(|role| d.participants.values().any(|p| p.role == *role))
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_with_structured_content(&self, format: Option<&str>) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                d.turns.iter().any(|t| {
+                    let content = &t.message.content;
+                    content.is_rich() && format.is_none_or(|f| content.format() == Some(f))
+                })
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_rebalancing_suggestions(&self, target_concurrency: usize) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+
+        // Active dialogs of every known agent, including agents with none
+        let mut assigned: std::collections::HashMap<&str, Vec<&SimpleDialogView>> =
+            std::collections::HashMap::new();
+        for dialog in updater.get_all_dialogs() {
+            for (agent_id, participant) in &dialog.participants {
+                if participant.participant_type != ParticipantType::AIAgent {
+                    continue;
+                }
+                let dialogs = assigned.entry(agent_id.as_str()).or_default();
+                if dialog.status == DialogStatus::Active {
+                    dialogs.push(dialog);
+                }
+            }
+        }
+        let mut load: std::collections::HashMap<&str, usize> = assigned
+            .iter()
+            .map(|(agent_id, dialogs)| (*agent_id, dialogs.len()))
+            .collect();
+
+        // Busiest agents first, handing over their most recently started dialogs
+        let mut overloaded: Vec<_> = assigned
+            .into_iter()
+            .filter(|(_, dialogs)| dialogs.len() > target_concurrency)
+            .collect();
+        overloaded.sort_by(|(a, a_dialogs), (b, b_dialogs)| {
+            b_dialogs.len().cmp(&a_dialogs.len()).then(a.cmp(b))
+        });
+
+        let mut suggestions = Vec::new();
+        for (from, mut dialogs) in overloaded {
+            dialogs.sort_by_key(|d| std::cmp::Reverse(d.started_at));
+            for dialog in dialogs {
+                if load[from] <= target_concurrency {
+                    break;
+                }
+                let to = load
+                    .iter()
+                    .filter(|(agent_id, count)| {
+                        **count < target_concurrency
+                            && !dialog.participants.contains_key(**agent_id)
+                    })
+                    .min_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(a.cmp(b)))
+                    .map(|(agent_id, _)| *agent_id);
+                let Some(to) = to else {
+                    continue;
+                };
+
+                *load.entry(from).or_default() -= 1;
+                *load.entry(to).or_default() += 1;
+                suggestions.push(RebalancingSuggestion {
+                    dialog_id: dialog.dialog_id,
+                    from: from.to_string(),
+                    to: to.to_string(),
+                });
+            }
+        }
+        DialogQueryResult::RebalancingSuggestions(suggestions)
+    }
+
+    async fn get_dialogs_by_first_responder(&self, agent_id: &str) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                d.first_agent_turn()
+                    .is_some_and(|turn| turn.participant_id.to_string() == agent_id)
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_resolution_time_percentiles(
+        &self,
+        dialog_type: Option<DialogType>,
+    ) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let mut secs: Vec<f64> = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| dialog_type.is_none_or(|t| d.dialog_type == t))
+            .filter_map(|d| d.resolution_time())
+            .map(|t| t.num_milliseconds() as f64 / 1000.0)
+            .collect();
+        secs.sort_by(f64::total_cmp);
+
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * secs.len() as f64).ceil() as usize;
+            secs.get(rank.max(1) - 1).copied()
+        };
+        DialogQueryResult::ResolutionTimePercentiles(ResolutionTimePercentiles {
+            dialog_count: secs.len(),
+            p50_secs: percentile(50.0),
+            p90_secs: percentile(90.0),
+            p99_secs: percentile(99.0),
+        })
+    }
+
+    async fn get_noisy_dialogs(&self, min_system_messages: usize) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                d.turns
+                    .iter()
+                    .filter(|t| t.metadata.turn_type == TurnType::SystemMessage)
+                    .count()
+                    >= min_system_messages
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_in_phase(&self, phase: ConversationPhase) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| d.current_phase() == Some(phase))
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_without_agent_response(
+        &self,
+        since: std::time::Duration,
+        now: DateTime<Utc>,
+    ) -> DialogQueryResult {
+        let window_start = now - chrono::Duration::from_std(since).unwrap_or(chrono::Duration::MAX);
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| matches!(d.status, DialogStatus::Active | DialogStatus::Paused))
+            .filter(|d| {
+                let window: Vec<_> = d
+                    .turns
+                    .iter()
+                    .filter(|t| (window_start..=now).contains(&t.timestamp))
+                    .collect();
+                let has_user_turn = window.iter().any(|t| {
+                    !d.is_agent_turn(t) && t.metadata.turn_type != TurnType::SystemMessage
+                });
+                has_user_turn && !window.iter().any(|t| d.is_agent_turn(t))
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn search_by_participant_name(&self, query: &str) -> DialogQueryResult {
+        let query = query.to_lowercase();
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                d.participants
+                    .values()
+                    .any(|p| p.name.to_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_dialogs_by_day_type(
+        &self,
+        weekend: bool,
+        timezone: Option<&str>,
+    ) -> DialogQueryResult {
+        let offset = match timezone {
+            None => FixedOffset::east_opt(0).unwrap(),
+            Some(tz) if tz.eq_ignore_ascii_case("UTC") || tz == "Z" => {
+                FixedOffset::east_opt(0).unwrap()
+            }
+            Some(tz) => match tz.parse::<FixedOffset>() {
+                Ok(offset) => offset,
+                Err(_) => return DialogQueryResult::Error(format!("Unsupported timezone {tz}")),
+            },
+        };
+
+        let updater = self.projection_updater.read().await;
+        let dialogs = updater
+            .get_all_dialogs()
+            .into_iter()
+            .filter(|d| {
+                let day = d.started_at.with_timezone(&offset).weekday();
+                matches!(day, Weekday::Sat | Weekday::Sun) == weekend
+            })
+            .cloned()
+            .collect();
+        DialogQueryResult::Dialogs(dialogs)
+    }
+
+    async fn get_average_duration_by_type(&self) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        let mut totals: std::collections::HashMap<DialogType, (f64, usize)> =
+            std::collections::HashMap::new();
+        for dialog in updater.get_all_dialogs() {
+            // Dialogs still running have no duration yet
+            if let Some(duration) = dialog.resolution_time() {
+                let (secs, count) = totals.entry(dialog.dialog_type).or_insert((0.0, 0));
+                *secs += duration.num_milliseconds() as f64 / 1000.0;
+                *count += 1;
+            }
+        }
+
+        // Only types with at least one ended dialog are reported
+        let mut averages: Vec<(DialogType, f64, usize)> = totals
+            .into_iter()
+            .map(|(dialog_type, (secs, count))| (dialog_type, secs / count as f64, count))
+            .collect();
+        averages.sort_by(|a, b| b.1.total_cmp(&a.1));
+        DialogQueryResult::AverageDurations(averages)
+    }
+
+    async fn get_conversation_tree(&self, root_id: Uuid) -> DialogQueryResult {
+        let updater = self.projection_updater.read().await;
+        if updater.get_view(&root_id).is_none() {
+            return DialogQueryResult::Error(format!("Dialog {root_id} not found"));
+        }
+
+        // Links that would form a cycle were refused as they arrived
+        DialogQueryResult::ConversationTree(updater.conversation_tree().tree_from_root(root_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{
+        ContextSwitched, DialogDomainEvent, DialogEnded, DialogMetadataSet, DialogPaused,
+        DialogStarted, ParticipantAdded, ParticipantRemoved, ResolutionSet, TopicCompleted,
+        TurnAdded,
+    };
+    use crate::value_objects::{
+        ConversationMetrics, EndReason, Message, MessageContent, MessageSegment, Participant,
+        Topic, Turn, TurnType,
+    };
+
+    fn participant(name: &str, participant_type: ParticipantType) -> Participant {
+        Participant {
+            id: Uuid::new_v4(),
+            participant_type,
+            role: ParticipantRole::Primary,
+            name: name.to_string(),
+            metadata: std::collections::HashMap::new(),
+        }
+    }
+
+    fn started(
+        dialog_id: Uuid,
+        dialog_type: DialogType,
+        primary_participant: &Participant,
+        started_at: DateTime<Utc>,
+    ) -> DialogDomainEvent {
+        DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            dialog_type,
+            primary_participant: primary_participant.clone(),
+            started_at,
+        })
+    }
+
+    fn joined(dialog_id: Uuid, participant: &Participant) -> DialogDomainEvent {
+        DialogDomainEvent::ParticipantAdded(ParticipantAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            participant: participant.clone(),
+            added_at: Utc::now(),
+        })
+    }
+
+    fn left(dialog_id: Uuid, participant: &Participant) -> DialogDomainEvent {
+        DialogDomainEvent::ParticipantRemoved(ParticipantRemoved {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            participant_id: participant.id,
+            removed_at: Utc::now(),
+            reason: None,
+        })
+    }
+
+    fn turn_added(
+        dialog_id: Uuid,
+        participant_id: Uuid,
+        message: Message,
+        turn_type: TurnType,
+        timestamp: DateTime<Utc>,
+    ) -> DialogDomainEvent {
+        let mut turn = Turn::new(1, participant_id, message, turn_type);
+        turn.timestamp = timestamp;
+        DialogDomainEvent::TurnAdded(TurnAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            turn,
+            turn_number: 1,
+        })
+    }
+
+    async fn handler_with(events: Vec<DialogDomainEvent>) -> DialogQueryHandler {
+        let mut updater = SimpleProjectionUpdater::new();
+        for event in events {
+            updater.handle_event(event).await.unwrap();
+        }
+        DialogQueryHandler::new(Arc::new(RwLock::new(updater)))
+    }
+
+    #[tokio::test]
+    async fn test_query_handler() {
+        // Create projection updater
+        let mut updater = SimpleProjectionUpdater::new();
+
+        // Create a test dialog
+        let dialog_id = Uuid::new_v4();
+        let event = DialogDomainEvent::DialogStarted(DialogStarted {
+            event_id: Uuid::new_v4(),
+            dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: Participant {
+                id: Uuid::new_v4(),
+                participant_type: ParticipantType::Human,
+                role: ParticipantRole::Primary,
+                name: "Test User".to_string(),
+                metadata: std::collections::HashMap::new(),
+            },
+            started_at: Utc::now(),
+        });
+
+        // Handle the event
+        updater.handle_event(event).await.unwrap();
+
+        // Create query handler
+        let updater_arc = Arc::new(RwLock::new(updater));
+        let handler = DialogQueryHandler::new(updater_arc);
+
+        // Test get by ID
+        let result = handler
+            .execute(DialogQuery::GetDialogById { dialog_id })
+            .await;
+        match result {
+            DialogQueryResult::Dialog(Some(dialog)) => {
+                assert_eq!(dialog.dialog_id, dialog_id);
+            }
+            _ => panic!("Expected dialog result"),
+        }
+
+        // Test get active dialogs
+        let result = handler.execute(DialogQuery::GetActiveDialogs).await;
+        match result {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+
+        // Test statistics
+        let result = handler.execute(DialogQuery::GetDialogStatistics).await;
+        match result {
+            DialogQueryResult::Statistics(stats) => {
+                assert_eq!(stats.total_dialogs, 1);
+                assert_eq!(stats.active_dialogs, 1);
+            }
+            _ => panic!("Expected statistics result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_opening_latencies() {
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        let user = participant("User", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let answered = Uuid::new_v4();
+        let waiting = Uuid::new_v4();
+
+        let handler = handler_with(vec![
+            started(answered, DialogType::Support, &user, start),
+            joined(answered, &agent),
+            turn_added(
+                answered,
+                user.id,
+                Message::text("Hello?"),
+                TurnType::UserQuery,
+                start + chrono::Duration::seconds(5),
+            ),
+            turn_added(
+                answered,
+                agent.id,
+                Message::text("Hi!"),
+                TurnType::AgentResponse,
+                start + chrono::Duration::seconds(42),
+            ),
+            started(waiting, DialogType::Direct, &user, start),
+            turn_added(
+                waiting,
+                user.id,
+                Message::text("Anyone?"),
+                TurnType::UserQuery,
+                start,
+            ),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetOpeningLatencies { dialog_type: None })
+            .await
+        {
+            DialogQueryResult::OpeningLatencies(latencies) => {
+                assert_eq!(latencies.len(), 2);
+                let answered_latency = latencies.iter().find(|l| l.dialog_id == answered).unwrap();
+                assert_eq!(answered_latency.latency_ms, Some(42_000));
+                let waiting_latency = latencies.iter().find(|l| l.dialog_id == waiting).unwrap();
+                assert_eq!(waiting_latency.latency_ms, None);
+            }
+            _ => panic!("Expected opening latencies result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetOpeningLatencies {
+                dialog_type: Some(DialogType::Support),
+            })
+            .await
+        {
+            DialogQueryResult::OpeningLatencies(latencies) => {
+                assert_eq!(latencies.len(), 1);
+                assert_eq!(latencies[0].dialog_id, answered);
+            }
+            _ => panic!("Expected opening latencies result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_high_churn_dialogs() {
+        let user = participant("User", ParticipantType::Human);
+        let guest = participant("Guest", ParticipantType::Human);
+        let stable = Uuid::new_v4();
+        let busy = Uuid::new_v4();
+
+        let handler = handler_with(vec![
+            started(stable, DialogType::Group, &user, Utc::now()),
+            joined(stable, &guest),
+            started(busy, DialogType::Group, &user, Utc::now()),
+            joined(busy, &guest),
+            left(busy, &guest),
+            joined(busy, &guest),
+            left(busy, &guest),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetHighChurnDialogs {
+                min_joins_leaves: 3,
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, busy);
+                assert_eq!(dialogs[0].membership_churn(), 4);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embedding_coverage() {
+        let user = participant("User", ParticipantType::Human);
+        let support = Uuid::new_v4();
+        let direct = Uuid::new_v4();
+        let embedded = || Message::text("indexed").with_embeddings(vec![0.1, 0.2]);
+        let structured = Message {
+            content: crate::value_objects::MessageContent::Structured(serde_json::json!({"a": 1})),
+            ..Message::text("")
+        };
+
+        let handler = handler_with(vec![
+            started(support, DialogType::Support, &user, Utc::now()),
+            turn_added(
+                support,
+                user.id,
+                embedded(),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            turn_added(
+                support,
+                user.id,
+                embedded(),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            turn_added(
+                support,
+                user.id,
+                Message::text("plain"),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            started(direct, DialogType::Direct, &user, Utc::now()),
+            turn_added(
+                direct,
+                user.id,
+                Message::text("plain"),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            // Structured turns are not text and are ignored
+            turn_added(direct, user.id, structured, TurnType::UserQuery, Utc::now()),
+        ])
+        .await;
+
+        match handler.execute(DialogQuery::GetEmbeddingCoverage).await {
+            DialogQueryResult::EmbeddingCoverage(report) => {
+                assert_eq!(report.overall.text_turns, 4);
+                assert_eq!(report.overall.embedded_turns, 2);
+                assert!((report.overall.coverage - 0.5).abs() < f64::EPSILON);
+
+                let support_coverage = &report
+                    .by_type
+                    .iter()
+                    .find(|(t, _)| *t == DialogType::Support)
+                    .unwrap()
+                    .1;
+                assert!((support_coverage.coverage - 2.0 / 3.0).abs() < 1e-9);
+                let direct_coverage = &report
+                    .by_type
+                    .iter()
+                    .find(|(t, _)| *t == DialogType::Direct)
+                    .unwrap()
+                    .1;
+                assert_eq!(direct_coverage.text_turns, 1);
+                assert_eq!(direct_coverage.coverage, 0.0);
+            }
+            _ => panic!("Expected embedding coverage result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_statistics_intent_counts() {
+        let user = participant("User", ParticipantType::Human);
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let with_intent = |intent| Message::text("...").with_intent(intent);
+
+        let handler = handler_with(vec![
+            started(first, DialogType::Direct, &user, Utc::now()),
+            turn_added(
+                first,
+                user.id,
+                with_intent(MessageIntent::Question),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            turn_added(
+                first,
+                user.id,
+                with_intent(MessageIntent::Command),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            turn_added(
+                first,
+                user.id,
+                Message::text("no intent"),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            started(second, DialogType::Support, &user, Utc::now()),
+            turn_added(
+                second,
+                user.id,
+                with_intent(MessageIntent::Question),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+        ])
+        .await;
+
+        match handler.execute(DialogQuery::GetDialogStatistics).await {
+            DialogQueryResult::Statistics(stats) => {
+                assert_eq!(stats.intents.len(), 2);
+                assert_eq!(stats.intents[&MessageIntent::Question], 2);
+                assert_eq!(stats.intents[&MessageIntent::Command], 1);
+            }
+            _ => panic!("Expected statistics result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_longest_gaps() {
+        let start = Utc::now();
+        let user = participant("User", ParticipantType::Human);
+        let dialog_id = Uuid::new_v4();
+        let offsets = [0, 10, 100, 105, 405];
+
+        let mut events = vec![started(dialog_id, DialogType::Direct, &user, start)];
+        for offset in offsets {
+            events.push(turn_added(
+                dialog_id,
+                user.id,
+                Message::text("..."),
+                TurnType::UserQuery,
+                start + chrono::Duration::seconds(offset),
+            ));
+        }
+        let handler = handler_with(events).await;
+
+        let turn_ids: Vec<Uuid> = match handler
+            .execute(DialogQuery::GetDialogById { dialog_id })
+            .await
+        {
+            DialogQueryResult::Dialog(Some(view)) => view.turns.iter().map(|t| t.turn_id).collect(),
+            _ => panic!("Expected dialog result"),
+        };
+
+        match handler
+            .execute(DialogQuery::GetLongestGaps {
+                dialog_id,
+                top_k: 2,
+            })
+            .await
+        {
+            DialogQueryResult::TurnGaps(gaps) => {
+                assert_eq!(gaps.len(), 2);
+                assert_eq!(gaps[0].gap_secs, 300.0);
+                assert_eq!(gaps[0].before_turn_id, turn_ids[3]);
+                assert_eq!(gaps[0].after_turn_id, turn_ids[4]);
+                assert_eq!(gaps[1].gap_secs, 90.0);
+                assert_eq!(gaps[1].before_turn_id, turn_ids[1]);
+            }
+            _ => panic!("Expected turn gaps result"),
+        }
+
+        assert!(matches!(
+            handler
+                .execute(DialogQuery::GetLongestGaps {
+                    dialog_id: Uuid::new_v4(),
+                    top_k: 2
+                })
+                .await,
+            DialogQueryResult::Error(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_needing_attention() {
+        let user = participant("User", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let unanswered = Uuid::new_v4();
+        let upset = Uuid::new_v4();
+        let looping = Uuid::new_v4();
+        let healthy = Uuid::new_v4();
+        let now = Utc::now();
+        let question = || Message::text("Where is my order?").with_intent(MessageIntent::Question);
+
+        let handler = handler_with(vec![
+            started(unanswered, DialogType::Support, &user, now),
+            turn_added(unanswered, user.id, question(), TurnType::UserQuery, now),
+            started(upset, DialogType::Support, &user, now),
+            joined(upset, &agent),
+            turn_added(
+                upset,
+                user.id,
+                Message::text("This is awful").with_sentiment(-0.9),
+                TurnType::UserQuery,
+                now,
+            ),
+            turn_added(
+                upset,
+                agent.id,
+                Message::text("Sorry to hear that"),
+                TurnType::AgentResponse,
+                now,
+            ),
+            started(looping, DialogType::Support, &user, now),
+            joined(looping, &agent),
+            turn_added(
+                looping,
+                agent.id,
+                Message::text("Which order?"),
+                TurnType::Clarification,
+                now,
+            ),
+            turn_added(
+                looping,
+                user.id,
+                Message::text("The big one"),
+                TurnType::UserQuery,
+                now,
+            ),
+            turn_added(
+                looping,
+                agent.id,
+                Message::text("Which big one?"),
+                TurnType::Clarification,
+                now,
+            ),
+            started(healthy, DialogType::Support, &user, now),
+            joined(healthy, &agent),
+            turn_added(healthy, user.id, question(), TurnType::UserQuery, now),
+            turn_added(
+                healthy,
+                agent.id,
+                Message::text("On its way").with_sentiment(0.4),
+                TurnType::AgentResponse,
+                now,
+            ),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetDialogsNeedingAttention {
+                sentiment_floor: -0.5,
+            })
+            .await
+        {
+            DialogQueryResult::NeedsAttention(items) => {
+                assert_eq!(items.len(), 3);
+                let reasons_for = |id: Uuid| {
+                    items
+                        .iter()
+                        .find(|i| i.dialog.dialog_id == id)
+                        .map(|i| i.reasons.clone())
+                };
+                assert_eq!(
+                    reasons_for(unanswered),
+                    Some(vec![AttentionReason::UnansweredQuestion])
+                );
+                assert_eq!(
+                    reasons_for(upset),
+                    Some(vec![AttentionReason::NegativeSentiment])
+                );
+                assert_eq!(
+                    reasons_for(looping),
+                    Some(vec![AttentionReason::ClarificationLoop])
+                );
+                assert_eq!(reasons_for(healthy), None);
+            }
+            _ => panic!("Expected needs attention result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sentiment_contrast() {
+        let user = participant("User", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let dialog_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        let handler = handler_with(vec![
+            started(dialog_id, DialogType::Support, &user, now),
+            joined(dialog_id, &agent),
+            turn_added(
+                dialog_id,
+                user.id,
+                Message::text("Still broken!").with_sentiment(-0.8),
+                TurnType::UserQuery,
+                now,
+            ),
+            turn_added(
+                dialog_id,
+                agent.id,
+                Message::text("Let me help").with_sentiment(0.4),
+                TurnType::AgentResponse,
+                now,
+            ),
+            turn_added(
+                dialog_id,
+                user.id,
+                Message::text("Useless").with_sentiment(-0.6),
+                TurnType::UserQuery,
+                now,
+            ),
+            turn_added(
+                dialog_id,
+                agent.id,
+                Message::text("I understand").with_sentiment(0.6),
+                TurnType::AgentResponse,
+                now,
+            ),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetSentimentContrast { dialog_id })
+            .await
+        {
+            DialogQueryResult::SentimentContrast(contrast) => {
+                assert_eq!(contrast.per_participant.len(), 2);
+                assert_eq!(contrast.per_participant[0].0, user.id);
+                assert!((contrast.per_participant[0].1 + 0.7).abs() < 1e-6);
+                assert!((contrast.per_participant[1].1 - 0.5).abs() < 1e-6);
+                assert!((contrast.max_difference - 1.2).abs() < 1e-6);
+            }
+            _ => panic!("Expected sentiment contrast result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolutions_containing() {
+        let user = participant("User", ParticipantType::Human);
+        let refunded = Uuid::new_v4();
+        let replaced = Uuid::new_v4();
+        let open = Uuid::new_v4();
+        let completed = |dialog_id, resolution: Option<&str>| {
+            DialogDomainEvent::TopicCompleted(TopicCompleted {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                topic_id: Uuid::new_v4(),
+                completed_at: Utc::now(),
+                resolution: resolution.map(str::to_string),
+            })
+        };
+
+        let handler = handler_with(vec![
+            started(refunded, DialogType::Support, &user, Utc::now()),
+            completed(refunded, Some("Customer was issued a Refund")),
+            started(replaced, DialogType::Support, &user, Utc::now()),
+            completed(replaced, Some("Sent a replacement unit")),
+            started(open, DialogType::Support, &user, Utc::now()),
+            completed(open, None),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetResolutionsContaining {
+                keyword: "refund".to_string(),
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, refunded);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetResolutionsContaining {
+                keyword: "unit".to_string(),
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, replaced);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_contact_dialog() {
+        let user = participant("User", ParticipantType::Human);
+        let host = participant("Host", ParticipantType::Human);
+        let now = Utc::now();
+        let (newest, oldest, middle, unrelated) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+
+        let handler = handler_with(vec![
+            started(newest, DialogType::Direct, &user, now),
+            started(
+                oldest,
+                DialogType::Group,
+                &host,
+                now - chrono::Duration::days(30),
+            ),
+            joined(oldest, &user),
+            // Leaving does not erase the first contact
+            left(oldest, &user),
+            started(
+                middle,
+                DialogType::Support,
+                &user,
+                now - chrono::Duration::days(3),
+            ),
+            started(
+                unrelated,
+                DialogType::Direct,
+                &host,
+                now - chrono::Duration::days(90),
+            ),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetFirstContactDialogs {
+                participant_id: user.id.to_string(),
+            })
+            .await
+        {
+            DialogQueryResult::Dialog(Some(dialog)) => assert_eq!(dialog.dialog_id, oldest),
+            _ => panic!("Expected dialog result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetFirstContactDialogs {
+                participant_id: Uuid::new_v4().to_string(),
+            })
+            .await
+        {
+            DialogQueryResult::Dialog(None) => {}
+            _ => panic!("Expected empty dialog result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_by_velocity() {
+        let user = participant("User", ParticipantType::Human);
+        let start = Utc::now() - chrono::Duration::hours(2);
+        let (fast, slow, instant) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let mut events = vec![
+            started(fast, DialogType::Direct, &user, start),
+            started(slow, DialogType::Direct, &user, start),
+            // All turns at the start instant: zero span, no velocity
+            started(instant, DialogType::Direct, &user, start),
+            turn_added(
+                instant,
+                user.id,
+                Message::text("..."),
+                TurnType::UserQuery,
+                start,
+            ),
+        ];
+        // 10 turns in one minute vs 3 turns in an hour
+        for i in 1..=10 {
+            events.push(turn_added(
+                fast,
+                user.id,
+                Message::text("..."),
+                TurnType::UserQuery,
+                start + chrono::Duration::seconds(i * 6),
+            ));
+        }
+        for i in 1..=3 {
+            events.push(turn_added(
+                slow,
+                user.id,
+                Message::text("..."),
+                TurnType::UserQuery,
+                start + chrono::Duration::minutes(i * 20),
+            ));
+        }
+        let handler = handler_with(events).await;
+
+        let ids = |result| match result {
+            DialogQueryResult::Dialogs(dialogs) => dialogs
+                .iter()
+                .map(|d: &SimpleDialogView| d.dialog_id)
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected dialogs result"),
+        };
+
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByVelocity {
+                    min_turns_per_min: Some(5.0),
+                    max: None
+                })
+                .await),
+            vec![fast]
+        );
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByVelocity {
+                    min_turns_per_min: None,
+                    max: Some(1.0)
+                })
+                .await),
+            vec![slow]
+        );
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByVelocity {
+                    min_turns_per_min: None,
+                    max: None
+                })
+                .await)
+            .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_by_end_code() {
+        let user = participant("User", ParticipantType::Human);
+        let (resolved, escalated, legacy, open) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let ended = |dialog_id, reason: Option<EndReason>| {
+            DialogDomainEvent::DialogEnded(DialogEnded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                ended_at: Utc::now(),
+                reason,
+                final_metrics: ConversationMetrics {
+                    turn_count: 0,
+                    avg_response_time_ms: 0.0,
+                    topic_switches: 0,
+                    clarification_count: 0,
+                    sentiment_trend: 0.0,
+                    coherence_score: 1.0,
+                },
+            })
+        };
+
+        let mut events = Vec::new();
+        for id in [resolved, escalated, legacy, open] {
+            events.push(started(id, DialogType::Support, &user, Utc::now()));
+        }
+        events.push(ended(
+            resolved,
+            Some(EndReason::new(EndReasonCode::Resolved)),
+        ));
+        events.push(ended(
+            escalated,
+            Some(EndReason::new(EndReasonCode::Escalated).with_detail("billing team")),
+        ));
+        // Free-text reasons map to Other
+        events.push(ended(legacy, Some("customer went quiet".into())));
+        let handler = handler_with(events).await;
+
+        let ids = |result| match result {
+            DialogQueryResult::Dialogs(dialogs) => dialogs
+                .iter()
+                .map(|d: &SimpleDialogView| d.dialog_id)
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected dialogs result"),
+        };
+
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByEndCode {
+                    code: EndReasonCode::Resolved
+                })
+                .await),
+            vec![resolved]
+        );
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByEndCode {
+                    code: EndReasonCode::Escalated
+                })
+                .await),
+            vec![escalated]
+        );
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByEndCode {
+                    code: EndReasonCode::Other
+                })
+                .await),
+            vec![legacy]
+        );
+        assert!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByEndCode {
+                    code: EndReasonCode::Timeout
+                })
+                .await)
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_returning_participants() {
+        let regular = participant("Regular", ParticipantType::Human);
+        let once = participant("Once", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let handler = handler_with(vec![
+            started(first, DialogType::Support, &regular, Utc::now()),
+            joined(first, &agent),
+            started(second, DialogType::Group, &agent, Utc::now()),
+            // Having since left still counts as a visit
+            joined(second, &regular),
+            left(second, &regular),
+            started(third, DialogType::Support, &once, Utc::now()),
+            joined(third, &agent),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetReturningParticipants { min_dialogs: 2 })
+            .await
+        {
+            DialogQueryResult::ReturningParticipants(returning) => {
+                assert_eq!(
+                    returning,
+                    vec![
+                        ReturningParticipant {
+                            participant_id: agent.id,
+                            dialog_count: 3
+                        },
+                        ReturningParticipant {
+                            participant_id: regular.id,
+                            dialog_count: 2
+                        },
+                    ]
+                );
+            }
+            _ => panic!("Expected returning participants result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_by_resolution() {
+        let user = participant("User", ParticipantType::Human);
+        let (fixed, open, unknown) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let resolution_set = |dialog_id, resolution| {
+            DialogDomainEvent::ResolutionSet(ResolutionSet {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                resolution,
+                set_at: Utc::now(),
+            })
+        };
+
+        let handler = handler_with(vec![
+            started(fixed, DialogType::Support, &user, Utc::now()),
+            started(open, DialogType::Support, &user, Utc::now()),
+            started(unknown, DialogType::Support, &user, Utc::now()),
+            resolution_set(fixed, ResolutionOutcome::Unresolved),
+            // The latest outcome wins
+            resolution_set(fixed, ResolutionOutcome::Resolved),
+            resolution_set(open, ResolutionOutcome::Unresolved),
+        ])
+        .await;
+
+        let ids = |result| match result {
+            DialogQueryResult::Dialogs(dialogs) => dialogs
+                .iter()
+                .map(|d: &SimpleDialogView| d.dialog_id)
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected dialogs result"),
+        };
+
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByResolution {
+                    resolution: ResolutionOutcome::Resolved
+                })
+                .await),
+            vec![fixed]
+        );
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByResolution {
+                    resolution: ResolutionOutcome::Unresolved
+                })
+                .await),
+            vec![open]
+        );
+        assert!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByResolution {
+                    resolution: ResolutionOutcome::Escalated
+                })
+                .await)
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conversation_tree() {
+        use crate::projections::conversation_tree::{BranchKind, FORKED_FROM_KEY};
+
+        let user = participant("User", ParticipantType::Human);
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let (root, first_fork, second_fork) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let forked_from = |dialog_id, parent: Uuid| {
+            DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                key: FORKED_FROM_KEY.to_string(),
+                value: serde_json::json!(parent.to_string()),
+                set_at: Utc::now(),
+            })
+        };
+
+        let handler = handler_with(vec![
+            started(root, DialogType::Direct, &user, start),
+            started(
+                first_fork,
+                DialogType::Direct,
+                &user,
+                start + chrono::Duration::minutes(1),
+            ),
+            forked_from(first_fork, root),
+            started(
+                second_fork,
+                DialogType::Direct,
+                &user,
+                start + chrono::Duration::minutes(2),
+            ),
+            forked_from(second_fork, root),
+            // Would close a cycle back to the root and is ignored
+            forked_from(root, second_fork),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetConversationTree { root_id: root })
+            .await
+        {
+            DialogQueryResult::ConversationTree(tree) => {
+                assert_eq!(tree.dialog_id, root);
+                assert_eq!(tree.branch, None);
+                let children: Vec<_> = tree
+                    .children
+                    .iter()
+                    .map(|c| (c.dialog_id, c.branch))
+                    .collect();
+                assert_eq!(
+                    children,
+                    vec![
+                        (first_fork, Some(BranchKind::Fork)),
+                        (second_fork, Some(BranchKind::Fork))
+                    ]
+                );
+                assert!(tree.children.iter().all(|c| c.children.is_empty()));
+            }
+            _ => panic!("Expected conversation tree result"),
+        }
+
+        // A fork can be used as the root of its own subtree
+        match handler
+            .execute(DialogQuery::GetConversationTree {
+                root_id: second_fork,
+            })
+            .await
+        {
+            DialogQueryResult::ConversationTree(tree) => assert!(tree.children.is_empty()),
+            _ => panic!("Expected conversation tree result"),
+        }
+
+        assert!(matches!(
+            handler
+                .execute(DialogQuery::GetConversationTree {
+                    root_id: Uuid::new_v4()
+                })
+                .await,
+            DialogQueryResult::Error(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_dialog_by_id_projected() {
+        let user = participant("User", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let dialog_id = Uuid::new_v4();
+        let mut events = vec![
+            started(dialog_id, DialogType::Support, &user, Utc::now()),
+            joined(dialog_id, &agent),
+        ];
+        for _ in 0..50 {
+            events.push(turn_added(
+                dialog_id,
+                user.id,
+                Message::text("..."),
+                TurnType::UserQuery,
+                Utc::now(),
+            ));
+        }
+        let handler = handler_with(events).await;
+
+        let projected = |include_turns, include_participants| DialogQuery::GetDialogByIdProjected {
+            dialog_id,
+            include_turns,
+            include_participants,
+            include_metadata: false,
+        };
+
+        match handler.execute(projected(false, true)).await {
+            DialogQueryResult::Dialog(Some(dialog)) => {
+                assert_eq!(dialog.dialog_id, dialog_id);
+                assert_eq!(dialog.status, DialogStatus::Active);
+                assert_eq!(dialog.primary_participant.id, user.id);
+                assert!(dialog.turns.is_empty());
+                assert_eq!(dialog.participants.len(), 2);
+            }
+            _ => panic!("Expected dialog result"),
+        }
+
+        match handler.execute(projected(true, false)).await {
+            DialogQueryResult::Dialog(Some(dialog)) => {
+                assert_eq!(dialog.turns.len(), 50);
+                assert!(dialog.participants.is_empty());
+                assert!(dialog.membership.is_empty());
+            }
+            _ => panic!("Expected dialog result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetDialogByIdProjected {
+                dialog_id: Uuid::new_v4(),
+                include_turns: true,
+                include_participants: true,
+                include_metadata: true,
+            })
+            .await
+        {
+            DialogQueryResult::Dialog(None) => {}
+            _ => panic!("Expected empty dialog result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_by_initial_topic() {
+        let user = participant("User", ParticipantType::Human);
+        let (billing_first, shipping_first, no_topic) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let switched = |dialog_id, name: &str, keyword: &str| {
+            DialogDomainEvent::ContextSwitched(ContextSwitched {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                previous_topic: None,
+                new_topic: Topic::new(name, vec![keyword.to_string()]),
+                switched_at: Utc::now(),
+            })
+        };
+
+        let handler = handler_with(vec![
+            started(billing_first, DialogType::Support, &user, Utc::now()),
+            switched(billing_first, "Billing", "invoice"),
+            switched(billing_first, "Shipping", "delivery"),
+            started(shipping_first, DialogType::Support, &user, Utc::now()),
+            switched(shipping_first, "Shipping", "delivery"),
+            // Billing came up later, so it is not the opening topic
+            switched(shipping_first, "Billing", "invoice"),
+            started(no_topic, DialogType::Support, &user, Utc::now()),
+        ])
+        .await;
+
+        let ids = |result| match result {
+            DialogQueryResult::Dialogs(dialogs) => dialogs
+                .iter()
+                .map(|d: &SimpleDialogView| d.dialog_id)
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected dialogs result"),
+        };
+
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByInitialTopic {
+                    keyword: "Invoice".to_string()
+                })
+                .await),
+            vec![billing_first]
+        );
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByInitialTopic {
+                    keyword: "delivery".to_string()
+                })
+                .await),
+            vec![shipping_first]
+        );
+        assert!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsByInitialTopic {
+                    keyword: "refund".to_string()
+                })
+                .await)
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_by_composition() {
+        let human = participant("Human", ParticipantType::Human);
+        let other_human = participant("Other human", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let other_agent = participant("Other agent", ParticipantType::AIAgent);
+        let system = participant("System", ParticipantType::System);
+        let (humans, assisted, agents, monitored) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+
+        let handler = handler_with(vec![
+            started(humans, DialogType::Group, &human, Utc::now()),
+            joined(humans, &other_human),
+            started(assisted, DialogType::Support, &human, Utc::now()),
+            joined(assisted, &agent),
+            started(agents, DialogType::Group, &agent, Utc::now()),
+            joined(agents, &other_agent),
+            started(monitored, DialogType::Direct, &human, Utc::now()),
+            joined(monitored, &system),
+        ])
+        .await;
+
+        let ids = |result| match result {
+            DialogQueryResult::Dialogs(dialogs) => dialogs
+                .iter()
+                .map(|d: &SimpleDialogView| d.dialog_id)
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected dialogs result"),
+        };
+
+        for (composition, expected) in [
+            (DialogComposition::HumanOnly, humans),
+            (DialogComposition::HumanAgent, assisted),
+            (DialogComposition::MultiAgent, agents),
+            (DialogComposition::HasSystem, monitored),
+        ] {
+            assert_eq!(
+                ids(handler
+                    .execute(DialogQuery::GetDialogsByComposition { composition })
+                    .await),
+                vec![expected],
+                "{composition:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unbalanced_dialogs() {
+        let interviewer = participant("Interviewer", ParticipantType::Human);
+        let candidate = participant("Candidate", ParticipantType::Human);
+        let (balanced, unbalanced, solo) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+
+        let mut events = vec![
+            started(balanced, DialogType::Direct, &interviewer, now),
+            joined(balanced, &candidate),
+            started(unbalanced, DialogType::Direct, &interviewer, now),
+            joined(unbalanced, &candidate),
+            started(solo, DialogType::Direct, &interviewer, now),
+        ];
+        for i in 0..4 {
+            events.push(turn_added(
+                balanced,
+                interviewer.id,
+                Message::text("Question"),
+                TurnType::UserQuery,
+                now,
+            ));
+            events.push(turn_added(
+                balanced,
+                candidate.id,
+                Message::text("Answer"),
+                TurnType::UserQuery,
+                now,
+            ));
+            events.push(turn_added(
+                unbalanced,
+                interviewer.id,
+                Message::text("Monologue"),
+                TurnType::UserQuery,
+                now,
+            ));
+            events.push(turn_added(
+                solo,
+                interviewer.id,
+                Message::text("Note"),
+                TurnType::UserQuery,
+                now,
+            ));
+            if i == 0 {
+                events.push(turn_added(
+                    unbalanced,
+                    candidate.id,
+                    Message::text("Okay"),
+                    TurnType::UserQuery,
+                    now,
+                ));
+            }
+        }
+        let handler = handler_with(events).await;
+
+        match handler
+            .execute(DialogQuery::GetUnbalancedDialogs { max_ratio: 2.0 })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, unbalanced);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetUnbalancedDialogs { max_ratio: 4.0 })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => assert!(dialogs.is_empty()),
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_frequently_reopened_dialogs() {
+        use crate::projections::conversation_tree::REOPENED_FROM_KEY;
+
+        let user = participant("User", ParticipantType::Human);
+        let (original, first_reopen, second_reopen, other) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let reopened_from = |dialog_id, parent: Uuid| {
+            DialogDomainEvent::DialogMetadataSet(DialogMetadataSet {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                key: REOPENED_FROM_KEY.to_string(),
+                value: serde_json::json!(parent.to_string()),
+                set_at: Utc::now(),
+            })
+        };
+
+        let handler = handler_with(vec![
+            started(original, DialogType::Support, &user, Utc::now()),
+            started(other, DialogType::Support, &user, Utc::now()),
+            started(first_reopen, DialogType::Support, &user, Utc::now()),
+            reopened_from(first_reopen, original),
+            started(second_reopen, DialogType::Support, &user, Utc::now()),
+            reopened_from(second_reopen, original),
+            // Setting the same link again does not count twice
+            reopened_from(second_reopen, original),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetFrequentlyReopenedDialogs { min_reopens: 2 })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, original);
+                assert_eq!(dialogs[0].reopen_count, 2);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetFrequentlyReopenedDialogs { min_reopens: 3 })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => assert!(dialogs.is_empty()),
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_high_topic_switch_dialogs() {
+        let user = participant("User", ParticipantType::Human);
+        let (focused, hopping) = (Uuid::new_v4(), Uuid::new_v4());
+        let switched = |dialog_id, name: &str| {
+            DialogDomainEvent::ContextSwitched(ContextSwitched {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                previous_topic: None,
+                new_topic: Topic::new(name, vec![]),
+                switched_at: Utc::now(),
+            })
+        };
+
+        let handler = handler_with(vec![
+            started(focused, DialogType::Support, &user, Utc::now()),
+            switched(focused, "Billing"),
+            started(hopping, DialogType::Support, &user, Utc::now()),
+            switched(hopping, "Billing"),
+            switched(hopping, "Shipping"),
+            switched(hopping, "Returns"),
+            switched(hopping, "Billing"),
+        ])
+        .await;
+
+        match handler
+            .execute(DialogQuery::GetHighTopicSwitchDialogs { min_switches: 3 })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, hopping);
+                assert_eq!(dialogs[0].topic_switches, 4);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_by_role_composition() {
+        let user = participant("User", ParticipantType::Human);
+        let assistant = Participant {
+            role: ParticipantRole::Assistant,
+            ..participant("Agent", ParticipantType::AIAgent)
+        };
+        let moderator = Participant {
+            role: ParticipantRole::Moderator,
+            ..participant("Moderator", ParticipantType::Human)
+        };
+        let (unmoderated, moderated) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let handler = handler_with(vec![
+            started(unmoderated, DialogType::Group, &user, Utc::now()),
+            joined(unmoderated, &assistant),
+            started(moderated, DialogType::Group, &user, Utc::now()),
+            joined(moderated, &assistant),
+            joined(moderated, &moderator),
+        ])
+        .await;
+
+        let query = DialogQuery::GetDialogsByRoleComposition {
+            require_roles: vec![ParticipantRole::Primary, ParticipantRole::Moderator],
+        };
+        match handler.execute(query).await {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, moderated);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_with_structured_content() {
+        let user = participant("User", ParticipantType::Human);
+        let (text_only, card, table) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let structured = |format: &str| Message {
+            content: MessageContent::Structured(
+                serde_json::json!({ "format": format, "rows": [] }),
+            ),
+            ..Message::text("")
+        };
+
+        let handler = handler_with(vec![
+            started(text_only, DialogType::Direct, &user, Utc::now()),
+            turn_added(
+                text_only,
+                user.id,
+                Message::text("Just words"),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            started(card, DialogType::Direct, &user, Utc::now()),
+            turn_added(
+                card,
+                user.id,
+                structured("card"),
+                TurnType::AgentResponse,
+                Utc::now(),
+            ),
+            started(table, DialogType::Direct, &user, Utc::now()),
+            turn_added(
+                table,
+                user.id,
+                structured("table"),
+                TurnType::AgentResponse,
+                Utc::now(),
+            ),
+        ])
+        .await;
+
+        let ids = |result| match result {
+            DialogQueryResult::Dialogs(dialogs) => {
+                let mut ids: Vec<Uuid> = dialogs.iter().map(|d| d.dialog_id).collect();
+                ids.sort();
+                ids
+            }
+            _ => panic!("Expected dialogs result"),
+        };
+
+        let mut rich = vec![card, table];
+        rich.sort();
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsWithStructuredContent { format: None })
+                .await),
+            rich
+        );
+        assert_eq!(
+            ids(handler
+                .execute(DialogQuery::GetDialogsWithStructuredContent {
+                    format: Some("card".to_string())
+                })
+                .await),
+            vec![card]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_view_cache() {
+        let user = participant("User", ParticipantType::Human);
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut updater = SimpleProjectionUpdater::new();
+        for dialog_id in [first, second, third] {
+            updater
+                .handle_event(started(dialog_id, DialogType::Direct, &user, Utc::now()))
+                .await
+                .unwrap();
+        }
+        let updater = Arc::new(RwLock::new(updater));
+        let handler = DialogQueryHandler::new(updater.clone()).with_view_cache(2);
+
+        // A hit shares the cached view
+        let cached = handler.get_view(first).await.unwrap();
+        let hit = handler.get_view(first).await.unwrap();
+        assert!(Arc::ptr_eq(&cached, &hit));
+        assert_eq!(hit.turns.len(), 0);
+
+        // A mutating event invalidates the entry
+        updater
+            .write()
+            .await
+            .handle_event(turn_added(
+                first,
+                user.id,
+                Message::text("Hello"),
+                TurnType::UserQuery,
+                Utc::now(),
+            ))
+            .await
+            .unwrap();
+        let fresh = handler.get_view(first).await.unwrap();
+        assert!(!Arc::ptr_eq(&cached, &fresh));
+        assert_eq!(fresh.turns.len(), 1);
+        match handler
+            .execute(DialogQuery::GetDialogById { dialog_id: first })
+            .await
+        {
+            DialogQueryResult::Dialog(Some(dialog)) => assert_eq!(dialog.turns.len(), 1),
+            _ => panic!("Expected dialog result"),
+        }
+
+        // The least recently used view is evicted at capacity
+        let second_view = handler.get_view(second).await.unwrap();
+        handler.get_view(first).await.unwrap();
+        handler.get_view(third).await.unwrap();
+        assert_eq!(
+            handler.view_cache.as_ref().unwrap().lock().unwrap().len(),
+            2
+        );
+        assert!(Arc::ptr_eq(&fresh, &handler.get_view(first).await.unwrap()));
+        assert!(!Arc::ptr_eq(
+            &second_view,
+            &handler.get_view(second).await.unwrap()
+        ));
+        assert!(handler.get_view(Uuid::new_v4()).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rebalancing_suggestions() {
+        let user = participant("User", ParticipantType::Human);
+        let busy = participant("Busy agent", ParticipantType::AIAgent);
+        let idle = participant("Idle agent", ParticipantType::AIAgent);
+        let (older, newest, paused) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let start = Utc::now() - chrono::Duration::hours(1);
+
+        let mut events = Vec::new();
+        for (i, dialog_id) in [older, Uuid::new_v4(), newest].into_iter().enumerate() {
+            events.push(started(
+                dialog_id,
+                DialogType::Support,
+                &user,
+                start + chrono::Duration::minutes(i as i64),
+            ));
+            events.push(joined(dialog_id, &busy));
+        }
+        // The idle agent's only dialog is paused
+        events.push(started(paused, DialogType::Support, &user, start));
+        events.push(joined(paused, &idle));
+        events.push(DialogDomainEvent::DialogPaused(DialogPaused {
+            event_id: Uuid::new_v4(),
+            dialog_id: paused,
+            paused_at: Utc::now(),
+            context_snapshot: std::collections::HashMap::new(),
+        }));
+        let handler = handler_with(events).await;
+
+        match handler
+            .execute(DialogQuery::GetRebalancingSuggestions {
+                target_concurrency: 2,
+            })
+            .await
+        {
+            DialogQueryResult::RebalancingSuggestions(suggestions) => {
+                assert_eq!(
+                    suggestions,
+                    vec![RebalancingSuggestion {
+                        dialog_id: newest,
+                        from: busy.id.to_string(),
+                        to: idle.id.to_string(),
+                    }]
+                );
+            }
+            _ => panic!("Expected rebalancing suggestions"),
+        }
+
+        // Nobody is over a higher target
+        match handler
+            .execute(DialogQuery::GetRebalancingSuggestions {
+                target_concurrency: 3,
+            })
+            .await
+        {
+            DialogQueryResult::RebalancingSuggestions(suggestions) => {
+                assert!(suggestions.is_empty())
+            }
+            _ => panic!("Expected rebalancing suggestions"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_by_first_responder() {
+        let user = participant("User", ParticipantType::Human);
+        let triage = participant("Triage", ParticipantType::AIAgent);
+        let billing = participant("Billing", ParticipantType::AIAgent);
+        let (triaged, direct, unanswered) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+
+        let mut events = Vec::new();
+        for dialog_id in [triaged, direct, unanswered] {
+            events.push(started(dialog_id, DialogType::Support, &user, now));
+            events.push(joined(dialog_id, &triage));
+            events.push(joined(dialog_id, &billing));
+            events.push(turn_added(
+                dialog_id,
+                user.id,
+                Message::text("Help"),
+                TurnType::UserQuery,
+                now,
+            ));
+        }
+        events.extend([
+            turn_added(
+                triaged,
+                triage.id,
+                Message::text("Routing you"),
+                TurnType::AgentResponse,
+                now,
+            ),
+            turn_added(
+                triaged,
+                billing.id,
+                Message::text("Billing here"),
+                TurnType::AgentResponse,
+                now,
+            ),
+            turn_added(
+                direct,
+                billing.id,
+                Message::text("Billing here"),
+                TurnType::AgentResponse,
+                now,
+            ),
+            turn_added(
+                direct,
+                triage.id,
+                Message::text("Anything else?"),
+                TurnType::AgentResponse,
+                now,
+            ),
+        ]);
+        let handler = handler_with(events).await;
+
+        for (agent, expected) in [(&triage, triaged), (&billing, direct)] {
+            let query = DialogQuery::GetDialogsByFirstResponder {
+                agent_id: agent.id.to_string(),
+            };
+            match handler.execute(query).await {
+                DialogQueryResult::Dialogs(dialogs) => {
+                    assert_eq!(dialogs.len(), 1);
+                    assert_eq!(dialogs[0].dialog_id, expected);
+                }
+                _ => panic!("Expected dialogs result"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolution_time_percentiles() {
+        let user = participant("User", ParticipantType::Human);
+        let start = Utc::now() - chrono::Duration::days(1);
+
+        // Support dialogs lasting 1 to 10 minutes, plus a long task and an open dialog
+        let mut events = Vec::new();
+        for (minutes, dialog_type) in (1..=10)
+            .map(|m| (m, DialogType::Support))
+            .chain([(120, DialogType::Task)])
+        {
+            let dialog_id = Uuid::new_v4();
+            events.push(started(dialog_id, dialog_type, &user, start));
+            events.push(DialogDomainEvent::DialogEnded(DialogEnded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                ended_at: start + chrono::Duration::minutes(minutes),
+                reason: None,
+                final_metrics: ConversationMetrics {
+                    turn_count: 0,
+                    avg_response_time_ms: 0.0,
+                    topic_switches: 0,
+                    clarification_count: 0,
+                    sentiment_trend: 0.0,
+                    coherence_score: 1.0,
+                },
+            }));
+        }
+        events.push(started(Uuid::new_v4(), DialogType::Support, &user, start));
+        let handler = handler_with(events).await;
+
+        let query = DialogQuery::GetResolutionTimePercentiles {
+            dialog_type: Some(DialogType::Support),
+        };
+        match handler.execute(query).await {
+            DialogQueryResult::ResolutionTimePercentiles(percentiles) => {
+                assert_eq!(
+                    percentiles,
+                    ResolutionTimePercentiles {
+                        dialog_count: 10,
+                        p50_secs: Some(300.0),
+                        p90_secs: Some(540.0),
+                        p99_secs: Some(600.0),
+                    }
+                );
+            }
+            _ => panic!("Expected resolution time percentiles"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetResolutionTimePercentiles { dialog_type: None })
+            .await
+        {
+            DialogQueryResult::ResolutionTimePercentiles(percentiles) => {
+                assert_eq!(percentiles.dialog_count, 11);
+                assert_eq!(percentiles.p99_secs, Some(7200.0));
+            }
+            _ => panic!("Expected resolution time percentiles"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetResolutionTimePercentiles {
+                dialog_type: Some(DialogType::Group),
+            })
+            .await
+        {
+            DialogQueryResult::ResolutionTimePercentiles(percentiles) => {
+                assert_eq!(percentiles.dialog_count, 0);
+                assert_eq!(percentiles.p50_secs, None);
+            }
+            _ => panic!("Expected resolution time percentiles"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_noisy_dialogs() {
+        let user = participant("User", ParticipantType::Human);
+        let system = participant("Scheduler", ParticipantType::System);
+        let (normal, noisy) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let mut events = vec![
+            started(normal, DialogType::Support, &user, Utc::now()),
+            started(noisy, DialogType::Support, &user, Utc::now()),
+            turn_added(
+                normal,
+                user.id,
+                Message::text("Hello"),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+            turn_added(
+                normal,
+                system.id,
+                Message::text("Agent assigned"),
+                TurnType::SystemMessage,
+                Utc::now(),
+            ),
+            turn_added(
+                noisy,
+                user.id,
+                Message::text("Hello"),
+                TurnType::UserQuery,
+                Utc::now(),
+            ),
+        ];
+        for _ in 0..5 {
+            events.push(turn_added(
+                noisy,
+                system.id,
+                Message::text("Heartbeat"),
+                TurnType::SystemMessage,
+                Utc::now(),
+            ));
+        }
+        let handler = handler_with(events).await;
+
+        match handler
+            .execute(DialogQuery::GetNoisyDialogs {
+                min_system_messages: 3,
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, noisy);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetNoisyDialogs {
+                min_system_messages: 6,
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => assert!(dialogs.is_empty()),
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_in_phase() {
+        use crate::events::PhaseChanged;
+
+        let user = participant("User", ParticipantType::Human);
+        let (walked, greeting) = (Uuid::new_v4(), Uuid::new_v4());
+        let phase_changed = |dialog_id, previous_phase, new_phase| {
+            DialogDomainEvent::PhaseChanged(PhaseChanged {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                previous_phase,
+                new_phase,
+                changed_at: Utc::now(),
+            })
+        };
+
+        let mut events = vec![
+            started(walked, DialogType::Support, &user, Utc::now()),
+            started(greeting, DialogType::Support, &user, Utc::now()),
+            started(Uuid::new_v4(), DialogType::Support, &user, Utc::now()),
+            phase_changed(greeting, None, ConversationPhase::Greeting),
+        ];
+
+        // Walk one dialog through the support flow
+        let mut previous = None;
+        for phase in [
+            ConversationPhase::Greeting,
+            ConversationPhase::Triage,
+            ConversationPhase::Resolution,
+        ] {
+            events.push(phase_changed(walked, previous, phase));
+            previous = Some(phase);
+        }
+        let handler = handler_with(events).await;
+
+        match handler
+            .execute(DialogQuery::GetDialogsInPhase {
+                phase: ConversationPhase::Resolution,
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, walked);
+                let phases: Vec<ConversationPhase> =
+                    dialogs[0].phase_history.iter().map(|e| e.phase).collect();
+                assert_eq!(
+                    phases,
+                    vec![
+                        ConversationPhase::Greeting,
+                        ConversationPhase::Triage,
+                        ConversationPhase::Resolution
+                    ]
+                );
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+
+        // Only the current phase counts
+        match handler
+            .execute(DialogQuery::GetDialogsInPhase {
+                phase: ConversationPhase::Greeting,
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, greeting);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+
+        match handler
+            .execute(DialogQuery::GetDialogsInPhase {
+                phase: ConversationPhase::Closing,
+            })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => assert!(dialogs.is_empty()),
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dialogs_without_agent_response() {
+        let user = participant("User", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let now = Utc::now();
+        let ago = |minutes| now - chrono::Duration::minutes(minutes);
+        let (waiting, answered, stale, ended) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+
+        let handler = handler_with(vec![
+            started(waiting, DialogType::Support, &user, ago(60)),
+            turn_added(
+                waiting,
+                agent.id,
+                Message::text("How can I help?"),
+                TurnType::AgentResponse,
+                ago(50),
+            ),
+            turn_added(
+                waiting,
+                user.id,
+                Message::text("My order is late"),
+                TurnType::UserQuery,
+                ago(10),
+            ),
+            started(answered, DialogType::Support, &user, ago(60)),
+            turn_added(
+                answered,
+                user.id,
+                Message::text("Where is my refund?"),
+                TurnType::UserQuery,
+                ago(10),
+            ),
+            turn_added(
+                answered,
+                agent.id,
+                Message::text("On its way"),
+                TurnType::AgentResponse,
+                ago(9),
+            ),
+            // Nothing from the user within the window
+            started(stale, DialogType::Support, &user, ago(120)),
+            turn_added(
+                stale,
+                user.id,
+                Message::text("Hello?"),
+                TurnType::UserQuery,
+                ago(90),
+            ),
+            started(ended, DialogType::Support, &user, ago(60)),
+            turn_added(
+                ended,
+                user.id,
+                Message::text("Never mind"),
+                TurnType::UserQuery,
+                ago(5),
+            ),
+            DialogDomainEvent::DialogEnded(DialogEnded {
+                event_id: Uuid::new_v4(),
+                dialog_id: ended,
+                ended_at: ago(4),
+                reason: None,
+                final_metrics: ConversationMetrics {
+                    turn_count: 1,
+                    avg_response_time_ms: 0.0,
+                    topic_switches: 0,
+                    clarification_count: 0,
+                    sentiment_trend: 0.0,
+                    coherence_score: 1.0,
+                },
+            }),
+        ])
+        .await;
+
+        let query = DialogQuery::GetDialogsWithoutAgentResponse {
+            since: std::time::Duration::from_secs(30 * 60),
+            now,
+        };
+        match handler.execute(query).await {
+            DialogQueryResult::Dialogs(dialogs) => {
+                assert_eq!(dialogs.len(), 1);
+                assert_eq!(dialogs[0].dialog_id, waiting);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_average_duration_by_type() {
+        let user = participant("User", ParticipantType::Human);
+        let start = Utc::now() - chrono::Duration::days(1);
+
+        // Support dialogs of 2 and 4 minutes, a direct dialog of 30 seconds,
+        // an open task dialog and an open support dialog
+        let mut events = Vec::new();
+        for (secs, dialog_type) in [
+            (120, DialogType::Support),
+            (240, DialogType::Support),
+            (30, DialogType::Direct),
+        ] {
+            let dialog_id = Uuid::new_v4();
+            events.push(started(dialog_id, dialog_type, &user, start));
+            events.push(DialogDomainEvent::DialogEnded(DialogEnded {
+                event_id: Uuid::new_v4(),
+                dialog_id,
+                ended_at: start + chrono::Duration::seconds(secs),
+                reason: None,
+                final_metrics: ConversationMetrics {
+                    turn_count: 0,
+                    avg_response_time_ms: 0.0,
+                    topic_switches: 0,
+                    clarification_count: 0,
+                    sentiment_trend: 0.0,
+                    coherence_score: 1.0,
+                },
+            }));
+        }
+        events.push(started(Uuid::new_v4(), DialogType::Task, &user, start));
+        events.push(started(Uuid::new_v4(), DialogType::Support, &user, start));
+        let handler = handler_with(events).await;
+
+        match handler.execute(DialogQuery::GetAverageDurationByType).await {
+            DialogQueryResult::AverageDurations(averages) => {
+                assert_eq!(
+                    averages,
+                    vec![
+                        (DialogType::Support, 180.0, 2),
+                        (DialogType::Direct, 30.0, 1)
+                    ]
+                );
+            }
+            _ => panic!("Expected average durations"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_by_participant_name() {
+        let alice = participant("Alice Smith", ParticipantType::Human);
+        let bob = participant("Bob", ParticipantType::Human);
+        let (with_alice, joined_by_alice, bob_only) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let handler = handler_with(vec![
+            started(with_alice, DialogType::Support, &alice, Utc::now()),
+            started(joined_by_alice, DialogType::Group, &bob, Utc::now()),
+            joined(joined_by_alice, &alice),
+            started(bob_only, DialogType::Direct, &bob, Utc::now()),
+        ])
+        .await;
+
+        let query = DialogQuery::SearchByParticipantName {
+            query: "alice".to_string(),
+        };
+        match handler.execute(query).await {
+            DialogQueryResult::Dialogs(dialogs) => {
+                let mut ids: Vec<Uuid> = dialogs.iter().map(|d| d.dialog_id).collect();
+                ids.sort();
+                let mut expected = vec![with_alice, joined_by_alice];
+                expected.sort();
+                assert_eq!(ids, expected);
+            }
+            _ => panic!("Expected dialogs result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_segmented_messages() {
+        let user = participant("User", ParticipantType::Human);
+        let agent = participant("Agent", ParticipantType::AIAgent);
+        let dialog_id = Uuid::new_v4();
+        let response = Message {
+            content: MessageContent::Segmented(vec![
+                MessageSegment::Text("Call the refund helper:".to_string()),
+                MessageSegment::Code {
+                    lang: "rust".to_string(),
+                    code: "issue_refund(order_id)".to_string(),
+                },
+                MessageSegment::Text("Then check the ledger.".to_string()),
+            ]),
+            ..Message::text("")
+        };
+        let handler = handler_with(vec![
+            started(dialog_id, DialogType::Support, &user, Utc::now()),
+            joined(dialog_id, &agent),
+            turn_added(
+                dialog_id,
+                agent.id,
+                response,
+                TurnType::AgentResponse,
+                Utc::now(),
+            ),
+            started(Uuid::new_v4(), DialogType::Support, &user, Utc::now()),
+        ])
+        .await;
+
+        // Terms are found in text and code segments alike
+        for term in ["REFUND HELPER", "issue_refund", "ledger"] {
+            match handler
+                .execute(DialogQuery::SearchDialogsByText {
+                    search_text: term.to_string(),
+                })
+                .await
+            {
+                DialogQueryResult::Dialogs(dialogs) => {
+                    assert_eq!(dialogs.len(), 1, "searching for {term}");
+                    assert_eq!(dialogs[0].dialog_id, dialog_id);
+                }
+                _ => panic!("Expected dialogs result"),
+            }
+        }
+
+        // The transcript renders the segments in order
+        let view = match handler
+            .execute(DialogQuery::GetDialogById { dialog_id })
+            .await
+        {
+            DialogQueryResult::Dialog(Some(view)) => view,
+            _ => panic!("Expected dialog result"),
+        };
+        assert!(view.to_markdown().contains(
+            "Call the refund helper:\n\n```rust\nissue_refund(order_id)\n```\n\nThen check the ledger."
+        ));
+
+        // Prose and code is text to embed, not rich content
+        match handler
+            .execute(DialogQuery::GetDialogsWithStructuredContent { format: None })
+            .await
+        {
+            DialogQueryResult::Dialogs(dialogs) => assert!(dialogs.is_empty()),
+            _ => panic!("Expected dialogs result"),
+        }
+        match handler.execute(DialogQuery::GetEmbeddingCoverage).await {
+            DialogQueryResult::EmbeddingCoverage(report) => {
+                assert_eq!(report.overall.text_turns, 1)
+            }
+            _ => panic!("Expected embedding coverage result"),
+        }
+        let backlog = view.turns_missing_embeddings();
+        assert_eq!(backlog.len(), 1);
+        assert_eq!(
+            backlog[0].1,
+            view.turns[0].message.content.to_searchable_string()
+        );
+
+        // Segmented content keeps the externally tagged encoding of the other variants
+        let json = serde_json::to_value(&view.turns[0].message.content).unwrap();
+        assert_eq!(json["Segmented"][1]["Code"]["lang"], "rust");
+        let text = serde_json::to_value(MessageContent::Text("hi".to_string())).unwrap();
+        assert_eq!(text, serde_json::json!({ "Text": "hi" }));
+    }
+
+    #[tokio::test]
+    async fn test_get_dialogs_by_day_type() {
+        use chrono::TimeZone;
+
+        let user = participant("User", ParticipantType::Human);
+        // 2024-01-06 was a Saturday and 2024-01-09 a Tuesday
+        let saturday_noon = Uuid::new_v4();
+        let saturday_early = Uuid::new_v4();
+        let tuesday = Uuid::new_v4();
+        let handler = handler_with(vec![
+            started(
+                saturday_noon,
+                DialogType::Support,
+                &user,
+                Utc.with_ymd_and_hms(2024, 1, 6, 12, 0, 0).unwrap(),
+            ),
+            started(
+                saturday_early,
+                DialogType::Support,
+                &user,
+                Utc.with_ymd_and_hms(2024, 1, 6, 5, 0, 0).unwrap(),
+            ),
+            started(
+                tuesday,
+                DialogType::Support,
+                &user,
+                Utc.with_ymd_and_hms(2024, 1, 9, 12, 0, 0).unwrap(),
+            ),
+        ])
+        .await;
+
+        let ids = |result| match result {
+            DialogQueryResult::Dialogs(dialogs) => {
+                let mut ids: Vec<Uuid> = dialogs
+                    .iter()
+                    .map(|d: &SimpleDialogView| d.dialog_id)
+                    .collect();
+                ids.sort();
+                ids
+            }
+            _ => panic!("Expected dialogs result"),
+        };
+        let sorted = |mut ids: Vec<Uuid>| {
+            ids.sort();
+            ids
+        };
+
+        let weekend = handler
+            .execute(DialogQuery::GetDialogsByDayType {
+                weekend: true,
+                timezone: None,
+            })
+            .await;
+        assert_eq!(ids(weekend), sorted(vec![saturday_noon, saturday_early]));
+        let weekdays = handler
+            .execute(DialogQuery::GetDialogsByDayType {
+                weekend: false,
+                timezone: None,
+            })
+            .await;
+        assert_eq!(ids(weekdays), vec![tuesday]);
+
+        // 05:00 UTC on Saturday is still Friday evening eight hours west
+        let weekend = handler
+            .execute(DialogQuery::GetDialogsByDayType {
+                weekend: true,
+                timezone: Some("-08:00".to_string()),
+            })
+            .await;
+        assert_eq!(ids(weekend), vec![saturday_noon]);
+        let weekend = handler
+            .execute(DialogQuery::GetDialogsByDayType {
+                weekend: true,
+                timezone: Some("utc".to_string()),
+            })
+            .await;
+        assert_eq!(ids(weekend), sorted(vec![saturday_noon, saturday_early]));
+
+        match handler
+            .execute(DialogQuery::GetDialogsByDayType {
+                weekend: true,
+                timezone: Some("Mars/Olympus".to_string()),
+            })
+            .await
+        {
+            DialogQueryResult::Error(message) => assert!(message.contains("Mars/Olympus")),
+            _ => panic!("Expected error result"),
+        }
+    }
+}
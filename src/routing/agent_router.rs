@@ -12,25 +12,42 @@ use uuid::Uuid;
 pub struct RoutingDecision {
     /// Target agents to receive the message
     pub targets: Vec<AgentId>,
-    
+
     /// Routing strategy used
     pub strategy: String,
-    
+
     /// Confidence score for the routing decision
     pub confidence: f32,
-    
+
     /// Metadata about the routing
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// Why the targets were chosen, for strategies that can explain it
+    #[serde(default)]
+    pub explanation: Option<RoutingExplanation>,
+}
+
+/// Capability match behind a routing decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutingExplanation {
+    /// Required capabilities offered by at least one target
+    pub matched_capabilities: Vec<String>,
+
+    /// Fraction of the required capabilities each target offers, by agent
+    pub per_agent_scores: HashMap<AgentId, f32>,
+
+    /// Capabilities the message was judged to need
+    pub required: Vec<String>,
 }
 
 /// Agent dialog router for intelligent message distribution
 pub struct AgentDialogRouter {
     /// Available routing strategies
     strategies: Vec<Box<dyn crate::routing::strategies::RoutingStrategy>>,
-    
+
     /// Agent capabilities cache
     agent_capabilities: HashMap<AgentId, Vec<String>>,
-    
+
     /// Active dialog channels
     channels: HashMap<Uuid, crate::routing::channel::DialogChannel>,
 }
@@ -48,12 +65,19 @@ impl AgentDialogRouter {
             channels: HashMap::new(),
         }
     }
-    
+
     /// Register agent capabilities
     pub fn register_agent(&mut self, agent_id: AgentId, capabilities: Vec<String>) {
         self.agent_capabilities.insert(agent_id, capabilities);
     }
-    
+
+    /// Drop the routing state kept for a dialog, e.g. once it has ended
+    pub fn forget_dialog(&self, dialog_id: Uuid) {
+        for strategy in &self.strategies {
+            strategy.forget(dialog_id);
+        }
+    }
+
     /// Route a message to appropriate agents
     pub fn route_message(
         &self,
@@ -66,22 +90,28 @@ impl AgentDialogRouter {
             .iter()
             .filter(|p| matches!(p.participant_type, ParticipantType::AIAgent))
             .collect();
-        
+
         if agent_participants.is_empty() {
             return RoutingDecision {
                 targets: vec![],
                 strategy: "none".to_string(),
                 confidence: 1.0,
                 metadata: HashMap::new(),
+                explanation: None,
             };
         }
-        
+
         // Try each strategy and pick the best one
         let mut best_decision: Option<RoutingDecision> = None;
         let mut best_score = 0.0;
-        
+
         for strategy in &self.strategies {
-            if let Some(decision) = strategy.route(message, &agent_participants, context, &self.agent_capabilities) {
+            if let Some(decision) = strategy.route(
+                message,
+                &agent_participants,
+                context,
+                &self.agent_capabilities,
+            ) {
                 let score = decision.confidence * strategy.priority();
                 if score > best_score {
                     best_score = score;
@@ -89,15 +119,16 @@ impl AgentDialogRouter {
                 }
             }
         }
-        
+
         best_decision.unwrap_or_else(|| RoutingDecision {
             targets: vec![],
             strategy: "fallback".to_string(),
             confidence: 0.0,
             metadata: HashMap::new(),
+            explanation: None,
         })
     }
-    
+
     /// Create a dialog channel for a group of agents
     pub fn create_agent_channel(
         &mut self,
@@ -109,31 +140,38 @@ impl AgentDialogRouter {
         self.channels.insert(channel.id.0, channel);
         channel_id
     }
-    
+
     /// Get agents in a channel
-    pub fn get_channel_agents(&self, channel_id: &crate::routing::channel::ChannelId) -> Option<Vec<AgentId>> {
+    pub fn get_channel_agents(
+        &self,
+        channel_id: &crate::routing::channel::ChannelId,
+    ) -> Option<Vec<AgentId>> {
         self.channels.get(&channel_id.0).map(|c| c.agents.clone())
     }
-    
+
     /// Broadcast to all agents in a channel
     pub fn broadcast_to_channel(
         &self,
         channel_id: &crate::routing::channel::ChannelId,
         message: &Message,
     ) -> Option<RoutingDecision> {
-        self.channels.get(&channel_id.0).map(|channel| {
-            RoutingDecision {
+        self.channels
+            .get(&channel_id.0)
+            .map(|channel| RoutingDecision {
                 targets: channel.agents.clone(),
                 strategy: "channel_broadcast".to_string(),
                 confidence: 1.0,
                 metadata: {
                     let mut meta = HashMap::new();
                     meta.insert("channel_id".to_string(), serde_json::json!(channel_id.0));
-                    meta.insert("channel_type".to_string(), serde_json::json!(channel.channel_type));
+                    meta.insert(
+                        "channel_type".to_string(),
+                        serde_json::json!(channel.channel_type),
+                    );
                     meta
                 },
-            }
-        })
+                explanation: None,
+            })
     }
 }
 
@@ -148,11 +186,11 @@ mod tests {
     use super::*;
     use crate::value_objects::{MessageContent, MessageIntent};
     use chrono::Utc;
-    
+
     #[test]
     fn test_agent_routing() {
         let mut router = AgentDialogRouter::new();
-        
+
         // Register agents
         router.register_agent(
             "deploy-agent".to_string(),
@@ -162,7 +200,7 @@ mod tests {
             "monitor-agent".to_string(),
             vec!["monitoring".to_string(), "alerts".to_string()],
         );
-        
+
         // Create participants
         let participants = vec![
             Participant {
@@ -180,7 +218,7 @@ mod tests {
                 metadata: HashMap::new(),
             },
         ];
-        
+
         // Create a deployment message
         let message = Message {
             content: MessageContent::Text("Deploy the new service".to_string()),
@@ -189,12 +227,12 @@ mod tests {
             sentiment: None,
             embeddings: None,
         };
-        
+
         // Route the message
         let context = crate::routing::context_sharing::SharedContext::new();
         let decision = router.route_message(&message, &participants, &context);
-        
+
         assert!(!decision.targets.is_empty());
         assert!(decision.confidence > 0.0);
     }
-}
\ No newline at end of file
+}
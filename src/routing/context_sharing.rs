@@ -1,22 +1,26 @@
 //! Context sharing and propagation for multi-agent dialogs
 
-use crate::value_objects::{ContextVariable, ContextScope};
+use crate::value_objects::{ContextScope, ContextVariable};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use chrono::{DateTime, Utc};
 
 /// Shared context between multiple agents
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SharedContext {
+    /// Dialog this context belongs to, if any
+    #[serde(default)]
+    pub dialog_id: Option<uuid::Uuid>,
+
     /// Variables in the shared context
     pub variables: HashMap<String, ContextVariable>,
-    
+
     /// Context metadata
     pub metadata: HashMap<String, serde_json::Value>,
-    
+
     /// Last update timestamp
     pub last_updated: DateTime<Utc>,
-    
+
     /// Version for conflict resolution
     pub version: u64,
 }
@@ -25,40 +29,51 @@ impl SharedContext {
     /// Create a new shared context
     pub fn new() -> Self {
         Self {
+            dialog_id: None,
             variables: HashMap::new(),
             metadata: HashMap::new(),
             last_updated: Utc::now(),
             version: 1,
         }
     }
-    
+
+    /// Create a new shared context for a dialog
+    pub fn for_dialog(dialog_id: uuid::Uuid) -> Self {
+        Self {
+            dialog_id: Some(dialog_id),
+            ..Self::new()
+        }
+    }
+
     /// Add or update a variable
     pub fn set_variable(&mut self, name: String, value: serde_json::Value, scope: ContextScope) {
-        self.variables.insert(name.clone(), ContextVariable {
-            name: name.clone(),
-            value,
-            scope,
-            set_at: Utc::now(),
-            expires_at: None,
-            source: uuid::Uuid::new_v4(),
-        });
+        self.variables.insert(
+            name.clone(),
+            ContextVariable {
+                name: name.clone(),
+                value,
+                scope,
+                set_at: Utc::now(),
+                expires_at: None,
+                source: uuid::Uuid::new_v4(),
+            },
+        );
         self.last_updated = Utc::now();
         self.version += 1;
     }
-    
+
     /// Get a variable value
     pub fn get_variable(&self, name: &str) -> Option<&serde_json::Value> {
         self.variables.get(name).map(|var| &var.value)
     }
-    
+
     /// Remove expired variables
     pub fn cleanup_expired(&mut self) {
         let now = Utc::now();
-        self.variables.retain(|_, var| {
-            var.expires_at.map(|exp| exp > now).unwrap_or(true)
-        });
+        self.variables
+            .retain(|_, var| var.expires_at.map(|exp| exp > now).unwrap_or(true));
     }
-    
+
     /// Merge another context into this one
     pub fn merge(&mut self, other: &SharedContext, strategy: &ContextMergeStrategy) {
         match strategy {
@@ -89,7 +104,7 @@ impl SharedContext {
                 merger(self, other);
             }
         }
-        
+
         self.last_updated = Utc::now();
         self.version += 1;
     }
@@ -119,16 +134,16 @@ pub enum ContextMergeStrategy {
 pub struct ContextPropagation {
     /// Whether to propagate global variables
     pub propagate_global: bool,
-    
+
     /// Whether to propagate session variables
     pub propagate_session: bool,
-    
+
     /// Whether to propagate turn variables
     pub propagate_turn: bool,
-    
+
     /// Specific variables to always propagate
     pub always_propagate: Vec<String>,
-    
+
     /// Specific variables to never propagate
     pub never_propagate: Vec<String>,
 }
@@ -155,7 +170,7 @@ impl ContextPropagation {
         if self.always_propagate.contains(&var.name) {
             return true;
         }
-        
+
         // Check scope-based rules
         match var.scope {
             ContextScope::Global => self.propagate_global,
@@ -165,17 +180,18 @@ impl ContextPropagation {
             ContextScope::Participant => true, // Propagate participant-scoped vars
         }
     }
-    
+
     /// Filter context based on propagation rules
     pub fn filter_context(&self, context: &SharedContext) -> SharedContext {
         let mut filtered = SharedContext::new();
-        
+
         for (name, var) in &context.variables {
             if self.should_propagate(var) {
                 filtered.variables.insert(name.clone(), var.clone());
             }
         }
-        
+
+        filtered.dialog_id = context.dialog_id;
         filtered.metadata = context.metadata.clone();
         filtered
     }
@@ -185,7 +201,7 @@ impl ContextPropagation {
 pub struct ContextSync {
     /// Local context version
     local_version: u64,
-    
+
     /// Known remote versions
     remote_versions: HashMap<String, u64>,
 }
@@ -197,7 +213,7 @@ impl ContextSync {
             remote_versions: HashMap::new(),
         }
     }
-    
+
     /// Check if sync is needed with a remote agent
     pub fn needs_sync(&self, agent_id: &str, remote_version: u64) -> bool {
         self.remote_versions
@@ -205,12 +221,12 @@ impl ContextSync {
             .map(|&v| v < remote_version)
             .unwrap_or(true)
     }
-    
+
     /// Update remote version after sync
     pub fn update_remote_version(&mut self, agent_id: String, version: u64) {
         self.remote_versions.insert(agent_id, version);
     }
-    
+
     /// Increment local version
     pub fn increment_local_version(&mut self) {
         self.local_version += 1;
@@ -221,33 +237,41 @@ impl ContextSync {
 mod tests {
     use super::*;
     use serde_json::json;
-    
+
     #[test]
     fn test_context_merge() {
         let mut ctx1 = SharedContext::new();
         ctx1.set_variable("var1".to_string(), json!("value1"), ContextScope::Global);
-        ctx1.set_variable("shared".to_string(), json!("old"), ContextScope::Session);
-        
+        ctx1.set_variable("shared".to_string(), json!("old"), ContextScope::Dialog);
+
         let mut ctx2 = SharedContext::new();
         ctx2.set_variable("var2".to_string(), json!("value2"), ContextScope::Global);
-        ctx2.set_variable("shared".to_string(), json!("new"), ContextScope::Session);
-        
+        ctx2.set_variable("shared".to_string(), json!("new"), ContextScope::Dialog);
+
         // Test TakeNewest strategy
         let mut merged = ctx1.clone();
         merged.merge(&ctx2, &ContextMergeStrategy::TakeNewest);
-        
+
         assert_eq!(merged.get_variable("var1"), Some(&json!("value1")));
         assert_eq!(merged.get_variable("var2"), Some(&json!("value2")));
         assert_eq!(merged.get_variable("shared"), Some(&json!("new")));
     }
-    
+
     #[test]
     fn test_context_propagation() {
         let mut context = SharedContext::new();
-        context.set_variable("global_var".to_string(), json!("global"), ContextScope::Global);
-        context.set_variable("session_var".to_string(), json!("session"), ContextScope::Session);
+        context.set_variable(
+            "global_var".to_string(),
+            json!("global"),
+            ContextScope::Global,
+        );
+        context.set_variable(
+            "session_var".to_string(),
+            json!("session"),
+            ContextScope::Dialog,
+        );
         context.set_variable("turn_var".to_string(), json!("turn"), ContextScope::Turn);
-        
+
         let prop_rules = ContextPropagation {
             propagate_global: true,
             propagate_session: true,
@@ -255,11 +279,11 @@ mod tests {
             always_propagate: vec![],
             never_propagate: vec![],
         };
-        
+
         let filtered = prop_rules.filter_context(&context);
-        
+
         assert!(filtered.variables.contains_key("global_var"));
         assert!(filtered.variables.contains_key("session_var"));
         assert!(!filtered.variables.contains_key("turn_var"));
     }
-}
\ No newline at end of file
+}
@@ -5,7 +5,9 @@ pub mod channel;
 pub mod context_sharing;
 pub mod strategies;
 
-pub use agent_router::{AgentDialogRouter, RoutingDecision};
-pub use channel::{DialogChannel, ChannelId, ChannelType};
-pub use context_sharing::{ContextPropagation, SharedContext, ContextMergeStrategy};
-pub use strategies::{RoutingStrategy, BroadcastStrategy, CapabilityBasedStrategy, RoundRobinStrategy};
\ No newline at end of file
+pub use agent_router::{AgentDialogRouter, RoutingDecision, RoutingExplanation};
+pub use channel::{ChannelId, ChannelType, DialogChannel};
+pub use context_sharing::{ContextMergeStrategy, ContextPropagation, SharedContext};
+pub use strategies::{
+    BroadcastStrategy, CapabilityBasedStrategy, RoundRobinStrategy, RoutingStrategy,
+};
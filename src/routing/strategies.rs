@@ -1,12 +1,12 @@
 //! Routing strategies for agent dialog distribution
 
-use crate::value_objects::{Message, Participant, MessageIntent};
-use crate::routing::{RoutingDecision, SharedContext};
+use crate::routing::{RoutingDecision, RoutingExplanation, SharedContext};
+use crate::value_objects::{Message, MessageIntent, Participant};
 // Use a simple string ID instead of importing from agent coordination
 type AgentId = String;
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::Mutex;
+use uuid::Uuid;
 
 /// Trait for dialog routing strategies
 pub trait RoutingStrategy: Send + Sync {
@@ -18,14 +18,17 @@ pub trait RoutingStrategy: Send + Sync {
         context: &SharedContext,
         agent_capabilities: &HashMap<AgentId, Vec<String>>,
     ) -> Option<RoutingDecision>;
-    
+
     /// Get the priority of this strategy (higher = preferred)
     fn priority(&self) -> f32 {
         1.0
     }
-    
+
     /// Get the name of this strategy
     fn name(&self) -> &str;
+
+    /// Drop any state kept for a dialog, e.g. once it has ended
+    fn forget(&self, _dialog_id: Uuid) {}
 }
 
 /// Broadcast strategy - sends to all agents
@@ -47,27 +50,25 @@ impl RoutingStrategy for BroadcastStrategy {
         _context: &SharedContext,
         _agent_capabilities: &HashMap<AgentId, Vec<String>>,
     ) -> Option<RoutingDecision> {
-        let targets: Vec<AgentId> = participants
-            .iter()
-            .map(|p| p.id.to_string())
-            .collect();
-        
+        let targets: Vec<AgentId> = participants.iter().map(|p| p.id.to_string()).collect();
+
         if targets.is_empty() {
             return None;
         }
-        
+
         Some(RoutingDecision {
             targets,
             strategy: self.name().to_string(),
             confidence: 1.0,
             metadata: HashMap::new(),
+            explanation: None,
         })
     }
-    
+
     fn priority(&self) -> f32 {
         self.priority
     }
-    
+
     fn name(&self) -> &str {
         "broadcast"
     }
@@ -82,18 +83,18 @@ impl CapabilityBasedStrategy {
     pub fn new() -> Self {
         Self { priority: 2.0 }
     }
-    
+
     /// Extract required capabilities from message
     fn extract_required_capabilities(&self, message: &Message) -> Vec<String> {
         let mut capabilities = Vec::new();
-        
+
         // Analyze message intent
         match &message.intent {
             Some(MessageIntent::Command) => {
                 // Look for keywords in message content
                 if let crate::value_objects::MessageContent::Text(text) = &message.content {
                     let text_lower = text.to_lowercase();
-                    
+
                     if text_lower.contains("deploy") {
                         capabilities.push("deployment".to_string());
                     }
@@ -113,11 +114,11 @@ impl CapabilityBasedStrategy {
             }
             _ => {}
         }
-        
+
         // Check for explicit capabilities in message content
         // (metadata field doesn't exist in this Message struct)
         // In a real implementation, we could extract capabilities from structured content
-        
+
         capabilities
     }
 }
@@ -131,73 +132,104 @@ impl RoutingStrategy for CapabilityBasedStrategy {
         agent_capabilities: &HashMap<AgentId, Vec<String>>,
     ) -> Option<RoutingDecision> {
         let required_capabilities = self.extract_required_capabilities(message);
-        
+
         if required_capabilities.is_empty() {
             return None;
         }
-        
+
         let mut targets = Vec::new();
         let mut capability_scores = HashMap::new();
-        
+
         for participant in participants {
             let agent_id = participant.id.to_string();
-            
+
             if let Some(capabilities) = agent_capabilities.get(&agent_id) {
                 let mut score = 0.0;
                 let mut matched = 0;
-                
+
                 for required in &required_capabilities {
                     if capabilities.contains(required) {
                         matched += 1;
                         score += 1.0;
                     }
                 }
-                
+
                 if matched > 0 {
                     targets.push(agent_id.clone());
-                    capability_scores.insert(agent_id.to_string(), score / required_capabilities.len() as f32);
+                    capability_scores.insert(
+                        agent_id.to_string(),
+                        score / required_capabilities.len() as f32,
+                    );
                 }
             }
         }
-        
+
         if targets.is_empty() {
             return None;
         }
-        
-        let avg_score: f32 = capability_scores.values().sum::<f32>() / capability_scores.len() as f32;
-        
+
+        let avg_score: f32 =
+            capability_scores.values().sum::<f32>() / capability_scores.len() as f32;
+
+        let matched_capabilities: Vec<String> = required_capabilities
+            .iter()
+            .filter(|required| {
+                targets.iter().any(|agent_id| {
+                    agent_capabilities
+                        .get(agent_id)
+                        .is_some_and(|capabilities| capabilities.contains(*required))
+                })
+            })
+            .cloned()
+            .collect();
+
         Some(RoutingDecision {
             targets,
             strategy: self.name().to_string(),
             confidence: avg_score,
             metadata: {
                 let mut meta = HashMap::new();
-                meta.insert("required_capabilities".to_string(), serde_json::json!(required_capabilities));
-                meta.insert("capability_scores".to_string(), serde_json::json!(capability_scores));
+                meta.insert(
+                    "required_capabilities".to_string(),
+                    serde_json::json!(required_capabilities),
+                );
+                meta.insert(
+                    "capability_scores".to_string(),
+                    serde_json::json!(capability_scores),
+                );
                 meta
             },
+            explanation: Some(RoutingExplanation {
+                matched_capabilities,
+                per_agent_scores: capability_scores,
+                required: required_capabilities,
+            }),
         })
     }
-    
+
     fn priority(&self) -> f32 {
         self.priority
     }
-    
+
     fn name(&self) -> &str {
         "capability_based"
     }
 }
 
 /// Round-robin routing strategy
+///
+/// Each dialog (identified by `SharedContext::dialog_id`) rotates through its
+/// participants independently; contexts without a dialog share one rotation.
+/// A dialog's rotation is kept until [`RoutingStrategy::forget`] drops it.
 pub struct RoundRobinStrategy {
-    last_index: Arc<RwLock<usize>>,
+    next_index: Mutex<HashMap<Option<Uuid>, usize>>,
     priority: f32,
 }
 
 impl RoundRobinStrategy {
     pub fn new() -> Self {
         Self {
-            last_index: Arc::new(RwLock::new(0)),
+            next_index: Mutex::new(HashMap::new()),
             priority: 1.0,
         }
     }
@@ -208,43 +240,51 @@ impl RoutingStrategy for RoundRobinStrategy {
         &self,
         _message: &Message,
         participants: &[&Participant],
-        _context: &SharedContext,
+        context: &SharedContext,
         _agent_capabilities: &HashMap<AgentId, Vec<String>>,
     ) -> Option<RoutingDecision> {
         if participants.is_empty() {
             return None;
         }
-        
-        let last_index = self.last_index.clone();
-        let participant_count = participants.len();
-        
-        // Use blocking read since this is synchronous
+
+        // A std mutex is enough: the lock is never held across an await
         let current_index = {
-            let mut index = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(last_index.write())
-            });
-            *index = (*index + 1) % participant_count;
-            *index
+            let mut next_index = self.next_index.lock().unwrap_or_else(|e| e.into_inner());
+            let next = next_index.entry(context.dialog_id).or_default();
+            let current = *next % participants.len();
+            *next = current + 1;
+            current
         };
-        
+
         let target = participants[current_index].id.to_string();
-        
+
         Some(RoutingDecision {
             targets: vec![target],
             strategy: self.name().to_string(),
             confidence: 1.0,
             metadata: {
                 let mut meta = HashMap::new();
-                meta.insert("round_robin_index".to_string(), serde_json::json!(current_index));
+                meta.insert(
+                    "round_robin_index".to_string(),
+                    serde_json::json!(current_index),
+                );
                 meta
             },
+            explanation: None,
         })
     }
-    
+
+    fn forget(&self, dialog_id: Uuid) {
+        self.next_index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&Some(dialog_id));
+    }
+
     fn priority(&self) -> f32 {
         self.priority
     }
-    
+
     fn name(&self) -> &str {
         "round_robin"
     }
@@ -275,13 +315,13 @@ impl RoutingStrategy for PriorityBasedStrategy {
             Some(MessageIntent::Feedback) => 3,
             _ => 7,
         };
-        
+
         let mut targets = Vec::new();
         let mut selected_priorities = Vec::new();
-        
+
         for participant in participants {
             let agent_id = participant.id.to_string();
-            
+
             if let Some(&priority) = self.agent_priorities.get(&agent_id) {
                 if priority <= priority_threshold {
                     targets.push(agent_id);
@@ -289,32 +329,39 @@ impl RoutingStrategy for PriorityBasedStrategy {
                 }
             }
         }
-        
+
         if targets.is_empty() {
             return None;
         }
-        
-        let avg_priority: f32 = selected_priorities.iter().map(|&p| p as f32).sum::<f32>() 
+
+        let avg_priority: f32 = selected_priorities.iter().map(|&p| p as f32).sum::<f32>()
             / selected_priorities.len() as f32;
         let confidence = 1.0 - (avg_priority / 10.0); // Higher priority = higher confidence
-        
+
         Some(RoutingDecision {
             targets,
             strategy: self.name().to_string(),
             confidence,
             metadata: {
                 let mut meta = HashMap::new();
-                meta.insert("priority_threshold".to_string(), serde_json::json!(priority_threshold));
-                meta.insert("average_priority".to_string(), serde_json::json!(avg_priority));
+                meta.insert(
+                    "priority_threshold".to_string(),
+                    serde_json::json!(priority_threshold),
+                );
+                meta.insert(
+                    "average_priority".to_string(),
+                    serde_json::json!(avg_priority),
+                );
                 meta
             },
+            explanation: None,
         })
     }
-    
+
     fn priority(&self) -> f32 {
         1.5
     }
-    
+
     fn name(&self) -> &str {
         "priority_based"
     }
@@ -324,9 +371,9 @@ impl RoutingStrategy for PriorityBasedStrategy {
 mod tests {
     use super::*;
     use crate::value_objects::{MessageContent, ParticipantRole, ParticipantType};
-    use uuid::Uuid;
     use chrono::Utc;
-    
+    use uuid::Uuid;
+
     fn create_test_participant(name: &str) -> Participant {
         Participant {
             id: Uuid::new_v4(),
@@ -336,7 +383,7 @@ mod tests {
             metadata: HashMap::new(),
         }
     }
-    
+
     fn create_test_message(content: &str, intent: MessageIntent) -> Message {
         Message {
             content: MessageContent::Text(content.to_string()),
@@ -346,7 +393,7 @@ mod tests {
             embeddings: None,
         }
     }
-    
+
     #[test]
     fn test_broadcast_strategy() {
         let strategy = BroadcastStrategy::new();
@@ -355,19 +402,19 @@ mod tests {
             create_test_participant("agent2"),
         ];
         let participant_refs: Vec<&Participant> = participants.iter().collect();
-        
+
         let message = create_test_message("Hello", MessageIntent::Statement);
         let context = SharedContext::new();
         let capabilities = HashMap::new();
-        
+
         let decision = strategy.route(&message, &participant_refs, &context, &capabilities);
-        
+
         assert!(decision.is_some());
         let decision = decision.unwrap();
         assert_eq!(decision.targets.len(), 2);
         assert_eq!(decision.strategy, "broadcast");
     }
-    
+
     #[test]
     fn test_capability_based_strategy() {
         let strategy = CapabilityBasedStrategy::new();
@@ -376,7 +423,7 @@ mod tests {
             create_test_participant("monitor-agent"),
         ];
         let participant_refs: Vec<&Participant> = participants.iter().collect();
-        
+
         let message = create_test_message("Deploy the new service", MessageIntent::Command);
         let context = SharedContext::new();
         let mut capabilities = HashMap::new();
@@ -388,12 +435,100 @@ mod tests {
             participants[1].id.to_string(),
             vec!["monitoring".to_string()],
         );
-        
+
         let decision = strategy.route(&message, &participant_refs, &context, &capabilities);
-        
+
         assert!(decision.is_some());
         let decision = decision.unwrap();
         assert_eq!(decision.targets.len(), 1); // Only deploy-agent should be selected
         assert_eq!(decision.strategy, "capability_based");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_capability_based_strategy_explains_match() {
+        let strategy = CapabilityBasedStrategy::new();
+        let participants = [
+            create_test_participant("deploy-agent"),
+            create_test_participant("monitor-agent"),
+        ];
+        let participant_refs: Vec<&Participant> = participants.iter().collect();
+
+        let message = create_test_message(
+            "Deploy and configure the new service",
+            MessageIntent::Command,
+        );
+        let context = SharedContext::new();
+        let deploy_agent = participants[0].id.to_string();
+        let mut capabilities = HashMap::new();
+        capabilities.insert(deploy_agent.clone(), vec!["deployment".to_string()]);
+        capabilities.insert(
+            participants[1].id.to_string(),
+            vec!["monitoring".to_string()],
+        );
+
+        let decision = strategy
+            .route(&message, &participant_refs, &context, &capabilities)
+            .unwrap();
+        let explanation = decision
+            .explanation
+            .expect("capability routing should explain its match");
+
+        assert_eq!(
+            explanation.required,
+            vec!["deployment".to_string(), "configuration".to_string()]
+        );
+        assert_eq!(
+            explanation.matched_capabilities,
+            vec!["deployment".to_string()]
+        );
+        assert_eq!(
+            explanation.per_agent_scores,
+            HashMap::from([(deploy_agent, 0.5)])
+        );
+    }
+
+    #[test]
+    fn test_round_robin_rotates_per_dialog() {
+        let strategy = RoundRobinStrategy::new();
+        let participants = [
+            create_test_participant("agent1"),
+            create_test_participant("agent2"),
+            create_test_participant("agent3"),
+        ];
+        let participant_refs: Vec<&Participant> = participants.iter().collect();
+        let message = create_test_message("Hello", MessageIntent::Statement);
+        let capabilities = HashMap::new();
+        let first = SharedContext::for_dialog(Uuid::new_v4());
+        let second = SharedContext::for_dialog(Uuid::new_v4());
+
+        let route = |context: &SharedContext| {
+            strategy
+                .route(&message, &participant_refs, context, &capabilities)
+                .unwrap()
+                .targets[0]
+                .clone()
+        };
+        let id = |index: usize| participants[index].id.to_string();
+
+        // Interleaved routing: each dialog starts at the first agent and rotates on its own
+        assert_eq!(route(&first), id(0));
+        assert_eq!(route(&second), id(0));
+        assert_eq!(route(&first), id(1));
+        assert_eq!(route(&first), id(2));
+        assert_eq!(route(&second), id(1));
+        assert_eq!(route(&first), id(0));
+
+        // A forgotten dialog starts over and no longer holds an entry
+        let first_id = first.dialog_id.unwrap();
+        strategy.forget(first_id);
+        assert!(
+            !strategy
+                .next_index
+                .lock()
+                .unwrap()
+                .contains_key(&Some(first_id))
+        );
+        assert_eq!(route(&second), id(2));
+        assert_eq!(route(&first), id(0));
+    }
+}
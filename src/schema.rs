@@ -0,0 +1,606 @@
+//! JSON Schemas for dialog event payloads
+//!
+//! Consumers that receive events over NATS without linking this crate can use
+//! these schemas to validate payloads. Schemas are keyed by the event's
+//! `event_type()` name and describe the JSON produced by serializing the event
+//! struct (not the `DialogDomainEvent` wrapper).
+
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+
+/// JSON Schema dialect used by every schema in this module
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Every event type with a schema, in `DialogDomainEvent` variant order
+pub(crate) const EVENT_TYPES: [&str; 32] = [
+    "DialogStarted",
+    "DialogEnded",
+    "DialogPaused",
+    "DialogResumed",
+    "TurnAdded",
+    "ParticipantAdded",
+    "ParticipantRemoved",
+    "ContextSwitched",
+    "ContextUpdated",
+    "ContextVariableAdded",
+    "DialogMetadataSet",
+    "TopicCompleted",
+    "TurnPinned",
+    "TurnUnpinned",
+    "TurnRetracted",
+    "TurnsArchived",
+    "DialogLocked",
+    "DialogUnlocked",
+    "TopicsRelated",
+    "TopicsUnrelated",
+    "TurnFlagged",
+    "ResolutionSet",
+    "EmbeddingAttached",
+    "TurnScheduled",
+    "MetricsUpdated",
+    "ContextStateChanged",
+    "PhaseChanged",
+    "DialogAbandoned",
+    "ResolutionTurnMarked",
+    "ContextVariablesExpired",
+    "TurnEdited",
+    "ScheduledTurnRejected",
+];
+
+/// Get the JSON Schema for an event type, e.g. `"TurnAdded"`
+pub fn event_schema(event_type: &str) -> Option<Value> {
+    let mut properties = match event_type {
+        "DialogStarted" => vec![
+            ("dialog_id", uuid()),
+            ("dialog_type", dialog_type()),
+            ("primary_participant", participant()),
+            ("started_at", timestamp()),
+        ],
+        "DialogEnded" => vec![
+            ("dialog_id", uuid()),
+            ("ended_at", timestamp()),
+            ("reason", nullable(end_reason())),
+            ("final_metrics", conversation_metrics()),
+        ],
+        "DialogPaused" => vec![
+            ("dialog_id", uuid()),
+            ("paused_at", timestamp()),
+            ("context_snapshot", map(context_variable())),
+        ],
+        "DialogResumed" => vec![
+            ("dialog_id", uuid()),
+            ("resumed_at", timestamp()),
+            ("restored_variables", nullable(array(string()))),
+        ],
+        "TurnAdded" => vec![
+            ("dialog_id", uuid()),
+            ("turn", turn()),
+            ("turn_number", unsigned()),
+        ],
+        "ParticipantAdded" => vec![
+            ("dialog_id", uuid()),
+            ("participant", participant()),
+            ("added_at", timestamp()),
+        ],
+        "ParticipantRemoved" => vec![
+            ("dialog_id", uuid()),
+            ("participant_id", uuid()),
+            ("removed_at", timestamp()),
+            ("reason", nullable(string())),
+        ],
+        "ContextSwitched" => vec![
+            ("dialog_id", uuid()),
+            ("previous_topic", nullable(uuid())),
+            ("new_topic", topic()),
+            ("switched_at", timestamp()),
+        ],
+        "ContextUpdated" => vec![
+            ("dialog_id", uuid()),
+            ("updated_variables", map(any())),
+            ("updated_at", timestamp()),
+        ],
+        "ContextVariableAdded" => vec![
+            ("dialog_id", uuid()),
+            ("variable", context_variable()),
+            ("added_at", timestamp()),
+        ],
+        "DialogMetadataSet" => vec![
+            ("dialog_id", uuid()),
+            ("key", string()),
+            ("value", any()),
+            ("set_at", timestamp()),
+        ],
+        "TopicCompleted" => vec![
+            ("dialog_id", uuid()),
+            ("topic_id", uuid()),
+            ("completed_at", timestamp()),
+            ("resolution", nullable(string())),
+        ],
+        "TurnPinned" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("pinned_at", timestamp()),
+        ],
+        "TurnUnpinned" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("unpinned_at", timestamp()),
+        ],
+        "TurnRetracted" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("retracted_at", timestamp()),
+            ("reason", nullable(string())),
+        ],
+        "TurnsArchived" => vec![
+            ("dialog_id", uuid()),
+            ("turn_ids", array(uuid())),
+            ("archived_at", timestamp()),
+        ],
+        "DialogLocked" => vec![
+            ("dialog_id", uuid()),
+            ("locked_at", timestamp()),
+            ("reason", nullable(string())),
+        ],
+        "DialogUnlocked" => vec![("dialog_id", uuid()), ("unlocked_at", timestamp())],
+        "TopicsRelated" => vec![
+            ("dialog_id", uuid()),
+            ("topic_a", uuid()),
+            ("topic_b", uuid()),
+            ("related_at", timestamp()),
+        ],
+        "TopicsUnrelated" => vec![
+            ("dialog_id", uuid()),
+            ("topic_a", uuid()),
+            ("topic_b", uuid()),
+            ("unrelated_at", timestamp()),
+        ],
+        "TurnFlagged" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("reason", string()),
+            ("flagged_at", timestamp()),
+        ],
+        "ResolutionSet" => vec![
+            ("dialog_id", uuid()),
+            (
+                "resolution",
+                string_enum(&["Resolved", "Unresolved", "Escalated", "NoActionNeeded"]),
+            ),
+            ("set_at", timestamp()),
+        ],
+        "EmbeddingAttached" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("embeddings", array(number())),
+            ("attached_at", timestamp()),
+        ],
+        "TurnScheduled" => vec![
+            ("dialog_id", uuid()),
+            ("turn", turn()),
+            ("deliver_at", timestamp()),
+            ("scheduled_at", timestamp()),
+        ],
+        "MetricsUpdated" => vec![
+            ("dialog_id", uuid()),
+            ("metrics", conversation_metrics()),
+            ("delta", metrics_delta()),
+            ("updated_at", timestamp()),
+        ],
+        "ContextStateChanged" => vec![
+            ("dialog_id", uuid()),
+            ("previous_state", context_state()),
+            ("new_state", context_state()),
+            ("changed_at", timestamp()),
+        ],
+        "PhaseChanged" => vec![
+            ("dialog_id", uuid()),
+            ("previous_phase", nullable(conversation_phase())),
+            ("new_phase", conversation_phase()),
+            ("changed_at", timestamp()),
+        ],
+        "DialogAbandoned" => vec![
+            ("dialog_id", uuid()),
+            ("abandoned_at", timestamp()),
+            ("reason", nullable(string())),
+        ],
+        "ResolutionTurnMarked" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("marked_at", timestamp()),
+        ],
+        "ContextVariablesExpired" => vec![
+            ("dialog_id", uuid()),
+            ("names", array(string())),
+            ("expired_at", timestamp()),
+        ],
+        "TurnEdited" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("new_message", message()),
+            ("edited_at", timestamp()),
+        ],
+        "ScheduledTurnRejected" => vec![
+            ("dialog_id", uuid()),
+            ("turn_id", uuid()),
+            ("reason", string()),
+            ("rejected_at", timestamp()),
+        ],
+        _ => return None,
+    };
+    properties.insert(0, ("event_id", uuid()));
+
+    let mut schema = object(properties);
+    schema["$schema"] = json!(SCHEMA_DIALECT);
+    schema["title"] = json!(event_type);
+    Some(schema)
+}
+
+/// Get the schema of every event type, keyed by event type
+pub fn all_schemas() -> HashMap<&'static str, Value> {
+    EVENT_TYPES
+        .iter()
+        .filter_map(|event_type| event_schema(event_type).map(|schema| (*event_type, schema)))
+        .collect()
+}
+
+fn uuid() -> Value {
+    json!({ "type": "string", "format": "uuid" })
+}
+
+fn timestamp() -> Value {
+    json!({ "type": "string", "format": "date-time" })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn number() -> Value {
+    json!({ "type": "number" })
+}
+
+fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+fn unsigned() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn integer() -> Value {
+    json!({ "type": "integer" })
+}
+
+fn any() -> Value {
+    json!({})
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "anyOf": [schema, { "type": "null" }] })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn map(values: Value) -> Value {
+    json!({ "type": "object", "additionalProperties": values })
+}
+
+fn string_enum(variants: &[&str]) -> Value {
+    json!({ "type": "string", "enum": variants })
+}
+
+/// Object whose listed properties are all required
+fn object(properties: Vec<(&str, Value)>) -> Value {
+    let required: Vec<&str> = properties.iter().map(|(name, _)| *name).collect();
+    let properties: Map<String, Value> = properties
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+/// Externally tagged enum variant with data, as serde writes it
+fn tagged(variant: &str, schema: Value) -> Value {
+    let mut tagged = object(vec![(variant, schema)]);
+    tagged["additionalProperties"] = json!(false);
+    tagged
+}
+
+fn dialog_type() -> Value {
+    string_enum(&["Direct", "Group", "Support", "Task", "Social", "System"])
+}
+
+fn participant() -> Value {
+    object(vec![
+        ("id", uuid()),
+        (
+            "participant_type",
+            string_enum(&["Human", "AIAgent", "System", "External"]),
+        ),
+        (
+            "role",
+            string_enum(&["Primary", "Assistant", "Observer", "Moderator"]),
+        ),
+        ("name", string()),
+        ("metadata", map(any())),
+    ])
+}
+
+fn turn() -> Value {
+    object(vec![
+        ("turn_id", uuid()),
+        ("turn_number", unsigned()),
+        ("participant_id", uuid()),
+        ("message", message()),
+        ("timestamp", timestamp()),
+        (
+            "metadata",
+            object(vec![
+                (
+                    "turn_type",
+                    string_enum(&[
+                        "UserQuery",
+                        "AgentResponse",
+                        "SystemMessage",
+                        "Clarification",
+                        "Feedback",
+                    ]),
+                ),
+                ("confidence", nullable(number())),
+                ("processing_time_ms", nullable(unsigned())),
+                ("references", array(uuid())),
+                ("properties", map(any())),
+            ]),
+        ),
+    ])
+}
+
+fn message() -> Value {
+    let segment = json!({
+        "oneOf": [
+            tagged("Text", string()),
+            tagged("Code", object(vec![("lang", string()), ("code", string())])),
+            tagged("Json", any()),
+        ]
+    });
+    let content = json!({
+        "oneOf": [
+            tagged("Text", string()),
+            tagged("Structured", any()),
+            tagged(
+                "Multimodal",
+                object(vec![("text", nullable(string())), ("data", map(any()))]),
+            ),
+            tagged("Segmented", array(segment)),
+        ]
+    });
+    let intent = string_enum(&[
+        "Question",
+        "Answer",
+        "Statement",
+        "Command",
+        "Acknowledgment",
+        "Clarification",
+        "Feedback",
+        "Social",
+    ]);
+
+    object(vec![
+        ("content", content),
+        ("intent", nullable(intent)),
+        ("language", string()),
+        ("sentiment", nullable(number())),
+        ("embeddings", nullable(array(number()))),
+    ])
+}
+
+fn topic() -> Value {
+    object(vec![
+        ("id", uuid()),
+        ("name", string()),
+        (
+            "status",
+            string_enum(&["Active", "Paused", "Completed", "Abandoned"]),
+        ),
+        (
+            "relevance",
+            object(vec![
+                ("score", number()),
+                ("last_updated", timestamp()),
+                ("decay_rate", number()),
+            ]),
+        ),
+        ("introduced_at", timestamp()),
+        ("related_topics", array(uuid())),
+        ("keywords", array(string())),
+        ("embedding", nullable(array(number()))),
+        (
+            "subtopics",
+            array(object(vec![("topic_id", uuid()), ("required", boolean())])),
+        ),
+    ])
+}
+
+fn context_variable() -> Value {
+    object(vec![
+        ("name", string()),
+        ("value", any()),
+        (
+            "scope",
+            string_enum(&["Turn", "Topic", "Dialog", "Participant", "Global"]),
+        ),
+        ("set_at", timestamp()),
+        ("expires_at", nullable(timestamp())),
+        ("source", uuid()),
+    ])
+}
+
+/// End reasons are written as objects; plain strings from older events are still accepted
+fn end_reason() -> Value {
+    let code = string_enum(&[
+        "Resolved",
+        "Abandoned",
+        "Escalated",
+        "Timeout",
+        "UserLeft",
+        "Error",
+        "Other",
+    ]);
+    json!({
+        "anyOf": [
+            object(vec![("code", code), ("detail", nullable(string()))]),
+            string(),
+        ]
+    })
+}
+
+fn conversation_metrics() -> Value {
+    object(vec![
+        ("turn_count", unsigned()),
+        ("avg_response_time_ms", number()),
+        ("topic_switches", unsigned()),
+        ("clarification_count", unsigned()),
+        ("sentiment_trend", number()),
+        ("coherence_score", number()),
+    ])
+}
+
+fn context_state() -> Value {
+    string_enum(&["Normal", "AwaitingClarification", "Processing", "Error"])
+}
+
+fn conversation_phase() -> Value {
+    string_enum(&["Greeting", "Triage", "Resolution", "Closing"])
+}
+
+fn metrics_delta() -> Value {
+    object(vec![
+        ("turn_count", integer()),
+        ("avg_response_time_ms", number()),
+        ("topic_switches", integer()),
+        ("clarification_count", integer()),
+        ("sentiment_trend", number()),
+        ("coherence_score", number()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::fixtures::all_variants;
+    use crate::events::{DialogDomainEvent, TurnAdded};
+    use crate::value_objects::{Message, Turn, TurnType};
+    use cim_domain::DomainEvent;
+    use uuid::Uuid;
+
+    /// Check `value` against the subset of JSON Schema these schemas use
+    fn validates(schema: &Value, value: &Value) -> bool {
+        if let Some(options) = schema.get("anyOf").or_else(|| schema.get("oneOf")) {
+            return options
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|option| validates(option, value));
+        }
+        if let Some(variants) = schema.get("enum")
+            && !variants.as_array().unwrap().contains(value)
+        {
+            return false;
+        }
+
+        match schema.get("type").and_then(Value::as_str) {
+            None => true,
+            Some("null") => value.is_null(),
+            Some("string") => value.is_string(),
+            Some("number") => value.is_number(),
+            Some("boolean") => value.is_boolean(),
+            Some("integer") => value.is_u64() || value.is_i64(),
+            Some("array") => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(|item| validates(&schema["items"], item))),
+            Some("object") => {
+                let Some(fields) = value.as_object() else {
+                    return false;
+                };
+                let required = schema["required"].as_array().cloned().unwrap_or_default();
+                if !required
+                    .iter()
+                    .all(|name| fields.contains_key(name.as_str().unwrap()))
+                {
+                    return false;
+                }
+                fields.iter().all(|(name, field)| {
+                    match schema.get("properties").and_then(|p| p.get(name)) {
+                        Some(property) => validates(property, field),
+                        None => match schema.get("additionalProperties") {
+                            Some(Value::Bool(allowed)) => *allowed,
+                            Some(values) => validates(values, field),
+                            None => true,
+                        },
+                    }
+                })
+            }
+            Some(other) => panic!("Unsupported schema type {other}"),
+        }
+    }
+
+    #[test]
+    fn test_turn_added_payload_matches_schema() {
+        let mut turn = Turn::new(
+            1,
+            Uuid::new_v4(),
+            Message::text("Hello"),
+            TurnType::UserQuery,
+        );
+        turn.metadata.references.push(Uuid::new_v4());
+        let event = TurnAdded {
+            event_id: Uuid::new_v4(),
+            dialog_id: Uuid::new_v4(),
+            turn,
+            turn_number: 1,
+        };
+
+        let schema = event_schema(event.event_type()).unwrap();
+        assert_eq!(schema["title"], "TurnAdded");
+
+        let mut payload = serde_json::to_value(&event).unwrap();
+        assert!(validates(&schema, &payload));
+
+        // A payload missing a field or with a wrong type does not validate
+        payload["turn"]["message"]["content"] = json!({ "Audio": "..." });
+        assert!(!validates(&schema, &payload));
+        payload.as_object_mut().unwrap().remove("turn");
+        assert!(!validates(&schema, &payload));
+
+        // Every event type has a schema, and unknown types have none
+        let schemas = all_schemas();
+        assert_eq!(schemas.len(), EVENT_TYPES.len());
+        assert!(schemas.contains_key(DialogDomainEvent::TurnAdded(event).event_type()));
+        assert!(event_schema("NotAnEvent").is_none());
+    }
+
+    #[test]
+    fn test_every_variant_matches_its_schema() {
+        let events = all_variants();
+        for event in &events {
+            let schema = event_schema(event.event_type())
+                .unwrap_or_else(|| panic!("{} has no schema", event.event_type()));
+            let payload = serde_json::to_value(event).unwrap();
+            let (_, fields) = payload.as_object().unwrap().iter().next().unwrap();
+            assert!(
+                validates(&schema, fields),
+                "{} does not match its schema",
+                event.event_type()
+            );
+        }
+
+        let mut listed: Vec<&str> = events.iter().map(|e| e.event_type()).collect();
+        let mut known = EVENT_TYPES.to_vec();
+        listed.sort();
+        known.sort();
+        assert_eq!(listed, known);
+    }
+}
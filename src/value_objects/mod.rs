@@ -37,6 +37,19 @@ pub enum TurnType {
     Feedback,
 }
 
+/// Turn metadata property set on flagged turns (holds the flag reason)
+pub const FLAGGED_PROPERTY: &str = "flagged";
+
+/// Turn metadata property recording how many milliseconds a turn's timestamp
+/// preceded the previous turn's when it was added
+pub const CLOCK_SKEW_PROPERTY: &str = "clock_skew_ms";
+
+/// Turn metadata property naming the conversation phase the turn belongs to
+pub const PHASE_PROPERTY: &str = "phase";
+
+/// Field of structured or multimodal content naming its template or format
+pub const FORMAT_FIELD: &str = "format";
+
 /// Metadata associated with a turn
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TurnMetadata {
@@ -120,6 +133,30 @@ pub enum MessageContent {
         text: Option<String>,
         data: HashMap<String, serde_json::Value>,
     },
+    /// One response made of ordered parts, rendered separately
+    Segmented(Vec<MessageSegment>),
+}
+
+/// Part of a segmented message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MessageSegment {
+    /// Prose
+    Text(String),
+    /// Source code in a language such as `rust` (may be empty)
+    Code { lang: String, code: String },
+    /// Structured data
+    Json(serde_json::Value),
+}
+
+impl MessageSegment {
+    /// Searchable text of the segment
+    fn searchable(&self) -> String {
+        match self {
+            MessageSegment::Text(text) => text.clone(),
+            MessageSegment::Code { code, .. } => code.clone(),
+            MessageSegment::Json(value) => value.to_string(),
+        }
+    }
 }
 
 /// Intent classification for messages
@@ -162,6 +199,17 @@ pub struct Topic {
     pub keywords: Vec<String>,
     /// Conceptual space embedding
     pub embedding: Option<Vec<f32>>,
+    /// Subtopics of this topic
+    #[serde(default)]
+    pub subtopics: Vec<SubtopicLink>,
+}
+
+/// Link from a topic to one of its subtopics
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubtopicLink {
+    pub topic_id: Uuid,
+    /// Whether the subtopic must be completed before its parent
+    pub required: bool,
 }
 
 /// Status of a topic
@@ -205,6 +253,13 @@ pub struct ContextVariable {
     pub source: Uuid,
 }
 
+impl ContextVariable {
+    /// Whether the variable has expired by `now`
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
 /// Scope of a context variable
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ContextScope {
@@ -220,6 +275,96 @@ pub enum ContextScope {
     Global,
 }
 
+/// Categorised reason for ending a dialog
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum EndReasonCode {
+    /// The participant's goal was met
+    Resolved,
+    /// Dropped without resolution
+    Abandoned,
+    /// Handed off to another channel or agent
+    Escalated,
+    /// Ended due to inactivity
+    Timeout,
+    /// The user left the conversation
+    UserLeft,
+    /// Ended by a failure
+    Error,
+    /// Anything else, including legacy free-text reasons
+    Other,
+}
+
+/// Why a dialog ended
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "EndReasonRepr")]
+pub struct EndReason {
+    /// Reason category
+    pub code: EndReasonCode,
+    /// Optional free-text detail
+    pub detail: Option<String>,
+}
+
+/// Accepts both the structured form and the legacy plain-string reason
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EndReasonRepr {
+    Structured(EndReasonFields),
+    Legacy(String),
+}
+
+#[derive(Deserialize)]
+struct EndReasonFields {
+    code: EndReasonCode,
+    detail: Option<String>,
+}
+
+impl From<EndReasonRepr> for EndReason {
+    fn from(repr: EndReasonRepr) -> Self {
+        match repr {
+            EndReasonRepr::Structured(EndReasonFields { code, detail }) => Self { code, detail },
+            EndReasonRepr::Legacy(text) => text.into(),
+        }
+    }
+}
+
+impl EndReason {
+    /// Create a reason with no detail
+    pub fn new(code: EndReasonCode) -> Self {
+        Self { code, detail: None }
+    }
+
+    /// Attach free-text detail
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+impl From<String> for EndReason {
+    fn from(text: String) -> Self {
+        Self::new(EndReasonCode::Other).with_detail(text)
+    }
+}
+
+impl From<&str> for EndReason {
+    fn from(text: &str) -> Self {
+        text.to_string().into()
+    }
+}
+
+/// Whether the issue behind a dialog was solved, independent of its lifecycle status
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ResolutionOutcome {
+    /// The issue was solved
+    Resolved,
+    /// The issue remains open
+    Unresolved,
+    /// The issue was handed off elsewhere
+    Escalated,
+    /// There was nothing to solve
+    NoActionNeeded,
+}
+
 /// Metrics about a conversation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConversationMetrics {
@@ -237,6 +382,37 @@ pub struct ConversationMetrics {
     pub coherence_score: f32,
 }
 
+/// Per-field change between two metrics snapshots (after minus before)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct MetricsDelta {
+    pub turn_count: i64,
+    pub avg_response_time_ms: f64,
+    pub topic_switches: i64,
+    pub clarification_count: i64,
+    pub sentiment_trend: f32,
+    pub coherence_score: f32,
+}
+
+impl MetricsDelta {
+    /// Compute the change from `before` to `after`
+    pub fn between(before: &ConversationMetrics, after: &ConversationMetrics) -> Self {
+        Self {
+            turn_count: i64::from(after.turn_count) - i64::from(before.turn_count),
+            avg_response_time_ms: after.avg_response_time_ms - before.avg_response_time_ms,
+            topic_switches: i64::from(after.topic_switches) - i64::from(before.topic_switches),
+            clarification_count: i64::from(after.clarification_count)
+                - i64::from(before.clarification_count),
+            sentiment_trend: after.sentiment_trend - before.sentiment_trend,
+            coherence_score: after.coherence_score - before.coherence_score,
+        }
+    }
+
+    /// Whether nothing changed
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 /// Engagement metrics for participants
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct EngagementMetrics {
@@ -252,6 +428,8 @@ pub struct EngagementMetrics {
     pub engagement_score: f32,
     /// Topics initiated
     pub topics_initiated: u32,
+    /// Coefficient of variation of inter-turn intervals (None with fewer than three turns)
+    pub burstiness: Option<f32>,
 }
 
 impl Turn {
@@ -277,6 +455,60 @@ impl Turn {
             },
         }
     }
+
+    /// Add a reference to a previous turn
+    pub fn with_reference(mut self, turn_id: Uuid) -> Self {
+        if !self.metadata.references.contains(&turn_id) {
+            self.metadata.references.push(turn_id);
+        }
+        self
+    }
+}
+
+/// Cosine similarity between two embeddings
+///
+/// Returns `None` if the embeddings differ in length, are empty, or either has
+/// zero magnitude.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+
+    Some(dot / (norm_a * norm_b))
+}
+
+/// Largest difference from 1.0 at which an embedding's norm counts as normalized
+///
+/// Loose enough to accept unit vectors rounded to `f32` by any embedding
+/// model, tight enough to catch raw, unscaled model output.
+pub const EMBEDDING_NORM_TOLERANCE: f32 = 1e-3;
+
+/// Euclidean norm of an embedding
+pub fn embedding_norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Whether an embedding's norm is within [`EMBEDDING_NORM_TOLERANCE`] of 1.0
+pub fn is_normalized(v: &[f32]) -> bool {
+    (embedding_norm(v) - 1.0).abs() <= EMBEDDING_NORM_TOLERANCE
+}
+
+/// Scale an embedding to unit length
+///
+/// Embeddings with zero (or non-finite) magnitude cannot be normalized and
+/// are left unchanged.
+pub fn normalize_embedding(v: &mut [f32]) {
+    let norm = embedding_norm(v);
+    if norm > 0.0 && norm.is_finite() {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
 }
 
 impl Message {
@@ -297,6 +529,12 @@ impl Message {
         self
     }
 
+    /// Set the sentiment score of the message
+    pub fn with_sentiment(mut self, sentiment: f32) -> Self {
+        self.sentiment = Some(sentiment);
+        self
+    }
+
     /// Add embeddings to the message
     pub fn with_embeddings(mut self, embeddings: Vec<f32>) -> Self {
         self.embeddings = Some(embeddings);
@@ -304,6 +542,139 @@ impl Message {
     }
 }
 
+impl MessageContent {
+    /// Text of the content, if it has any
+    ///
+    /// Segmented content has no single text; see [`text_content`](Self::text_content).
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            MessageContent::Text(text) => Some(text),
+            MessageContent::Multimodal { text, .. } => text.as_deref(),
+            MessageContent::Structured(_) | MessageContent::Segmented(_) => None,
+        }
+    }
+
+    /// Whether the content carries any prose, i.e. is worth embedding
+    pub fn has_text(&self) -> bool {
+        match self {
+            MessageContent::Segmented(segments) => segments
+                .iter()
+                .any(|segment| matches!(segment, MessageSegment::Text(_))),
+            _ => self.as_text().is_some(),
+        }
+    }
+
+    /// Text of content that [has text](Self::has_text); segmented content
+    /// yields every segment in order, one per line
+    pub fn text_content(&self) -> Option<String> {
+        match self {
+            MessageContent::Segmented(_) if self.has_text() => Some(self.to_searchable_string()),
+            _ => self.as_text().map(str::to_string),
+        }
+    }
+
+    /// Everything a text search should match: the text, structured data as
+    /// JSON, and every segment in order, one per line
+    pub fn to_searchable_string(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Structured(value) => value.to_string(),
+            MessageContent::Multimodal { text, .. } => text.clone().unwrap_or_default(),
+            MessageContent::Segmented(segments) => segments
+                .iter()
+                .map(MessageSegment::searchable)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Whether the content is structured or multimodal rather than plain text
+    /// (segmented prose and code is not rich)
+    pub fn is_rich(&self) -> bool {
+        matches!(
+            self,
+            MessageContent::Structured(_) | MessageContent::Multimodal { .. }
+        )
+    }
+
+    /// Template or format name of rich content, taken from its `"format"` field
+    pub fn format(&self) -> Option<&str> {
+        match self {
+            MessageContent::Text(_) | MessageContent::Segmented(_) => None,
+            MessageContent::Structured(value) => value.get(FORMAT_FIELD)?.as_str(),
+            MessageContent::Multimodal { data, .. } => data.get(FORMAT_FIELD)?.as_str(),
+        }
+    }
+
+    /// Rough token count: whitespace-separated words of the text, or of
+    /// every segment of segmented content
+    pub fn estimated_tokens(&self) -> usize {
+        match self {
+            MessageContent::Segmented(_) => self.to_searchable_string().split_whitespace().count(),
+            _ => self
+                .as_text()
+                .map_or(0, |text| text.split_whitespace().count()),
+        }
+    }
+}
+
+/// Resolves the name shown for a participant in transcripts and exports
+pub trait DisplayNameResolver {
+    /// Name to display for the participant
+    fn display_name(&self, participant: &Participant) -> String;
+}
+
+/// Resolver that displays the name stored on the participant
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StoredNameResolver;
+
+impl DisplayNameResolver for StoredNameResolver {
+    fn display_name(&self, participant: &Participant) -> String {
+        participant.name.clone()
+    }
+}
+
+/// Clean up message content before it is stored
+pub trait Sanitizer: Send + Sync + std::fmt::Debug {
+    /// Return the sanitized content
+    fn sanitize(&self, content: &MessageContent) -> MessageContent;
+}
+
+/// Trims text content and strips control characters other than newlines and tabs
+///
+/// Text segments of segmented content are cleaned the same way. Code, JSON,
+/// structured and multimodal content is left as is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultSanitizer;
+
+impl DefaultSanitizer {
+    fn clean(text: &str) -> String {
+        text.chars()
+            .filter(|c| !c.is_control() || matches!(c, '\n' | '\t'))
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+}
+
+impl Sanitizer for DefaultSanitizer {
+    fn sanitize(&self, content: &MessageContent) -> MessageContent {
+        match content {
+            MessageContent::Text(text) => MessageContent::Text(Self::clean(text)),
+            MessageContent::Segmented(segments) => MessageContent::Segmented(
+                segments
+                    .iter()
+                    .map(|segment| match segment {
+                        MessageSegment::Text(text) => MessageSegment::Text(Self::clean(text)),
+                        other => other.clone(),
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
 impl Topic {
     /// Create a new topic
     pub fn new(name: impl Into<String>, keywords: Vec<String>) -> Self {
@@ -320,9 +691,16 @@ impl Topic {
             related_topics: Vec::new(),
             keywords,
             embedding: None,
+            subtopics: Vec::new(),
         }
     }
 
+    /// Add a subtopic link
+    pub fn with_subtopic(mut self, topic_id: Uuid, required: bool) -> Self {
+        self.subtopics.push(SubtopicLink { topic_id, required });
+        self
+    }
+
     /// Calculate current relevance considering decay
     pub fn current_relevance(&self) -> f32 {
         let elapsed = Utc::now()
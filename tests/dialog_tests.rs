@@ -1,9 +1,15 @@
 //! Tests for the Dialog domain
 
 use chrono::Utc;
+use cim_domain::{AggregateRoot, DomainError, DomainEvent};
 use cim_domain_dialog::{
-    ContextScope, ContextVariable, Dialog, DialogType, Message, MessageIntent, Participant,
-    ParticipantRole, ParticipantType, Topic, Turn, TurnType,
+    ClockSkewPolicy, ComputedVariable, ContextScope, ContextState, ContextVariable,
+    ConversationContext, ConversationPhase, Dialog, DialogConfig, DialogDomainEvent, DialogEnded,
+    DialogError, DialogStatus, DialogType, EmbeddingNormalization, EndReason, EndReasonCode,
+    ExpressionError, FlowSpec, FlowViolation, IncompleteSubtopicsError, MAX_EXPRESSION_DEPTH,
+    Message, MessageContent, MessageIntent, MessageSegment, Participant, ParticipantRole,
+    ParticipantType, Topic, TopicStatus, Turn, TurnReferenceError, TurnType, ValidationWarning,
+    value_objects::{CLOCK_SKEW_PROPERTY, PHASE_PROPERTY, cosine_similarity, normalize_embedding},
 };
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -78,7 +84,8 @@ fn test_add_turn() {
     );
 
     let events = dialog.add_turn(turn).unwrap();
-    assert_eq!(events.len(), 1);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].event_type(), "MetricsUpdated");
     assert_eq!(dialog.turns().len(), 1);
 }
 
@@ -131,7 +138,9 @@ fn test_dialog_lifecycle() {
     assert_eq!(dialog.status(), cim_domain_dialog::DialogStatus::Active);
 
     // End the dialog
-    let end_events = dialog.end(Some("Test completed".to_string())).unwrap();
+    let end_events = dialog
+        .end(Some("Test completed".to_string().into()))
+        .unwrap();
     assert_eq!(end_events.len(), 1);
     assert_eq!(dialog.status(), cim_domain_dialog::DialogStatus::Ended);
 }
@@ -164,3 +173,2165 @@ fn test_context_variables() {
     assert_eq!(dialog.context().variables.len(), 1);
     assert!(dialog.context().variables.contains_key("user_preference"));
 }
+
+#[test]
+fn test_robust_sentiment() {
+    let user_id = Uuid::new_v4();
+    let user = Participant {
+        id: user_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    assert_eq!(dialog.robust_sentiment(0.2), None);
+
+    // Mostly mildly positive turns with one extreme outlier
+    let sentiments = [0.2, 0.3, 0.25, 0.35, 0.3, -1.0];
+    for (i, sentiment) in sentiments.iter().enumerate() {
+        let turn = Turn::new(
+            i as u32 + 1,
+            user_id,
+            Message::text("How is it going?").with_sentiment(*sentiment),
+            TurnType::UserQuery,
+        );
+        dialog.add_turn(turn).unwrap();
+    }
+
+    let plain_mean = sentiments.iter().sum::<f32>() / sentiments.len() as f32;
+    let robust = dialog.robust_sentiment(0.2).unwrap();
+
+    // Trimming one value from each end leaves 0.2, 0.25, 0.3, 0.3
+    assert!((robust - 0.2625).abs() < 1e-6);
+    assert!((robust - 0.28).abs() < (plain_mean - 0.28).abs());
+    assert!((dialog.robust_sentiment(0.0).unwrap() - plain_mean).abs() < 1e-6);
+}
+
+#[test]
+fn test_pin_turns() {
+    let user_id = Uuid::new_v4();
+    let user = Participant {
+        id: user_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    dialog.set_max_pinned_turns(2);
+
+    let mut turn_ids = Vec::new();
+    for i in 0..3 {
+        let turn = Turn::new(i + 1, user_id, Message::text("Noted"), TurnType::UserQuery);
+        turn_ids.push(turn.turn_id);
+        dialog.add_turn(turn).unwrap();
+    }
+
+    // Pin two turns, out of conversation order
+    assert_eq!(dialog.pin_turn(turn_ids[2]).unwrap().len(), 1);
+    dialog.pin_turn(turn_ids[0]).unwrap();
+
+    let pinned: Vec<Uuid> = dialog.pinned_turns().iter().map(|t| t.turn_id).collect();
+    assert_eq!(pinned, vec![turn_ids[2], turn_ids[0]]);
+
+    // The limit is enforced, and pinning twice or pinning unknown turns fails
+    assert!(dialog.pin_turn(turn_ids[1]).is_err());
+    assert!(dialog.pin_turn(turn_ids[0]).is_err());
+    assert!(dialog.pin_turn(Uuid::new_v4()).is_err());
+
+    // Unpinning frees a slot
+    dialog.unpin_turn(turn_ids[2]).unwrap();
+    assert!(dialog.unpin_turn(turn_ids[2]).is_err());
+    dialog.pin_turn(turn_ids[1]).unwrap();
+
+    let pinned: Vec<Uuid> = dialog.pinned_turns().iter().map(|t| t.turn_id).collect();
+    assert_eq!(pinned, vec![turn_ids[0], turn_ids[1]]);
+}
+
+#[test]
+fn test_turn_count_stays_consistent() {
+    let user_id = Uuid::new_v4();
+    let user = Participant {
+        id: user_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+
+    let mut turn_ids = Vec::new();
+    for i in 0..4 {
+        let turn = Turn::new(i + 1, user_id, Message::text("Hello"), TurnType::UserQuery);
+        turn_ids.push(turn.turn_id);
+        dialog.add_turn(turn).unwrap();
+    }
+    assert_eq!(dialog.metrics().turn_count, 4);
+    dialog.validate_invariants().unwrap();
+
+    // Retracting a live turn decrements the counter
+    dialog
+        .retract_turn(turn_ids[3], Some("sent by mistake".to_string()))
+        .unwrap();
+    assert_eq!(dialog.metrics().turn_count, 3);
+    assert_eq!(dialog.turn_count(), 3);
+    dialog.validate_invariants().unwrap();
+
+    // Archived turns still count
+    dialog.archive_turns(3).unwrap();
+    assert_eq!(dialog.turn_count(), 1);
+    assert_eq!(dialog.archived_turns().len(), 2);
+    assert_eq!(dialog.metrics().turn_count, 3);
+    dialog.validate_invariants().unwrap();
+
+    // Retracting an archived turn also decrements the counter
+    dialog.retract_turn(turn_ids[0], None).unwrap();
+    assert_eq!(dialog.metrics().turn_count, 2);
+    dialog.validate_invariants().unwrap();
+
+    assert!(dialog.retract_turn(turn_ids[0], None).is_err());
+
+    dialog.reconcile_turn_count();
+    assert_eq!(dialog.metrics().turn_count, 2);
+}
+
+#[test]
+fn test_namespaced_context_variables() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Group, user);
+    let planner = Uuid::new_v4();
+    let researcher = Uuid::new_v4();
+    let variable = |source, value| ContextVariable {
+        name: "goal".to_string(),
+        value: serde_json::json!(value),
+        scope: ContextScope::Dialog,
+        set_at: Utc::now(),
+        expires_at: None,
+        source,
+    };
+
+    dialog
+        .add_context_variable(variable(planner, "book flight"))
+        .unwrap();
+    dialog
+        .add_context_variable(variable(researcher, "compare prices"))
+        .unwrap();
+
+    // Neither source overwrites the other
+    assert_eq!(
+        dialog.get_variable_from(planner, "goal").unwrap().value,
+        serde_json::json!("book flight")
+    );
+    assert_eq!(
+        dialog.get_variable_from(researcher, "goal").unwrap().value,
+        serde_json::json!("compare prices")
+    );
+
+    // Own value wins, otherwise the most recent value is used
+    assert_eq!(
+        dialog.resolve_variable(planner, "goal").unwrap().source,
+        planner
+    );
+    assert_eq!(
+        dialog
+            .resolve_variable(Uuid::new_v4(), "goal")
+            .unwrap()
+            .source,
+        researcher
+    );
+    assert_eq!(dialog.context().variables.len(), 1);
+
+    // Bulk updates are namespaced under the dialog itself
+    let mut updates = HashMap::new();
+    updates.insert("goal".to_string(), serde_json::json!("summarize"));
+    dialog.update_context(updates).unwrap();
+    assert_eq!(
+        dialog.get_variable_from(dialog.id(), "goal").unwrap().value,
+        serde_json::json!("summarize")
+    );
+    assert_eq!(
+        dialog.get_variable_from(planner, "goal").unwrap().value,
+        serde_json::json!("book flight")
+    );
+}
+
+#[test]
+fn test_lock_dialog() {
+    let user_id = Uuid::new_v4();
+    let user = Participant {
+        id: user_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Group, user);
+    let turn = |n| Turn::new(n, user_id, Message::text("Hello"), TurnType::UserQuery);
+
+    dialog.lock(Some("Cooling off".to_string())).unwrap();
+    assert!(dialog.is_locked());
+    assert_eq!(dialog.status(), DialogStatus::Active);
+
+    // Locked dialogs reject turns with a validation error, not a state transition error
+    let err = dialog.add_turn(turn(1)).unwrap_err();
+    assert!(matches!(
+        err,
+        DialogError::Domain(DomainError::ValidationError(_))
+    ));
+    assert_eq!(dialog.turn_count(), 0);
+    assert_eq!(dialog.status(), DialogStatus::Active);
+
+    dialog.unlock().unwrap();
+    assert!(!dialog.is_locked());
+    dialog.add_turn(turn(1)).unwrap();
+    assert_eq!(dialog.turn_count(), 1);
+    assert_eq!(dialog.status(), DialogStatus::Active);
+}
+
+#[test]
+fn test_relate_topics_symmetrically() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    let weather = Topic::new("Weather", vec!["rain".to_string()]);
+    let travel = Topic::new("Travel", vec!["flight".to_string()]);
+    let (a, b) = (weather.id, travel.id);
+    dialog.switch_topic(weather).unwrap();
+    dialog.switch_topic(travel).unwrap();
+
+    assert_eq!(dialog.relate_topics(a, b).unwrap().len(), 1);
+    assert_eq!(dialog.topic(a).unwrap().related_topics, vec![b]);
+    assert_eq!(dialog.topic(b).unwrap().related_topics, vec![a]);
+
+    // Relating again (in either direction) does not duplicate links
+    assert!(dialog.relate_topics(b, a).unwrap().is_empty());
+    assert_eq!(dialog.topic(a).unwrap().related_topics, vec![b]);
+    assert_eq!(dialog.topic(b).unwrap().related_topics, vec![a]);
+
+    assert!(dialog.relate_topics(a, a).is_err());
+    assert!(dialog.relate_topics(a, Uuid::new_v4()).is_err());
+
+    assert_eq!(dialog.unrelate_topics(b, a).unwrap().len(), 1);
+    assert!(dialog.topic(a).unwrap().related_topics.is_empty());
+    assert!(dialog.topic(b).unwrap().related_topics.is_empty());
+}
+
+#[test]
+fn test_turn_reference_validation() {
+    let user_id = Uuid::new_v4();
+    let user = Participant {
+        id: user_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    let question = Turn::new(1, user_id, Message::text("Question"), TurnType::UserQuery);
+    let question_id = question.turn_id;
+    dialog.add_turn(question).unwrap();
+
+    // A reply to an existing turn is accepted
+    let reply = Turn::new(2, user_id, Message::text("Follow-up"), TurnType::UserQuery)
+        .with_reference(question_id);
+    dialog.add_turn(reply).unwrap();
+
+    // A reply to an unknown turn is rejected with a typed error
+    let missing = Uuid::new_v4();
+    let dangling =
+        Turn::new(3, user_id, Message::text("Orphan"), TurnType::UserQuery).with_reference(missing);
+    assert_eq!(
+        dialog.check_references(&dangling),
+        Err(TurnReferenceError::DanglingReference {
+            turn_id: dangling.turn_id,
+            reference: missing,
+        })
+    );
+    assert!(matches!(
+        dialog.add_turn(dangling.clone()),
+        Err(DialogError::TurnReference(TurnReferenceError::DanglingReference { reference, .. }))
+            if reference == missing
+    ));
+    assert_eq!(dialog.turn_count(), 2);
+
+    // Bulk imports can opt out
+    dialog.set_reference_validation(false);
+    dialog.add_turn(dangling).unwrap();
+    assert_eq!(dialog.turn_count(), 3);
+}
+
+#[test]
+fn test_topic_drift() {
+    let user_id = Uuid::new_v4();
+    let user = Participant {
+        id: user_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    assert_eq!(dialog.max_drift(), None);
+
+    let mut topic = Topic::new("Weather", vec!["rain".to_string()]);
+    topic.embedding = Some(vec![1.0, 0.0]);
+    dialog.switch_topic(topic).unwrap();
+
+    let embeddings = [
+        Some(vec![2.0, 0.0]),
+        Some(vec![1.0, 1.0]),
+        None,
+        Some(vec![0.0, 3.0]),
+    ];
+    let mut turn_ids = Vec::new();
+    for (i, embedding) in embeddings.into_iter().enumerate() {
+        let mut message = Message::text("...");
+        message.embeddings = embedding;
+        let turn = Turn::new(i as u32 + 1, user_id, message, TurnType::UserQuery);
+        turn_ids.push(turn.turn_id);
+        dialog.add_turn(turn).unwrap();
+    }
+
+    // The turn without an embedding is skipped
+    let series = dialog.topic_drift_series();
+    assert_eq!(series.len(), 3);
+    assert_eq!(series[0].0, turn_ids[0]);
+    assert!(series[0].1.abs() < 1e-6);
+    assert_eq!(series[1].0, turn_ids[1]);
+    assert!((series[1].1 - (1.0 - std::f32::consts::FRAC_1_SQRT_2)).abs() < 1e-6);
+    assert_eq!(series[2].0, turn_ids[3]);
+    assert!((series[2].1 - 1.0).abs() < 1e-6);
+
+    assert!((dialog.max_drift().unwrap() - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_burstiness() {
+    let steady_id = Uuid::new_v4();
+    let bursty_id = Uuid::new_v4();
+    let user = Participant {
+        id: steady_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Steady".to_string(),
+        metadata: HashMap::new(),
+    };
+    let other = Participant {
+        id: bursty_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Assistant,
+        name: "Bursty".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Group, user);
+    dialog.add_participant(other).unwrap();
+    // Turns are added per participant rather than chronologically
+    dialog.set_clock_skew_policy(ClockSkewPolicy::Allow);
+
+    let start = Utc::now();
+    let mut add = |participant_id, seconds| {
+        let mut turn = Turn::new(1, participant_id, Message::text("..."), TurnType::UserQuery);
+        turn.timestamp = start + chrono::Duration::seconds(seconds);
+        dialog.add_turn(turn).unwrap();
+    };
+
+    // Steady: one turn a minute. Bursty: quick flurries separated by long pauses.
+    for minute in 0..5 {
+        add(steady_id, minute * 60);
+    }
+    for seconds in [0, 2, 4, 600, 602, 604] {
+        add(bursty_id, seconds);
+    }
+
+    let steady = dialog.burstiness(steady_id).unwrap();
+    let bursty = dialog.burstiness(bursty_id).unwrap();
+    assert!(steady.abs() < 1e-6);
+    assert!(bursty > 1.0);
+    assert!(bursty > steady);
+
+    // Too few turns
+    assert_eq!(dialog.burstiness(Uuid::new_v4()), None);
+
+    // Engagement metrics carry the same burstiness
+    assert_eq!(
+        dialog.engagement_metrics(steady_id).burstiness,
+        Some(steady)
+    );
+    assert_eq!(
+        dialog.engagement_metrics(bursty_id).burstiness,
+        Some(bursty)
+    );
+    assert_eq!(dialog.engagement_metrics(Uuid::new_v4()).burstiness, None);
+}
+
+#[test]
+fn test_engagement_metrics() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let (user_id, agent_id) = (user.id, agent.id);
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    dialog.add_participant(agent).unwrap();
+    dialog.switch_topic(Topic::new("Billing", vec![])).unwrap();
+
+    // The user asks every minute and the agent answers two seconds later
+    let start = Utc::now() + chrono::Duration::seconds(1);
+    for minute in 0..3 {
+        let at = start + chrono::Duration::minutes(minute);
+        let mut query = Turn::new(1, user_id, Message::text("Why?"), TurnType::UserQuery);
+        query.timestamp = at;
+        dialog.add_turn(query).unwrap();
+        let mut answer = Turn::new(
+            1,
+            agent_id,
+            Message::text("Because."),
+            TurnType::AgentResponse,
+        );
+        answer.timestamp = at + chrono::Duration::seconds(2);
+        dialog.add_turn(answer).unwrap();
+    }
+
+    let agent_metrics = dialog.engagement_metrics(agent_id);
+    assert_eq!(agent_metrics.turn_contributions, 3);
+    assert_eq!(agent_metrics.avg_message_length, 8.0);
+    assert_eq!(agent_metrics.avg_response_latency_ms, 2000.0);
+    assert_eq!(agent_metrics.engagement_score, 0.5);
+    assert_eq!(agent_metrics.topics_initiated, 0);
+    assert!(agent_metrics.burstiness.unwrap().abs() < 1e-6);
+
+    let user_metrics = dialog.engagement_metrics(user_id);
+    assert_eq!(user_metrics.topics_initiated, 1);
+    assert_eq!(user_metrics.avg_response_latency_ms, 58_000.0);
+}
+
+#[test]
+fn test_end_reason() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    let reason = EndReason::new(EndReasonCode::Escalated).with_detail("needs a human");
+    let events = dialog.end(Some(reason.clone())).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(dialog.status(), DialogStatus::Ended);
+
+    // Plain strings are kept as detail under Other
+    let legacy: EndReason = "done".into();
+    assert_eq!(legacy.code, EndReasonCode::Other);
+    assert_eq!(legacy.detail.as_deref(), Some("done"));
+
+    // Events stored before reason codes existed still deserialize
+    let mut json = serde_json::json!({
+        "dialog_id": dialog.id(),
+        "ended_at": Utc::now(),
+        "reason": "Issue resolved",
+        "final_metrics": {
+            "turn_count": 0,
+            "avg_response_time_ms": 0.0,
+            "topic_switches": 0,
+            "clarification_count": 0,
+            "sentiment_trend": 0.0,
+            "coherence_score": 1.0
+        }
+    });
+    let ended: DialogEnded = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(ended.reason, Some("Issue resolved".into()));
+
+    json["reason"] = serde_json::to_value(&reason).unwrap();
+    let ended: DialogEnded = serde_json::from_value(json).unwrap();
+    assert_eq!(ended.reason, Some(reason));
+}
+
+#[test]
+fn test_repeated_responses() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let (user_id, agent_id) = (user.id, agent.id);
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    dialog.add_participant(agent).unwrap();
+
+    let mut repeated = Vec::new();
+    for _ in 0..3 {
+        // The user repeating themselves is not an agent loop
+        let question = Turn::new(
+            1,
+            user_id,
+            Message::text("It still doesn't work"),
+            TurnType::UserQuery,
+        );
+        dialog.add_turn(question).unwrap();
+
+        let answer = Turn::new(
+            1,
+            agent_id,
+            Message::text("Please restart your router."),
+            TurnType::AgentResponse,
+        );
+        repeated.push(answer.turn_id);
+        dialog.add_turn(answer).unwrap();
+    }
+    let varied = Turn::new(
+        1,
+        agent_id,
+        Message::text("Let me escalate this."),
+        TurnType::AgentResponse,
+    );
+    dialog.add_turn(varied).unwrap();
+
+    assert_eq!(dialog.has_repeated_responses(3), Some(repeated));
+    assert_eq!(dialog.has_repeated_responses(4), None);
+}
+
+#[test]
+fn test_language_consistency() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let user_id = user.id;
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    assert_eq!(dialog.dominant_language(), None);
+
+    for _ in 0..9 {
+        let turn = Turn::new(1, user_id, Message::text("Hello"), TurnType::UserQuery);
+        dialog.add_turn(turn).unwrap();
+    }
+    let mut message = Message::text("Bonjour");
+    message.language = "fr".to_string();
+    let stray = Turn::new(1, user_id, message, TurnType::UserQuery);
+    let stray_id = stray.turn_id;
+    dialog.add_turn(stray).unwrap();
+
+    assert_eq!(dialog.dominant_language().as_deref(), Some("en"));
+
+    // The check is off by default
+    assert!(dialog.validate().is_clean());
+
+    dialog.set_language_consistency_check(Some(0.05));
+    let report = dialog.validate();
+    match &report.warnings[..] {
+        [
+            ValidationWarning::MixedLanguage {
+                dominant,
+                differing_turns,
+                fraction,
+            },
+        ] => {
+            assert_eq!(dominant, "en");
+            assert_eq!(differing_turns, &vec![stray_id]);
+            assert!((fraction - 0.1).abs() < 1e-6);
+        }
+        other => panic!("Expected a mixed language warning, got {other:?}"),
+    }
+
+    // One stray turn in ten is within a 20% allowance
+    dialog.set_language_consistency_check(Some(0.2));
+    assert!(dialog.validate().is_clean());
+}
+
+#[test]
+fn test_clock_skew_policies() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let user_id = user.id;
+    let now = Utc::now();
+    let skewed_turn = || {
+        let mut turn = Turn::new(1, user_id, Message::text("late clock"), TurnType::UserQuery);
+        turn.timestamp = now - chrono::Duration::seconds(5);
+        turn
+    };
+    let dialog_with = |policy| {
+        let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user.clone());
+        dialog.set_clock_skew_policy(policy);
+        let mut first = Turn::new(1, user_id, Message::text("first"), TurnType::UserQuery);
+        first.timestamp = now;
+        dialog.add_turn(first).unwrap();
+        dialog
+    };
+
+    // Clamp is the default
+    assert_eq!(ClockSkewPolicy::default(), ClockSkewPolicy::Clamp);
+    let mut dialog = dialog_with(ClockSkewPolicy::Clamp);
+    dialog.add_turn(skewed_turn()).unwrap();
+    let added = &dialog.turns()[1];
+    assert_eq!(added.timestamp, now);
+    assert_eq!(
+        added.metadata.properties[CLOCK_SKEW_PROPERTY],
+        serde_json::json!(5000)
+    );
+
+    let mut dialog = dialog_with(ClockSkewPolicy::Allow);
+    dialog.add_turn(skewed_turn()).unwrap();
+    let added = &dialog.turns()[1];
+    assert_eq!(added.timestamp, now - chrono::Duration::seconds(5));
+    assert_eq!(
+        added.metadata.properties[CLOCK_SKEW_PROPERTY],
+        serde_json::json!(5000)
+    );
+
+    let mut dialog = dialog_with(ClockSkewPolicy::Reject);
+    assert!(matches!(
+        dialog.add_turn(skewed_turn()),
+        Err(DialogError::Domain(DomainError::ValidationError(_)))
+    ));
+    assert_eq!(dialog.turns().len(), 1);
+
+    // Turns in order are untouched
+    let mut in_order = Turn::new(1, user_id, Message::text("on time"), TurnType::UserQuery);
+    in_order.timestamp = now + chrono::Duration::seconds(1);
+    dialog.add_turn(in_order).unwrap();
+    assert!(
+        !dialog.turns()[1]
+            .metadata
+            .properties
+            .contains_key(CLOCK_SKEW_PROPERTY)
+    );
+}
+
+#[test]
+fn test_handoff_briefing() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let (user_id, agent_id) = (user.id, agent.id);
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    dialog.add_participant(agent).unwrap();
+
+    let billing = Topic::new("Billing", vec!["invoice".to_string()]);
+    let billing_id = billing.id;
+    dialog.switch_topic(billing).unwrap();
+
+    let variable = |name: &str, scope| ContextVariable {
+        name: name.to_string(),
+        value: serde_json::json!("value"),
+        scope,
+        set_at: Utc::now(),
+        expires_at: None,
+        source: user_id,
+    };
+    dialog
+        .add_context_variable(variable("account_id", ContextScope::Dialog))
+        .unwrap();
+    dialog
+        .add_context_variable(variable("scratch", ContextScope::Turn))
+        .unwrap();
+
+    let answered = Turn::new(
+        1,
+        user_id,
+        Message::text("Where is my invoice?").with_intent(MessageIntent::Question),
+        TurnType::UserQuery,
+    );
+    let answered_id = answered.turn_id;
+    dialog.add_turn(answered).unwrap();
+    let answer = Turn::new(
+        2,
+        agent_id,
+        Message::text("In your inbox."),
+        TurnType::AgentResponse,
+    )
+    .with_reference(answered_id);
+    dialog.add_turn(answer).unwrap();
+    let open = Turn::new(
+        3,
+        user_id,
+        Message::text("Why was I charged twice?").with_intent(MessageIntent::Question),
+        TurnType::UserQuery,
+    );
+    let open_id = open.turn_id;
+    dialog.add_turn(open).unwrap();
+
+    dialog.set_phase(ConversationPhase::Triage).unwrap();
+
+    let briefing = dialog.handoff_briefing();
+    assert_eq!(briefing.dialog_id, dialog.id());
+    assert_eq!(briefing.phase, Some(ConversationPhase::Triage));
+    assert_eq!(briefing.context_state, ContextState::Normal);
+    assert_eq!(briefing.current_topic, Some(billing_id));
+    assert_eq!(briefing.open_topics.len(), 1);
+    assert_eq!(briefing.open_topics[0].name, "Billing");
+    assert_eq!(briefing.recent_turns.len(), 3);
+
+    let unanswered: Vec<Uuid> = briefing
+        .unanswered_questions
+        .iter()
+        .map(|t| t.turn_id)
+        .collect();
+    assert_eq!(unanswered, vec![open_id]);
+
+    let variables: Vec<&str> = briefing.variables.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(variables, vec!["account_id"]);
+
+    // The briefing can be sent to the receiving agent as-is
+    let json = serde_json::to_value(&briefing).unwrap();
+    assert_eq!(
+        json["unanswered_questions"][0]["turn_id"],
+        serde_json::json!(open_id)
+    );
+}
+
+#[test]
+fn test_scheduled_turns() {
+    let bot = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::System,
+        role: ParticipantRole::Primary,
+        name: "Reminder bot".to_string(),
+        metadata: HashMap::new(),
+    };
+    let bot_id = bot.id;
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, bot);
+
+    let now = Utc::now();
+    let later = Turn::new(
+        1,
+        bot_id,
+        Message::text("Second reminder"),
+        TurnType::SystemMessage,
+    );
+    let sooner = Turn::new(
+        1,
+        bot_id,
+        Message::text("First reminder"),
+        TurnType::SystemMessage,
+    );
+    let (later_id, sooner_id) = (later.turn_id, sooner.turn_id);
+    dialog
+        .schedule_turn(later, now + chrono::Duration::hours(2))
+        .unwrap();
+    dialog
+        .schedule_turn(sooner, now + chrono::Duration::hours(1))
+        .unwrap();
+
+    // Queued, not yet part of the conversation
+    assert!(dialog.turns().is_empty());
+    assert_eq!(dialog.scheduled_turns().len(), 2);
+    assert_eq!(dialog.scheduled_turns()[0].1.turn_id, sooner_id);
+
+    assert!(dialog.release_due_turns(now).unwrap().is_empty());
+
+    let events = dialog
+        .release_due_turns(now + chrono::Duration::minutes(90))
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(dialog.turns().len(), 1);
+    assert_eq!(dialog.turns()[0].turn_id, sooner_id);
+    assert_eq!(
+        dialog.turns()[0].timestamp,
+        now + chrono::Duration::hours(1)
+    );
+    assert_eq!(dialog.scheduled_turns().len(), 1);
+
+    dialog
+        .release_due_turns(now + chrono::Duration::hours(3))
+        .unwrap();
+    assert_eq!(dialog.turns()[1].turn_id, later_id);
+    assert!(dialog.scheduled_turns().is_empty());
+
+    // Ended dialogs take no new scheduled turns
+    dialog.end(None).unwrap();
+    let turn = Turn::new(
+        1,
+        bot_id,
+        Message::text("Too late"),
+        TurnType::SystemMessage,
+    );
+    assert!(matches!(
+        dialog.schedule_turn(turn, now),
+        Err(DomainError::InvalidStateTransition { .. })
+    ));
+}
+
+#[test]
+fn test_release_due_turns_drops_rejected_turn() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let bot = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::System,
+        role: ParticipantRole::Assistant,
+        name: "Reminder bot".to_string(),
+        metadata: HashMap::new(),
+    };
+    let (user_id, bot_id) = (user.id, bot.id);
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    dialog.add_participant(bot).unwrap();
+
+    let now = Utc::now();
+    let reminder = Turn::new(
+        1,
+        bot_id,
+        Message::text("Reminder"),
+        TurnType::SystemMessage,
+    );
+    let follow_up = Turn::new(1, user_id, Message::text("Thanks"), TurnType::UserQuery);
+    let (reminder_id, follow_up_id) = (reminder.turn_id, follow_up.turn_id);
+    dialog
+        .schedule_turn(reminder, now + chrono::Duration::hours(1))
+        .unwrap();
+    dialog
+        .schedule_turn(follow_up, now + chrono::Duration::hours(2))
+        .unwrap();
+
+    // Nothing is released while the dialog is paused
+    dialog.pause().unwrap();
+    assert!(
+        dialog
+            .release_due_turns(now + chrono::Duration::hours(3))
+            .is_err()
+    );
+    assert_eq!(dialog.scheduled_turns().len(), 2);
+    dialog.resume().unwrap();
+
+    // The bot leaves, so its reminder is refused but does not block the next turn
+    dialog.remove_participant(bot_id, None).unwrap();
+    let events = dialog
+        .release_due_turns(now + chrono::Duration::hours(3))
+        .unwrap();
+    let types: Vec<_> = events.iter().map(|e| e.event_type()).collect();
+    assert_eq!(
+        types,
+        ["ScheduledTurnRejected", "TurnAdded", "MetricsUpdated"]
+    );
+    match &events[0] {
+        DialogDomainEvent::ScheduledTurnRejected(e) => {
+            assert_eq!(e.turn_id, reminder_id);
+            assert!(e.reason.contains("Participant not in dialog"));
+        }
+        other => panic!("Expected ScheduledTurnRejected, got {other:?}"),
+    }
+    assert_eq!(dialog.turns().len(), 1);
+    assert_eq!(dialog.turns()[0].turn_id, follow_up_id);
+    assert!(dialog.scheduled_turns().is_empty());
+}
+
+#[test]
+fn test_export_participant_data() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let (user_id, agent_id) = (user.id, agent.id);
+
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+    dialog.add_participant(agent).unwrap();
+
+    let variable = |name: &str, source| ContextVariable {
+        name: name.to_string(),
+        value: serde_json::json!("value"),
+        scope: ContextScope::Dialog,
+        set_at: Utc::now(),
+        expires_at: None,
+        source,
+    };
+    dialog
+        .add_context_variable(variable("email", user_id))
+        .unwrap();
+    dialog
+        .add_context_variable(variable("ticket", agent_id))
+        .unwrap();
+
+    let question = Turn::new(
+        1,
+        user_id,
+        Message::text("Delete my data"),
+        TurnType::UserQuery,
+    );
+    let question_id = question.turn_id;
+    dialog.add_turn(question).unwrap();
+    dialog
+        .add_turn(Turn::new(
+            2,
+            agent_id,
+            Message::text("Done."),
+            TurnType::AgentResponse,
+        ))
+        .unwrap();
+
+    let export = dialog.export_participant_data(user_id);
+    assert_eq!(export.participant, Some(user));
+    let turns: Vec<Uuid> = export.turns.iter().map(|t| t.turn_id).collect();
+    assert_eq!(turns, vec![question_id]);
+    let variables: Vec<&str> = export.variables.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(variables, vec!["email"]);
+
+    let json = serde_json::to_string(&export).unwrap();
+    assert!(!json.contains("Done."));
+    assert!(!json.contains("ticket"));
+}
+
+#[test]
+fn test_computed_context_variables() {
+    let source = Uuid::new_v4();
+    let variable = |name: &str, value: serde_json::Value| ContextVariable {
+        name: name.to_string(),
+        value,
+        scope: ContextScope::Dialog,
+        set_at: Utc::now(),
+        expires_at: None,
+        source,
+    };
+
+    let mut context = ConversationContext::default();
+    context.set_variable(variable("a", serde_json::json!(2)));
+    context.set_variable(variable("b", serde_json::json!(3)));
+    context.set_variable(variable("name", serde_json::json!("Ada")));
+    context.define_computed(ComputedVariable::new("sum", "a + b", ContextScope::Dialog).unwrap());
+    context
+        .define_computed(ComputedVariable::new("double", "sum * 2", ContextScope::Dialog).unwrap());
+    context.define_computed(
+        ComputedVariable::new("greeting", "\"Hello, \" + name", ContextScope::Dialog).unwrap(),
+    );
+
+    context.recompute_derived();
+    assert_eq!(context.variables["sum"].value, serde_json::json!(5));
+    assert_eq!(context.variables["double"].value, serde_json::json!(10));
+    assert_eq!(
+        context.variables["greeting"].value,
+        serde_json::json!("Hello, Ada")
+    );
+
+    context.set_variable(variable("a", serde_json::json!(10)));
+    context.recompute_derived();
+    assert_eq!(context.variables["sum"].value, serde_json::json!(13));
+    assert_eq!(context.variables["double"].value, serde_json::json!(26));
+
+    // Ordinary variables are left alone
+    assert_eq!(context.variables["a"].value, serde_json::json!(10));
+    assert_eq!(context.variables["a"].source, source);
+
+    // Failing expressions keep the previous value
+    context.define_computed(
+        ComputedVariable::new("sum", "a + missing", ContextScope::Dialog).unwrap(),
+    );
+    context.recompute_derived();
+    assert_eq!(context.variables["sum"].value, serde_json::json!(13));
+
+    assert!(matches!(
+        ComputedVariable::new("bad", "a +", ContextScope::Dialog),
+        Err(ExpressionError::Parse(_))
+    ));
+
+    // Deep nesting is rejected instead of exhausting the stack
+    let chained = format!("1{}", " + 1".repeat(MAX_EXPRESSION_DEPTH));
+    assert!(ComputedVariable::new("chained", &chained, ContextScope::Dialog).is_ok());
+    for source in [
+        "-".repeat(1_000_000) + "1",
+        "(".repeat(1_000_000) + "1" + &")".repeat(1_000_000),
+        "1".to_string() + &" + 1".repeat(1_000_000),
+    ] {
+        assert!(matches!(
+            ComputedVariable::new("deep", source, ContextScope::Dialog),
+            Err(ExpressionError::Parse(_))
+        ));
+    }
+
+    // Overflow is an error, not a null value
+    let huge = format!("{} * 10", "9".repeat(308));
+    let overflow = ComputedVariable::new("overflow", huge, ContextScope::Dialog).unwrap();
+    assert_eq!(
+        overflow.expression.evaluate(&mut |_| unreachable!()),
+        Err(ExpressionError::NonFinite)
+    );
+}
+
+#[test]
+fn test_content_sanitization() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let user_id = user.id;
+    let config = DialogConfig {
+        sanitize_content: true,
+        ..DialogConfig::default()
+    };
+    let mut dialog = Dialog::with_config(Uuid::new_v4(), DialogType::Direct, user, config);
+
+    let text = |dialog: &Dialog| match &dialog.turns().last().unwrap().message.content {
+        MessageContent::Text(text) => text.clone(),
+        other => panic!("Expected text content, got {other:?}"),
+    };
+
+    dialog
+        .add_turn(Turn::new(
+            1,
+            user_id,
+            Message::text("  Hel\u{0}lo\u{7}\u{1b}[31m!  \n"),
+            TurnType::UserQuery,
+        ))
+        .unwrap();
+    assert_eq!(text(&dialog), "Hello[31m!");
+
+    dialog
+        .add_turn(Turn::new(
+            2,
+            user_id,
+            Message::text("Line one\nline\ttwo"),
+            TurnType::UserQuery,
+        ))
+        .unwrap();
+    assert_eq!(text(&dialog), "Line one\nline\ttwo");
+
+    // Sanitization is opt-in
+    dialog.set_content_sanitization(false);
+    dialog
+        .add_turn(Turn::new(
+            3,
+            user_id,
+            Message::text(" raw\u{0} "),
+            TurnType::UserQuery,
+        ))
+        .unwrap();
+    assert_eq!(text(&dialog), " raw\u{0} ");
+    // Only the prose of segmented content is cleaned
+    dialog.set_content_sanitization(true);
+    let segmented = Message {
+        content: MessageContent::Segmented(vec![
+            MessageSegment::Text(" Run\u{7} this: ".to_string()),
+            MessageSegment::Code {
+                lang: "sh".to_string(),
+                code: " ls\u{7} ".to_string(),
+            },
+        ]),
+        ..Message::text("")
+    };
+    dialog
+        .add_turn(Turn::new(4, user_id, segmented, TurnType::UserQuery))
+        .unwrap();
+    assert_eq!(
+        dialog.turns().last().unwrap().message.content,
+        MessageContent::Segmented(vec![
+            MessageSegment::Text("Run this:".to_string()),
+            MessageSegment::Code {
+                lang: "sh".to_string(),
+                code: " ls\u{7} ".to_string()
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_low_confidence_agent_turn_awaits_clarification() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent_id = agent.id;
+    let config = DialogConfig {
+        clarification_confidence_threshold: Some(0.5),
+        ..DialogConfig::default()
+    };
+    let mut dialog = Dialog::with_config(Uuid::new_v4(), DialogType::Direct, user, config);
+    dialog.add_participant(agent).unwrap();
+
+    let reply = |confidence: f32| {
+        let mut turn = Turn::new(
+            1,
+            agent_id,
+            Message::text("Perhaps?"),
+            TurnType::AgentResponse,
+        );
+        turn.metadata.confidence = Some(confidence);
+        turn
+    };
+
+    // A confident reply leaves the context alone
+    let events = dialog.add_turn(reply(0.9)).unwrap();
+    assert!(
+        events
+            .iter()
+            .all(|e| e.event_type() != "ContextStateChanged")
+    );
+    assert_eq!(dialog.context().state, ContextState::Normal);
+    assert_eq!(dialog.metrics().clarification_count, 0);
+
+    // An unsure one asks for clarification
+    let version = dialog.version();
+    let events = dialog.add_turn(reply(0.2)).unwrap();
+    let types: Vec<_> = events.iter().map(|e| e.event_type()).collect();
+    assert_eq!(
+        types,
+        ["TurnAdded", "ContextStateChanged", "MetricsUpdated"]
+    );
+    assert_eq!(dialog.context().state, ContextState::AwaitingClarification);
+    assert_eq!(dialog.metrics().clarification_count, 1);
+    assert_eq!(dialog.version(), version + 2);
+}
+
+#[test]
+fn test_end_keeps_low_confidence_clarifications() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let (user_id, agent_id) = (user.id, agent.id);
+    let config = DialogConfig {
+        clarification_confidence_threshold: Some(0.5),
+        ..DialogConfig::default()
+    };
+    let mut dialog = Dialog::with_config(Uuid::new_v4(), DialogType::Direct, user, config);
+    dialog.add_participant(agent).unwrap();
+
+    let mut unsure = Turn::new(
+        1,
+        agent_id,
+        Message::text("Perhaps?"),
+        TurnType::AgentResponse,
+    );
+    unsure.metadata.confidence = Some(0.2);
+    dialog.add_turn(unsure).unwrap();
+    dialog
+        .add_turn(Turn::new(
+            2,
+            user_id,
+            Message::text("Which one?"),
+            TurnType::Clarification,
+        ))
+        .unwrap();
+
+    // The low-confidence flag and the clarification turn both count
+    assert_eq!(dialog.compute_metrics().clarification_count, 2);
+    let events = dialog.end(None).unwrap();
+    let Some(DialogDomainEvent::DialogEnded(ended)) = events.first() else {
+        panic!("expected DialogEnded, got {events:?}");
+    };
+    assert_eq!(ended.final_metrics.clarification_count, 2);
+    assert_eq!(dialog.metrics().clarification_count, 2);
+}
+
+#[test]
+fn test_add_turns_preserves_numbering() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let user_id = user.id;
+    let config = DialogConfig {
+        turn_number_base: 0,
+        ..DialogConfig::default()
+    };
+    let mut dialog = Dialog::with_config(Uuid::new_v4(), DialogType::Direct, user, config);
+    let turn = |number| {
+        Turn::new(
+            number,
+            user_id,
+            Message::text("Imported"),
+            TurnType::UserQuery,
+        )
+    };
+
+    // 0-based with a gap
+    dialog.add_turns(vec![turn(0), turn(1), turn(3)]).unwrap();
+    let numbers: Vec<u32> = dialog.turns().iter().map(|t| t.turn_number).collect();
+    assert_eq!(numbers, [0, 1, 3]);
+    assert_eq!(dialog.metrics().turn_count, 3);
+
+    // A decrease rejects the whole batch
+    let result = dialog.add_turns(vec![turn(4), turn(6), turn(5)]);
+    assert!(matches!(
+        result,
+        Err(DialogError::Domain(DomainError::ValidationError(_)))
+    ));
+    assert!(dialog.add_turns(vec![turn(2)]).is_err());
+    assert_eq!(dialog.turns().len(), 3);
+
+    // The default base rejects 0-based numbering
+    let user = dialog.participants().values().next().unwrap().clone();
+    let mut one_based = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    assert!(one_based.add_turns(vec![turn(0)]).is_err());
+    assert!(one_based.add_turns(vec![turn(1), turn(2)]).is_ok());
+}
+
+#[test]
+fn test_required_subtopics_block_completion() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+
+    let shipping = Topic::new("Shipping address", vec![]);
+    let gift_wrap = Topic::new("Gift wrap", vec![]);
+    let order = Topic::new("Order", vec![])
+        .with_subtopic(shipping.id, true)
+        .with_subtopic(gift_wrap.id, false);
+    let (shipping_id, gift_wrap_id, order_id) = (shipping.id, gift_wrap.id, order.id);
+    for topic in [shipping, gift_wrap, order] {
+        dialog.switch_topic(topic).unwrap();
+    }
+
+    // The required subtopic is still open
+    assert_eq!(
+        dialog.check_subtopics(order_id),
+        Err(IncompleteSubtopicsError {
+            topic_id: order_id,
+            incomplete: vec![shipping_id]
+        })
+    );
+    assert!(matches!(
+        dialog.mark_topic_complete(order_id, None, false),
+        Err(DialogError::IncompleteSubtopics(err)) if err.incomplete == [shipping_id]
+    ));
+    assert_eq!(dialog.topic(order_id).unwrap().status, TopicStatus::Active);
+
+    // Cascading completes it first; the optional subtopic is left alone
+    let events = dialog
+        .mark_topic_complete(order_id, Some("Shipped".to_string()), true)
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        dialog.topic(shipping_id).unwrap().status,
+        TopicStatus::Completed
+    );
+    assert_eq!(
+        dialog.topic(order_id).unwrap().status,
+        TopicStatus::Completed
+    );
+    assert_ne!(
+        dialog.topic(gift_wrap_id).unwrap().status,
+        TopicStatus::Completed
+    );
+}
+
+#[test]
+fn test_reply_target_participant() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let (user_id, agent_id) = (user.id, agent.id);
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user);
+    dialog.add_participant(agent).unwrap();
+
+    let start = Utc::now();
+    let turn = |n: u32, participant_id, turn_type| {
+        let mut turn = Turn::new(n, participant_id, Message::text("..."), turn_type);
+        turn.timestamp = start + chrono::Duration::seconds(n.into());
+        turn
+    };
+    let question = turn(1, user_id, TurnType::UserQuery);
+    let answer = turn(2, agent_id, TurnType::AgentResponse);
+    let (question_id, answer_id) = (question.turn_id, answer.turn_id);
+    // References the user's question and the agent's later answer
+    let follow_up = turn(3, user_id, TurnType::UserQuery)
+        .with_reference(question_id)
+        .with_reference(answer_id);
+    let follow_up_id = follow_up.turn_id;
+    for t in [question, answer, follow_up] {
+        dialog.add_turn(t).unwrap();
+    }
+
+    assert_eq!(
+        dialog.reply_target_participant(follow_up_id).map(|p| p.id),
+        Some(agent_id)
+    );
+    assert!(dialog.reply_target_participant(question_id).is_none());
+    assert!(dialog.reply_target_participant(Uuid::new_v4()).is_none());
+}
+
+#[test]
+fn test_validate_flow() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let user_id = user.id;
+    let spec = FlowSpec::linear(&["greeting", "identify", "resolve", "confirm", "close"]);
+    let phased = |phase: &str| {
+        let mut turn = Turn::new(1, user_id, Message::text("..."), TurnType::UserQuery);
+        turn.metadata
+            .properties
+            .insert(PHASE_PROPERTY.to_string(), serde_json::json!(phase));
+        turn
+    };
+    let dialog_with = |phases: &[&str]| {
+        let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Task, user.clone());
+        for phase in phases {
+            dialog.add_turn(phased(phase)).unwrap();
+        }
+        // Turns without a phase do not change it
+        dialog
+            .add_turn(Turn::new(
+                1,
+                user_id,
+                Message::text("Thanks"),
+                TurnType::UserQuery,
+            ))
+            .unwrap();
+        dialog
+    };
+
+    let conforming = dialog_with(&[
+        "greeting", "greeting", "identify", "resolve", "confirm", "close",
+    ]);
+    assert_eq!(conforming.phase_transitions().len(), 5);
+    assert_eq!(conforming.validate_flow(&spec), Ok(()));
+
+    let skipping = dialog_with(&["greeting", "resolve", "confirm", "close"]);
+    let resolve_turn = skipping.turns()[1].turn_id;
+    assert_eq!(
+        skipping.validate_flow(&spec),
+        Err(vec![FlowViolation::SkippedPhases {
+            from: "greeting".to_string(),
+            to: "resolve".to_string(),
+            skipped: vec!["identify".to_string()],
+            turn_id: resolve_turn,
+        }])
+    );
+
+    // Going back is out of order unless the spec allows it
+    let backtracking = dialog_with(&["greeting", "identify", "greeting"]);
+    assert!(matches!(
+        backtracking.validate_flow(&spec).unwrap_err()[..],
+        [FlowViolation::OutOfOrder { .. }]
+    ));
+    assert_eq!(
+        backtracking.validate_flow(&spec.clone().allow("identify", "greeting")),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_semantic_spread() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let dialog_with = |embeddings: Vec<Option<Vec<f32>>>| {
+        let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user.clone());
+        for embedding in embeddings {
+            let mut message = Message::text("...");
+            message.embeddings = embedding;
+            dialog
+                .add_turn(Turn::new(1, user.id, message, TurnType::UserQuery))
+                .unwrap();
+        }
+        dialog
+    };
+
+    let focused = dialog_with(vec![
+        Some(vec![1.0, 0.1, 0.0]),
+        Some(vec![0.9, 0.2, 0.0]),
+        None,
+        Some(vec![1.0, 0.0, 0.1]),
+    ]);
+    let wide = dialog_with(vec![
+        Some(vec![1.0, 0.0, 0.0]),
+        Some(vec![0.0, 1.0, 0.0]),
+        Some(vec![0.0, 0.0, 1.0]),
+    ]);
+    let focused_spread = focused.semantic_spread().unwrap();
+    let wide_spread = wide.semantic_spread().unwrap();
+    assert!(focused_spread < 0.05, "{focused_spread}");
+    assert!((wide_spread - 1.0).abs() < 1e-6, "{wide_spread}");
+    assert!(focused_spread < wide_spread);
+
+    // Not enough comparable embeddings
+    assert_eq!(
+        dialog_with(vec![Some(vec![1.0, 0.0]), None]).semantic_spread(),
+        None
+    );
+    assert_eq!(
+        dialog_with(vec![Some(vec![1.0, 0.0]), Some(vec![1.0, 0.0, 0.0])]).semantic_spread(),
+        None
+    );
+}
+
+#[test]
+fn test_require_normalized_embeddings() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let embedded = |embedding: Vec<f32>| {
+        Turn::new(
+            1,
+            user.id,
+            Message::text("Hello").with_embeddings(embedding),
+            TurnType::UserQuery,
+        )
+    };
+
+    let mut unit = vec![3.0, 4.0];
+    normalize_embedding(&mut unit);
+    assert_eq!(unit, vec![0.6, 0.8]);
+
+    // Unnormalized embeddings are accepted as they are by default
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user.clone());
+    dialog.add_turn(embedded(vec![3.0, 4.0])).unwrap();
+    assert_eq!(dialog.turns()[0].message.embeddings, Some(vec![3.0, 4.0]));
+
+    // Normalize scales turn and topic embeddings to unit length
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user.clone());
+    dialog.set_require_normalized_embeddings(Some(EmbeddingNormalization::Normalize));
+    dialog.add_turn(embedded(vec![3.0, 4.0])).unwrap();
+    dialog.add_turn(embedded(vec![0.0, 10.0])).unwrap();
+    let mut topic = Topic::new("Billing", vec![]);
+    topic.embedding = Some(vec![5.0, 0.0]);
+    dialog.switch_topic(topic).unwrap();
+
+    let first = dialog.turns()[0].message.embeddings.clone().unwrap();
+    let second = dialog.turns()[1].message.embeddings.clone().unwrap();
+    assert_eq!(first, vec![0.6, 0.8]);
+    assert_eq!(second, vec![0.0, 1.0]);
+    assert_eq!(
+        dialog.current_topic().unwrap().embedding,
+        Some(vec![1.0, 0.0])
+    );
+    // Similarity is unchanged by normalization and equals the dot product
+    let similarity = cosine_similarity(&first, &second).unwrap();
+    assert!((similarity - 0.8).abs() < 1e-6);
+    let dot: f32 = first.iter().zip(&second).map(|(a, b)| a * b).sum();
+    assert!((similarity - dot).abs() < 1e-6);
+    // Zero vectors cannot be normalized
+    assert!(dialog.add_turn(embedded(vec![0.0, 0.0])).is_err());
+
+    // Reject refuses embeddings outside the tolerance
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user.clone());
+    dialog.set_require_normalized_embeddings(Some(EmbeddingNormalization::Reject));
+    assert!(matches!(
+        dialog.add_turn(embedded(vec![3.0, 4.0])),
+        Err(DialogError::Domain(DomainError::ValidationError(_)))
+    ));
+    assert_eq!(dialog.turn_count(), 0);
+    dialog.add_turn(embedded(vec![0.6, 0.8])).unwrap();
+    let turn_id = dialog.turns()[0].turn_id;
+    assert!(dialog.attach_embedding(turn_id, vec![1.0, 1.0]).is_err());
+    dialog.attach_embedding(turn_id, vec![0.0, 1.0005]).unwrap();
+    assert_eq!(
+        dialog.turns()[0].message.embeddings,
+        Some(vec![0.0, 1.0005])
+    );
+}
+
+#[test]
+fn test_set_phase() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    assert_eq!(dialog.phase(), None);
+
+    for phase in [
+        ConversationPhase::Greeting,
+        ConversationPhase::Triage,
+        ConversationPhase::Resolution,
+    ] {
+        let version = dialog.version();
+        let events = dialog.set_phase(phase).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type(), "PhaseChanged");
+        assert_eq!(dialog.phase(), Some(phase));
+        assert_eq!(dialog.version(), version + 1);
+    }
+
+    // Re-entering the current phase is a no-op
+    assert!(
+        dialog
+            .set_phase(ConversationPhase::Resolution)
+            .unwrap()
+            .is_empty()
+    );
+
+    // Phases are independent of the context state
+    assert_eq!(dialog.context().state, ContextState::Normal);
+
+    // Flow validation only checks phases tagged on turns
+    assert!(dialog.phase_transitions().is_empty());
+    assert!(dialog.validate_flow(&FlowSpec::linear(&["intake"])).is_ok());
+
+    dialog.set_phase(ConversationPhase::Closing).unwrap();
+    dialog.end(None).unwrap();
+    assert!(dialog.set_phase(ConversationPhase::Greeting).is_err());
+    assert_eq!(dialog.phase(), Some(ConversationPhase::Closing));
+}
+
+#[test]
+fn test_all_topics() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    assert!(dialog.all_topics().is_empty());
+
+    let start = Utc::now() - chrono::Duration::hours(1);
+    let topic = |name: &str, minutes: i64| {
+        let mut topic = Topic::new(name, vec![]);
+        topic.introduced_at = start + chrono::Duration::minutes(minutes);
+        topic
+    };
+    // Introduced out of order: billing first, then shipping, then returns
+    let (billing, shipping, returns) = (
+        topic("Billing", 0),
+        topic("Shipping", 10),
+        topic("Returns", 20),
+    );
+    let ids = [billing.id, shipping.id, returns.id];
+    dialog.switch_topic(shipping).unwrap();
+    dialog.switch_topic(billing).unwrap();
+    dialog.mark_topic_complete(ids[0], None, false).unwrap();
+    dialog.switch_topic(returns).unwrap();
+
+    let all: Vec<Uuid> = dialog.all_topics().iter().map(|t| t.id).collect();
+    assert_eq!(all, ids);
+
+    let by_status = |status| -> Vec<Uuid> {
+        dialog
+            .topics_by_status(status)
+            .iter()
+            .map(|t| t.id)
+            .collect()
+    };
+    assert_eq!(by_status(TopicStatus::Completed), vec![ids[0]]);
+    assert_eq!(by_status(TopicStatus::Paused), vec![ids[1]]);
+    assert_eq!(by_status(TopicStatus::Active), vec![ids[2]]);
+    assert!(by_status(TopicStatus::Abandoned).is_empty());
+}
+
+#[test]
+fn test_abandon_dialog() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    // Paused dialogs can be abandoned
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+    dialog.pause().unwrap();
+    let events = dialog
+        .abandon(Some("User walked away".to_string()))
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type(), "DialogAbandoned");
+    assert_eq!(dialog.status(), DialogStatus::Abandoned);
+    assert!(dialog.is_ended());
+    assert!(matches!(
+        dialog.abandon(None),
+        Err(DomainError::InvalidStateTransition { .. })
+    ));
+
+    // Ended dialogs cannot
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    dialog.end(None).unwrap();
+    let version = dialog.version();
+    assert!(matches!(
+        dialog.abandon(None),
+        Err(DomainError::InvalidStateTransition { .. })
+    ));
+    assert_eq!(dialog.status(), DialogStatus::Ended);
+    assert_eq!(dialog.version(), version);
+}
+
+#[test]
+fn test_check_inactivity() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let timeout = std::time::Duration::from_secs(600);
+
+    // Without turns, inactivity is measured from the start
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+    assert_eq!(dialog.last_activity(), dialog.started_at());
+    let soon = dialog.started_at() + chrono::Duration::minutes(5);
+    assert!(
+        dialog
+            .check_inactivity(soon, Some(timeout))
+            .unwrap()
+            .is_none()
+    );
+    let later = dialog.started_at() + chrono::Duration::minutes(11);
+    let event = dialog
+        .check_inactivity(later, Some(timeout))
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.event_type(), "DialogAbandoned");
+    assert_eq!(dialog.status(), DialogStatus::Abandoned);
+    assert!(
+        dialog
+            .check_inactivity(later, Some(timeout))
+            .unwrap()
+            .is_none()
+    );
+
+    // A recent turn keeps the dialog alive
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+    let mut turn = Turn::new(
+        1,
+        user.id,
+        Message::text("Still there?"),
+        TurnType::UserQuery,
+    );
+    turn.timestamp = dialog.started_at() + chrono::Duration::minutes(8);
+    dialog.add_turn(turn).unwrap();
+    assert_eq!(
+        dialog.last_activity(),
+        dialog.started_at() + chrono::Duration::minutes(8)
+    );
+    assert!(
+        dialog
+            .check_inactivity(later, Some(timeout))
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(dialog.status(), DialogStatus::Active);
+
+    // A stale turn does not
+    let version = dialog.version();
+    let stale = dialog.started_at() + chrono::Duration::minutes(30);
+    assert!(
+        dialog
+            .check_inactivity(stale, Some(timeout))
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(dialog.status(), DialogStatus::Abandoned);
+    assert_eq!(dialog.version(), version + 1);
+
+    // Without an explicit timeout the configured idle timeout applies
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    assert!(!dialog.is_idle(stale));
+    assert!(dialog.check_inactivity(later, None).unwrap().is_none());
+    dialog.set_idle_timeout(Some(std::time::Duration::from_secs(1200)));
+    assert!(!dialog.is_idle(later));
+    assert!(dialog.is_idle(stale));
+    assert!(dialog.check_inactivity(later, None).unwrap().is_none());
+    assert!(dialog.check_inactivity(stale, None).unwrap().is_some());
+    assert_eq!(dialog.status(), DialogStatus::Abandoned);
+}
+
+#[test]
+fn test_resolution_turn() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+    assert!(dialog.resolution_turn().is_none());
+
+    let question = Turn::new(
+        1,
+        user.id,
+        Message::text("My invoice is wrong"),
+        TurnType::UserQuery,
+    );
+    let answer = Turn::new(
+        2,
+        user.id,
+        Message::text("Found it, thanks"),
+        TurnType::UserQuery,
+    );
+    let answer_id = answer.turn_id;
+    dialog.add_turn(question).unwrap();
+    dialog.add_turn(answer).unwrap();
+
+    let events = dialog.mark_resolution_turn(answer_id).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type(), "ResolutionTurnMarked");
+    assert_eq!(dialog.resolution_turn().map(|t| t.turn_id), Some(answer_id));
+
+    // Unknown turns are rejected and the mark is kept
+    let version = dialog.version();
+    assert!(matches!(
+        dialog.mark_resolution_turn(Uuid::new_v4()),
+        Err(DomainError::EntityNotFound { .. })
+    ));
+    assert_eq!(dialog.version(), version);
+    assert_eq!(dialog.resolution_turn().map(|t| t.turn_id), Some(answer_id));
+
+    // Retracting the turn clears the mark
+    dialog.retract_turn(answer_id, None).unwrap();
+    assert!(dialog.resolution_turn().is_none());
+}
+
+#[test]
+fn test_compute_metrics() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+    dialog.switch_topic(Topic::new("Billing", vec![])).unwrap();
+
+    // Sentiment rises by 0.5 per turn; the last turn has no sentiment. The
+    // agent answers the user's query after 300ms.
+    let start = Utc::now() - chrono::Duration::minutes(1);
+    let turns = [
+        (TurnType::UserQuery, Some(-0.5), 0),
+        (TurnType::AgentResponse, Some(0.0), 300),
+        (TurnType::Clarification, Some(0.5), 500),
+        (TurnType::UserQuery, None, 900),
+    ];
+    for (number, (turn_type, sentiment, offset_ms)) in turns.into_iter().enumerate() {
+        let mut message = Message::text("Hello");
+        message.sentiment = sentiment;
+        let mut turn = Turn::new(number as u32 + 1, user.id, message, turn_type);
+        turn.timestamp = start + chrono::Duration::milliseconds(offset_ms);
+        dialog.add_turn(turn).unwrap();
+    }
+
+    let metrics = dialog.compute_metrics();
+    assert_eq!(metrics.turn_count, 4);
+    assert_eq!(metrics.avg_response_time_ms, 300.0);
+    assert_eq!(metrics.clarification_count, 1);
+    assert_eq!(metrics.topic_switches, 1);
+    assert!((metrics.sentiment_trend - 0.5).abs() < 1e-6);
+
+    // Ending the dialog records the computed metrics
+    dialog.end(None).unwrap();
+    assert_eq!(*dialog.metrics(), metrics);
+}
+
+#[test]
+fn test_resume_restoring_context() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let dialog_id = Uuid::new_v4();
+    let mut dialog = Dialog::new(dialog_id, DialogType::Direct, user);
+    let variable = |name: &str, value: &str| ContextVariable {
+        name: name.to_string(),
+        value: serde_json::json!(value),
+        scope: ContextScope::Dialog,
+        set_at: Utc::now(),
+        expires_at: None,
+        source: dialog_id,
+    };
+
+    let billing = Topic::new("Billing", vec![]);
+    let billing_id = billing.id;
+    dialog.switch_topic(billing).unwrap();
+    dialog
+        .add_context_variable(variable("theme", "dark"))
+        .unwrap();
+    dialog.pause().unwrap();
+
+    // Changes made while paused are discarded on restore
+    dialog
+        .add_context_variable(variable("theme", "light"))
+        .unwrap();
+    dialog
+        .add_context_variable(variable("locale", "fr"))
+        .unwrap();
+
+    let events = dialog.resume_restoring().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type(), "DialogResumed");
+    assert_eq!(dialog.status(), DialogStatus::Active);
+    assert_eq!(dialog.context().variables.len(), 1);
+    assert_eq!(
+        dialog.context().variables["theme"].value,
+        serde_json::json!("dark")
+    );
+    assert_eq!(dialog.current_topic().unwrap().id, billing_id);
+    assert!(dialog.context().history.is_empty());
+
+    // A plain resume keeps the paused changes and the snapshot
+    dialog.pause().unwrap();
+    dialog
+        .add_context_variable(variable("locale", "fr"))
+        .unwrap();
+    dialog.resume().unwrap();
+    assert!(dialog.context().variables.contains_key("locale"));
+    assert_eq!(dialog.context().history.len(), 1);
+
+    assert!(dialog.resume_restoring().is_err());
+}
+
+#[test]
+fn test_coherence_score() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    // Six turns; `switching` moves to a new topic before every turn after the
+    // first, `referencing` makes each turn refer to the one before it
+    let build = |switching: bool, referencing: bool| {
+        let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+        dialog.switch_topic(Topic::new("Topic 0", vec![])).unwrap();
+        let mut previous: Option<Uuid> = None;
+        for number in 1..=6 {
+            if switching && number > 1 {
+                dialog
+                    .switch_topic(Topic::new(format!("Topic {number}"), vec![]))
+                    .unwrap();
+            }
+            let mut turn = Turn::new(number, user.id, Message::text("Hello"), TurnType::UserQuery);
+            if referencing {
+                turn.metadata.references.extend(previous);
+            }
+            previous = Some(turn.turn_id);
+            dialog.add_turn(turn).unwrap();
+        }
+        dialog
+    };
+
+    let focused = build(false, false);
+    let scattered = build(true, false);
+    let scattered_referencing = build(true, true);
+
+    assert_eq!(focused.coherence_score(), 1.0);
+    assert_eq!(scattered.coherence_score(), 0.0);
+    assert!(scattered_referencing.coherence_score() > scattered.coherence_score());
+    assert!(scattered_referencing.coherence_score() < focused.coherence_score());
+    assert_eq!(
+        scattered_referencing.compute_metrics().coherence_score,
+        scattered_referencing.coherence_score()
+    );
+}
+
+#[test]
+fn test_prune_expired_context() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let dialog_id = Uuid::new_v4();
+    let mut dialog = Dialog::new(dialog_id, DialogType::Direct, user);
+    let now = Utc::now();
+    let variable = |name: &str, expires_at| ContextVariable {
+        name: name.to_string(),
+        value: serde_json::json!(name),
+        scope: ContextScope::Dialog,
+        set_at: now,
+        expires_at,
+        source: dialog_id,
+    };
+
+    dialog
+        .add_context_variable(variable(
+            "session",
+            Some(now + chrono::Duration::minutes(5)),
+        ))
+        .unwrap();
+    dialog
+        .add_context_variable(variable("locale", None))
+        .unwrap();
+
+    // An already expired value is never stored
+    let events = dialog
+        .add_context_variable(variable("stale", Some(now - chrono::Duration::minutes(1))))
+        .unwrap();
+    assert!(events.is_empty());
+    assert!(!dialog.context().variables.contains_key("stale"));
+
+    assert!(dialog.prune_expired_context(now).is_empty());
+    let version = dialog.version();
+
+    let removed = dialog.prune_expired_context(now + chrono::Duration::minutes(10));
+    assert_eq!(removed, vec!["session".to_string()]);
+    assert!(!dialog.context().variables.contains_key("session"));
+    assert!(dialog.context().get_from(dialog_id, "session").is_none());
+    assert!(dialog.context().variables.contains_key("locale"));
+    assert_eq!(dialog.version(), version + 1);
+
+    // expire_context hands back the recorded event for persisting
+    dialog
+        .add_context_variable(variable("token", Some(now + chrono::Duration::minutes(15))))
+        .unwrap();
+    assert!(dialog.expire_context(now).is_none());
+    let event = dialog
+        .expire_context(now + chrono::Duration::minutes(20))
+        .unwrap();
+    assert_eq!(event.event_type(), "ContextVariablesExpired");
+    assert!(!dialog.context().variables.contains_key("token"));
+}
+
+#[test]
+fn test_activity_heatmap() {
+    use chrono::{TimeZone, Weekday};
+
+    let user_id = Uuid::new_v4();
+    let agent_id = Uuid::new_v4();
+    let user = Participant {
+        id: user_id,
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: agent_id,
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user);
+    dialog.add_participant(agent).unwrap();
+
+    // 2024-01-06 was a Saturday and 2024-01-09 a Tuesday
+    let turns = [
+        (user_id, Utc.with_ymd_and_hms(2024, 1, 6, 9, 15, 0).unwrap()),
+        (
+            agent_id,
+            Utc.with_ymd_and_hms(2024, 1, 6, 9, 20, 0).unwrap(),
+        ),
+        (user_id, Utc.with_ymd_and_hms(2024, 1, 6, 9, 45, 0).unwrap()),
+        (user_id, Utc.with_ymd_and_hms(2024, 1, 9, 17, 5, 0).unwrap()),
+    ];
+    for (number, (participant_id, timestamp)) in turns.into_iter().enumerate() {
+        let mut turn = Turn::new(
+            number as u32 + 1,
+            participant_id,
+            Message::text("Hi"),
+            TurnType::UserQuery,
+        );
+        turn.timestamp = timestamp;
+        dialog.add_turn(turn).unwrap();
+    }
+
+    let heatmap = dialog.activity_heatmap(user_id);
+    assert_eq!(heatmap.len(), 2);
+    assert_eq!(heatmap[&(Weekday::Sat, 9)], 2);
+    assert_eq!(heatmap[&(Weekday::Tue, 17)], 1);
+
+    let heatmap = dialog.activity_heatmap(agent_id);
+    assert_eq!(heatmap, HashMap::from([((Weekday::Sat, 9), 1)]));
+    assert!(dialog.activity_heatmap(Uuid::new_v4()).is_empty());
+}
+
+#[test]
+fn test_average_response_time() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Support, user.clone());
+    dialog.add_participant(agent.clone()).unwrap();
+    assert_eq!(dialog.metrics().avg_response_time_ms, 0.0);
+
+    // Only responses directly after a user query count: 1500ms and 500ms
+    let start = Utc::now() - chrono::Duration::minutes(1);
+    let turns = [
+        (user.id, TurnType::UserQuery, 0),
+        (agent.id, TurnType::AgentResponse, 1_500),
+        (agent.id, TurnType::AgentResponse, 4_000),
+        (user.id, TurnType::UserQuery, 10_000),
+        (agent.id, TurnType::AgentResponse, 10_500),
+    ];
+    for (number, (participant_id, turn_type, offset_ms)) in turns.into_iter().enumerate() {
+        let mut turn = Turn::new(
+            number as u32 + 1,
+            participant_id,
+            Message::text("Hello"),
+            turn_type,
+        );
+        turn.timestamp = start + chrono::Duration::milliseconds(offset_ms);
+        dialog.add_turn(turn).unwrap();
+    }
+
+    assert_eq!(dialog.metrics().turn_count, 5);
+    assert_eq!(dialog.metrics().avg_response_time_ms, 1000.0);
+
+    dialog.end(None).unwrap();
+    assert_eq!(dialog.metrics().avg_response_time_ms, 1000.0);
+}
+
+#[test]
+fn test_edit_turn() {
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut dialog = Dialog::new(Uuid::new_v4(), DialogType::Direct, user.clone());
+    let turn = Turn::new(1, user.id, Message::text("Helo"), TurnType::UserQuery);
+    let turn_id = turn.turn_id;
+    dialog.add_turn(turn).unwrap();
+    let version = dialog.version();
+
+    // Unknown turns are not found
+    let err = dialog
+        .edit_turn(Uuid::new_v4(), Message::text("Hello"))
+        .unwrap_err();
+    assert!(matches!(err, DomainError::EntityNotFound { .. }));
+
+    let events = dialog.edit_turn(turn_id, Message::text("Hello")).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event_type(), "TurnEdited");
+    assert_eq!(dialog.turns()[0].message.content.as_text(), Some("Hello"));
+    assert_eq!(dialog.turns().len(), 1);
+    assert_eq!(dialog.version(), version + 1);
+
+    // Ended dialogs are closed to edits
+    dialog.end(None).unwrap();
+    assert!(dialog.edit_turn(turn_id, Message::text("Hi")).is_err());
+    assert_eq!(dialog.turns()[0].message.content.as_text(), Some("Hello"));
+}
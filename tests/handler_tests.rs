@@ -1,14 +1,22 @@
 //! Tests for dialog command and event handlers
 
-use cim_domain::{AggregateRepository, EntityId, InMemoryRepository};
+use cim_domain::{AggregateRepository, AggregateRoot, DomainError, EntityId, InMemoryRepository};
 use cim_domain_dialog::{
-    aggregate::{Dialog, DialogType, DialogMarker},
+    aggregate::{Dialog, DialogConfig, DialogMarker, DialogStatus, DialogType, RateLimit},
     commands::*,
-    handlers::DialogCommandHandler,
-    value_objects::{Participant, ParticipantType, ParticipantRole, Turn, TurnType, TurnMetadata, Message, MessageContent, Topic, TopicStatus, TopicRelevance},
+    events::DialogDomainEvent,
+    handlers::{CommandInterceptor, ContentFilter, DialogCommandHandler, FilterVerdict},
+    projections::SimpleProjectionUpdater,
+    queries::{DialogQuery, DialogQueryHandler, DialogQueryResult},
+    value_objects::{
+        ContextScope, ContextVariable, EndReason, EndReasonCode, FLAGGED_PROPERTY, Message,
+        MessageContent, MetricsDelta, Participant, ParticipantRole, ParticipantType,
+        ResolutionOutcome, Topic, TopicRelevance, TopicStatus, Turn, TurnMetadata, TurnType,
+    },
 };
-use std::sync::Arc;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
 use uuid::Uuid;
 
 #[test]
@@ -26,15 +34,19 @@ fn test_handle_start_dialog() {
         name: "Test User".to_string(),
         metadata: HashMap::new(),
     };
-    
+
     let mut metadata = HashMap::new();
-    metadata.insert("source".to_string(), serde_json::Value::String("test".to_string()));
+    metadata.insert(
+        "source".to_string(),
+        serde_json::Value::String("test".to_string()),
+    );
 
     let cmd = StartDialog {
         id: dialog_id,
         dialog_type: DialogType::Direct,
         primary_participant: participant.clone(),
         metadata: Some(metadata),
+        config: None,
     };
 
     // Execute
@@ -72,6 +84,7 @@ fn test_handle_add_turn() {
         dialog_type: DialogType::Direct,
         primary_participant: participant.clone(),
         metadata: None,
+        config: None,
     };
 
     handler.handle_start_dialog(start_cmd).unwrap();
@@ -111,7 +124,20 @@ fn test_handle_add_turn() {
     // Verify
     assert!(result.is_ok());
     let events = result.unwrap();
-    assert_eq!(events.len(), 1); // TurnAdded event
+    assert_eq!(events.len(), 2); // TurnAdded, MetricsUpdated
+    match &events[1] {
+        DialogDomainEvent::MetricsUpdated(e) => {
+            assert_eq!(e.metrics.turn_count, 1);
+            assert_eq!(
+                e.delta,
+                MetricsDelta {
+                    turn_count: 1,
+                    ..Default::default()
+                }
+            );
+        }
+        other => panic!("expected MetricsUpdated, got {other:?}"),
+    }
 
     // Check that turn was added to dialog
     let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
@@ -141,6 +167,7 @@ fn test_handle_switch_context() {
         dialog_type: DialogType::Direct,
         primary_participant: participant,
         metadata: None,
+        config: None,
     };
 
     handler.handle_start_dialog(start_cmd).unwrap();
@@ -159,6 +186,7 @@ fn test_handle_switch_context() {
         related_topics: Vec::new(),
         keywords: vec!["topic".to_string(), "new".to_string()],
         embedding: None,
+        subtopics: Vec::new(),
     };
 
     let switch_cmd = SwitchContext {
@@ -196,6 +224,7 @@ fn test_handle_pause_resume_dialog() {
         dialog_type: DialogType::Direct,
         primary_participant: participant,
         metadata: None,
+        config: None,
     };
 
     handler.handle_start_dialog(start_cmd).unwrap();
@@ -215,6 +244,71 @@ fn test_handle_pause_resume_dialog() {
     assert_eq!(events.len(), 1); // DialogResumed
 }
 
+#[test]
+fn test_handle_resume_restoring_context() {
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Direct,
+            primary_participant: participant,
+            metadata: None,
+            config: None,
+        })
+        .unwrap();
+
+    let variable = |name: &str| ContextVariable {
+        name: name.to_string(),
+        value: serde_json::json!(true),
+        scope: ContextScope::Dialog,
+        set_at: chrono::Utc::now(),
+        expires_at: None,
+        source: dialog_id,
+    };
+    handler
+        .handle_add_context_variable(AddContextVariable {
+            dialog_id,
+            variable: variable("theme"),
+        })
+        .unwrap();
+    handler
+        .handle_pause_dialog(PauseDialog { id: dialog_id })
+        .unwrap();
+    handler
+        .handle_add_context_variable(AddContextVariable {
+            dialog_id,
+            variable: variable("locale"),
+        })
+        .unwrap();
+
+    let events = handler
+        .handle_resume_dialog_restoring(ResumeDialogRestoring { id: dialog_id })
+        .unwrap();
+    match &events[0] {
+        DialogDomainEvent::DialogResumed(e) => {
+            assert_eq!(e.restored_variables, Some(vec!["theme".to_string()]));
+        }
+        other => panic!("expected DialogResumed, got {other:?}"),
+    }
+
+    let dialog = repository
+        .load(EntityId::<DialogMarker>::from_uuid(dialog_id))
+        .unwrap()
+        .unwrap();
+    assert_eq!(dialog.status(), DialogStatus::Active);
+    assert!(!dialog.context().variables.contains_key("locale"));
+}
+
 #[test]
 fn test_handle_add_remove_participant() {
     // Setup
@@ -236,6 +330,7 @@ fn test_handle_add_remove_participant() {
         dialog_type: DialogType::Direct,
         primary_participant,
         metadata: None,
+        config: None,
     };
 
     handler.handle_start_dialog(start_cmd).unwrap();
@@ -293,6 +388,7 @@ fn test_handle_end_dialog() {
         dialog_type: DialogType::Direct,
         primary_participant: participant,
         metadata: None,
+        config: None,
     };
 
     handler.handle_start_dialog(start_cmd).unwrap();
@@ -300,7 +396,7 @@ fn test_handle_end_dialog() {
     // End dialog
     let end_cmd = EndDialog {
         id: dialog_id,
-        reason: Some("Test completion".to_string()),
+        reason: Some(EndReason::new(EndReasonCode::Resolved).with_detail("Test completion")),
     };
 
     // Execute
@@ -318,6 +414,596 @@ fn test_handle_end_dialog() {
     assert!(dialog.is_ended());
 }
 
+#[tokio::test]
+async fn test_handle_abandon_dialog() {
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut events = handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: participant,
+            metadata: None,
+            config: None,
+        })
+        .unwrap();
+
+    events.extend(
+        handler
+            .handle_abandon_dialog(AbandonDialog {
+                id: dialog_id,
+                reason: Some("User walked away".to_string()),
+            })
+            .unwrap(),
+    );
+
+    let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
+    let dialog = repository.load(entity_id).unwrap().unwrap();
+    assert_eq!(dialog.status(), DialogStatus::Abandoned);
+
+    // The projection records the abandonment without ending the dialog
+    let updater = Arc::new(RwLock::new(SimpleProjectionUpdater::new()));
+    for event in events {
+        updater.write().await.handle_event(event).await.unwrap();
+    }
+    let view = updater.read().await.get_view(&dialog_id).unwrap().clone();
+    assert_eq!(view.status, DialogStatus::Abandoned);
+    assert!(view.abandoned_at.is_some());
+    assert!(view.ended_at.is_none());
+    assert!(view.resolution_time().is_none());
+
+    // so it stays out of resolution times and average durations
+    let queries = DialogQueryHandler::new(updater);
+    let result = queries
+        .execute(DialogQuery::GetResolutionTimePercentiles { dialog_type: None })
+        .await;
+    let DialogQueryResult::ResolutionTimePercentiles(percentiles) = result else {
+        panic!("expected percentiles");
+    };
+    assert_eq!(percentiles.dialog_count, 0);
+    let result = queries.execute(DialogQuery::GetAverageDurationByType).await;
+    assert!(matches!(result, DialogQueryResult::AverageDurations(averages) if averages.is_empty()));
+}
+
+#[test]
+fn test_handle_set_resolution() {
+    // Setup
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: participant,
+            metadata: None,
+            config: None,
+        })
+        .unwrap();
+    handler
+        .handle_end_dialog(EndDialog {
+            id: dialog_id,
+            reason: None,
+        })
+        .unwrap();
+
+    // Resolution can be recorded after the conversation is over
+    let events = handler
+        .dispatch(DialogCommand::SetResolution(SetResolution {
+            dialog_id,
+            resolution: ResolutionOutcome::Unresolved,
+        }))
+        .unwrap();
+    assert!(matches!(
+        &events[..],
+        [DialogDomainEvent::ResolutionSet(e)] if e.resolution == ResolutionOutcome::Unresolved
+    ));
+
+    // ...and revised later
+    handler
+        .handle_set_resolution(SetResolution {
+            dialog_id,
+            resolution: ResolutionOutcome::Resolved,
+        })
+        .unwrap();
+
+    let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
+    let dialog = repository.load(entity_id).unwrap().unwrap();
+    assert!(dialog.is_ended());
+    assert_eq!(dialog.resolution(), Some(ResolutionOutcome::Resolved));
+}
+
+#[test]
+fn test_handle_attach_embedding() {
+    // Setup
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Direct,
+            primary_participant: participant.clone(),
+            metadata: None,
+            config: None,
+        })
+        .unwrap();
+    let turn = Turn::new(
+        1,
+        participant.id,
+        Message::text("Hello"),
+        TurnType::UserQuery,
+    );
+    let turn_id = turn.turn_id;
+    handler
+        .handle_add_turn(AddTurn { dialog_id, turn })
+        .unwrap();
+
+    // Backfills run against historical dialogs
+    handler
+        .handle_end_dialog(EndDialog {
+            id: dialog_id,
+            reason: None,
+        })
+        .unwrap();
+
+    let events = handler
+        .handle_attach_embedding(AttachEmbedding {
+            dialog_id,
+            turn_id,
+            embeddings: vec![0.5, 0.5],
+        })
+        .unwrap();
+    assert!(
+        matches!(&events[..], [DialogDomainEvent::EmbeddingAttached(e)] if e.turn_id == turn_id)
+    );
+
+    let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
+    let dialog = repository.load(entity_id).unwrap().unwrap();
+    assert_eq!(dialog.turns()[0].message.embeddings, Some(vec![0.5, 0.5]));
+
+    // Unknown turns and empty embeddings are rejected
+    assert!(
+        handler
+            .handle_attach_embedding(AttachEmbedding {
+                dialog_id,
+                turn_id: Uuid::new_v4(),
+                embeddings: vec![0.5],
+            })
+            .is_err()
+    );
+    assert!(
+        handler
+            .handle_attach_embedding(AttachEmbedding {
+                dialog_id,
+                turn_id,
+                embeddings: Vec::new(),
+            })
+            .is_err()
+    );
+}
+
+#[test]
+fn test_start_dialog_with_config() {
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Direct,
+            primary_participant: participant.clone(),
+            metadata: None,
+            config: Some(DialogConfig {
+                token_budget: Some(5),
+                rate_limit: Some(RateLimit {
+                    max_turns: 2,
+                    window: std::time::Duration::from_secs(60),
+                }),
+                ..DialogConfig::default()
+            }),
+        })
+        .unwrap();
+
+    let start = chrono::Utc::now();
+    let add = |text: &str, seconds: i64| {
+        let mut turn = Turn::new(1, participant.id, Message::text(text), TurnType::UserQuery);
+        turn.timestamp = start + chrono::Duration::seconds(seconds);
+        handler.handle_add_turn(AddTurn { dialog_id, turn })
+    };
+
+    assert!(add("one two", 0).is_ok());
+    assert!(add("three four", 1).is_ok());
+
+    // Third turn inside the window
+    let err = add("five", 2).unwrap_err();
+    assert!(err.to_string().contains("Rate limit"), "{err}");
+
+    // Window has passed, but the turn would exceed the token budget
+    let err = add("five six", 120).unwrap_err();
+    assert!(err.to_string().contains("budget"), "{err}");
+
+    assert!(add("five", 120).is_ok());
+
+    let dialog = repository
+        .load(EntityId::<DialogMarker>::from_uuid(dialog_id))
+        .unwrap()
+        .unwrap();
+    assert_eq!(dialog.turn_count(), 3);
+    assert_eq!(dialog.tokens_used(), 5);
+}
+
+#[test]
+fn test_version_matches_event_count() {
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+    let dialog_id = Uuid::new_v4();
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let mut metadata = HashMap::new();
+    metadata.insert("channel".to_string(), serde_json::json!("web"));
+
+    let mut events = handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            metadata: Some(metadata),
+            config: None,
+        })
+        .unwrap();
+    events.extend(
+        handler
+            .handle_add_participant(AddParticipant {
+                dialog_id,
+                participant: agent.clone(),
+            })
+            .unwrap(),
+    );
+    for (participant_id, text) in [(user.id, "Hi"), (agent.id, "Hello!")] {
+        let turn = Turn::new(1, participant_id, Message::text(text), TurnType::UserQuery);
+        events.extend(
+            handler
+                .handle_add_turn(AddTurn { dialog_id, turn })
+                .unwrap(),
+        );
+    }
+    events.extend(
+        handler
+            .handle_pause_dialog(PauseDialog { id: dialog_id })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_resume_dialog(ResumeDialog { id: dialog_id })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_end_dialog(EndDialog {
+                id: dialog_id,
+                reason: None,
+            })
+            .unwrap(),
+    );
+
+    // Started, metadata set, participant added, two turns (each with a metrics
+    // update that does not advance the version), paused, resumed, ended
+    assert_eq!(events.len(), 10);
+    let dialog = repository
+        .load(EntityId::<DialogMarker>::from_uuid(dialog_id))
+        .unwrap()
+        .unwrap();
+    assert_eq!(Dialog::expected_version_from_events(&events), 7);
+    assert_eq!(dialog.version(), 7);
+}
+
+#[test]
+fn test_handlers_return_recorded_events() {
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+    let dialog_id = Uuid::new_v4();
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let mut events = handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            metadata: None,
+            config: None,
+        })
+        .unwrap();
+    let turn = Turn::new(1, user.id, Message::text("Hello"), TurnType::UserQuery);
+    events.extend(
+        handler
+            .handle_add_turn(AddTurn { dialog_id, turn })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_pause_dialog(PauseDialog { id: dialog_id })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_resume_dialog(ResumeDialog { id: dialog_id })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_end_dialog(EndDialog {
+                id: dialog_id,
+                reason: None,
+            })
+            .unwrap(),
+    );
+
+    // The pause snapshot carries the event's timestamp, so replaying only
+    // matches the stored aggregate if these are the events it recorded
+    let stored = repository
+        .load(EntityId::<DialogMarker>::from_uuid(dialog_id))
+        .unwrap()
+        .unwrap();
+    assert_eq!(Dialog::from_events(events.clone()).unwrap(), stored);
+
+    let ids: std::collections::HashSet<Uuid> = events.iter().map(|e| e.event_id()).collect();
+    assert_eq!(ids.len(), events.len());
+    assert!(!ids.contains(&Uuid::nil()));
+}
+
+#[test]
+fn test_handle_with_outcome() {
+    // Setup
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+
+    // Create dialog
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    let start_cmd = StartDialog {
+        id: dialog_id,
+        dialog_type: DialogType::Direct,
+        primary_participant: participant,
+        metadata: None,
+        config: None,
+    };
+
+    let started = handler
+        .dispatch_with_outcome(DialogCommand::StartDialog(start_cmd))
+        .unwrap();
+    assert_eq!(started.status, DialogStatus::Active);
+
+    // End dialog and inspect the outcome
+    let end_cmd = EndDialog {
+        id: dialog_id,
+        reason: Some("Test completion".into()),
+    };
+
+    let outcome = handler
+        .dispatch_with_outcome(DialogCommand::EndDialog(end_cmd))
+        .unwrap();
+    assert_eq!(outcome.events.len(), 1);
+    assert_eq!(outcome.status, DialogStatus::Ended);
+    assert!(outcome.aggregate_version > started.aggregate_version);
+
+    // Outcome matches a subsequent load
+    let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
+    let dialog = repository.load(entity_id).unwrap().unwrap();
+    assert_eq!(dialog.version(), outcome.aggregate_version);
+    assert_eq!(dialog.status(), outcome.status);
+
+    // Command errors are passed through
+    let result = handler.dispatch_with_outcome(DialogCommand::EndDialog(EndDialog {
+        id: dialog_id,
+        reason: None,
+    }));
+    assert!(result.is_err());
+}
+
+struct WordFilter;
+
+impl ContentFilter for WordFilter {
+    fn check(&self, content: &MessageContent) -> FilterVerdict {
+        match content {
+            MessageContent::Text(text) if text.contains("banned") => {
+                FilterVerdict::Block("banned word".to_string())
+            }
+            MessageContent::Text(text) if text.contains("dubious") => {
+                FilterVerdict::Flag("needs review".to_string())
+            }
+            _ => FilterVerdict::Allow,
+        }
+    }
+}
+
+#[test]
+fn test_content_filter() {
+    // Setup
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let mut handler = DialogCommandHandler::new(repository.clone());
+    handler.set_content_filter(Box::new(WordFilter));
+
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Direct,
+            primary_participant: participant.clone(),
+            metadata: None,
+            config: None,
+        })
+        .unwrap();
+
+    let add = |text: &str| AddTurn {
+        dialog_id,
+        turn: Turn::new(1, participant.id, Message::text(text), TurnType::UserQuery),
+    };
+
+    // Blocked content is rejected
+    assert!(handler.handle_add_turn(add("a banned phrase")).is_err());
+
+    // Flagged content is added and flagged
+    let flagged = add("something dubious");
+    let flagged_id = flagged.turn.turn_id;
+    let events = handler.handle_add_turn(flagged).unwrap();
+    assert_eq!(events.len(), 3);
+    assert!(matches!(&events[2], DialogDomainEvent::TurnFlagged(e) if e.turn_id == flagged_id));
+
+    // Allowed content passes unchanged
+    assert_eq!(handler.handle_add_turn(add("hello")).unwrap().len(), 2);
+
+    let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
+    let dialog = repository.load(entity_id).unwrap().unwrap();
+    assert_eq!(dialog.turn_count(), 2);
+    assert_eq!(
+        dialog.turns()[0].metadata.properties.get(FLAGGED_PROPERTY),
+        Some(&serde_json::json!("needs review"))
+    );
+    assert!(
+        !dialog.turns()[1]
+            .metadata
+            .properties
+            .contains_key(FLAGGED_PROPERTY)
+    );
+}
+
+struct DenyEnd;
+
+impl CommandInterceptor for DenyEnd {
+    fn before(&self, cmd: &DialogCommand) -> Result<(), cim_domain::DomainError> {
+        match cmd {
+            DialogCommand::EndDialog(_) => Err(cim_domain::DomainError::ValidationError(
+                "Ending dialogs is not allowed".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+struct Audit(Arc<Mutex<Vec<&'static str>>>);
+
+impl CommandInterceptor for Audit {
+    fn before(&self, cmd: &DialogCommand) -> Result<(), cim_domain::DomainError> {
+        self.0.lock().unwrap().push(cmd.name());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_command_interceptors() {
+    // Setup
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let mut handler = DialogCommandHandler::new(repository.clone());
+    let audit_log = Arc::new(Mutex::new(Vec::new()));
+    handler.add_interceptor(Box::new(DenyEnd));
+    handler.add_interceptor(Box::new(Audit(audit_log.clone())));
+
+    let dialog_id = Uuid::new_v4();
+    let participant = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "Test User".to_string(),
+        metadata: HashMap::new(),
+    };
+
+    // Allowed commands pass through every interceptor
+    let events = handler
+        .dispatch(DialogCommand::StartDialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Direct,
+            primary_participant: participant,
+            metadata: None,
+            config: None,
+        }))
+        .unwrap();
+    assert_eq!(events.len(), 1);
+
+    // Denied commands short-circuit before later interceptors and the handler
+    let result = handler.dispatch(DialogCommand::EndDialog(EndDialog {
+        id: dialog_id,
+        reason: None,
+    }));
+    assert!(matches!(
+        result,
+        Err(cim_domain::DomainError::ValidationError(_))
+    ));
+    assert_eq!(*audit_log.lock().unwrap(), vec!["StartDialog"]);
+
+    let entity_id = EntityId::<DialogMarker>::from_uuid(dialog_id);
+    let dialog = repository.load(entity_id).unwrap().unwrap();
+    assert!(!dialog.is_ended());
+}
+
 #[test]
 fn test_error_handling_dialog_not_found() {
     // Setup
@@ -341,4 +1027,219 @@ fn test_error_handling_dialog_not_found() {
         }
         _ => panic!("Expected EntityNotFound error"),
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_dialog_from_events() {
+    let repository = Arc::new(InMemoryRepository::<Dialog>::new());
+    let handler = DialogCommandHandler::new(repository.clone());
+    let dialog_id = Uuid::new_v4();
+    let user = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Primary,
+        name: "User".to_string(),
+        metadata: HashMap::new(),
+    };
+    let agent = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::AIAgent,
+        role: ParticipantRole::Assistant,
+        name: "Agent".to_string(),
+        metadata: HashMap::new(),
+    };
+    let observer = Participant {
+        id: Uuid::new_v4(),
+        participant_type: ParticipantType::Human,
+        role: ParticipantRole::Observer,
+        name: "Observer".to_string(),
+        metadata: HashMap::new(),
+    };
+    let topic = Topic::new("Billing", vec!["invoice".to_string()]);
+    let topic_id = topic.id;
+
+    let mut events = handler
+        .handle_start_dialog(StartDialog {
+            id: dialog_id,
+            dialog_type: DialogType::Support,
+            primary_participant: user.clone(),
+            metadata: Some(HashMap::from([(
+                "channel".to_string(),
+                serde_json::json!("web"),
+            )])),
+            config: None,
+        })
+        .unwrap();
+    events.extend(
+        handler
+            .handle_add_participant(AddParticipant {
+                dialog_id,
+                participant: agent.clone(),
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_add_participant(AddParticipant {
+                dialog_id,
+                participant: observer.clone(),
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_remove_participant(RemoveParticipant {
+                dialog_id,
+                participant_id: observer.id,
+                reason: None,
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_switch_context(SwitchContext { dialog_id, topic })
+            .unwrap(),
+    );
+    let mut turn_ids = Vec::new();
+    for (participant_id, text) in [(user.id, "My invoice is wrong"), (agent.id, "Let me check")] {
+        let turn = Turn::new(1, participant_id, Message::text(text), TurnType::UserQuery);
+        turn_ids.push(turn.turn_id);
+        events.extend(
+            handler
+                .handle_add_turn(AddTurn { dialog_id, turn })
+                .unwrap(),
+        );
+    }
+    events.extend(
+        handler
+            .handle_update_context(UpdateContext {
+                dialog_id,
+                variables: HashMap::from([("order".to_string(), serde_json::json!(42))]),
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_pin_turn(PinTurn {
+                dialog_id,
+                turn_id: turn_ids[0],
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_attach_embedding(AttachEmbedding {
+                dialog_id,
+                turn_id: turn_ids[1],
+                embeddings: vec![0.6, 0.8],
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_mark_topic_complete(MarkTopicComplete {
+                dialog_id,
+                topic_id,
+                resolution: Some("Refunded".to_string()),
+                cascade: false,
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_pause_dialog(PauseDialog { id: dialog_id })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_resume_dialog(ResumeDialog { id: dialog_id })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_set_resolution(SetResolution {
+                dialog_id,
+                resolution: ResolutionOutcome::Resolved,
+            })
+            .unwrap(),
+    );
+    events.extend(
+        handler
+            .handle_end_dialog(EndDialog {
+                id: dialog_id,
+                reason: None,
+            })
+            .unwrap(),
+    );
+
+    let stored = repository
+        .load(EntityId::<DialogMarker>::from_uuid(dialog_id))
+        .unwrap()
+        .unwrap();
+    let rebuilt = Dialog::from_events(events.clone()).unwrap();
+
+    assert_eq!(rebuilt.id(), dialog_id);
+    assert_eq!(rebuilt.version(), stored.version());
+    assert_eq!(
+        rebuilt.version(),
+        Dialog::expected_version_from_events(&events)
+    );
+    assert_eq!(rebuilt.status(), DialogStatus::Ended);
+    assert_eq!(rebuilt.dialog_type(), DialogType::Support);
+    assert_eq!(rebuilt.primary_participant(), user.id);
+    let mut participants: Vec<Uuid> = rebuilt.participants().keys().copied().collect();
+    participants.sort();
+    let mut expected: Vec<Uuid> = stored.participants().keys().copied().collect();
+    expected.sort();
+    assert_eq!(participants, expected);
+    assert_eq!(
+        rebuilt
+            .turns()
+            .iter()
+            .map(|t| t.turn_id)
+            .collect::<Vec<_>>(),
+        stored.turns().iter().map(|t| t.turn_id).collect::<Vec<_>>()
+    );
+    assert_eq!(rebuilt.turns()[1].message.embeddings, Some(vec![0.6, 0.8]));
+    assert_eq!(rebuilt.pinned_turns()[0].turn_id, turn_ids[0]);
+    assert_eq!(
+        rebuilt.topic(topic_id).unwrap().status,
+        TopicStatus::Completed
+    );
+    assert_eq!(rebuilt.current_topic().map(|t| t.id), Some(topic_id));
+    assert_eq!(
+        rebuilt.context().variables["order"].value,
+        serde_json::json!(42)
+    );
+    assert_eq!(rebuilt.metadata(), stored.metadata());
+    assert_eq!(rebuilt.resolution(), Some(ResolutionOutcome::Resolved));
+    assert_eq!(rebuilt.metrics().turn_count, 2);
+    assert_eq!(
+        rebuilt.metrics().topic_switches,
+        stored.metrics().topic_switches
+    );
+    assert!(rebuilt.validate_invariants().is_ok());
+
+    // The stream must open with DialogStarted and hold only this dialog's events
+    assert!(matches!(
+        Dialog::from_events(events[1..].to_vec()),
+        Err(DomainError::ValidationError(_))
+    ));
+    assert!(matches!(
+        Dialog::from_events(Vec::new()),
+        Err(DomainError::ValidationError(_))
+    ));
+    let mut foreign = events.clone();
+    if let DialogDomainEvent::ParticipantAdded(e) = &mut foreign[2] {
+        e.dialog_id = Uuid::new_v4();
+    } else {
+        panic!("Expected ParticipantAdded");
+    }
+    assert!(matches!(
+        Dialog::from_events(foreign),
+        Err(DomainError::ValidationError(_))
+    ));
+    let mut restarted = events[..3].to_vec();
+    restarted.push(events[0].clone());
+    assert!(Dialog::from_events(restarted).is_err());
+}